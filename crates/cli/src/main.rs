@@ -5,12 +5,18 @@ mod uds;
 use crate::init_detect::{detect, InitSystem};
 use crate::init_service::*;
 use clap::{Parser, Subcommand};
-use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::io;
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
 use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
+use synchron_ffi::xattr::Namespace;
+use synchron_utils::{
+    pathenc, read_frame, write_frame, OverflowStatus, PairState, QueueStatus, DEBOUNCE_MS_MAX,
+    DEBOUNCE_MS_MIN,
+};
+use synchron_watcher::roots::check_root_with;
 use thiserror::Error;
 use time;
 use tokio::fs;
@@ -38,10 +44,44 @@ pub enum Action {
     },
 
     /// Add a pair of directories into syncing
-    Add { dir_a: String, dir_b: String },
+    Add {
+        dir_a: PathBuf,
+        dir_b: PathBuf,
+
+        /// Extended attribute namespaces to propagate between the two sides
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_parser = parse_xattr_namespace,
+            default_values_t = [Namespace::User, Namespace::PosixAcl],
+        )]
+        xattrs: Vec<Namespace>,
+
+        /// Do not propagate any extended attributes
+        #[arg(long, conflicts_with = "xattrs")]
+        no_xattrs: bool,
+
+        /// Sync only the filesystem each root is on, leaving mounts beneath it out
+        #[arg(long)]
+        skip_submounts: bool,
+
+        /// Debounce window for this pair in milliseconds (default: the manager's)
+        #[arg(
+            long,
+            value_name = "MS",
+            value_parser = clap::value_parser!(u64).range(DEBOUNCE_MS_MIN..=DEBOUNCE_MS_MAX),
+        )]
+        debounce_ms: Option<u64>,
+    },
 
     /// Remove a pair of directories from sync list
-    Remove { pair_id: String },
+    Remove {
+        pair_id: String,
+
+        /// Also delete the pair's marker from both roots, so they can be added again
+        #[arg(long)]
+        purge_state: bool,
+    },
 
     /// List the syncing pairs and their pair ids
     List,
@@ -164,141 +204,18 @@ pub async fn connect_uds(sock_path: &Path) -> Result<UnixStream, UdsConnectError
         .map_err(|e| UdsConnectError::Connect(sock_path.display().to_string(), e))
 }
 
-async fn write_frame<W: AsyncWriteExt + Unpin>(w: &mut W, payload: &[u8]) -> io::Result<()> {
-    let len = (payload.len() as u32).to_be_bytes();
-    w.write_all(&len).await?;
-    w.write_all(payload).await?;
-    w.flush().await
-}
-async fn read_frame<R: AsyncReadExt + Unpin>(r: &mut R) -> io::Result<Vec<u8>> {
-    let mut len = [0u8; 4];
-    r.read_exact(&mut len).await?;
-    let n = u32::from_be_bytes(len) as usize;
-    let mut buf = vec![0u8; n];
-    r.read_exact(&mut buf).await?;
-    Ok(buf)
-}
-
 // ==========================================================
 // ========== payload protocol (the simplest json) ==========
 // ==========================================================
 
-#[derive(Serialize, Deserialize, Default, Clone, Copy)]
-#[serde(rename_all = "snake_case")]
-pub enum Scope {
-    #[default]
-    One,
-    All,
-}
-
-#[derive(Serialize, Deserialize, Clone, Copy)]
-#[serde(rename_all = "snake_case")]
-pub enum Mode {
-    Bi,
-    A2b,
-    B2a,
-}
-
-#[derive(Serialize, Deserialize, Clone, Copy)]
-#[serde(rename_all = "snake_case")]
-pub enum ConflictPolicy {
-    Ours,
-    Theirs,
-    Manual,
-}
-
-#[derive(Serialize, Deserialize, Clone, Copy)]
-#[serde(rename_all = "snake_case")]
-pub enum LogLevel {
-    Debug,
-    Info,
-    Warn,
-    Error,
-}
-
-#[derive(serde::Serialize)]
-struct Request {
-    op: &'static str,
-    id: u64,
-    ts: String,
-    #[serde(flatten)]
-    params: Params,
-}
-
-#[derive(Serialize, Deserialize)]
-enum Params {
-    // pair.*
-    PairAdd(PairAddParams),
-    PairRemove {
-        pair_id: String,
-        #[serde(default)]
-        purge_state: bool,
-    },
-    PairList,
-    PairPause {
-        scope: Scope,
-        #[serde(default)]
-        pair_id: Option<String>,
-    },
-    PairResume {
-        scope: Scope,
-        #[serde(default)]
-        pair_id: Option<String>,
-    },
-    PairRestart {
-        scope: Scope,
-        #[serde(default)]
-        pair_id: Option<String>,
-    },
-
-    // service.*
-    ServiceStatus {
-        #[serde(default)]
-        detail: Option<String>,
-    }, // "summary"|"full"
+// 请求与参数的类型定义在 synchron_utils::protocol，manager 用它们解析请求
 
-    // logs.*
-    LogsTail(LogsTailParams),
-}
-
-#[derive(Serialize, Deserialize)]
-pub struct PairAddParams {
-    pub dir_a: PathBuf,
-    pub dir_b: PathBuf,
-    #[serde(default = "default_mode")]
-    pub mode: Mode,
-    #[serde(default)]
-    pub name: Option<String>,
-    #[serde(default)]
-    pub include: Vec<String>,
-    #[serde(default)]
-    pub exclude: Vec<String>,
-    #[serde(default)]
-    pub bandwidth_mb: Option<u32>,
-    #[serde(default)]
-    pub max_inflight: Option<u32>,
-    #[serde(default = "default_conflict_policy")]
-    pub conflict_policy: ConflictPolicy,
-}
-fn default_mode() -> Mode {
-    Mode::Bi
-}
-fn default_conflict_policy() -> ConflictPolicy {
-    ConflictPolicy::Manual
-}
-
-#[derive(Serialize, Deserialize)]
-pub struct LogsTailParams {
-    #[serde(default)]
-    pub scope: Scope, // 默认 One；若 All, 可不填 pair_id
-    #[serde(default)]
-    pub pair_id: Option<String>,
-    #[serde(default)]
-    pub since: Option<String>, // 支持 "1h" 或 RFC3339；也可换成自定义新类型
-    #[serde(default)]
-    pub level: Option<LogLevel>,
-    #[serde(default)]
-    pub follow: bool,
+/// `--xattrs` entry: `user`, `trusted`, `security`, `acl` or `system`.
+fn parse_xattr_namespace(s: &str) -> Result<Namespace, String> {
+    s.parse().map_err(|_| {
+        let names: Vec<_> = Namespace::ALL.iter().map(|ns| ns.as_str()).collect();
+        format!("expected one of {}", names.join(", "))
+    })
 }
 
 #[derive(serde::Serialize)]
@@ -336,6 +253,46 @@ async fn recv_json<R: AsyncReadExt + Unpin>(r: &mut R) -> io::Result<Value> {
     Ok(v)
 }
 
+/// Path field of a response (see `synchron_utils::pathenc`); "?" if absent.
+fn decode_path(v: Option<&Value>) -> PathBuf {
+    v.and_then(|v| pathenc::deserialize(v).ok())
+        .unwrap_or_else(|| PathBuf::from("?"))
+}
+
+/// Human-readable `state` of a pair entry; unknown states are shown as sent.
+fn state_label(v: Option<&Value>) -> String {
+    match v.map(|v| serde_json::from_value::<PairState>(v.clone())) {
+        Some(Ok(st)) => st.to_string(),
+        _ => v.and_then(|x| x.as_str()).unwrap_or("unknown").to_string(),
+    }
+}
+
+/// Queue and overflow info of a `service.status` pair entry, e.g.
+/// `, queue 12 (3 dirs to rescan), 2 overflows, rescan pending`.
+fn status_details(p: &Value) -> String {
+    let field = |k: &str| p.get(k).cloned().unwrap_or(Value::Null);
+    let mut s = String::new();
+    if let Ok(q) = serde_json::from_value::<QueueStatus>(field("queue")) {
+        s += &format!(", queue {}", q.depth);
+        if q.dirty > 0 {
+            s += &format!(" ({} dirs to rescan)", q.dirty);
+        }
+    }
+    if let Ok(o) = serde_json::from_value::<OverflowStatus>(field("overflow")) {
+        if o.count > 0 {
+            s += &format!(
+                ", {} overflow{}",
+                o.count,
+                if o.count == 1 { "" } else { "s" }
+            );
+        }
+        if o.rescan_pending {
+            s += ", rescan pending";
+        }
+    }
+    s
+}
+
 // 统一解析响应：要求 { "ok": bool, "error": null|{...}, "data": {...}, "request_id": ... }
 fn unwrap_ok(resp: Value) -> io::Result<Value> {
     let ok = resp.get("ok").and_then(|b| b.as_bool()).unwrap_or(false);
@@ -384,18 +341,52 @@ async fn main() {
     let code: i32 = match args.action {
         Action::Service { service } => handle_service(service).await,
 
-        Action::Add { dir_a, dir_b } => {
+        Action::Add {
+            dir_a,
+            dir_b,
+            xattrs,
+            no_xattrs,
+            skip_submounts,
+            debounce_ms,
+        } => 'add_branch: {
+            let xattrs: Vec<&str> = if no_xattrs {
+                Vec::new()
+            } else {
+                xattrs.iter().map(|ns| ns.as_str()).collect()
+            };
+
+            // 发送前先检查挂载拓扑：不支持的文件系统、跨挂载点的 root 直接拒绝。
+            // 标记是否还属于活着的 pair 只有 manager 知道，交给它判断
+            let mut roots = Vec::with_capacity(2);
+            for dir in [&dir_a, &dir_b] {
+                match check_root_with(dir, skip_submounts, |_| true) {
+                    Ok(checked) => {
+                        for w in &checked.warnings {
+                            eprintln!("warning: {w}");
+                        }
+                        roots.push(checked.root);
+                    }
+                    Err(e) => {
+                        eprintln!("add failed: {e}");
+                        break 'add_branch 2;
+                    }
+                }
+            }
+            let (dir_a, dir_b) = (&roots[0], &roots[1]);
             let req = serde_json::json!({
                 "op": "pair.add",
                 "id": next_req_id(),
                 "ts": now_rfc3339(),
                 "params": {
-                    "dir_a": dir_a,
-                    "dir_b": dir_b,
+                    "dir_a": pathenc::Encoded(dir_a),
+                    "dir_b": pathenc::Encoded(dir_b),
                     "mode": "bi",
                     "include": [],
                     "exclude": [],
-                    "conflict_policy": "manual"
+                    "conflict_policy": "manual",
+                    "xattrs": xattrs,
+                    "skip_submounts": skip_submounts,
+                    "debounce_ms": debounce_ms
                 }
             });
 
@@ -421,12 +412,15 @@ async fn main() {
             }
         }
 
-        Action::Remove { pair_id } => {
+        Action::Remove {
+            pair_id,
+            purge_state,
+        } => {
             let req = serde_json::json!({
                 "op": "pair.remove",
                 "request_id": next_req_id(),
                 "ts": now_rfc3339(),
-                "params": { "pair_id": pair_id, "purge_state": false }
+                "params": { "pair_id": pair_id, "purge_state": purge_state }
             });
 
             if let Err(e) = send_json(&mut w, &req).await {
@@ -464,16 +458,20 @@ async fn main() {
                             if pairs.is_empty() {
                                 println!("No pairs.");
                             } else {
+                                let mut out = io::stdout().lock();
                                 for p in pairs {
                                     let id =
                                         p.get("pair_id").and_then(|x| x.as_str()).unwrap_or("?");
-                                    let a = p.get("dir_a").and_then(|x| x.as_str()).unwrap_or("?");
-                                    let b = p.get("dir_b").and_then(|x| x.as_str()).unwrap_or("?");
-                                    let st = p
-                                        .get("state")
-                                        .and_then(|x| x.as_str())
-                                        .unwrap_or("unknown");
-                                    println!("{id}\t{st}\t{a}\t<->\t{b}");
+                                    let a = decode_path(p.get("dir_a"));
+                                    let b = decode_path(p.get("dir_b"));
+                                    let st = state_label(p.get("state"));
+                                    // 路径按原始字节输出，非 UTF-8 名字也能原样复制回 `synchron add`
+                                    let mut line = format!("{id}\t{st}\t").into_bytes();
+                                    line.extend_from_slice(a.as_os_str().as_bytes());
+                                    line.extend_from_slice(b"\t<->\t");
+                                    line.extend_from_slice(b.as_os_str().as_bytes());
+                                    line.push(b'\n');
+                                    let _ = out.write_all(&line);
                                 }
                             }
                             0
@@ -613,8 +611,8 @@ async fn main() {
                         if let Some(pairs) = data.get("pairs").and_then(|x| x.as_array()) {
                             for p in pairs {
                                 let id = p.get("pair_id").and_then(|x| x.as_str()).unwrap_or("?");
-                                let st = p.get("state").and_then(|x| x.as_str()).unwrap_or("?");
-                                println!("  pair {id}: {st}");
+                                let st = state_label(p.get("state"));
+                                println!("  pair {id}: {st}{}", status_details(p));
                             }
                         }
                         0
@@ -0,0 +1,129 @@
+//! Allocating vs. buffer-reusing read paths.
+//!
+//! `cargo bench -p synchron-ffi --bench read_path`
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use std::io::Write;
+use std::os::unix::net::UnixStream;
+use synchron_ffi::{Epoll, EpollCreateFlags, EpollEventFlags, EventBuffer, Events, FanotifyEvent};
+
+const FAN_CREATE: u64 = 0x0000_0100;
+const FAN_EVENT_INFO_TYPE_DFID_NAME: u8 = 2;
+
+/// One FAN_REPORT_DFID_NAME event without fds, laid out like the kernel does.
+fn dfid_name_event(name: &str) -> Vec<u8> {
+    let handle = [0xabu8; 8];
+    let mut rec = Vec::new();
+    rec.extend_from_slice(&[FAN_EVENT_INFO_TYPE_DFID_NAME, 0, 0, 0]); // hdr, len patched below
+    rec.extend_from_slice(&[0u8; 8]); // fsid
+    rec.extend_from_slice(&(handle.len() as u32).to_ne_bytes());
+    rec.extend_from_slice(&1i32.to_ne_bytes());
+    rec.extend_from_slice(&handle);
+    rec.extend_from_slice(name.as_bytes());
+    rec.push(0);
+    while rec.len() % 4 != 0 {
+        rec.push(0);
+    }
+    let rec_len = rec.len() as u16;
+    rec[2..4].copy_from_slice(&rec_len.to_ne_bytes());
+
+    let event_len = (24 + rec.len()) as u32;
+    let mut ev = Vec::with_capacity(event_len as usize);
+    ev.extend_from_slice(&event_len.to_ne_bytes());
+    ev.push(3); // FANOTIFY_METADATA_VERSION
+    ev.push(0);
+    ev.extend_from_slice(&24u16.to_ne_bytes());
+    ev.extend_from_slice(&FAN_CREATE.to_ne_bytes());
+    ev.extend_from_slice(&(-1i32).to_ne_bytes()); // FAN_NOFD
+    ev.extend_from_slice(&4242i32.to_ne_bytes());
+    ev.extend_from_slice(&rec);
+    ev
+}
+
+fn fanotify_batch() -> Vec<u8> {
+    let mut buf = Vec::new();
+    let mut i = 0;
+    loop {
+        let ev = dfid_name_event(&format!("target/debug/deps/file-{i:06}.o"));
+        if buf.len() + ev.len() > 64 * 1024 {
+            break;
+        }
+        buf.extend_from_slice(&ev);
+        i += 1;
+    }
+    buf
+}
+
+fn bench_fanotify(c: &mut Criterion) {
+    let batch = fanotify_batch();
+    let mut group = c.benchmark_group("fanotify_parse");
+    group.throughput(Throughput::Bytes(batch.len() as u64));
+
+    // SAFETY (both): the synthetic records carry no fds (FAN_NOFD, no PIDFD record).
+    group.bench_function("owned_vec", |b| {
+        b.iter_batched_ref(
+            || unsafe { EventBuffer::from_raw(&batch) },
+            |buf| {
+                let events: Vec<FanotifyEvent> =
+                    buf.events().map(|ev| ev.unwrap().into_owned()).collect();
+                black_box(events);
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.bench_function("borrowed_view", |b| {
+        b.iter_batched_ref(
+            || unsafe { EventBuffer::from_raw(&batch) },
+            |buf| {
+                let mut total = 0usize;
+                for ev in buf.events() {
+                    let ev = ev.unwrap();
+                    if let Some((dir, name)) = ev.dir_name() {
+                        total += dir.handle.len() + name.len();
+                    }
+                }
+                black_box(total);
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn bench_epoll(c: &mut Criterion) {
+    const N: usize = 256;
+    let epoll = Epoll::new(EpollCreateFlags::CLOEXEC).unwrap();
+    // Level-triggered sockets with unread data: every wait returns all N.
+    let pairs: Vec<(UnixStream, UnixStream)> = (0..N)
+        .map(|i| {
+            let (a, mut b) = UnixStream::pair().unwrap();
+            b.write_all(b"x").unwrap();
+            epoll.add(&a, EpollEventFlags::IN, i as u64).unwrap();
+            (a, b)
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("epoll_wait");
+    group.throughput(Throughput::Elements(N as u64));
+
+    group.bench_function("wait_vec", |b| {
+        b.iter(|| {
+            let ready = epoll.wait(N, 0).unwrap();
+            black_box(ready.iter().map(|e| e.data_u64).sum::<u64>());
+        })
+    });
+
+    let mut events = Events::with_capacity(N);
+    group.bench_function("wait_into", |b| {
+        b.iter(|| {
+            epoll.wait_into(&mut events, 0).unwrap();
+            black_box(events.iter().map(|e| e.data_u64).sum::<u64>());
+        })
+    });
+    group.finish();
+    drop(pairs);
+}
+
+criterion_group!(benches, bench_fanotify, bench_epoll);
+criterion_main!(benches);
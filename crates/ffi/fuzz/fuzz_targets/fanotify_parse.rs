@@ -0,0 +1,15 @@
+//! `cargo fuzz run fanotify_parse` (from crates/ffi)
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use synchron_ffi::fanotify_parse::parse_events;
+
+fuzz_target!(|data: &[u8]| {
+    for event in parse_events(data) {
+        let Ok(event) = event else { break };
+        for info in event.info() {
+            let _ = info.to_info();
+        }
+    }
+});
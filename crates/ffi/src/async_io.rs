@@ -0,0 +1,168 @@
+//! tokio integration: readiness-driven wrappers around `Fanotify` / `Epoll`.
+//! Enabled with the `tokio` feature.
+
+use crate::epoll::{Epoll, ReadyEvent};
+use crate::error::{Errno, Error, Result};
+use crate::fanotify::{Fanotify, FanotifyEvent};
+use crate::raw;
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, RawFd};
+use tokio::io::unix::AsyncFd;
+
+/// Default read buffer; large enough for a few hundred FID events.
+const DEFAULT_BUF_LEN: usize = 64 * 1024;
+
+/// `Fanotify` registered with the tokio reactor, yielding events as a `Stream`.
+///
+/// Events are parsed synchronously inside `poll_next` and queued, so dropping
+/// a pending `next()` future never loses a batch; dropping the stream drops
+/// every queued event and with it the per-event `OwnedFd`s.
+pub struct AsyncFanotify {
+    inner: AsyncFd<FanotifyFd>,
+    buf: Vec<u8>,
+    pending: VecDeque<FanotifyEvent>,
+}
+
+/// `AsRawFd` adapter: `AsyncFd` needs it, `Fanotify` only exposes `AsFd`.
+struct FanotifyFd(Fanotify);
+
+impl AsRawFd for FanotifyFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_fd().as_raw_fd()
+    }
+}
+
+impl AsyncFanotify {
+    /// Wrap a fanotify instance. The fd is switched to O_NONBLOCK if the
+    /// group was created without `FanotifyInitFlags::NONBLOCK`.
+    /// Must be called from within a tokio runtime.
+    pub fn new(fan: Fanotify) -> Result<Self> {
+        Self::with_capacity(fan, DEFAULT_BUF_LEN)
+    }
+
+    pub fn with_capacity(fan: Fanotify, buf_len: usize) -> Result<Self> {
+        raw::set_nonblocking(fan.as_fd())?;
+        let inner = AsyncFd::new(FanotifyFd(fan))?;
+        Ok(Self {
+            inner,
+            buf: vec![0u8; buf_len],
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Access the underlying group, e.g. to add or remove marks.
+    pub fn get_ref(&self) -> &Fanotify {
+        &self.inner.get_ref().0
+    }
+
+    /// Deregister from the reactor and return the group; queued events are dropped.
+    pub fn into_inner(self) -> Fanotify {
+        self.inner.into_inner().0
+    }
+
+    /// Wait for the next batch of events read in one `read(2)`.
+    pub async fn read_batch(&mut self) -> Result<Vec<FanotifyEvent>> {
+        if !self.pending.is_empty() {
+            return Ok(self.pending.drain(..).collect());
+        }
+        loop {
+            let mut guard = self.inner.readable().await?;
+            match guard.try_io(|fd| read_nonblocking(&fd.get_ref().0, &mut self.buf)) {
+                Ok(res) => return res.map_err(Error::from),
+                // EAGAIN：就绪状态已被清掉，继续等下一次
+                Err(_would_block) => continue,
+            }
+        }
+    }
+
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        loop {
+            let mut guard = ready!(self.inner.poll_read_ready(cx))?;
+            match guard.try_io(|fd| read_nonblocking(&fd.get_ref().0, &mut self.buf)) {
+                Ok(Ok(events)) => {
+                    self.pending.extend(events);
+                    return Poll::Ready(Ok(()));
+                }
+                Ok(Err(err)) => return Poll::Ready(Err(err.into())),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+impl Stream for AsyncFanotify {
+    type Item = Result<FanotifyEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        while this.pending.is_empty() {
+            if let Err(err) = ready!(this.poll_fill(cx)) {
+                return Poll::Ready(Some(Err(err)));
+            }
+        }
+        Poll::Ready(this.pending.pop_front().map(Ok))
+    }
+}
+
+/// `read_events` reports EAGAIN as an empty batch; `try_io` needs it as
+/// `WouldBlock` to clear readiness, otherwise we'd spin.
+fn read_nonblocking(fan: &Fanotify, buf: &mut [u8]) -> io::Result<Vec<FanotifyEvent>> {
+    match fan.read_events(buf) {
+        Ok(events) if events.is_empty() => Err(io::ErrorKind::WouldBlock.into()),
+        Ok(events) => Ok(events),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// `Epoll` registered with the tokio reactor: the epoll fd itself becomes
+/// readable when any registered target is ready.
+pub struct AsyncEpoll {
+    inner: AsyncFd<EpollFd>,
+}
+
+struct EpollFd(Epoll);
+
+impl AsRawFd for EpollFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_fd().as_raw_fd()
+    }
+}
+
+impl AsyncEpoll {
+    /// Must be called from within a tokio runtime.
+    pub fn new(epoll: Epoll) -> Result<Self> {
+        let inner = AsyncFd::new(EpollFd(epoll))?;
+        Ok(Self { inner })
+    }
+
+    pub fn get_ref(&self) -> &Epoll {
+        &self.inner.get_ref().0
+    }
+
+    pub fn into_inner(self) -> Epoll {
+        self.inner.into_inner().0
+    }
+
+    /// Wait until at least one registered target is ready.
+    pub async fn wait(&self, max_events: usize) -> Result<Vec<ReadyEvent>> {
+        if max_events == 0 {
+            return Err(Error::from_errno(Errno::EINVAL));
+        }
+        loop {
+            let mut guard = self.inner.readable().await?;
+            let res = guard.try_io(|fd| match fd.get_ref().0.wait(max_events, 0) {
+                Ok(ready) if ready.is_empty() => Err(io::ErrorKind::WouldBlock.into()),
+                Ok(ready) => Ok(ready),
+                Err(err) => Err(err.into()),
+            });
+            match res {
+                Ok(res) => return res.map_err(Error::from),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
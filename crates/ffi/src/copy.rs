@@ -0,0 +1,281 @@
+//! In-kernel file copies.
+//!
+//! [`fast_copy`] tries, in order:
+//! 1. `FICLONE`: reflink, shares extents (btrfs, XFS, bcachefs, ...), O(1);
+//! 2. `copy_file_range(2)`: server-side / in-kernel copy, may still reflink;
+//! 3. `sendfile(2)`: in-kernel, but always moves the bytes;
+//! 4. `pread`/`pwrite`: last resort, so the call never fails just because
+//!    the fast paths are unavailable.
+//!
+//! EXDEV, EOPNOTSUPP, EINVAL (and ENOSYS / ENOTTY from old kernels or
+//! exotic filesystems) move on to the next strategy; anything else is a
+//! real I/O error and is returned.
+//!
+//! Strategies 2-4 copy data extents only (SEEK_DATA / SEEK_HOLE), so holes in
+//! sparse files (VM images, databases) stay holes on the destination.
+
+use crate::error::{retry_eintr, Errno, Result};
+use crate::file::{data_segments, fallocate, ftruncate, lseek, FallocFlags, Whence};
+use crate::flags::fs as fsflag;
+use crate::raw;
+use crate::statx::{fstatx, StatxMask};
+use crate::types::*;
+use std::os::fd::{AsRawFd, BorrowedFd};
+
+/// How a [`fast_copy`] was carried out.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CopyStrategy {
+    Clone,
+    CopyFileRange,
+    Sendfile,
+    ReadWrite,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CopyOutcome {
+    /// Last strategy used; earlier ones were unavailable for this pair of files.
+    pub strategy: CopyStrategy,
+    /// Less than requested only if `src` turned out to be shorter.
+    pub copied: u64,
+}
+
+/// Files at least this large get their data extents preallocated, so ENOSPC
+/// shows up before the copy starts rather than halfway through.
+const PREALLOC_MIN: u64 = 1 << 20;
+
+/// Make the first `len` bytes of `dst` a copy of the first `len` bytes of
+/// `src`, holes included, then truncate `dst` to the amount copied.
+///
+/// `dst` is expected to be a fresh staging file opened for writing; any
+/// previous content is discarded. Offsets of both fds are unspecified
+/// afterwards.
+pub fn fast_copy(src: BorrowedFd<'_>, dst: BorrowedFd<'_>, len: u64) -> Result<CopyOutcome> {
+    let size = fstatx(src, StatxMask::SIZE)?.size;
+
+    // FICLONE 只能克隆整个文件：仅在要拷贝的正好是整个 src 时尝试（空洞随 extent 共享保留）
+    if len > 0 && size == len {
+        match ficlone(src, dst) {
+            Ok(()) => {
+                return Ok(CopyOutcome {
+                    strategy: CopyStrategy::Clone,
+                    copied: len,
+                })
+            }
+            Err(e) if is_fallback(e.errno) || e.errno == Errno::ENOTTY => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    ftruncate(dst, 0)?;
+    let segments = data_segments(src, len).collect::<Result<Vec<_>>>()?;
+    if len >= PREALLOC_MIN {
+        for seg in &segments {
+            match fallocate(dst, FallocFlags::KEEP_SIZE, seg.start, seg.end - seg.start) {
+                Ok(()) => {}
+                // 文件系统不支持预分配：照常拷贝即可
+                Err(e) if matches!(e.errno, Errno::EOPNOTSUPP | Errno::ENOSYS) => break,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // 只拷数据段；段之间留空，最后的 ftruncate 补上尾部空洞
+    let mut strategy = CopyStrategy::ReadWrite;
+    let mut copied = len.min(size);
+    for seg in segments {
+        let (used, n) = copy_range(src, dst, seg.start, seg.end - seg.start)?;
+        strategy = used;
+        if n < seg.end - seg.start {
+            // src 在拷贝过程中变短了
+            copied = seg.start + n;
+            break;
+        }
+    }
+    ftruncate(dst, copied)?;
+    Ok(CopyOutcome { strategy, copied })
+}
+
+/// Copy `len` bytes at `offset` of `src` to the same offset of `dst`,
+/// falling through the strategies. Returns the one that finished the job
+/// and the bytes copied (short only at EOF of `src`).
+pub(crate) fn copy_range(
+    src: BorrowedFd<'_>,
+    dst: BorrowedFd<'_>,
+    offset: u64,
+    len: u64,
+) -> Result<(CopyStrategy, u64)> {
+    let mut done = 0u64;
+    for strategy in [
+        CopyStrategy::CopyFileRange,
+        CopyStrategy::Sendfile,
+        CopyStrategy::ReadWrite,
+    ] {
+        let step = match strategy {
+            CopyStrategy::CopyFileRange => copy_file_range_step,
+            CopyStrategy::Sendfile => sendfile_step,
+            _ => read_write_step,
+        };
+        match pump(src, dst, offset + done, len - done, step) {
+            // 某些文件系统（procfs 等）在开头直接返回 0：换下一种方式再试
+            Ok(0) if done == 0 && strategy != CopyStrategy::ReadWrite => continue,
+            Ok(n) => return Ok((strategy, done + n)),
+            // 已经拷了一部分之后失败：从断点换下一种方式继续
+            Err((n, e)) if is_fallback(e.errno) && strategy != CopyStrategy::ReadWrite => done += n,
+            Err((_, e)) => return Err(e),
+        }
+    }
+    unreachable!("read/write fallback never asks for another strategy")
+}
+
+type Step = fn(BorrowedFd<'_>, BorrowedFd<'_>, u64, usize) -> Result<usize>;
+
+/// Drive one strategy until `len` bytes are copied or `src` hits EOF.
+/// Errors carry the progress made so far.
+fn pump(
+    src: BorrowedFd<'_>,
+    dst: BorrowedFd<'_>,
+    offset: u64,
+    len: u64,
+    step: Step,
+) -> core::result::Result<u64, (u64, crate::Error)> {
+    let mut done = 0u64;
+    while done < len {
+        // 单次调用上限 MAX_RW_COUNT（约 2 GiB），超出部分内核只会做一部分
+        let chunk = (len - done).min(fsflag::MAX_RW_COUNT as u64) as usize;
+        match step(src, dst, offset + done, chunk) {
+            Ok(0) => break,
+            Ok(n) => done += n as u64,
+            Err(e) => return Err((done, e)),
+        }
+    }
+    Ok(done)
+}
+
+fn copy_file_range_step(
+    src: BorrowedFd<'_>,
+    dst: BorrowedFd<'_>,
+    offset: u64,
+    chunk: usize,
+) -> Result<usize> {
+    let mut off_in = offset as off64_t;
+    let mut off_out = offset as off64_t;
+    let n = retry_eintr(|| unsafe {
+        raw::copy_file_range(
+            src.as_raw_fd(),
+            &mut off_in,
+            dst.as_raw_fd(),
+            &mut off_out,
+            chunk,
+            0,
+        )
+    })?;
+    Ok(n as usize)
+}
+
+fn sendfile_step(
+    src: BorrowedFd<'_>,
+    dst: BorrowedFd<'_>,
+    offset: u64,
+    chunk: usize,
+) -> Result<usize> {
+    // sendfile 写到 dst 的当前偏移：先定位
+    lseek(dst, offset as i64, Whence::Set)?;
+    let mut off_in = offset as off64_t;
+    let n = retry_eintr(|| unsafe {
+        raw::sendfile64(dst.as_raw_fd(), src.as_raw_fd(), &mut off_in, chunk)
+    })?;
+    Ok(n as usize)
+}
+
+fn read_write_step(
+    src: BorrowedFd<'_>,
+    dst: BorrowedFd<'_>,
+    offset: u64,
+    chunk: usize,
+) -> Result<usize> {
+    const BUF: usize = 128 * 1024;
+    let mut buf = vec![0u8; chunk.min(BUF)];
+    let n = retry_eintr(|| unsafe {
+        raw::pread64(
+            src.as_raw_fd(),
+            buf.as_mut_ptr() as *mut _,
+            buf.len(),
+            offset as off64_t,
+        )
+    })? as usize;
+
+    let mut written = 0;
+    while written < n {
+        let w = retry_eintr(|| unsafe {
+            raw::pwrite64(
+                dst.as_raw_fd(),
+                buf[written..n].as_ptr() as *const _,
+                n - written,
+                (offset + written as u64) as off64_t,
+            )
+        })?;
+        written += w as usize;
+    }
+    Ok(n)
+}
+
+/// `ioctl(dst, FICLONE, src)`: reflink the whole of `src` into `dst`.
+pub fn ficlone(src: BorrowedFd<'_>, dst: BorrowedFd<'_>) -> Result<()> {
+    retry_eintr(|| unsafe { raw::ioctl(dst.as_raw_fd(), fsflag::FICLONE, src.as_raw_fd()) })?;
+    Ok(())
+}
+
+/// One `copy_file_range(2)` call between explicit offsets (neither file
+/// offset is touched). Returns the bytes copied, possibly fewer than `len`.
+pub fn copy_file_range(
+    src: BorrowedFd<'_>,
+    src_offset: &mut u64,
+    dst: BorrowedFd<'_>,
+    dst_offset: &mut u64,
+    len: usize,
+) -> Result<usize> {
+    let mut off_in = *src_offset as off64_t;
+    let mut off_out = *dst_offset as off64_t;
+    let n = retry_eintr(|| unsafe {
+        raw::copy_file_range(
+            src.as_raw_fd(),
+            &mut off_in,
+            dst.as_raw_fd(),
+            &mut off_out,
+            len.min(fsflag::MAX_RW_COUNT),
+            0,
+        )
+    })?;
+    *src_offset = off_in as u64;
+    *dst_offset = off_out as u64;
+    Ok(n as usize)
+}
+
+/// One `sendfile(2)` call: reads `src` at `*src_offset` (advanced), writes
+/// at `dst`'s current file offset.
+pub fn sendfile(
+    dst: BorrowedFd<'_>,
+    src: BorrowedFd<'_>,
+    src_offset: &mut u64,
+    len: usize,
+) -> Result<usize> {
+    let mut off = *src_offset as off64_t;
+    let n = retry_eintr(|| unsafe {
+        raw::sendfile64(
+            dst.as_raw_fd(),
+            src.as_raw_fd(),
+            &mut off,
+            len.min(fsflag::MAX_RW_COUNT),
+        )
+    })?;
+    *src_offset = off as u64;
+    Ok(n as usize)
+}
+
+#[inline]
+fn is_fallback(errno: Errno) -> bool {
+    matches!(
+        errno,
+        Errno::EXDEV | Errno::EOPNOTSUPP | Errno::EINVAL | Errno::ENOSYS
+    )
+}
@@ -106,80 +106,102 @@ impl Epoll {
     }
 
     /// Wait with millisecond timeout. `timeout_ms = -1` means infinite.
+    ///
+    /// Allocates per call; hot loops should use [`Epoll::wait_into`].
     pub fn wait(&self, max_events: usize, timeout_ms: i32) -> Result<Vec<ReadyEvent>> {
-        let mut buf: Vec<MaybeUninit<EpollEvent>> = Vec::with_capacity(max_events);
-        // Safety: set_len to let kernel write into the slice.
-        unsafe {
-            buf.set_len(max_events);
-        }
+        let mut events = Events::with_capacity(max_events);
+        self.wait_into(&mut events, timeout_ms)?;
+        Ok(events.iter().collect())
+    }
+
+    /// Wait with temporary signal mask.
+    pub fn pwait(
+        &self,
+        max_events: usize,
+        timeout_ms: i32,
+        mask: Option<&SigSet>,
+    ) -> Result<Vec<ReadyEvent>> {
+        let mut events = Events::with_capacity(max_events);
+        self.pwait_into(&mut events, timeout_ms, mask)?;
+        Ok(events.iter().collect())
+    }
+
+    /// Wait with relative timeout as `Duration` (epoll_pwait2, Linux 5.11+;
+    /// ENOSYS on older kernels, see [`crate::probe`]).
+    pub fn pwait2(
+        &self,
+        max_events: usize,
+        timeout: Option<Duration>,
+        mask: Option<&SigSet>,
+    ) -> Result<Vec<ReadyEvent>> {
+        let mut events = Events::with_capacity(max_events);
+        self.pwait2_into(&mut events, timeout, mask)?;
+        Ok(events.iter().collect())
+    }
 
+    /// `wait` into a caller-owned buffer; fills up to `events.capacity()`.
+    /// Returns the number of ready events.
+    pub fn wait_into(&self, events: &mut Events, timeout_ms: i32) -> Result<usize> {
+        events.len = 0;
         let n = retry_eintr(|| unsafe {
             raw::epoll_wait(
                 self.fd.as_raw_fd() as c_int,
-                buf.as_mut_ptr() as *mut EpollEvent,
-                max_events as c_int,
+                events.buf.as_mut_ptr() as *mut EpollEvent,
+                events.buf.len() as c_int,
                 timeout_ms as c_int,
             )
         })?;
-
-        Ok(unsafe { transmute_events(&buf[..n as usize]) })
+        events.len = n as usize;
+        Ok(events.len)
     }
 
-    /// Wait with temporary signal mask.
-    pub fn pwait(
+    /// `pwait` into a caller-owned buffer.
+    pub fn pwait_into(
         &self,
-        max_events: usize,
+        events: &mut Events,
         timeout_ms: i32,
         mask: Option<&SigSet>,
-    ) -> Result<Vec<ReadyEvent>> {
-        let mut buf: Vec<MaybeUninit<EpollEvent>> = Vec::with_capacity(max_events);
-        unsafe {
-            buf.set_len(max_events);
-        }
-
+    ) -> Result<usize> {
+        events.len = 0;
         let n = retry_eintr(|| unsafe {
             raw::epoll_pwait(
                 self.fd.as_raw_fd() as c_int,
-                buf.as_mut_ptr() as *mut EpollEvent,
-                max_events as c_int,
+                events.buf.as_mut_ptr() as *mut EpollEvent,
+                events.buf.len() as c_int,
                 timeout_ms as c_int,
                 mask.map(|m| m as *const _).unwrap_or(core::ptr::null()),
             )
         })?;
-
-        Ok(unsafe { transmute_events(&buf[..n as usize]) })
+        events.len = n as usize;
+        Ok(events.len)
     }
 
-    /// Wait with relative timeout as `Duration` (uses epoll_pwait2 if available).
-    pub fn pwait2(
+    /// `pwait2` into a caller-owned buffer.
+    pub fn pwait2_into(
         &self,
-        max_events: usize,
+        events: &mut Events,
         timeout: Option<Duration>,
         mask: Option<&SigSet>,
-    ) -> Result<Vec<ReadyEvent>> {
-        let mut buf: Vec<MaybeUninit<EpollEvent>> = Vec::with_capacity(max_events);
-        unsafe {
-            buf.set_len(max_events);
-        }
-
-        let ts = timeout.map(|d| TimeSpec {
-            tv_sec: d.as_secs() as i64,
-            tv_nsec: d.subsec_nanos() as i64,
+    ) -> Result<usize> {
+        events.len = 0;
+        let ts = timeout.map(|d| kernel_timespec {
+            tv_sec: d.as_secs() as _,
+            tv_nsec: d.subsec_nanos() as _,
         });
 
         let n = retry_eintr(|| unsafe {
             raw::epoll_pwait2(
                 self.fd.as_raw_fd() as c_int,
-                buf.as_mut_ptr() as *mut EpollEvent,
-                max_events as c_int,
+                events.buf.as_mut_ptr() as *mut EpollEvent,
+                events.buf.len() as c_int,
                 ts.as_ref()
                     .map(|t| t as *const _)
                     .unwrap_or(core::ptr::null()),
                 mask.map(|m| m as *const _).unwrap_or(core::ptr::null()),
             )
         })?;
-
-        Ok(unsafe { transmute_events(&buf[..n as usize]) })
+        events.len = n as usize;
+        Ok(events.len)
     }
 
     /// Internal helper for ADD/MOD.
@@ -217,17 +239,88 @@ impl AsFd for Epoll {
     }
 }
 
-/// SAFETY: Convert the kernel-filled epoll_event array into safe `ReadyEvent`s.
-unsafe fn transmute_events(raws: &[MaybeUninit<EpollEvent>]) -> Vec<ReadyEvent> {
-    let mut out = Vec::with_capacity(raws.len());
-    for e in raws {
-        let ev = e.assume_init_ref();
+/// Caller-owned ready list, reused across `*_into` waits.
+pub struct Events {
+    buf: Vec<MaybeUninit<EpollEvent>>,
+    len: usize,
+}
+
+impl Events {
+    /// Room for `max_events` ready events per wait (must be > 0 for the kernel).
+    pub fn with_capacity(max_events: usize) -> Self {
+        Self {
+            buf: (0..max_events).map(|_| MaybeUninit::uninit()).collect(),
+            len: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Number of events filled in by the last wait.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn get(&self, idx: usize) -> Option<ReadyEvent> {
+        if idx >= self.len {
+            return None;
+        }
+        // SAFETY: the first `len` slots were written by the kernel.
+        let ev = unsafe { self.buf[idx].assume_init_ref() };
         // SAFETY: reading union as u64 (what we wrote) is ok.
         let data = unsafe { ev.data.u64_ };
-        out.push(ReadyEvent {
+        Some(ReadyEvent {
             flags: EpollEventFlags(ev.events),
             data_u64: data,
-        });
+        })
+    }
+
+    pub fn iter(&self) -> EventsIter<'_> {
+        EventsIter {
+            events: self,
+            idx: 0,
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Events {
+    type Item = ReadyEvent;
+    type IntoIter = EventsIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Borrowing iterator over the ready events of the last wait.
+pub struct EventsIter<'a> {
+    events: &'a Events,
+    idx: usize,
+}
+
+impl Iterator for EventsIter<'_> {
+    type Item = ReadyEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ev = self.events.get(self.idx)?;
+        self.idx += 1;
+        Some(ev)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.events.len - self.idx;
+        (left, Some(left))
     }
-    out
 }
+
+impl ExactSizeIterator for EventsIter<'_> {}
@@ -39,8 +39,13 @@ pub enum Errno {
     EPIPE,
     EDOM,
     ERANGE,
+    ENOSYS,
+    ENODATA,
     EPROTO,
     EOVERFLOW,
+    EOPNOTSUPP,
+    ESTALE,
+    ECANCELED,
     Unknown(i32),
 }
 
@@ -82,8 +87,13 @@ impl Errno {
             x if x == e::EPIPE => Errno::EPIPE,
             x if x == e::EDOM => Errno::EDOM,
             x if x == e::ERANGE => Errno::ERANGE,
+            x if x == e::ENOSYS => Errno::ENOSYS,
+            x if x == e::ENODATA => Errno::ENODATA,
             x if x == e::EPROTO => Errno::EPROTO,
             x if x == e::EOVERFLOW => Errno::EOVERFLOW,
+            x if x == e::EOPNOTSUPP => Errno::EOPNOTSUPP,
+            x if x == e::ESTALE => Errno::ESTALE,
+            x if x == e::ECANCELED => Errno::ECANCELED,
             other => Errno::Unknown(other),
         }
     }
@@ -127,17 +137,89 @@ impl Errno {
             EPIPE => e::EPIPE,
             EDOM => e::EDOM,
             ERANGE => e::ERANGE,
+            ENOSYS => e::ENOSYS,
+            ENODATA => e::ENODATA,
             EPROTO => e::EPROTO,
             EOVERFLOW => e::EOVERFLOW,
+            EOPNOTSUPP => e::EOPNOTSUPP,
+            ESTALE => e::ESTALE,
+            ECANCELED => e::ECANCELED,
             Unknown(x) => x,
         }
     }
 }
 
+/// Why a kernel record (fanotify buffer, procfs table) was rejected by its parser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Malformed {
+    /// Fewer bytes left than a `fanotify_event_metadata` header.
+    ShortMetadata { remaining: usize },
+    /// `vers` differs from `FANOTIFY_METADATA_VERSION`.
+    Version { found: u8 },
+    /// `metadata_len` smaller than the header or larger than `event_len`.
+    MetadataLen { metadata_len: u16, event_len: u32 },
+    /// `event_len` runs past the bytes actually read.
+    EventLen { event_len: u32, remaining: usize },
+    /// Info record header truncated, or its `len` runs past the event.
+    InfoLen { len: u16, remaining: usize },
+    /// Info record too short for its declared type.
+    InfoTooShort { info_type: u8, len: u16 },
+    /// `file_handle.handle_bytes` runs past the info record.
+    HandleLen { handle_bytes: u32, room: usize },
+    /// `*_DFID_NAME` record without a NUL-terminated name.
+    UnterminatedName { info_type: u8 },
+    /// `/proc/self/mountinfo` line (1-based) that does not follow proc(5).
+    MountInfo { line: usize },
+}
+
+impl fmt::Display for Malformed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Malformed::*;
+        match *self {
+            ShortMetadata { remaining } => {
+                write!(f, "{remaining} trailing bytes, shorter than event metadata")
+            }
+            Version { found } => write!(f, "unsupported metadata version {found}"),
+            MetadataLen {
+                metadata_len,
+                event_len,
+            } => write!(
+                f,
+                "metadata_len {metadata_len} invalid for event_len {event_len}"
+            ),
+            EventLen {
+                event_len,
+                remaining,
+            } => write!(
+                f,
+                "event_len {event_len} exceeds {remaining} remaining bytes"
+            ),
+            InfoLen { len, remaining } => {
+                write!(f, "info record len {len} invalid, {remaining} bytes left")
+            }
+            InfoTooShort { info_type, len } => {
+                write!(f, "info record type {info_type} too short ({len} bytes)")
+            }
+            HandleLen { handle_bytes, room } => {
+                write!(
+                    f,
+                    "file handle of {handle_bytes} bytes exceeds record ({room} bytes)"
+                )
+            }
+            UnterminatedName { info_type } => {
+                write!(f, "info record type {info_type} has no NUL-terminated name")
+            }
+            MountInfo { line } => write!(f, "mountinfo line {line} does not parse"),
+        }
+    }
+}
+
 /// 统一错误类型：保存 Errno；Display 里带上 errno 文案
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Error {
     pub errno: Errno,
+    /// 解析内核记录失败时的具体原因（此时 errno 为 EPROTO）
+    pub malformed: Option<Malformed>,
 }
 
 impl fmt::Display for Error {
@@ -178,11 +260,19 @@ impl fmt::Display for Error {
             EPIPE => "EPIPE",
             EDOM => "EDOM",
             ERANGE => "ERANGE",
+            ENOSYS => "ENOSYS",
+            ENODATA => "ENODATA",
             EPROTO => "EPROTO",
             EOVERFLOW => "EOVERFLOW",
+            EOPNOTSUPP => "EOPNOTSUPP",
+            ESTALE => "ESTALE",
+            ECANCELED => "ECANCELED",
             Unknown(x) => return write!(f, "Unknown errno {}", x),
         };
-        write!(f, "{}", name)
+        match self.malformed {
+            Some(m) => write!(f, "{}: malformed kernel record: {}", name, m),
+            None => write!(f, "{}", name),
+        }
     }
 }
 impl std::error::Error for Error {}
@@ -190,9 +280,7 @@ impl std::error::Error for Error {}
 impl From<io::Error> for Error {
     fn from(e: io::Error) -> Self {
         let raw = e.raw_os_error().unwrap_or(-1);
-        Error {
-            errno: Errno::from_raw(raw),
-        }
+        Error::from_errno(Errno::from_raw(raw))
     }
 }
 
@@ -200,6 +288,7 @@ impl From<Error> for io::Error {
     fn from(e: Error) -> Self {
         // 尽力还原成一个带 raw_os_error 的 io::Error（Unknown 则用 Other）
         match e.errno {
+            _ if e.malformed.is_some() => io::Error::new(io::ErrorKind::InvalidData, e),
             Errno::Unknown(_) => io::Error::new(io::ErrorKind::Other, e),
             _ => io::Error::from_raw_os_error(e.errno.to_raw()),
         }
@@ -209,22 +298,36 @@ impl From<Error> for io::Error {
 impl Error {
     #[inline]
     pub fn from_errno(errno: Errno) -> Self {
-        Self { errno }
+        Self {
+            errno,
+            malformed: None,
+        }
     }
 
     #[inline]
     pub fn invalid_data() -> Self {
-        Self {
-            errno: Errno::EPROTO,
-        }
+        Self::from_errno(Errno::EPROTO)
     }
 
     #[inline]
     pub fn truncated() -> Self {
+        Self::from_errno(Errno::EOVERFLOW)
+    }
+
+    /// A kernel record failed validation.
+    #[inline]
+    pub fn malformed(why: Malformed) -> Self {
         Self {
-            errno: Errno::EOVERFLOW,
+            errno: Errno::EPROTO,
+            malformed: Some(why),
         }
     }
+
+    /// The object behind a file handle no longer exists.
+    #[inline]
+    pub fn is_stale(&self) -> bool {
+        self.errno == Errno::ESTALE
+    }
 }
 
 /// 统一 Result
@@ -0,0 +1,90 @@
+//! `eventfd(2)`: a counter fd, mostly used to wake an epoll loop from
+//! another thread (shutdown, new work queued).
+
+use crate::error::{retry_eintr, Errno, Result};
+use crate::flags::eventfd as eflag;
+use crate::raw;
+use crate::types::*;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+
+// ---------- Strong-typed flag ----------
+
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct EventFdFlags(pub i32);
+impl EventFdFlags {
+    pub const EMPTY: Self = Self(0);
+    pub const CLOEXEC: Self = Self(eflag::EFD_CLOEXEC);
+    pub const NONBLOCK: Self = Self(eflag::EFD_NONBLOCK);
+    /// each read takes 1 off the counter instead of draining it
+    pub const SEMAPHORE: Self = Self(eflag::EFD_SEMAPHORE);
+}
+impl core::ops::BitOr for EventFdFlags {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+impl core::ops::BitOrAssign for EventFdFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// RAII eventfd. Readable (EPOLLIN) while the counter is non-zero.
+#[derive(Debug)]
+pub struct EventFd {
+    fd: OwnedFd,
+}
+
+impl EventFd {
+    pub fn new(initval: u32, flags: EventFdFlags) -> Result<Self> {
+        let fd = retry_eintr(|| unsafe { raw::eventfd(initval as c_uint, flags.0) })?;
+        // Safety: fresh descriptor owned by us.
+        Ok(Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+        })
+    }
+
+    /// Add `n` to the counter. EAGAIN (non-blocking) if it would overflow.
+    pub fn write(&self, n: u64) -> Result<()> {
+        retry_eintr(|| unsafe {
+            raw::write(
+                self.fd.as_raw_fd() as c_int,
+                &n as *const u64 as *const _,
+                core::mem::size_of::<u64>(),
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Wake whoever is waiting on this fd.
+    #[inline]
+    pub fn notify(&self) -> Result<()> {
+        self.write(1)
+    }
+
+    /// Take the counter (or 1 in SEMAPHORE mode); 0 on EAGAIN.
+    pub fn read(&self) -> Result<u64> {
+        let mut n: u64 = 0;
+        let rc = retry_eintr(|| unsafe {
+            raw::read(
+                self.fd.as_raw_fd() as c_int,
+                &mut n as *mut u64 as *mut _,
+                core::mem::size_of::<u64>(),
+            )
+        });
+        match rc {
+            Ok(_) => Ok(n),
+            // 计数器为 0 且非阻塞
+            Err(e) if e.errno == Errno::EAGAIN => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl AsFd for EventFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
@@ -1,14 +1,17 @@
-use crate::error::{retry_eintr, Errno, Error, Result};
+use crate::error::{retry_eintr, Errno, Result};
+use crate::fanotify_parse::{self, RawEvents};
 use crate::flags::{fanotify as fflag, fcntl as fcntl_flag};
+use crate::handle::{FileHandle, FsId};
 use crate::raw;
 use crate::types::*;
-use std::ffi::CString;
+use std::ffi::{CStr, OsStr, OsString};
 use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::path::Path;
 
 /// ---------- Strong-typed flag ----------
 
 #[repr(transparent)]
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct FanotifyInitFlags(pub u32);
 
 impl FanotifyInitFlags {
@@ -23,9 +26,17 @@ impl FanotifyInitFlags {
     pub const REPORT_FID: Self = Self(fflag::FAN_REPORT_FID);
     pub const REPORT_DIR_FID: Self = Self(fflag::FAN_REPORT_DIR_FID);
     pub const REPORT_TID: Self = Self(fflag::FAN_REPORT_TID);
+    pub const REPORT_NAME: Self = Self(fflag::FAN_REPORT_NAME);
+    pub const REPORT_TARGET_FID: Self = Self(fflag::FAN_REPORT_TARGET_FID);
     pub const REPORT_DFID_NAME: Self = Self(fflag::FAN_REPORT_DFID_NAME);
+    pub const REPORT_DFID_NAME_TARGET: Self = Self(fflag::FAN_REPORT_DFID_NAME_TARGET);
     pub const UNLIMITED_QUEUE: Self = Self(fflag::FAN_UNLIMITED_QUEUE);
     pub const UNLIMITED_MARKS: Self = Self(fflag::FAN_UNLIMITED_MARKS);
+
+    #[inline]
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
 }
 impl core::ops::BitOr for FanotifyInitFlags {
     type Output = Self;
@@ -50,6 +61,9 @@ impl OpenFlags {
     pub const CLOEXEC: Self = Self(fcntl_flag::O_CLOEXEC);
     pub const NONBLOCK: Self = Self(fcntl_flag::O_NONBLOCK);
     pub const LARGEFILE: Self = Self(fcntl_flag::O_LARGEFILE);
+    pub const DIRECTORY: Self = Self(fcntl_flag::O_DIRECTORY);
+    pub const NOFOLLOW: Self = Self(fcntl_flag::O_NOFOLLOW);
+    pub const PATH: Self = Self(fcntl_flag::O_PATH);
 }
 impl core::ops::BitOr for OpenFlags {
     type Output = Self;
@@ -64,7 +78,7 @@ impl core::ops::BitOrAssign for OpenFlags {
 }
 
 #[repr(transparent)]
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct FanotifyMarkFlags(pub u32);
 impl FanotifyMarkFlags {
     pub const EMPTY: Self = Self(0);
@@ -78,6 +92,9 @@ impl FanotifyMarkFlags {
     pub const IGNORED_MASK: Self = Self(fflag::FAN_MARK_IGNORED_MASK);
     pub const IGNORED_SURV_MODIFY: Self = Self(fflag::FAN_MARK_IGNORED_SURV_MODIFY);
     pub const EVICTABLE: Self = Self(fflag::FAN_MARK_EVICTABLE);
+    pub const IGNORE: Self = Self(fflag::FAN_MARK_IGNORE);
+    /// without MOUNT / FILESYSTEM: drop every inode mark of the group
+    pub const FLUSH: Self = Self(fflag::FAN_MARK_FLUSH);
 }
 impl core::ops::BitOr for FanotifyMarkFlags {
     type Output = Self;
@@ -92,7 +109,7 @@ impl core::ops::BitOrAssign for FanotifyMarkFlags {
 }
 
 #[repr(transparent)]
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct FanotifyEventMask(pub u64);
 impl FanotifyEventMask {
     pub const EMPTY: Self = Self(0);
@@ -111,6 +128,7 @@ impl FanotifyEventMask {
     pub const RENAME: Self = Self(fflag::FAN_RENAME);
     pub const EVENT_ON_CHILD: Self = Self(fflag::FAN_EVENT_ON_CHILD);
     pub const ONDIR: Self = Self(fflag::FAN_ONDIR);
+    pub const CLOSE_WRITE: Self = Self(fflag::FAN_CLOSE_WRITE);
     pub const CLOSE: Self = Self(fflag::FAN_CLOSE);
     pub const OPEN_PERM: Self = Self(fflag::FAN_OPEN_PERM);
     pub const ACCESS_PERM: Self = Self(fflag::FAN_ACCESS_PERM);
@@ -129,15 +147,84 @@ impl core::ops::BitOrAssign for FanotifyEventMask {
     }
 }
 
+/// Typed variable-length info record following the event metadata.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FanotifyInfo {
+    /// FAN_EVENT_INFO_TYPE_FID: the object itself (FAN_REPORT_FID / TARGET_FID)
+    Fid(FileHandle),
+    /// FAN_EVENT_INFO_TYPE_DFID: parent directory without name
+    Dfid(FileHandle),
+    /// FAN_EVENT_INFO_TYPE_DFID_NAME: parent directory + entry name
+    DfidName { dir: FileHandle, name: OsString },
+    /// FAN_EVENT_INFO_TYPE_OLD_DFID_NAME: source side of FAN_RENAME
+    OldDfidName { dir: FileHandle, name: OsString },
+    /// FAN_EVENT_INFO_TYPE_NEW_DFID_NAME: target side of FAN_RENAME
+    NewDfidName { dir: FileHandle, name: OsString },
+    /// FAN_EVENT_INFO_TYPE_ERROR (FAN_FS_ERROR)
+    Error { error: i32, error_count: u32 },
+}
+
 pub struct FanotifyEvent {
     pub mask: FanotifyEventMask,
     pub pid: i32,
     /// 事件对象文件句柄；部分事件会给出一个临时 fd（需关闭）
     pub object: Option<OwnedFd>,
+    /// FAN_REPORT_PIDFD 时内核附带的 pidfd（FAN_NOPIDFD / FAN_EPIDFD 时为 None）
+    pub pidfd: Option<OwnedFd>,
+    /// metadata 之后的 info records（FID / DFID_NAME / ...），按内核顺序
+    pub info: Vec<FanotifyInfo>,
     /// 原始 metadata 长度（调试用）
     pub raw_len: u32,
 }
 
+impl FanotifyEvent {
+    /// Events were dropped by the kernel; the caller has to rescan.
+    #[inline]
+    pub fn is_overflow(&self) -> bool {
+        self.mask.0 & FanotifyEventMask::Q_OVERFLOW.0 != 0
+    }
+
+    /// Target object FID (FAN_EVENT_INFO_TYPE_FID).
+    pub fn fid(&self) -> Option<&FileHandle> {
+        self.info.iter().find_map(|i| match i {
+            FanotifyInfo::Fid(fid) => Some(fid),
+            _ => None,
+        })
+    }
+
+    /// Parent directory FID, with or without an entry name.
+    pub fn dir_fid(&self) -> Option<&FileHandle> {
+        self.info.iter().find_map(|i| match i {
+            FanotifyInfo::Dfid(dir) | FanotifyInfo::DfidName { dir, .. } => Some(dir),
+            _ => None,
+        })
+    }
+
+    /// Parent directory FID plus entry name (FAN_EVENT_INFO_TYPE_DFID_NAME).
+    pub fn dir_name(&self) -> Option<(&FileHandle, &OsStr)> {
+        self.info.iter().find_map(|i| match i {
+            FanotifyInfo::DfidName { dir, name } => Some((dir, name.as_os_str())),
+            _ => None,
+        })
+    }
+
+    /// Old location of a FAN_RENAME event.
+    pub fn old_name(&self) -> Option<(&FileHandle, &OsStr)> {
+        self.info.iter().find_map(|i| match i {
+            FanotifyInfo::OldDfidName { dir, name } => Some((dir, name.as_os_str())),
+            _ => None,
+        })
+    }
+
+    /// New location of a FAN_RENAME event.
+    pub fn new_name(&self) -> Option<(&FileHandle, &OsStr)> {
+        self.info.iter().find_map(|i| match i {
+            FanotifyInfo::NewDfidName { dir, name } => Some((dir, name.as_os_str())),
+            _ => None,
+        })
+    }
+}
+
 /// Strong-typed directory fd (for pathname resolution).
 #[repr(transparent)]
 #[derive(Copy, Clone)]
@@ -163,24 +250,27 @@ impl Fanotify {
     }
 
     /// Add/Remove a mark. `pathname=None` for mount/filesystem marks.
+    /// The path is passed to the kernel byte for byte (no UTF-8 requirement).
     pub fn mark(
         &self,
         mark_flags: FanotifyMarkFlags,
         mask: FanotifyEventMask,
         dirfd: DirFd,
-        pathname: Option<&str>,
+        pathname: Option<&Path>,
     ) -> Result<()> {
-        let c_path = pathname
-            .map(|p| {
-                CString::new(p).map_err(|_| Error {
-                    errno: Errno::EINVAL,
-                })
-            })
-            .transpose()?;
-        let ptr = c_path
-            .as_ref()
-            .map(|s| s.as_ptr())
-            .unwrap_or(core::ptr::null());
+        let c_path = pathname.map(raw::path_cstring).transpose()?;
+        self.mark_cstr(mark_flags, mask, dirfd, c_path.as_deref())
+    }
+
+    /// [`Fanotify::mark`] with an already NUL-terminated path.
+    pub fn mark_cstr(
+        &self,
+        mark_flags: FanotifyMarkFlags,
+        mask: FanotifyEventMask,
+        dirfd: DirFd,
+        pathname: Option<&CStr>,
+    ) -> Result<()> {
+        let ptr = pathname.map(|s| s.as_ptr()).unwrap_or(core::ptr::null());
 
         retry_eintr(|| unsafe {
             raw::fanotify_mark(
@@ -198,36 +288,49 @@ impl Fanotify {
     /// 从 fanotify 实例 fd 读取事件并解析。
     /// - `buf`：可写缓冲（如 8KB/64KB）；返回解析出的事件列表（每个自带 RAII fd）。
     /// - EAGAIN 时返回 Ok(vec![])。
+    ///
+    /// Allocates per call; hot loops should use [`Fanotify::read_into`].
     pub fn read_events(&self, buf: &mut [u8]) -> Result<Vec<FanotifyEvent>> {
+        let n = self.read_raw(buf)?;
+        // SAFETY: `buf[..n]` was just filled by the kernel.
+        let events = unsafe { FanotifyEvents::from_kernel(&buf[..n]) };
+        events
+            .map(|ev| ev.map(FanotifyEventRef::into_owned))
+            .collect()
+    }
+
+    /// Read one batch into a reusable [`EventBuffer`]; iterate it with
+    /// [`EventBuffer::events`]. Returns the number of bytes read (0 on EAGAIN).
+    ///
+    /// Events left undrained from the previous read are dropped first so
+    /// their fds are closed.
+    pub fn read_into(&self, buf: &mut EventBuffer) -> Result<usize> {
+        if buf.filled != 0 {
+            drop(buf.events());
+        }
+        let n = self.read_raw(buf.as_bytes_mut())?;
+        buf.filled = n;
+        Ok(n)
+    }
+
+    /// `read(2)` with EAGAIN/0 mapped to `Ok(0)`; EINTR retried.
+    fn read_raw(&self, buf: &mut [u8]) -> Result<usize> {
         // 手动处理 EAGAIN 语义；EINTR 交给 retry_eintr。
-        let rc = unsafe {
+        let rc = retry_eintr(|| unsafe {
             raw::read(
                 self.fd.as_raw_fd() as c_int,
                 buf.as_mut_ptr() as *mut _,
                 buf.len(),
             )
-        };
-
-        if rc < 0 {
-            let ioerr = std::io::Error::last_os_error();
-            // WouldBlock / EAGAIN -> 空集合（保持你原有行为）
-            if ioerr.raw_os_error() == Some(crate::flags::errno::EAGAIN)
-                || ioerr.kind() == std::io::ErrorKind::WouldBlock
-            {
-                return Ok(vec![]);
-            }
-            // 其它错误转成 Error（包含可匹配的 Errno）
-            return Err(Error::from(ioerr));
-        }
+        });
 
-        let n = rc as usize;
-        if n == 0 {
-            // 读到 0：暂无可读事件（极少见），与 EAGAIN 一致地返回空
-            return Ok(vec![]);
+        match rc {
+            // 读到 0：暂无可读事件（极少见），与 EAGAIN 一致
+            Ok(n) => Ok(n as usize),
+            // WouldBlock / EAGAIN -> 空
+            Err(e) if e.errno == Errno::EAGAIN => Ok(0),
+            Err(e) => Err(e),
         }
-
-        // 解析环形缓冲里的多条事件（这里的 parse_* 应改为返回 Result<_, Error>）
-        unsafe { parse_events_from_filled(buf, n) }
     }
 
     pub fn respond_permission(&self, event_fd: &OwnedFd, allow: bool) -> Result<()> {
@@ -253,54 +356,284 @@ impl Fanotify {
     }
 }
 
-unsafe fn parse_events_from_filled(buf: &mut [u8], filled: usize) -> Result<Vec<FanotifyEvent>> {
-    let mut out = Vec::new();
-    let mut off = 0usize;
+impl AsFd for Fanotify {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+// ---------- Zero-allocation read path ----------
+
+/// Reusable, 8-byte aligned read buffer for [`Fanotify::read_into`].
+///
+/// 一次分配、反复读取；`events()` 借出迭代器，事件视图直接引用缓冲内容。
+pub struct EventBuffer {
+    words: Vec<u64>,
+    filled: usize,
+}
+
+impl EventBuffer {
+    /// `bytes` is rounded up to a multiple of 8; 64 KiB is a good default.
+    pub fn with_capacity(bytes: usize) -> Self {
+        Self {
+            words: vec![0u64; bytes.div_ceil(8).max(1)],
+            filled: 0,
+        }
+    }
+
+    /// Build a buffer from already-read record bytes (tests, benchmarks, replay).
+    ///
+    /// # Safety
+    /// Every non-negative fd / pidfd in `bytes` must be an open descriptor the
+    /// caller owns; it is adopted (and eventually closed) by the buffer.
+    pub unsafe fn from_raw(bytes: &[u8]) -> Self {
+        let mut buf = Self::with_capacity(bytes.len());
+        buf.as_bytes_mut()[..bytes.len()].copy_from_slice(bytes);
+        buf.filled = bytes.len();
+        buf
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.words.len() * 8
+    }
+
+    /// Bytes of the last read not yet drained through `events()`.
+    pub fn len(&self) -> usize {
+        self.filled
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filled == 0
+    }
+
+    /// Drain the last read. Each yielded view owns its fds; whatever the
+    /// caller does not consume is closed when the iterator is dropped.
+    pub fn events(&mut self) -> FanotifyEvents<'_> {
+        let filled = core::mem::take(&mut self.filled);
+        let bytes = self.as_bytes();
+        // SAFETY: contents come from `read_into` (kernel) or `from_raw` (caller contract),
+        // and `filled` was reset so the fds can't be adopted twice.
+        unsafe { FanotifyEvents::from_kernel(&bytes[..filled]) }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        // SAFETY: u64 storage reinterpreted as bytes, same allocation.
+        unsafe { core::slice::from_raw_parts(self.words.as_ptr() as *const u8, self.capacity()) }
+    }
 
-    while off + core::mem::size_of::<fanotify_event_metadata>() <= filled {
-        let meta_ptr = buf.as_ptr().add(off) as *const fanotify_event_metadata;
-        let meta = &*meta_ptr;
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        let len = self.capacity();
+        // SAFETY: u64 storage reinterpreted as bytes, same allocation.
+        unsafe { core::slice::from_raw_parts_mut(self.words.as_mut_ptr() as *mut u8, len) }
+    }
+}
 
-        // 基本一致性检查
-        if meta.vers != fflag::FANOTIFY_METADATA_VERSION && fflag::FANOTIFY_METADATA_VERSION != 0 {
-            return Err(crate::error::Error::invalid_data());
+impl Drop for EventBuffer {
+    fn drop(&mut self) {
+        // 未被取走的事件 fd 也要关掉
+        if self.filled != 0 {
+            drop(self.events());
         }
+    }
+}
+
+/// Borrowing iterator over one read's worth of events.
+///
+/// Validation is done by [`fanotify_parse`](crate::fanotify_parse); an fd is
+/// only adopted once its record has been accepted. Iteration stops at the
+/// first malformed record (its fds, and those of anything after it, are left
+/// alone rather than closed on a guess). Dropping the iterator closes the fds
+/// of every valid event that was not yielded.
+pub struct FanotifyEvents<'a> {
+    inner: RawEvents<'a>,
+}
 
-        let evlen = meta.event_len as usize;
-        if evlen == 0 || off + evlen > filled {
-            return Err(crate::error::Error::truncated());
+impl<'a> FanotifyEvents<'a> {
+    /// SAFETY: `bytes` must be kernel-filled (or satisfy `EventBuffer::from_raw`);
+    /// the iterator adopts every fd of every valid record it walks over.
+    unsafe fn from_kernel(bytes: &'a [u8]) -> Self {
+        Self {
+            inner: fanotify_parse::parse_events(bytes),
         }
+    }
+}
 
-        // 取出 mask/pid/fd
-        let mask = FanotifyEventMask(meta.mask);
-        let pid = meta.pid;
-        let raw_fd = meta.fd;
+impl<'a> Iterator for FanotifyEvents<'a> {
+    type Item = Result<FanotifyEventRef<'a>>;
 
-        // 注意：内核把对象 fd 放在 metadata 头里；它是“需要你关闭”的临时 fd。
-        // 用 OwnedFd 接住，交给 RAII。
-        let obj_fd = if raw_fd >= 0 {
-            // SAFETY: 事件拥有这个 fd 的所有权；把它转成 OwnedFd 以便 Drop 关闭。
-            Some(OwnedFd::from_raw_fd(raw_fd))
-        } else {
-            None
+    fn next(&mut self) -> Option<Self::Item> {
+        let raw = match self.inner.next()? {
+            Ok(raw) => raw,
+            Err(err) => return Some(Err(err)),
         };
 
-        out.push(FanotifyEvent {
-            mask,
-            pid,
-            object: obj_fd,
-            raw_len: meta.event_len,
-        });
+        // 注意：内核把对象 fd 放在 metadata 头里；它是“需要你关闭”的临时 fd。
+        // SAFETY: 记录已通过校验，事件拥有这些 fd（from_kernel 的约定）；交给 OwnedFd 以便 Drop 关闭。
+        let object = (raw.fd >= 0).then(|| unsafe { OwnedFd::from_raw_fd(raw.fd) });
+        let pidfd = raw.pidfd.map(|fd| unsafe { OwnedFd::from_raw_fd(fd) });
 
-        // 下一个事件
-        off += evlen;
+        Some(Ok(FanotifyEventRef {
+            mask: raw.mask,
+            pid: raw.pid,
+            object,
+            pidfd,
+            raw_len: raw.event_len,
+            info: raw.info(),
+        }))
     }
+}
 
-    Ok(out)
+impl Drop for FanotifyEvents<'_> {
+    fn drop(&mut self) {
+        // 把剩下的事件走一遍，视图析构时关闭 fd
+        for _ in self.by_ref() {}
+    }
 }
 
-impl AsFd for Fanotify {
-    fn as_fd(&self) -> BorrowedFd<'_> {
-        self.fd.as_fd()
+/// Borrowed view of one event; info records are decoded lazily from the
+/// read buffer. `object` / `pidfd` are owned and closed on drop.
+pub struct FanotifyEventRef<'a> {
+    pub mask: FanotifyEventMask,
+    pub pid: i32,
+    pub object: Option<OwnedFd>,
+    pub pidfd: Option<OwnedFd>,
+    pub raw_len: u32,
+    info: FanotifyInfoIter<'a>,
+}
+
+impl<'a> FanotifyEventRef<'a> {
+    /// Info records in kernel order (PIDFD is surfaced as `pidfd` instead).
+    pub fn info(&self) -> FanotifyInfoIter<'a> {
+        self.info.clone()
+    }
+
+    #[inline]
+    pub fn is_overflow(&self) -> bool {
+        self.mask.0 & FanotifyEventMask::Q_OVERFLOW.0 != 0
+    }
+
+    pub fn fid(&self) -> Option<FileHandleRef<'a>> {
+        self.info().find_map(|i| match i {
+            FanotifyInfoRef::Fid(fid) => Some(fid),
+            _ => None,
+        })
+    }
+
+    pub fn dir_fid(&self) -> Option<FileHandleRef<'a>> {
+        self.info().find_map(|i| match i {
+            FanotifyInfoRef::Dfid(dir) | FanotifyInfoRef::DfidName { dir, .. } => Some(dir),
+            _ => None,
+        })
+    }
+
+    pub fn dir_name(&self) -> Option<(FileHandleRef<'a>, &'a OsStr)> {
+        self.info().find_map(|i| match i {
+            FanotifyInfoRef::DfidName { dir, name } => Some((dir, name)),
+            _ => None,
+        })
+    }
+
+    pub fn old_name(&self) -> Option<(FileHandleRef<'a>, &'a OsStr)> {
+        self.info().find_map(|i| match i {
+            FanotifyInfoRef::OldDfidName { dir, name } => Some((dir, name)),
+            _ => None,
+        })
+    }
+
+    pub fn new_name(&self) -> Option<(FileHandleRef<'a>, &'a OsStr)> {
+        self.info().find_map(|i| match i {
+            FanotifyInfoRef::NewDfidName { dir, name } => Some((dir, name)),
+            _ => None,
+        })
+    }
+
+    /// Copy the view into an owned [`FanotifyEvent`] (allocates).
+    pub fn into_owned(self) -> FanotifyEvent {
+        let info = self.info().map(|i| i.to_info()).collect();
+        FanotifyEvent {
+            mask: self.mask,
+            pid: self.pid,
+            object: self.object,
+            pidfd: self.pidfd,
+            info,
+            raw_len: self.raw_len,
+        }
+    }
+}
+
+/// Borrowed `FileHandle`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FileHandleRef<'a> {
+    pub fsid: FsId,
+    pub handle_type: i32,
+    pub handle: &'a [u8],
+}
+
+impl FileHandleRef<'_> {
+    pub fn to_handle(&self) -> FileHandle {
+        FileHandle {
+            fsid: self.fsid,
+            handle_type: self.handle_type,
+            handle: self.handle.to_vec(),
+        }
+    }
+}
+
+/// Borrowed counterpart of [`FanotifyInfo`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FanotifyInfoRef<'a> {
+    Fid(FileHandleRef<'a>),
+    Dfid(FileHandleRef<'a>),
+    DfidName {
+        dir: FileHandleRef<'a>,
+        name: &'a OsStr,
+    },
+    OldDfidName {
+        dir: FileHandleRef<'a>,
+        name: &'a OsStr,
+    },
+    NewDfidName {
+        dir: FileHandleRef<'a>,
+        name: &'a OsStr,
+    },
+    Error {
+        error: i32,
+        error_count: u32,
+    },
+}
+
+impl FanotifyInfoRef<'_> {
+    pub fn to_info(&self) -> FanotifyInfo {
+        match *self {
+            Self::Fid(fid) => FanotifyInfo::Fid(fid.to_handle()),
+            Self::Dfid(dir) => FanotifyInfo::Dfid(dir.to_handle()),
+            Self::DfidName { dir, name } => FanotifyInfo::DfidName {
+                dir: dir.to_handle(),
+                name: name.to_owned(),
+            },
+            Self::OldDfidName { dir, name } => FanotifyInfo::OldDfidName {
+                dir: dir.to_handle(),
+                name: name.to_owned(),
+            },
+            Self::NewDfidName { dir, name } => FanotifyInfo::NewDfidName {
+                dir: dir.to_handle(),
+                name: name.to_owned(),
+            },
+            Self::Error { error, error_count } => FanotifyInfo::Error { error, error_count },
+        }
+    }
+}
+
+/// Lazily decodes the info records of one (already validated) event.
+#[derive(Clone, Debug)]
+pub struct FanotifyInfoIter<'a> {
+    pub(crate) records: &'a [u8],
+}
+
+impl<'a> Iterator for FanotifyInfoIter<'a> {
+    type Item = FanotifyInfoRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        fanotify_parse::next_info(&mut self.records)
     }
 }
@@ -0,0 +1,294 @@
+//! Bounds-checked decoder for the buffer returned by `read(2)` on a fanotify fd.
+//!
+//! Works on plain `&[u8]` without any pointer casts and never adopts a file
+//! descriptor, so it can be fed arbitrary bytes (fuzzing, property tests).
+//! `FanotifyEvents` layers fd ownership on top of it, for validated records only.
+
+use crate::error::{Malformed, Result};
+use crate::fanotify::{FanotifyEventMask, FanotifyInfoIter, FanotifyInfoRef, FileHandleRef};
+use crate::flags::fanotify as fflag;
+use crate::handle::FsId;
+use crate::types::*;
+use crate::Error;
+use core::mem::size_of;
+use std::ffi::OsStr;
+use std::os::fd::RawFd;
+use std::os::unix::ffi::OsStrExt;
+
+/// `sizeof(struct fanotify_event_metadata)`
+pub const METADATA_LEN: usize = size_of::<fanotify_event_metadata>();
+const INFO_HDR_LEN: usize = size_of::<fanotify_event_info_header>();
+const INFO_FID_LEN: usize = size_of::<fanotify_event_info_fid>();
+const FILE_HANDLE_LEN: usize = size_of::<file_handle>();
+const INFO_PIDFD_LEN: usize = size_of::<fanotify_event_info_pidfd>();
+const INFO_ERROR_LEN: usize = size_of::<fanotify_event_info_error>();
+
+// 下面按偏移量取字段，和 types.rs 里的 #[repr(C)] 布局对齐
+const _: () = {
+    assert!(METADATA_LEN == 24);
+    assert!(INFO_HDR_LEN == 4);
+    assert!(INFO_FID_LEN == 12);
+    assert!(FILE_HANDLE_LEN == 8);
+    assert!(INFO_PIDFD_LEN == 8);
+    assert!(INFO_ERROR_LEN == 12);
+};
+
+/// One validated event record. `fd` / `pidfd` are plain numbers here; nothing
+/// has taken ownership of them.
+#[derive(Clone, Copy, Debug)]
+pub struct RawEvent<'a> {
+    pub mask: FanotifyEventMask,
+    /// FAN_NOFD (-1) in FID mode
+    pub fd: RawFd,
+    pub pid: i32,
+    /// non-negative pidfd from a PIDFD record, if any
+    pub pidfd: Option<RawFd>,
+    pub event_len: u32,
+    records: &'a [u8],
+}
+
+impl<'a> RawEvent<'a> {
+    /// Info records in kernel order (PIDFD excluded).
+    pub fn info(&self) -> FanotifyInfoIter<'a> {
+        FanotifyInfoIter {
+            records: self.records,
+        }
+    }
+}
+
+/// Iterator over the records of one read. Yields `Err` once for the first
+/// malformed record and then stops: nothing after it can be located safely.
+pub struct RawEvents<'a> {
+    bytes: &'a [u8],
+    failed: bool,
+}
+
+/// Decode `bytes` (the filled part of a fanotify read buffer).
+pub fn parse_events(bytes: &[u8]) -> RawEvents<'_> {
+    RawEvents {
+        bytes,
+        failed: false,
+    }
+}
+
+impl<'a> Iterator for RawEvents<'a> {
+    type Item = Result<RawEvent<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.bytes.is_empty() {
+            return None;
+        }
+        match parse_one(self.bytes) {
+            Ok((event, rest)) => {
+                self.bytes = rest;
+                Some(Ok(event))
+            }
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+fn parse_one(bytes: &[u8]) -> Result<(RawEvent<'_>, &[u8])> {
+    if bytes.len() < METADATA_LEN {
+        return Err(Error::malformed(Malformed::ShortMetadata {
+            remaining: bytes.len(),
+        }));
+    }
+
+    let event_len = u32_at(bytes, 0);
+    let vers = bytes[4];
+    let metadata_len = u16_at(bytes, 6);
+
+    if vers != fflag::FANOTIFY_METADATA_VERSION {
+        return Err(Error::malformed(Malformed::Version { found: vers }));
+    }
+    if (metadata_len as usize) < METADATA_LEN || metadata_len as u32 > event_len {
+        return Err(Error::malformed(Malformed::MetadataLen {
+            metadata_len,
+            event_len,
+        }));
+    }
+    if event_len as usize > bytes.len() {
+        return Err(Error::malformed(Malformed::EventLen {
+            event_len,
+            remaining: bytes.len(),
+        }));
+    }
+
+    let (event, rest) = bytes.split_at(event_len as usize);
+    let records = &event[metadata_len as usize..];
+    let pidfd = validate_info_records(records)?;
+
+    Ok((
+        RawEvent {
+            mask: FanotifyEventMask(u64_at(event, 8)),
+            fd: i32_at(event, 16),
+            pid: i32_at(event, 20),
+            pidfd,
+            event_len,
+            records,
+        },
+        rest,
+    ))
+}
+
+/// Pop one info record off `records`: `Ok(None)` once exhausted.
+fn split_info_record<'a>(records: &mut &'a [u8]) -> Result<Option<(u8, &'a [u8])>> {
+    if records.is_empty() {
+        return Ok(None);
+    }
+    if records.len() < INFO_HDR_LEN {
+        return Err(Error::malformed(Malformed::InfoLen {
+            len: 0,
+            remaining: records.len(),
+        }));
+    }
+    let info_type = records[0];
+    let len = u16_at(records, 2);
+    if (len as usize) < INFO_HDR_LEN || len as usize > records.len() {
+        return Err(Error::malformed(Malformed::InfoLen {
+            len,
+            remaining: records.len(),
+        }));
+    }
+    let (rec, rest) = records.split_at(len as usize);
+    *records = rest;
+    Ok(Some((info_type, rec)))
+}
+
+/// Check every info record of one event, so `FanotifyInfoIter` can stay
+/// infallible. Returns the pidfd, if the kernel supplied a usable one.
+fn validate_info_records(mut records: &[u8]) -> Result<Option<RawFd>> {
+    let mut pidfd = None;
+    while let Some((info_type, rec)) = split_info_record(&mut records)? {
+        match info_type {
+            fflag::FAN_EVENT_INFO_TYPE_FID | fflag::FAN_EVENT_INFO_TYPE_DFID => {
+                parse_fid(info_type, rec, false)?;
+            }
+            fflag::FAN_EVENT_INFO_TYPE_DFID_NAME
+            | fflag::FAN_EVENT_INFO_TYPE_OLD_DFID_NAME
+            | fflag::FAN_EVENT_INFO_TYPE_NEW_DFID_NAME => {
+                parse_fid(info_type, rec, true)?;
+            }
+            fflag::FAN_EVENT_INFO_TYPE_PIDFD => {
+                check_len(info_type, rec, INFO_PIDFD_LEN)?;
+                // FAN_NOPIDFD / FAN_EPIDFD 都是负数：进程已退出或取 pidfd 失败
+                let fd = i32_at(rec, 4);
+                if fd >= 0 {
+                    pidfd = Some(fd);
+                }
+            }
+            fflag::FAN_EVENT_INFO_TYPE_ERROR => check_len(info_type, rec, INFO_ERROR_LEN)?,
+            // 未知类型：按 len 跳过，兼容更新的内核
+            _ => {}
+        }
+    }
+    Ok(pidfd)
+}
+
+/// Decode the next info record of an event already checked by
+/// `validate_info_records`; PIDFD and unknown types are skipped.
+pub(crate) fn next_info<'a>(records: &mut &'a [u8]) -> Option<FanotifyInfoRef<'a>> {
+    loop {
+        let (info_type, rec) = split_info_record(records).ok()??;
+        let item = match info_type {
+            fflag::FAN_EVENT_INFO_TYPE_FID => {
+                FanotifyInfoRef::Fid(parse_fid(info_type, rec, false).ok()?.0)
+            }
+            fflag::FAN_EVENT_INFO_TYPE_DFID => {
+                FanotifyInfoRef::Dfid(parse_fid(info_type, rec, false).ok()?.0)
+            }
+            fflag::FAN_EVENT_INFO_TYPE_DFID_NAME => {
+                let (dir, name) = parse_fid(info_type, rec, true).ok()?;
+                FanotifyInfoRef::DfidName { dir, name }
+            }
+            fflag::FAN_EVENT_INFO_TYPE_OLD_DFID_NAME => {
+                let (dir, name) = parse_fid(info_type, rec, true).ok()?;
+                FanotifyInfoRef::OldDfidName { dir, name }
+            }
+            fflag::FAN_EVENT_INFO_TYPE_NEW_DFID_NAME => {
+                let (dir, name) = parse_fid(info_type, rec, true).ok()?;
+                FanotifyInfoRef::NewDfidName { dir, name }
+            }
+            fflag::FAN_EVENT_INFO_TYPE_ERROR if rec.len() >= INFO_ERROR_LEN => {
+                FanotifyInfoRef::Error {
+                    error: i32_at(rec, 4),
+                    error_count: u32_at(rec, 8),
+                }
+            }
+            _ => continue,
+        };
+        return Some(item);
+    }
+}
+
+/// FID-style record: fsid, `struct file_handle`, then for the *_NAME types a
+/// NUL-terminated name (followed by alignment padding).
+fn parse_fid(info_type: u8, rec: &[u8], named: bool) -> Result<(FileHandleRef<'_>, &OsStr)> {
+    check_len(info_type, rec, INFO_FID_LEN + FILE_HANDLE_LEN)?;
+
+    let handle_bytes = u32_at(rec, INFO_FID_LEN);
+    let start = INFO_FID_LEN + FILE_HANDLE_LEN;
+    let room = rec.len() - start;
+    if handle_bytes as usize > room {
+        return Err(Error::malformed(Malformed::HandleLen {
+            handle_bytes,
+            room,
+        }));
+    }
+    let end = start + handle_bytes as usize;
+
+    let name = if named {
+        let tail = &rec[end..];
+        let nul = tail
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(Error::malformed(Malformed::UnterminatedName { info_type }))?;
+        &tail[..nul]
+    } else {
+        &[][..]
+    };
+
+    Ok((
+        FileHandleRef {
+            fsid: FsId([i32_at(rec, 4), i32_at(rec, 8)]),
+            handle_type: i32_at(rec, INFO_FID_LEN + 4),
+            handle: &rec[start..end],
+        },
+        OsStr::from_bytes(name),
+    ))
+}
+
+fn check_len(info_type: u8, rec: &[u8], need: usize) -> Result<()> {
+    if rec.len() < need {
+        return Err(Error::malformed(Malformed::InfoTooShort {
+            info_type,
+            len: rec.len() as u16,
+        }));
+    }
+    Ok(())
+}
+
+// 调用方保证 off + N <= buf.len()
+#[inline]
+fn u16_at(buf: &[u8], off: usize) -> u16 {
+    u16::from_ne_bytes([buf[off], buf[off + 1]])
+}
+
+#[inline]
+fn u32_at(buf: &[u8], off: usize) -> u32 {
+    u32::from_ne_bytes(buf[off..off + 4].try_into().unwrap())
+}
+
+#[inline]
+fn i32_at(buf: &[u8], off: usize) -> i32 {
+    i32::from_ne_bytes(buf[off..off + 4].try_into().unwrap())
+}
+
+#[inline]
+fn u64_at(buf: &[u8], off: usize) -> u64 {
+    u64::from_ne_bytes(buf[off..off + 8].try_into().unwrap())
+}
@@ -0,0 +1,150 @@
+//! Plain file-descriptor operations: seeking (including SEEK_DATA /
+//! SEEK_HOLE for sparse files), truncation and `fallocate(2)`.
+
+use crate::error::{retry_eintr, Errno, Result};
+use crate::flags::fs as fsflag;
+use crate::raw;
+use crate::types::*;
+use core::ops::Range;
+use std::os::fd::{AsRawFd, BorrowedFd};
+
+/// `lseek(2)` origin.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Whence {
+    Set,
+    Cur,
+    End,
+    /// next offset >= `offset` that holds data (ENXIO past the last extent)
+    Data,
+    /// next hole at or after `offset`; EOF counts as a hole
+    Hole,
+}
+
+impl Whence {
+    fn to_raw(self) -> c_int {
+        match self {
+            Whence::Set => fsflag::SEEK_SET,
+            Whence::Cur => fsflag::SEEK_CUR,
+            Whence::End => fsflag::SEEK_END,
+            Whence::Data => fsflag::SEEK_DATA,
+            Whence::Hole => fsflag::SEEK_HOLE,
+        }
+    }
+}
+
+// ---------- Strong-typed flag ----------
+
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct FallocFlags(pub i32);
+impl FallocFlags {
+    /// allocate and extend the file size if needed
+    pub const EMPTY: Self = Self(0);
+    pub const KEEP_SIZE: Self = Self(fsflag::FALLOC_FL_KEEP_SIZE);
+    /// deallocate the range; must be combined with KEEP_SIZE
+    pub const PUNCH_HOLE: Self = Self(fsflag::FALLOC_FL_PUNCH_HOLE);
+    pub const COLLAPSE_RANGE: Self = Self(fsflag::FALLOC_FL_COLLAPSE_RANGE);
+    pub const ZERO_RANGE: Self = Self(fsflag::FALLOC_FL_ZERO_RANGE);
+    pub const INSERT_RANGE: Self = Self(fsflag::FALLOC_FL_INSERT_RANGE);
+    pub const UNSHARE_RANGE: Self = Self(fsflag::FALLOC_FL_UNSHARE_RANGE);
+}
+impl core::ops::BitOr for FallocFlags {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// `lseek(2)`; returns the resulting offset.
+pub fn lseek(fd: BorrowedFd<'_>, offset: i64, whence: Whence) -> Result<u64> {
+    // lseek64 返回 off64_t（i64），不在 retry_eintr 的 RetVal 里：手动判断
+    let rc = unsafe { raw::lseek64(fd.as_raw_fd(), offset, whence.to_raw()) };
+    if rc < 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(rc as u64)
+}
+
+/// `ftruncate(2)`: growing the file leaves a hole.
+pub fn ftruncate(fd: BorrowedFd<'_>, len: u64) -> Result<()> {
+    retry_eintr(|| unsafe { raw::ftruncate64(fd.as_raw_fd(), len as off64_t) })?;
+    Ok(())
+}
+
+/// `fallocate(2)`. ENOSPC here means the space is not there, before any
+/// data was written. EOPNOTSUPP if the filesystem cannot do `mode`.
+pub fn fallocate(fd: BorrowedFd<'_>, mode: FallocFlags, offset: u64, len: u64) -> Result<()> {
+    retry_eintr(|| unsafe {
+        raw::fallocate64(fd.as_raw_fd(), mode.0, offset as off64_t, len as off64_t)
+    })?;
+    Ok(())
+}
+
+/// Deallocate `offset..offset+len` without changing the file size.
+pub fn punch_hole(fd: BorrowedFd<'_>, offset: u64, len: u64) -> Result<()> {
+    fallocate(
+        fd,
+        FallocFlags::PUNCH_HOLE | FallocFlags::KEEP_SIZE,
+        offset,
+        len,
+    )
+}
+
+/// Data extents of the first `len` bytes of `fd`, via SEEK_DATA / SEEK_HOLE.
+///
+/// Filesystems without hole reporting (EINVAL / EOPNOTSUPP) yield the whole
+/// range as one extent. Moves the file offset.
+pub fn data_segments(fd: BorrowedFd<'_>, len: u64) -> DataSegments<'_> {
+    DataSegments {
+        fd,
+        pos: 0,
+        end: len,
+    }
+}
+
+pub struct DataSegments<'a> {
+    fd: BorrowedFd<'a>,
+    pos: u64,
+    end: u64,
+}
+
+impl Iterator for DataSegments<'_> {
+    type Item = Result<Range<u64>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.end {
+            return None;
+        }
+        let start = match lseek(self.fd, self.pos as i64, Whence::Data) {
+            Ok(off) => off,
+            // 之后全是空洞
+            Err(e) if e.errno == Errno::ENXIO => {
+                self.pos = self.end;
+                return None;
+            }
+            // 不支持 SEEK_DATA：把剩余部分当作一整段数据
+            Err(e) if matches!(e.errno, Errno::EINVAL | Errno::EOPNOTSUPP) => {
+                let seg = self.pos..self.end;
+                self.pos = self.end;
+                return Some(Ok(seg));
+            }
+            Err(e) => {
+                self.pos = self.end;
+                return Some(Err(e));
+            }
+        };
+        if start >= self.end {
+            self.pos = self.end;
+            return None;
+        }
+        let hole = match lseek(self.fd, start as i64, Whence::Hole) {
+            Ok(off) => off.min(self.end),
+            Err(e) => {
+                self.pos = self.end;
+                return Some(Err(e));
+            }
+        };
+        self.pos = hole;
+        Some(Ok(start..hole))
+    }
+}
@@ -0,0 +1,29 @@
+#![allow(dead_code)]
+// See: include/uapi/linux/capability.h, include/uapi/linux/prctl.h
+
+pub const _LINUX_CAPABILITY_VERSION_3: u32 = 0x20080522;
+pub const _LINUX_CAPABILITY_U32S_3: usize = 2;
+
+pub const CAP_CHOWN: u32 = 0;
+pub const CAP_DAC_OVERRIDE: u32 = 1;
+pub const CAP_DAC_READ_SEARCH: u32 = 2;
+pub const CAP_FOWNER: u32 = 3;
+pub const CAP_FSETID: u32 = 4;
+pub const CAP_KILL: u32 = 5;
+pub const CAP_SETGID: u32 = 6;
+pub const CAP_SETUID: u32 = 7;
+pub const CAP_SETPCAP: u32 = 8;
+pub const CAP_LINUX_IMMUTABLE: u32 = 9;
+pub const CAP_SYS_ADMIN: u32 = 21;
+pub const CAP_SYS_RESOURCE: u32 = 24;
+pub const CAP_MKNOD: u32 = 27;
+pub const CAP_AUDIT_CONTROL: u32 = 30;
+pub const CAP_LAST_CAP: u32 = 40; // CAP_CHECKPOINT_RESTORE (5.9)
+
+pub const PR_CAPBSET_READ: i32 = 23;
+pub const PR_CAPBSET_DROP: i32 = 24;
+pub const PR_GET_KEEPCAPS: i32 = 7;
+pub const PR_SET_KEEPCAPS: i32 = 8;
+pub const PR_SET_NO_NEW_PRIVS: i32 = 38;
+pub const PR_CAP_AMBIENT: i32 = 47;
+pub const PR_CAP_AMBIENT_CLEAR_ALL: i32 = 4;
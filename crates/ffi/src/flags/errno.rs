@@ -34,5 +34,10 @@ pub const EDOM: i32 = 33; // Math argument out of domain of func
 pub const ERANGE: i32 = 34; // Math result not representable
 
 /// self implemented
+pub const ENOSYS: i32 = 38; // Function not implemented
+pub const ENODATA: i32 = 61; // No data available
 pub const EPROTO: i32 = 71; // Protocol error
 pub const EOVERFLOW: i32 = 75; // Value too large for defined data type
+pub const EOPNOTSUPP: i32 = 95; // Operation not supported
+pub const ESTALE: i32 = 116; // Stale file handle
+pub const ECANCELED: i32 = 125; // Operation Canceled
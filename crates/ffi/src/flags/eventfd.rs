@@ -0,0 +1,6 @@
+#![allow(dead_code)]
+// See: include/uapi/linux/eventfd.h
+
+pub const EFD_SEMAPHORE: i32 = 0o0000001;
+pub const EFD_CLOEXEC: i32 = 0o2000000; // O_CLOEXEC
+pub const EFD_NONBLOCK: i32 = 0o0004000; // O_NONBLOCK
@@ -77,3 +77,7 @@ pub const AT_STATX_FORCE_SYNC: i32 = 0x2000;
 pub const AT_STATX_DONT_SYNC: i32 = 0x4000;
 /// Apply operation to the entire subtree (e.g. renameat2)
 pub const AT_RECURSIVE: i32 = 0x8000;
+
+/// fcntl commands
+pub const F_GETFL: i32 = 3;
+pub const F_SETFL: i32 = 4;
@@ -0,0 +1,46 @@
+#![allow(dead_code)]
+// See: include/uapi/linux/fs.h, include/uapi/linux/falloc.h, unistd.h
+
+use crate::types::c_ulong;
+
+/// `_IOW(0x94, 9, int)`: reflink a whole file
+#[cfg(not(any(
+    target_arch = "powerpc",
+    target_arch = "powerpc64",
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "sparc64"
+)))]
+pub const FICLONE: c_ulong = 0x4004_9409;
+// 这些架构上 _IOC_WRITE 是 4 而不是 1
+#[cfg(any(
+    target_arch = "powerpc",
+    target_arch = "powerpc64",
+    target_arch = "mips",
+    target_arch = "mips64",
+    target_arch = "sparc64"
+))]
+pub const FICLONE: c_ulong = 0x8004_9409;
+
+/// MAX_RW_COUNT: one read/write/copy call moves at most INT_MAX & PAGE_MASK bytes
+pub const MAX_RW_COUNT: usize = 0x7fff_f000;
+
+// lseek whence
+pub const SEEK_SET: i32 = 0;
+pub const SEEK_CUR: i32 = 1;
+pub const SEEK_END: i32 = 2;
+pub const SEEK_DATA: i32 = 3;
+pub const SEEK_HOLE: i32 = 4;
+
+// fallocate modes
+pub const FALLOC_FL_KEEP_SIZE: i32 = 0x01;
+pub const FALLOC_FL_PUNCH_HOLE: i32 = 0x02;
+pub const FALLOC_FL_COLLAPSE_RANGE: i32 = 0x08;
+pub const FALLOC_FL_ZERO_RANGE: i32 = 0x10;
+pub const FALLOC_FL_INSERT_RANGE: i32 = 0x20;
+pub const FALLOC_FL_UNSHARE_RANGE: i32 = 0x40;
+
+// renameat2 flags
+pub const RENAME_NOREPLACE: u32 = 1 << 0;
+pub const RENAME_EXCHANGE: u32 = 1 << 1;
+pub const RENAME_WHITEOUT: u32 = 1 << 2;
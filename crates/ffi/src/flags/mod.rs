@@ -1,4 +1,13 @@
+pub mod capability;
 pub mod epoll;
 pub mod errno;
+pub mod eventfd;
 pub mod fanotify;
 pub mod fcntl;
+pub mod fs;
+pub mod inotify;
+pub mod signal;
+pub mod statx;
+pub mod sys;
+pub mod timerfd;
+pub mod xattr;
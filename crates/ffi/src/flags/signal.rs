@@ -0,0 +1,26 @@
+#![allow(dead_code)]
+// See: include/uapi/asm-generic/signal.h, include/uapi/linux/signalfd.h
+// 信号编号按 x86 / arm / generic；alpha、mips、sparc、parisc 不同
+
+pub const SIGHUP: i32 = 1;
+pub const SIGINT: i32 = 2;
+pub const SIGQUIT: i32 = 3;
+pub const SIGUSR1: i32 = 10;
+pub const SIGUSR2: i32 = 12;
+pub const SIGPIPE: i32 = 13;
+pub const SIGALRM: i32 = 14;
+pub const SIGTERM: i32 = 15;
+pub const SIGCHLD: i32 = 17;
+pub const SIGWINCH: i32 = 28;
+
+/// highest signal number + 1
+pub const NSIG: i32 = 65;
+
+// pthread_sigmask() how
+pub const SIG_BLOCK: i32 = 0;
+pub const SIG_UNBLOCK: i32 = 1;
+pub const SIG_SETMASK: i32 = 2;
+
+// signalfd() flags
+pub const SFD_CLOEXEC: i32 = 0o2000000; // O_CLOEXEC
+pub const SFD_NONBLOCK: i32 = 0o0004000; // O_NONBLOCK
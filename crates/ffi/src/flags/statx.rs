@@ -0,0 +1,39 @@
+#![allow(dead_code)]
+// See: include/uapi/linux/stat.h
+
+// ===========================
+// stx_mask bits (u32)
+// ===========================
+pub const STATX_TYPE: u32 = 0x0000_0001;
+pub const STATX_MODE: u32 = 0x0000_0002;
+pub const STATX_NLINK: u32 = 0x0000_0004;
+pub const STATX_UID: u32 = 0x0000_0008;
+pub const STATX_GID: u32 = 0x0000_0010;
+pub const STATX_ATIME: u32 = 0x0000_0020;
+pub const STATX_MTIME: u32 = 0x0000_0040;
+pub const STATX_CTIME: u32 = 0x0000_0080;
+pub const STATX_INO: u32 = 0x0000_0100;
+pub const STATX_SIZE: u32 = 0x0000_0200;
+pub const STATX_BLOCKS: u32 = 0x0000_0400;
+/// everything `stat(2)` returns
+pub const STATX_BASIC_STATS: u32 = 0x0000_07ff;
+pub const STATX_BTIME: u32 = 0x0000_0800;
+/// 5.8
+pub const STATX_MNT_ID: u32 = 0x0000_1000;
+/// 6.1
+pub const STATX_DIOALIGN: u32 = 0x0000_2000;
+/// 6.8, never reused (unlike STATX_MNT_ID)
+pub const STATX_MNT_ID_UNIQUE: u32 = 0x0000_4000;
+
+// ===========================
+// stx_attributes bits (u64)
+// ===========================
+pub const STATX_ATTR_COMPRESSED: u64 = 0x0000_0004;
+pub const STATX_ATTR_IMMUTABLE: u64 = 0x0000_0010;
+pub const STATX_ATTR_APPEND: u64 = 0x0000_0020;
+pub const STATX_ATTR_NODUMP: u64 = 0x0000_0040;
+pub const STATX_ATTR_ENCRYPTED: u64 = 0x0000_0800;
+pub const STATX_ATTR_AUTOMOUNT: u64 = 0x0000_1000;
+pub const STATX_ATTR_MOUNT_ROOT: u64 = 0x0000_2000;
+pub const STATX_ATTR_VERITY: u64 = 0x0010_0000;
+pub const STATX_ATTR_DAX: u64 = 0x0020_0000;
@@ -0,0 +1,10 @@
+//! Syscall numbers for calls that older glibc does not wrap.
+//! New syscalls share one number across architectures since 5.x (alpha excepted).
+
+use crate::types::c_long;
+
+pub const SYS_PIDFD_OPEN: c_long = 434;
+pub const SYS_EPOLL_PWAIT2: c_long = 441;
+
+/// `sizeof(kernel sigset_t)` (_NSIG / 8), not glibc's 128-byte sigset_t.
+pub const KERNEL_SIGSET_SIZE: usize = 8;
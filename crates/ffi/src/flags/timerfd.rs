@@ -0,0 +1,17 @@
+#![allow(dead_code)]
+// See: include/uapi/linux/timerfd.h, include/uapi/linux/time.h
+
+// timerfd_create() flags
+pub const TFD_CLOEXEC: i32 = 0o2000000; // O_CLOEXEC
+pub const TFD_NONBLOCK: i32 = 0o0004000; // O_NONBLOCK
+
+// timerfd_settime() flags
+pub const TFD_TIMER_ABSTIME: i32 = 1 << 0;
+pub const TFD_TIMER_CANCEL_ON_SET: i32 = 1 << 1;
+
+// clockid_t
+pub const CLOCK_REALTIME: i32 = 0;
+pub const CLOCK_MONOTONIC: i32 = 1;
+pub const CLOCK_BOOTTIME: i32 = 7;
+pub const CLOCK_REALTIME_ALARM: i32 = 8;
+pub const CLOCK_BOOTTIME_ALARM: i32 = 9;
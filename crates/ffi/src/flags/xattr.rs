@@ -0,0 +1,14 @@
+#![allow(dead_code)]
+// See: include/uapi/linux/xattr.h
+
+pub const XATTR_CREATE: i32 = 0x1;
+pub const XATTR_REPLACE: i32 = 0x2;
+
+pub const XATTR_USER_PREFIX: &[u8] = b"user.";
+pub const XATTR_TRUSTED_PREFIX: &[u8] = b"trusted.";
+pub const XATTR_SECURITY_PREFIX: &[u8] = b"security.";
+pub const XATTR_SYSTEM_PREFIX: &[u8] = b"system.";
+
+pub const XATTR_NAME_POSIX_ACL_ACCESS: &[u8] = b"system.posix_acl_access";
+pub const XATTR_NAME_POSIX_ACL_DEFAULT: &[u8] = b"system.posix_acl_default";
+pub const XATTR_NAME_SELINUX: &[u8] = b"security.selinux";
@@ -0,0 +1,204 @@
+use crate::error::{retry_eintr, Errno, Error, Result};
+use crate::fanotify::{DirFd, OpenFlags};
+use crate::flags::fcntl as fcntl_flag;
+use crate::raw;
+use crate::types::*;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// MAX_HANDLE_SZ from include/linux/exportfs.h
+const MAX_HANDLE_SZ: usize = 128;
+
+/// Filesystem id reported alongside a file handle (`__kernel_fsid_t`).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FsId(pub [i32; 2]);
+
+impl FsId {
+    /// fsid of the filesystem backing `fd` (`fstatfs(2)`).
+    pub fn of(fd: impl AsFd) -> Result<Self> {
+        let mut st = core::mem::MaybeUninit::<statfs64>::zeroed();
+        retry_eintr(|| unsafe { raw::fstatfs64(fd.as_fd().as_raw_fd(), st.as_mut_ptr()) })?;
+        // SAFETY: fstatfs64 succeeded and filled the struct.
+        let st = unsafe { st.assume_init() };
+        Ok(Self(st.f_fsid.val))
+    }
+}
+
+/// Owned `struct file_handle` plus the fsid of the filesystem it belongs to.
+///
+/// Produced by fanotify FID records or by [`FileHandle::at`]; turned back into
+/// a path through a [`MountCache`].
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct FileHandle {
+    pub fsid: FsId,
+    pub handle_type: i32,
+    /// `f_handle` bytes (length is `handle_bytes`)
+    pub handle: Vec<u8>,
+}
+
+impl FileHandle {
+    /// `name_to_handle_at(2)` for `path` relative to `dirfd`.
+    /// Returns the handle and the mount id the kernel reported for it.
+    pub fn at(dirfd: DirFd, path: &Path, follow: bool) -> Result<(Self, i32)> {
+        // 只解析一次路径：句柄和 fsid 都取自同一个 O_PATH fd，中途被替换也不会错配
+        let fd = open_path(dirfd, path, follow)?;
+        let mut size = MAX_HANDLE_SZ;
+        loop {
+            let mut buf = HandleBuf::new(size, 0, &[]);
+            let mut mount_id: c_int = 0;
+            let rc = retry_eintr(|| unsafe {
+                raw::name_to_handle_at(
+                    fd.as_raw_fd(),
+                    c"".as_ptr(),
+                    buf.as_mut_ptr(),
+                    &mut mount_id,
+                    fcntl_flag::AT_EMPTY_PATH,
+                )
+            });
+            match rc {
+                Ok(_) => {
+                    let (handle_type, bytes) = buf.parts();
+                    let handle = Self {
+                        fsid: FsId::of(&fd)?,
+                        handle_type,
+                        handle: bytes.to_vec(),
+                    };
+                    return Ok((handle, mount_id));
+                }
+                // 缓冲不够：内核在 handle_bytes 里回填所需大小
+                Err(err) if err.errno == Errno::EOVERFLOW && buf.wanted() > size => {
+                    size = buf.wanted();
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// `open_by_handle_at(2)` against a mount fd on the same filesystem.
+    /// Needs CAP_DAC_READ_SEARCH; a vanished object yields `ESTALE`.
+    pub fn open(&self, mount_fd: BorrowedFd<'_>, flags: OpenFlags) -> Result<OwnedFd> {
+        let mut buf = HandleBuf::new(self.handle.len(), self.handle_type, &self.handle);
+        let fd = retry_eintr(|| unsafe {
+            raw::open_by_handle_at(mount_fd.as_raw_fd(), buf.as_mut_ptr(), flags.0 as c_int)
+        })?;
+        // Safety: fresh descriptor owned by us.
+        Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+    }
+}
+
+/// Mount fds keyed by fsid, used as the `mount_fd` argument of
+/// `open_by_handle_at`. One entry per filesystem is enough.
+#[derive(Default)]
+pub struct MountCache {
+    mounts: HashMap<FsId, OwnedFd>,
+}
+
+impl MountCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the filesystem containing `path` (typically a pair root).
+    /// Returns its fsid; registering the same filesystem twice is a no-op.
+    pub fn register(&mut self, path: &Path) -> Result<FsId> {
+        // open_by_handle_at 不接受 O_PATH 的 mount_fd，这里用真正打开的目录
+        let c_path = raw::path_cstring(path)?;
+        let flags = OpenFlags::RDONLY | OpenFlags::DIRECTORY | OpenFlags::CLOEXEC;
+        let fd = retry_eintr(|| unsafe { raw::openat(DirFd::CWD.0, c_path.as_ptr(), flags.0) })?;
+        // Safety: fresh descriptor owned by us.
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        let fsid = FsId::of(&fd)?;
+        self.mounts.entry(fsid).or_insert(fd);
+        Ok(fsid)
+    }
+
+    pub fn get(&self, fsid: &FsId) -> Option<BorrowedFd<'_>> {
+        self.mounts.get(fsid).map(|fd| fd.as_fd())
+    }
+
+    pub fn remove(&mut self, fsid: &FsId) -> Option<OwnedFd> {
+        self.mounts.remove(fsid)
+    }
+
+    /// Resolve a handle to the absolute path of the object it names.
+    ///
+    /// - unknown fsid -> `ENODEV`
+    /// - object deleted (or handle expired) -> `ESTALE`
+    pub fn resolve(&self, handle: &FileHandle) -> Result<PathBuf> {
+        let mount_fd = self
+            .get(&handle.fsid)
+            .ok_or_else(|| Error::from_errno(Errno::ENODEV))?;
+        let fd = handle.open(mount_fd, OpenFlags::PATH | OpenFlags::CLOEXEC)?;
+
+        let link = PathBuf::from(format!("/proc/self/fd/{}", fd.as_raw_fd()));
+        // 已删除但仍被引用的对象 open 也能成功，readlink 会带 " (deleted)"；按 nlink 判定
+        let meta = std::fs::metadata(&link)?;
+        if meta.nlink() == 0 {
+            return Err(Error::from_errno(Errno::ESTALE));
+        }
+        Ok(std::fs::read_link(&link)?)
+    }
+
+    /// Resolve a DFID_NAME pair: the directory handle plus the entry name.
+    /// The kernel reports `.` for events on the directory itself.
+    pub fn resolve_name(&self, dir: &FileHandle, name: &OsStr) -> Result<PathBuf> {
+        let dir_path = self.resolve(dir)?;
+        if name.is_empty() || name == "." {
+            return Ok(dir_path);
+        }
+        Ok(dir_path.join(name))
+    }
+}
+
+/// 4-byte aligned scratch buffer laid out as `struct file_handle`.
+struct HandleBuf {
+    words: Vec<u32>,
+}
+
+impl HandleBuf {
+    fn new(capacity: usize, handle_type: i32, bytes: &[u8]) -> Self {
+        let hdr = core::mem::size_of::<file_handle>();
+        let mut words = vec![0u32; (hdr + capacity).div_ceil(4)];
+        words[0] = capacity as u32;
+        words[1] = handle_type as u32;
+        // SAFETY: `words` spans at least hdr + capacity bytes, bytes.len() <= capacity.
+        unsafe {
+            let dst = (words.as_mut_ptr() as *mut u8).add(hdr);
+            core::ptr::copy_nonoverlapping(bytes.as_ptr(), dst, bytes.len());
+        }
+        Self { words }
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut file_handle {
+        self.words.as_mut_ptr() as *mut file_handle
+    }
+
+    fn wanted(&self) -> usize {
+        self.words[0] as usize
+    }
+
+    fn parts(&self) -> (i32, &[u8]) {
+        let hdr = core::mem::size_of::<file_handle>();
+        let len = (self.words[0] as usize).min(self.words.len() * 4 - hdr);
+        // SAFETY: range checked against the backing allocation.
+        let bytes = unsafe {
+            core::slice::from_raw_parts((self.words.as_ptr() as *const u8).add(hdr), len)
+        };
+        (self.words[1] as i32, bytes)
+    }
+}
+
+/// O_PATH open the handle and its fsid are both taken from.
+fn open_path(dirfd: DirFd, path: &Path, follow: bool) -> Result<OwnedFd> {
+    let c_path = raw::path_cstring(path)?;
+    let mut flags = OpenFlags::PATH | OpenFlags::CLOEXEC;
+    if !follow {
+        flags |= OpenFlags::NOFOLLOW;
+    }
+    let fd = retry_eintr(|| unsafe { raw::openat(dirfd.0, c_path.as_ptr(), flags.0) })?;
+    // Safety: fresh descriptor owned by us.
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
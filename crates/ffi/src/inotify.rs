@@ -0,0 +1,486 @@
+use crate::error::{retry_eintr, Errno, Error, Result};
+use crate::flags::inotify as iflag;
+use crate::raw;
+use crate::types::*;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+// ---------- Strong-typed flag ----------
+
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct InotifyFlags(pub i32);
+impl InotifyFlags {
+    pub const EMPTY: Self = Self(0);
+    pub const CLOEXEC: Self = Self(iflag::IN_CLOEXEC);
+    pub const NONBLOCK: Self = Self(iflag::IN_NONBLOCK);
+}
+impl core::ops::BitOr for InotifyFlags {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+impl core::ops::BitOrAssign for InotifyFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct InotifyMask(pub u32);
+impl InotifyMask {
+    pub const EMPTY: Self = Self(0);
+    pub const ACCESS: Self = Self(iflag::IN_ACCESS);
+    pub const MODIFY: Self = Self(iflag::IN_MODIFY);
+    pub const ATTRIB: Self = Self(iflag::IN_ATTRIB);
+    pub const CLOSE_WRITE: Self = Self(iflag::IN_CLOSE_WRITE);
+    pub const CLOSE_NOWRITE: Self = Self(iflag::IN_CLOSE_NOWRITE);
+    pub const CLOSE: Self = Self(iflag::IN_CLOSE);
+    pub const OPEN: Self = Self(iflag::IN_OPEN);
+    pub const MOVED_FROM: Self = Self(iflag::IN_MOVED_FROM);
+    pub const MOVED_TO: Self = Self(iflag::IN_MOVED_TO);
+    pub const MOVE: Self = Self(iflag::IN_MOVE);
+    pub const CREATE: Self = Self(iflag::IN_CREATE);
+    pub const DELETE: Self = Self(iflag::IN_DELETE);
+    pub const DELETE_SELF: Self = Self(iflag::IN_DELETE_SELF);
+    pub const MOVE_SELF: Self = Self(iflag::IN_MOVE_SELF);
+    pub const ALL_EVENTS: Self = Self(iflag::IN_ALL_EVENTS);
+    // kernel -> user only
+    pub const UNMOUNT: Self = Self(iflag::IN_UNMOUNT);
+    pub const Q_OVERFLOW: Self = Self(iflag::IN_Q_OVERFLOW);
+    pub const IGNORED: Self = Self(iflag::IN_IGNORED);
+    pub const ISDIR: Self = Self(iflag::IN_ISDIR);
+    // inotify_add_watch only
+    pub const ONLYDIR: Self = Self(iflag::IN_ONLYDIR);
+    pub const DONT_FOLLOW: Self = Self(iflag::IN_DONT_FOLLOW);
+    pub const EXCL_UNLINK: Self = Self(iflag::IN_EXCL_UNLINK);
+    pub const MASK_CREATE: Self = Self(iflag::IN_MASK_CREATE);
+    pub const MASK_ADD: Self = Self(iflag::IN_MASK_ADD);
+    pub const ONESHOT: Self = Self(iflag::IN_ONESHOT);
+
+    /// True if any bit of `other` is set.
+    #[inline]
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+impl core::ops::BitOr for InotifyMask {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+impl core::ops::BitOrAssign for InotifyMask {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Parsed inotify event with its path already resolved from the watch table.
+///
+/// A matched `MOVED_FROM`/`MOVED_TO` pair (same cookie) is folded into one
+/// event: `mask` has both bits set, `path` is the destination and `from` the
+/// source. An unmatched half is passed through as-is.
+pub struct InotifyEvent {
+    pub mask: InotifyMask,
+    /// watch descriptor the event was reported on (-1 for `Q_OVERFLOW`)
+    pub wd: i32,
+    /// rename cookie; 0 when not part of a move
+    pub cookie: u32,
+    /// 绝对路径（watch 目录 + name）；`Q_OVERFLOW` 时为空
+    pub path: PathBuf,
+    /// rename 源路径（仅成对的 MOVED_FROM/MOVED_TO）
+    pub from: Option<PathBuf>,
+}
+
+impl InotifyEvent {
+    /// Events were dropped by the kernel; the caller has to rescan.
+    #[inline]
+    pub fn is_overflow(&self) -> bool {
+        self.mask.intersects(InotifyMask::Q_OVERFLOW)
+    }
+
+    #[inline]
+    pub fn is_dir(&self) -> bool {
+        self.mask.intersects(InotifyMask::ISDIR)
+    }
+}
+
+struct Watch {
+    path: PathBuf,
+    /// `Some(mask)` if new subdirectories should be watched automatically
+    recursive: Option<InotifyMask>,
+}
+
+/// RAII inotify handle plus the wd -> path table needed to resolve names.
+pub struct Inotify {
+    fd: OwnedFd,
+    watches: HashMap<i32, Watch>,
+    /// directories whose MOVED_FROM ended the previous read, by cookie; their
+    /// watches are kept until the next read shows whether MOVED_TO follows
+    moved_out: HashMap<u32, PathBuf>,
+}
+
+impl Inotify {
+    /// Create an inotify instance (RAII). No privileges required.
+    pub fn new(flags: InotifyFlags) -> Result<Self> {
+        let fd = retry_eintr(|| unsafe { raw::inotify_init1(flags.0 as c_int) })?;
+        // Safety: fd is a fresh, owned descriptor from the kernel.
+        let owned = unsafe { OwnedFd::from_raw_fd(fd) };
+        Ok(Self {
+            fd: owned,
+            watches: HashMap::new(),
+            moved_out: HashMap::new(),
+        })
+    }
+
+    /// Watch a single path. Returns the watch descriptor.
+    pub fn add_watch(&mut self, path: &Path, mask: InotifyMask) -> Result<i32> {
+        self.add_watch_inner(path, mask, None)
+    }
+
+    /// Watch `root` and every directory below it. Directories created (or
+    /// moved in) later are picked up automatically by `read_events`.
+    ///
+    /// Symlinks are not followed. Subdirectories that vanish during the walk
+    /// are skipped; any other error (e.g. `ENOSPC` once
+    /// `fs.inotify.max_user_watches` is exhausted) is returned.
+    pub fn add_watch_recursive(&mut self, root: &Path, mask: InotifyMask) -> Result<()> {
+        let dir_mask = mask | InotifyMask::ONLYDIR | InotifyMask::DONT_FOLLOW;
+        self.add_watch_inner(root, dir_mask, Some(dir_mask))?;
+        self.walk(root, dir_mask, &mut |_, _| {})
+    }
+
+    /// Remove a watch. The kernel answers with an `IGNORED` event, which
+    /// `read_events` swallows.
+    pub fn rm_watch(&mut self, wd: i32) -> Result<()> {
+        self.watches.remove(&wd);
+        retry_eintr(|| unsafe { raw::inotify_rm_watch(self.fd.as_raw_fd() as c_int, wd) })?;
+        Ok(())
+    }
+
+    /// Drop every watch on `path` or below it.
+    pub fn rm_watch_tree(&mut self, path: &Path) {
+        let wds: Vec<i32> = self
+            .watches
+            .iter()
+            .filter(|(_, w)| w.path.starts_with(path))
+            .map(|(wd, _)| *wd)
+            .collect();
+        for wd in wds {
+            // EINVAL：内核已经自动移除（目录被删），忽略
+            let _ = self.rm_watch(wd);
+        }
+    }
+
+    /// Path a watch descriptor refers to.
+    pub fn path_of(&self, wd: i32) -> Option<&Path> {
+        self.watches.get(&wd).map(|w| w.path.as_path())
+    }
+
+    /// Number of live watches (each costs one `max_user_watches` slot).
+    pub fn watch_count(&self) -> usize {
+        self.watches.len()
+    }
+
+    /// 从 inotify fd 读取并解析事件。
+    /// - EAGAIN 时返回 Ok(vec![])。
+    /// - 同一次 read 内按 cookie 配对 MOVED_FROM/MOVED_TO；read 末尾未配对的
+    ///   目录 MOVED_FROM 等下一次 read 再决定是改名还是移出。
+    /// - 新建/移入的目录会自动加 watch，并为其中已存在的条目补发 CREATE。
+    pub fn read_events(&mut self, buf: &mut [u8]) -> Result<Vec<InotifyEvent>> {
+        // 手动处理 EAGAIN 语义；EINTR 交给 retry_eintr。
+        let rc = retry_eintr(|| unsafe {
+            raw::read(
+                self.fd.as_raw_fd() as c_int,
+                buf.as_mut_ptr() as *mut _,
+                buf.len(),
+            )
+        });
+        let n = match rc {
+            Ok(n) => n as usize,
+            Err(e) if e.errno == Errno::EAGAIN => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+        if n == 0 {
+            return Ok(vec![]);
+        }
+
+        let raws = parse_raw_events(&buf[..n])?;
+        Ok(self.resolve(raws))
+    }
+
+    fn add_watch_inner(
+        &mut self,
+        path: &Path,
+        mask: InotifyMask,
+        recursive: Option<InotifyMask>,
+    ) -> Result<i32> {
+        let c_path = raw::path_cstring(path)?;
+        let wd = retry_eintr(|| unsafe {
+            raw::inotify_add_watch(self.fd.as_raw_fd() as c_int, c_path.as_ptr(), mask.0)
+        })?;
+        // 同一 inode 重复 add 会返回同一个 wd；路径以最新的为准
+        self.watches.insert(
+            wd,
+            Watch {
+                path: path.to_path_buf(),
+                recursive,
+            },
+        );
+        Ok(wd)
+    }
+
+    /// Add watches for every directory below `dir`, calling `found` for each
+    /// entry (so callers can synthesize events for pre-existing content).
+    fn walk(
+        &mut self,
+        dir: &Path,
+        mask: InotifyMask,
+        found: &mut dyn FnMut(&Path, bool),
+    ) -> Result<()> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(it) => it,
+            Err(e) if vanished(&e) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let entry = match entry {
+                Ok(e) => e,
+                Err(e) if vanished(&e) => continue,
+                Err(e) => return Err(e.into()),
+            };
+            let path = entry.path();
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            found(&path, is_dir);
+            if !is_dir {
+                continue;
+            }
+            match self.add_watch_inner(&path, mask, Some(mask)) {
+                Ok(_) => {}
+                Err(e) if e.errno == Errno::ENOENT || e.errno == Errno::ENOTDIR => continue,
+                Err(e) => return Err(e),
+            }
+            self.walk(&path, mask, found)?;
+        }
+        Ok(())
+    }
+
+    /// Turn raw records into path-resolved events and keep the watch table
+    /// in sync with directory creates/moves/deletes.
+    fn resolve(&mut self, raws: Vec<RawInotifyEvent<'_>>) -> Vec<InotifyEvent> {
+        let mut out: Vec<Option<InotifyEvent>> = Vec::with_capacity(raws.len());
+        // cookie -> index of the pending MOVED_FROM in `out`
+        let mut pending: HashMap<u32, usize> = HashMap::new();
+
+        // 内核把 MOVED_FROM/MOVED_TO 连续入队：上次 read 留下的目录只可能由
+        // 本次的第一个事件配对，否则它已移出监视范围
+        let mut moved_out = std::mem::take(&mut self.moved_out);
+        if let Some(first) = raws.first() {
+            let to = InotifyMask(first.mask).intersects(InotifyMask::MOVED_TO);
+            let from = moved_out.remove(&first.cookie).filter(|_| to);
+            for p in moved_out.into_values() {
+                self.rm_watch_tree(&p);
+            }
+            moved_out = from
+                .map(|p| HashMap::from([(first.cookie, p)]))
+                .unwrap_or_default();
+        }
+
+        for raw in raws {
+            let mask = InotifyMask(raw.mask);
+
+            if mask.intersects(InotifyMask::Q_OVERFLOW) {
+                out.push(Some(InotifyEvent {
+                    mask,
+                    wd: raw.wd,
+                    cookie: 0,
+                    path: PathBuf::new(),
+                    from: None,
+                }));
+                continue;
+            }
+
+            if mask.intersects(InotifyMask::IGNORED) {
+                self.watches.remove(&raw.wd);
+                continue;
+            }
+
+            // wd 已被移除（竞态）：事件无法定位，丢弃
+            let Some(watch) = self.watches.get(&raw.wd) else {
+                continue;
+            };
+            let recursive = watch.recursive;
+            let path = if raw.name.is_empty() {
+                watch.path.clone()
+            } else {
+                watch.path.join(raw.name)
+            };
+
+            if mask.intersects(InotifyMask::MOVED_FROM) && raw.cookie != 0 {
+                pending.insert(raw.cookie, out.len());
+                out.push(Some(InotifyEvent {
+                    mask,
+                    wd: raw.wd,
+                    cookie: raw.cookie,
+                    path,
+                    from: None,
+                }));
+                continue;
+            }
+
+            if mask.intersects(InotifyMask::MOVED_TO) {
+                // 另一半在上次 read 里已经交出去了：只更新 watch 路径
+                if let Some(from) = moved_out.remove(&raw.cookie) {
+                    self.rename_tree(&from, &path);
+                    out.push(Some(InotifyEvent {
+                        mask,
+                        wd: raw.wd,
+                        cookie: raw.cookie,
+                        path,
+                        from: None,
+                    }));
+                    continue;
+                }
+                if let Some(ev) = pending.remove(&raw.cookie).and_then(|i| out[i].take()) {
+                    if mask.intersects(InotifyMask::ISDIR) {
+                        self.rename_tree(&ev.path, &path);
+                    }
+                    out.push(Some(InotifyEvent {
+                        mask: mask | InotifyMask::MOVED_FROM,
+                        wd: raw.wd,
+                        cookie: raw.cookie,
+                        path,
+                        from: Some(ev.path),
+                    }));
+                    continue;
+                }
+            }
+
+            let new_dir = mask.intersects(InotifyMask::ISDIR)
+                && mask.intersects(InotifyMask::CREATE | InotifyMask::MOVED_TO);
+
+            out.push(Some(InotifyEvent {
+                mask,
+                wd: raw.wd,
+                cookie: raw.cookie,
+                path: path.clone(),
+                from: None,
+            }));
+
+            // 新目录：补 watch，并把 watch 建立前已经出现的内容补发为 CREATE
+            if let (true, Some(dir_mask)) = (new_dir, recursive) {
+                let wd = match self.add_watch_inner(&path, dir_mask, Some(dir_mask)) {
+                    Ok(wd) => wd,
+                    Err(_) => continue,
+                };
+                let mut found = Vec::new();
+                let _ = self.walk(&path, dir_mask, &mut |p, is_dir| {
+                    found.push((p.to_path_buf(), is_dir));
+                });
+                for (p, is_dir) in found {
+                    let mut mask = InotifyMask::CREATE;
+                    if is_dir {
+                        mask |= InotifyMask::ISDIR;
+                    }
+                    out.push(Some(InotifyEvent {
+                        mask,
+                        wd,
+                        cookie: 0,
+                        path: p,
+                        from: None,
+                    }));
+                }
+            }
+        }
+
+        // 未配对的 MOVED_FROM：在 read 中间的已移出被监视范围，撤掉目录的子树
+        // watch；在 read 末尾的可能由下一次 read 的 MOVED_TO 配对，先留着
+        let last = out.len().checked_sub(1);
+        for (cookie, i) in pending {
+            let Some(ev) = out[i].as_ref().filter(|ev| ev.is_dir()) else {
+                continue;
+            };
+            let p = ev.path.clone();
+            if Some(i) == last {
+                self.moved_out.insert(cookie, p);
+            } else {
+                self.rm_watch_tree(&p);
+            }
+        }
+
+        out.into_iter().flatten().collect()
+    }
+
+    /// Rewrite watch paths after a directory moved inside the watched tree.
+    fn rename_tree(&mut self, from: &Path, to: &Path) {
+        for w in self.watches.values_mut() {
+            if let Ok(rest) = w.path.strip_prefix(from) {
+                w.path = if rest.as_os_str().is_empty() {
+                    to.to_path_buf()
+                } else {
+                    to.join(rest)
+                };
+            }
+        }
+    }
+}
+
+impl AsFd for Inotify {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+struct RawInotifyEvent<'a> {
+    wd: i32,
+    mask: u32,
+    cookie: u32,
+    name: &'a OsStr,
+}
+
+fn parse_raw_events(buf: &[u8]) -> Result<Vec<RawInotifyEvent<'_>>> {
+    let hdr_len = core::mem::size_of::<inotify_event>();
+    let mut out = Vec::new();
+    let mut off = 0usize;
+
+    while off + hdr_len <= buf.len() {
+        // SAFETY: bounds checked; unaligned read of plain-old-data.
+        let hdr =
+            unsafe { core::ptr::read_unaligned(buf.as_ptr().add(off) as *const inotify_event) };
+        let name_start = off + hdr_len;
+        let end = name_start
+            .checked_add(hdr.len as usize)
+            .filter(|&end| end <= buf.len())
+            .ok_or_else(Error::truncated)?;
+
+        // name 以 NUL 填充到对齐边界
+        let name = &buf[name_start..end];
+        let name = match name.iter().position(|&b| b == 0) {
+            Some(nul) => &name[..nul],
+            None => name,
+        };
+
+        out.push(RawInotifyEvent {
+            wd: hdr.wd,
+            mask: hdr.mask,
+            cookie: hdr.cookie,
+            name: OsStr::from_bytes(name),
+        });
+        off = end;
+    }
+
+    Ok(out)
+}
+
+fn vanished(e: &std::io::Error) -> bool {
+    matches!(
+        e.raw_os_error(),
+        Some(crate::flags::errno::ENOENT) | Some(crate::flags::errno::ENOTDIR)
+    )
+}
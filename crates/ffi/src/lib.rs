@@ -6,13 +6,52 @@ pub(crate) mod types;
 
 pub mod error;
 
+pub mod copy;
+
 pub mod epoll;
+pub mod eventfd;
 pub mod fanotify;
+pub mod fanotify_parse;
+pub mod file;
+pub mod handle;
+pub mod inotify;
+pub mod mountinfo;
+pub mod pidfd;
+pub mod privdrop;
+pub mod probe;
+pub mod rename;
+pub mod signalfd;
+pub mod statx;
+pub mod timerfd;
+pub mod xattr;
 
 pub mod uid;
 
+#[cfg(feature = "tokio")]
+pub mod async_io;
+
+#[cfg(feature = "tokio")]
+pub use async_io::{AsyncEpoll, AsyncFanotify};
+pub use copy::{fast_copy, CopyOutcome, CopyStrategy};
 pub use epoll::*;
-pub use error::{Errno, Error};
+pub use error::{Errno, Error, Malformed};
+pub use eventfd::{EventFd, EventFdFlags};
 pub use fanotify::*;
+pub use file::{
+    data_segments, fallocate, ftruncate, lseek, punch_hole, DataSegments, FallocFlags, Whence,
+};
+pub use handle::{FileHandle, FsId, MountCache};
+pub use inotify::*;
+pub use mountinfo::{FsSupport, MountInfo, MountTable, Propagation};
+pub use pidfd::PidFd;
+pub use privdrop::{drop_privileges, CapState, Capabilities, Credentials};
 pub use raw::{read, write};
+pub use rename::{
+    emulate_exchange, emulate_noreplace, rename_exchange, rename_noreplace, renameat2, Atomicity,
+    RenameFlags,
+};
+pub use signalfd::{SigInfo, Signal, SignalFd, SignalFdFlags};
+pub use statx::{fstatx, statx, statx_nofollow, AtFlags, Statx, StatxAttr, StatxMask, Timestamp};
+pub use timerfd::{ClockId, TimerFd, TimerFdFlags, TimerSetFlags, TimerSpec};
+pub use types::SigSet;
 pub use uid::effective;
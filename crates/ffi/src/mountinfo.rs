@@ -0,0 +1,239 @@
+//! `/proc/self/mountinfo` (proc(5)): mount topology for mark placement and
+//! pair root validation.
+//!
+//! ```text
+//! 36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue
+//! (1)(2) (3)   (4)   (5)      (6)     (7)   (8)  (9)    (10)        (11)
+//! ```
+//!
+//! Paths keep their raw bytes; the kernel's octal escapes (`\040` for space,
+//! `\011`, `\012`, `\134`) are decoded.
+
+use crate::error::{Errno, Error, Malformed, Result};
+use crate::fanotify::DirFd;
+use crate::statx::{statx, AtFlags, StatxMask};
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+
+/// Optional field (7): mount propagation.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Propagation {
+    /// `shared:N`
+    Shared(u32),
+    /// `master:N`
+    Master(u32),
+    /// `propagate_from:N`
+    PropagateFrom(u32),
+    /// `unbindable`
+    Unbindable,
+}
+
+/// One line of mountinfo.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MountInfo {
+    pub mount_id: u32,
+    pub parent_id: u32,
+    /// (major, minor) of `st_dev` for files on this mount
+    pub dev: (u32, u32),
+    /// root of the mount within its filesystem (not `/` for bind mounts)
+    pub root: PathBuf,
+    pub mount_point: PathBuf,
+    /// per-mount options (`rw`, `nosuid`, `relatime`, ...)
+    pub options: Vec<String>,
+    /// empty for private mounts
+    pub propagation: Vec<Propagation>,
+    /// `ext4`, `tmpfs`, `fuse.sshfs`, ...
+    pub fs_type: String,
+    pub source: OsString,
+    /// per-superblock options
+    pub super_options: Vec<String>,
+}
+
+impl MountInfo {
+    pub fn is_read_only(&self) -> bool {
+        self.options.iter().any(|o| o == "ro")
+    }
+
+    /// Bind mount of a subdirectory rather than of the filesystem root.
+    pub fn is_bind_subtree(&self) -> bool {
+        self.root != Path::new("/")
+    }
+
+    pub fn support(&self) -> FsSupport {
+        FsSupport::of(&self.fs_type)
+    }
+}
+
+/// How well a filesystem type works as a pair root.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FsSupport {
+    Supported,
+    /// usable, but changes made elsewhere (other clients, the server) are
+    /// not reported by fanotify
+    RemoteChangesInvisible,
+    /// pseudo filesystem: no real files, or no fanotify events
+    Unsupported,
+}
+
+impl FsSupport {
+    pub fn of(fs_type: &str) -> Self {
+        match fs_type {
+            "proc" | "sysfs" | "devtmpfs" | "devpts" | "cgroup" | "cgroup2" | "debugfs"
+            | "tracefs" | "securityfs" | "pstore" | "bpf" | "configfs" | "fusectl" | "mqueue"
+            | "binfmt_misc" | "autofs" | "efivarfs" | "nsfs" | "hugetlbfs" | "rpc_pipefs"
+            | "selinuxfs" => FsSupport::Unsupported,
+            "nfs" | "nfs4" | "cifs" | "smb3" | "9p" | "ceph" | "glusterfs" | "afs" | "fuse"
+            | "fuseblk" => FsSupport::RemoteChangesInvisible,
+            t if t.starts_with("fuse.") => FsSupport::RemoteChangesInvisible,
+            _ => FsSupport::Supported,
+        }
+    }
+}
+
+/// Parsed mount table, in kernel order (parents before children).
+#[derive(Clone, Debug, Default)]
+pub struct MountTable {
+    mounts: Vec<MountInfo>,
+}
+
+impl MountTable {
+    /// Mount table of the calling process's mount namespace.
+    pub fn read() -> Result<Self> {
+        Self::parse(&std::fs::read("/proc/self/mountinfo")?)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mounts = bytes
+            .split(|&b| b == b'\n')
+            .enumerate()
+            .filter(|(_, l)| !l.is_empty())
+            .map(|(i, l)| {
+                parse_line(l).ok_or(Error::malformed(Malformed::MountInfo { line: i + 1 }))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { mounts })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &MountInfo> {
+        self.mounts.iter()
+    }
+
+    pub fn by_id(&self, mount_id: u32) -> Option<&MountInfo> {
+        // 同一 id 只出现一次
+        self.mounts.iter().find(|m| m.mount_id == mount_id)
+    }
+
+    /// Mount that `path` lives on, from its `statx` mount id. Falls back to
+    /// the longest mount point prefix on kernels without STATX_MNT_ID.
+    pub fn containing(&self, path: &Path) -> Result<&MountInfo> {
+        let st = statx(DirFd::CWD, path, AtFlags::EMPTY, StatxMask::MNT_ID)?;
+        if let Some(m) = st.mnt_id().and_then(|id| self.by_id(id as u32)) {
+            return Ok(m);
+        }
+        let path = std::fs::canonicalize(path)?;
+        self.covering(&path).ok_or(Error::from_errno(Errno::ENOENT))
+    }
+
+    /// Mount whose mount point is the longest prefix of `path`, from the
+    /// table alone (no syscalls). `path` should be canonical.
+    pub fn covering(&self, path: &Path) -> Option<&MountInfo> {
+        // 同一挂载点可能被多次挂载：取最后（最上层）的那个
+        self.mounts
+            .iter()
+            .filter(|m| path.starts_with(&m.mount_point))
+            .max_by_key(|m| m.mount_point.as_os_str().len())
+    }
+
+    /// Mounts strictly beneath `root`, i.e. the ones a pair rooted there
+    /// would span. `root` should be canonical.
+    pub fn submounts(&self, root: &Path) -> Vec<&MountInfo> {
+        self.mounts
+            .iter()
+            .filter(|m| m.mount_point != root && m.mount_point.starts_with(root))
+            .collect()
+    }
+}
+
+fn parse_line(line: &[u8]) -> Option<MountInfo> {
+    let mut fields = line.split(|&b| b == b' ');
+    let mount_id = num(fields.next()?)?;
+    let parent_id = num(fields.next()?)?;
+    let (major, minor) = split_once(fields.next()?, b':')?;
+    let root = PathBuf::from(unescape(fields.next()?));
+    let mount_point = PathBuf::from(unescape(fields.next()?));
+    let options = list(fields.next()?);
+
+    let mut propagation = Vec::new();
+    loop {
+        let f = fields.next()?;
+        if f == b"-" {
+            break;
+        }
+        // 未知的可选字段按 proc(5) 要求忽略
+        let (tag, val) = split_once(f, b':').unwrap_or((f, b""));
+        propagation.push(match tag {
+            b"shared" => Propagation::Shared(num(val)?),
+            b"master" => Propagation::Master(num(val)?),
+            b"propagate_from" => Propagation::PropagateFrom(num(val)?),
+            b"unbindable" => Propagation::Unbindable,
+            _ => continue,
+        });
+    }
+
+    let fs_type = String::from_utf8(unescape(fields.next()?).into_vec()).ok()?;
+    let source = unescape(fields.next()?);
+    let super_options = list(fields.next().unwrap_or(b""));
+
+    Some(MountInfo {
+        mount_id,
+        parent_id,
+        dev: (num(major)?, num(minor)?),
+        root,
+        mount_point,
+        options,
+        propagation,
+        fs_type,
+        source,
+        super_options,
+    })
+}
+
+fn num(b: &[u8]) -> Option<u32> {
+    std::str::from_utf8(b).ok()?.parse().ok()
+}
+
+fn split_once(b: &[u8], sep: u8) -> Option<(&[u8], &[u8])> {
+    let i = b.iter().position(|&c| c == sep)?;
+    Some((&b[..i], &b[i + 1..]))
+}
+
+fn list(b: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(b)
+        .split(',')
+        .filter(|s| !s.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Undo the kernel's `\ooo` escaping (`mangle_path` / `seq_escape`).
+fn unescape(b: &[u8]) -> OsString {
+    let mut out = Vec::with_capacity(b.len());
+    let mut i = 0;
+    while i < b.len() {
+        if b[i] == b'\\'
+            && i + 3 < b.len()
+            && b[i + 1..i + 4].iter().all(|c| (b'0'..=b'7').contains(c))
+        {
+            let v = b[i + 1..i + 4]
+                .iter()
+                .fold(0u32, |acc, c| acc * 8 + (c - b'0') as u32);
+            out.push(v as u8);
+            i += 4;
+        } else {
+            out.push(b[i]);
+            i += 1;
+        }
+    }
+    OsString::from_vec(out)
+}
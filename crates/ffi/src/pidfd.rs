@@ -0,0 +1,62 @@
+use crate::error::{retry_eintr, Errno, Error, Result};
+use crate::flags::sys;
+use crate::raw;
+use crate::types::*;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+
+/// Process file descriptor (`pidfd_open(2)`).
+///
+/// Unlike a bare pid it keeps referring to the same process after that
+/// process exits, so it can tell a live pid apart from a recycled one.
+#[derive(Debug)]
+pub struct PidFd {
+    fd: OwnedFd,
+}
+
+impl PidFd {
+    /// `pidfd_open(pid, 0)`; needs Linux 5.3.
+    pub fn open(pid: i32) -> Result<Self> {
+        let fd = retry_eintr(|| unsafe {
+            raw::syscall(sys::SYS_PIDFD_OPEN, pid as c_long, 0 as c_long)
+        })?;
+        // Safety: fresh descriptor owned by us.
+        Ok(Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd as c_int) },
+        })
+    }
+
+    /// Wrap a pidfd received from the kernel (e.g. a fanotify PIDFD record).
+    pub fn from_owned(fd: OwnedFd) -> Self {
+        Self { fd }
+    }
+
+    /// Pid of the process, `None` once it has exited.
+    pub fn pid(&self) -> Result<Option<i32>> {
+        pid_of(self.fd.as_fd())
+    }
+
+    #[inline]
+    pub fn is_alive(&self) -> Result<bool> {
+        Ok(self.pid()?.is_some())
+    }
+}
+
+impl AsFd for PidFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+/// Pid behind any pidfd, read from the `Pid:` line of its fdinfo
+/// (Linux 5.10+). The kernel prints -1 there once the process has exited.
+pub fn pid_of(pidfd: BorrowedFd<'_>) -> Result<Option<i32>> {
+    let path = format!("/proc/self/fdinfo/{}", pidfd.as_raw_fd());
+    let info = std::fs::read_to_string(path)?;
+    let pid = info
+        .lines()
+        .find_map(|l| l.strip_prefix("Pid:"))
+        .and_then(|v| v.trim().parse::<i32>().ok())
+        // 不是 pidfd，或内核太旧没有 Pid 行
+        .ok_or_else(|| Error::from_errno(Errno::EINVAL))?;
+    Ok((pid > 0).then_some(pid))
+}
@@ -0,0 +1,277 @@
+//! Dropping root after setup.
+//!
+//! `fanotify_init` and filesystem/mount marks need root, copying user files
+//! does not. [`drop_privileges`] switches every uid/gid to the target user,
+//! replaces the supplementary groups and keeps only the capabilities asked
+//! for, then checks that none of it can be undone.
+//!
+//! Call it before spawning threads: glibc applies set*id to the whole
+//! process, but capabilities are per thread and threads created earlier
+//! keep their full set.
+
+use crate::error::{retry_eintr, Errno, Error, Result};
+use crate::flags::capability as cap;
+use crate::raw;
+use crate::types::*;
+use crate::uid;
+use std::ffi::{CStr, CString};
+
+// ---------- Strong-typed flag ----------
+
+/// Capability set, bit `n` = `CAP_*` number `n`.
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Capabilities(pub u64);
+impl Capabilities {
+    pub const EMPTY: Self = Self(0);
+    pub const CHOWN: Self = Self::of(cap::CAP_CHOWN);
+    pub const DAC_OVERRIDE: Self = Self::of(cap::CAP_DAC_OVERRIDE);
+    /// open_by_handle_at(2)
+    pub const DAC_READ_SEARCH: Self = Self::of(cap::CAP_DAC_READ_SEARCH);
+    pub const FOWNER: Self = Self::of(cap::CAP_FOWNER);
+    pub const FSETID: Self = Self::of(cap::CAP_FSETID);
+    pub const SETGID: Self = Self::of(cap::CAP_SETGID);
+    pub const SETUID: Self = Self::of(cap::CAP_SETUID);
+    /// fanotify_init(2), FAN_MARK_MOUNT / FAN_MARK_FILESYSTEM
+    pub const SYS_ADMIN: Self = Self::of(cap::CAP_SYS_ADMIN);
+
+    const fn of(n: u32) -> Self {
+        Self(1 << n)
+    }
+
+    #[inline]
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+impl core::ops::BitOr for Capabilities {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+impl core::ops::BitOrAssign for Capabilities {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Capability sets of the calling thread (`capget(2)`).
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct CapState {
+    pub effective: Capabilities,
+    pub permitted: Capabilities,
+    pub inheritable: Capabilities,
+}
+
+impl CapState {
+    pub fn current() -> Result<Self> {
+        let mut hdr = cap_header();
+        let mut data = [cap_user_data::default(); cap::_LINUX_CAPABILITY_U32S_3];
+        retry_eintr(|| unsafe { raw::capget(&mut hdr, data.as_mut_ptr()) })?;
+        let join = |f: fn(&cap_user_data) -> u32| {
+            Capabilities(f(&data[0]) as u64 | (f(&data[1]) as u64) << 32)
+        };
+        Ok(Self {
+            effective: join(|d| d.effective),
+            permitted: join(|d| d.permitted),
+            inheritable: join(|d| d.inheritable),
+        })
+    }
+
+    fn set(&self) -> Result<()> {
+        let mut hdr = cap_header();
+        let split = |c: Capabilities, hi: bool| (if hi { c.0 >> 32 } else { c.0 }) as u32;
+        let data = [false, true].map(|hi| cap_user_data {
+            effective: split(self.effective, hi),
+            permitted: split(self.permitted, hi),
+            inheritable: split(self.inheritable, hi),
+        });
+        retry_eintr(|| unsafe { raw::capset(&mut hdr, data.as_ptr()) })?;
+        Ok(())
+    }
+}
+
+fn cap_header() -> cap_user_header {
+    cap_user_header {
+        version: cap::_LINUX_CAPABILITY_VERSION_3,
+        pid: 0,
+    }
+}
+
+/// Who to run as after the drop.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Credentials {
+    pub uid: u32,
+    pub gid: u32,
+    /// supplementary groups
+    pub groups: Vec<u32>,
+}
+
+impl Credentials {
+    /// Look `name` up in the user database (`getpwnam_r(3)`), with its
+    /// supplementary groups (`getgrouplist(3)`). `None` if there is no such
+    /// user.
+    pub fn of_user(name: &str) -> Result<Option<Self>> {
+        let cname = CString::new(name).map_err(|_| Error::from_errno(Errno::EINVAL))?;
+        let mut pwd = core::mem::MaybeUninit::<passwd>::uninit();
+        let mut result: *mut passwd = core::ptr::null_mut();
+        let mut buf = vec![0 as core::ffi::c_char; 1024];
+        loop {
+            let rc = unsafe {
+                raw::getpwnam_r(
+                    cname.as_ptr(),
+                    pwd.as_mut_ptr(),
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                    &mut result,
+                )
+            };
+            match rc {
+                0 => break,
+                e if e == Errno::ERANGE.to_raw() => buf.resize(buf.len() * 2, 0),
+                e if e == Errno::EINTR.to_raw() => {}
+                e => return Err(Error::from_errno(Errno::from_raw(e))),
+            }
+        }
+        if result.is_null() {
+            return Ok(None);
+        }
+        // Safety: getpwnam_r 成功且 result 非空时 pwd 已初始化
+        let pwd = unsafe { pwd.assume_init() };
+        let groups = group_list(unsafe { CStr::from_ptr(pwd.pw_name) }, pwd.pw_gid)?;
+        Ok(Some(Self {
+            uid: pwd.pw_uid,
+            gid: pwd.pw_gid,
+            groups,
+        }))
+    }
+
+    /// Real, effective and saved ids all equal `uid`/`gid`, and the
+    /// supplementary groups are exactly `groups`.
+    pub fn is_current(&self) -> Result<bool> {
+        let (mut r, mut e, mut s) = (0, 0, 0);
+        retry_eintr(|| unsafe { raw::getresuid(&mut r, &mut e, &mut s) })?;
+        if [r, e, s] != [self.uid; 3] {
+            return Ok(false);
+        }
+        retry_eintr(|| unsafe { raw::getresgid(&mut r, &mut e, &mut s) })?;
+        if [r, e, s] != [self.gid; 3] {
+            return Ok(false);
+        }
+        let mut current = current_groups()?;
+        let mut want = self.groups.clone();
+        current.sort_unstable();
+        want.sort_unstable();
+        want.dedup();
+        current.dedup();
+        Ok(current == want)
+    }
+}
+
+fn group_list(name: &CStr, gid: u32) -> Result<Vec<u32>> {
+    let mut groups = vec![0u32; 32];
+    loop {
+        let mut n = groups.len() as c_int;
+        // -1 且 n 被改成所需大小：缓冲区不够
+        let rc = unsafe { raw::getgrouplist(name.as_ptr(), gid, groups.as_mut_ptr(), &mut n) };
+        if rc >= 0 {
+            groups.truncate(n as usize);
+            return Ok(groups);
+        }
+        groups.resize((n as usize).max(groups.len() * 2), 0);
+    }
+}
+
+fn current_groups() -> Result<Vec<u32>> {
+    let n = retry_eintr(|| unsafe { raw::getgroups(0, core::ptr::null_mut()) })?;
+    let mut groups = vec![0u32; n as usize];
+    let n = retry_eintr(|| unsafe { raw::getgroups(n, groups.as_mut_ptr()) })?;
+    groups.truncate(n as usize);
+    Ok(groups)
+}
+
+/// Become `to`, keeping only `keep` (effective and permitted; inheritable,
+/// ambient and the bounding set are cleared of everything else), and make
+/// sure it stuck:
+///
+/// - real, effective and saved uid/gid and the groups are `to`'s,
+///   cross-checked with [`uid::real`] / [`uid::effective`];
+/// - `setresuid(0, 0, 0)` fails;
+/// - the thread's capability sets are exactly `keep`.
+///
+/// Must be called as root; `to.uid` must not be 0. A failed check returns
+/// EPERM. `PR_SET_NO_NEW_PRIVS` is set too, so nothing exec'd later can
+/// regain privileges.
+pub fn drop_privileges(to: &Credentials, keep: Capabilities) -> Result<()> {
+    if to.uid == 0 {
+        return Err(Error::from_errno(Errno::EINVAL));
+    }
+    if uid::effective() != 0 {
+        return Err(Error::from_errno(Errno::EPERM));
+    }
+
+    // 先收紧 bounding set：之后就没有 CAP_SETPCAP 了
+    for n in 0..64 {
+        if keep.0 & (1 << n) != 0 {
+            continue;
+        }
+        match retry_eintr(|| unsafe { raw::prctl(cap::PR_CAPBSET_DROP, n as c_ulong) }) {
+            Ok(_) => {}
+            // 超出内核支持的最大 capability
+            Err(e) if e.errno == Errno::EINVAL => break,
+            Err(e) => return Err(e),
+        }
+    }
+    retry_eintr(|| unsafe {
+        raw::prctl(
+            cap::PR_CAP_AMBIENT,
+            cap::PR_CAP_AMBIENT_CLEAR_ALL as c_ulong,
+            0 as c_ulong,
+            0 as c_ulong,
+            0 as c_ulong,
+        )
+    })?;
+    retry_eintr(|| unsafe { raw::prctl(cap::PR_SET_KEEPCAPS, 1 as c_ulong) })?;
+
+    retry_eintr(|| unsafe { raw::setgroups(to.groups.len(), to.groups.as_ptr()) })?;
+    retry_eintr(|| unsafe { raw::setresgid(to.gid, to.gid, to.gid) })?;
+    retry_eintr(|| unsafe { raw::setresuid(to.uid, to.uid, to.uid) })?;
+
+    // uid 变化后 effective 集已被清空，permitted 因 KEEPCAPS 保留
+    CapState {
+        effective: keep,
+        permitted: keep,
+        inheritable: Capabilities::EMPTY,
+    }
+    .set()?;
+    retry_eintr(|| unsafe { raw::prctl(cap::PR_SET_KEEPCAPS, 0 as c_ulong) })?;
+    // 其余参数必须为 0，否则 EINVAL；可变参数不会替我们补 0
+    retry_eintr(|| unsafe {
+        raw::prctl(
+            cap::PR_SET_NO_NEW_PRIVS,
+            1 as c_ulong,
+            0 as c_ulong,
+            0 as c_ulong,
+            0 as c_ulong,
+        )
+    })?;
+
+    verify(to, keep)
+}
+
+fn verify(to: &Credentials, keep: Capabilities) -> Result<()> {
+    let denied = || Error::from_errno(Errno::EPERM);
+    if uid::real() != to.uid || uid::effective() != to.uid || !to.is_current()? {
+        return Err(denied());
+    }
+    // 能切回 root 说明 drop 并未生效（除非调用者要求保留 CAP_SETUID）
+    if !keep.contains(Capabilities::SETUID) && unsafe { raw::setresuid(0, 0, 0) } == 0 {
+        return Err(denied());
+    }
+    let caps = CapState::current()?;
+    if caps.effective != keep || caps.permitted != keep || caps.inheritable != Capabilities::EMPTY {
+        return Err(denied());
+    }
+    Ok(())
+}
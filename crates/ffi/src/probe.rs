@@ -0,0 +1,270 @@
+//! Runtime feature probing.
+//!
+//! Which fanotify flags work depends on the kernel (and on privileges), so
+//! the watcher asks the kernel instead of trusting version numbers:
+//!
+//! | feature                  | kernel |
+//! |--------------------------|--------|
+//! | FAN_MARK_FILESYSTEM      | 4.20   |
+//! | FAN_REPORT_FID           | 5.1    |
+//! | FAN_REPORT_DFID_NAME     | 5.9    |
+//! | epoll_pwait2             | 5.11   |
+//! | unprivileged fanotify    | 5.13   |
+//! | FAN_REPORT_PIDFD         | 5.15   |
+//! | FAN_RENAME, TARGET_FID   | 5.17   |
+//! | FAN_MARK_EVICTABLE       | 5.19   |
+//! | FAN_MARK_IGNORE          | 6.0    |
+
+use crate::epoll::{Epoll, EpollCreateFlags, Events};
+use crate::error::{Errno, Error, Result};
+use crate::fanotify::*;
+use std::path::Path;
+use std::time::Duration;
+
+/// Capability bits from `include/uapi/linux/capability.h`.
+const CAP_DAC_READ_SEARCH: u32 = 2;
+const CAP_SYS_ADMIN: u32 = 21;
+
+/// Everything [`probe`] found out. A `None` limit means the sysctl is absent
+/// (older kernel) or unreadable.
+#[derive(Clone, Debug)]
+pub struct Capabilities {
+    /// `uname -r`
+    pub kernel_release: Option<String>,
+    pub privileges: Privileges,
+    pub fanotify: FanotifySupport,
+    /// `epoll_pwait2(2)` is implemented (`Epoll::pwait2` returns ENOSYS otherwise).
+    pub epoll_pwait2: bool,
+    pub limits: Limits,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Privileges {
+    pub euid: u32,
+    /// fanotify mount/filesystem marks, permission events, unlimited queue
+    pub cap_sys_admin: bool,
+    /// `open_by_handle_at(2)` (FID -> path resolution)
+    pub cap_dac_read_search: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FanotifySupport {
+    /// Init flags accepted by `fanotify_init`, each tried on its own on top of
+    /// `CLASS_NOTIF | CLOEXEC`. `EMPTY` when fanotify is unusable.
+    pub init: FanotifyInitFlags,
+    /// Why a plain notification group could not be created (ENOSYS, EPERM, ...).
+    pub init_error: Option<Errno>,
+    /// Marks probed on `/`; see [`probe_marks`] for a specific root.
+    pub marks: MarkSupport,
+}
+
+/// What kind of marks a FID group may place on a given path.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MarkSupport {
+    pub inode: bool,
+    pub mount: bool,
+    pub filesystem: bool,
+    pub evictable: bool,
+    pub ignore: bool,
+    /// FAN_RENAME event mask
+    pub rename: bool,
+    /// Error of the plain inode mark, if even that failed
+    /// (EXDEV / EOPNOTSUPP: the filesystem cannot encode file handles).
+    pub error: Option<Errno>,
+}
+
+/// `fs.fanotify.*` (5.13+) and `fs.inotify.*` sysctls.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    pub fanotify_max_queued_events: Option<u64>,
+    pub fanotify_max_user_groups: Option<u64>,
+    pub fanotify_max_user_marks: Option<u64>,
+    pub inotify_max_queued_events: Option<u64>,
+    pub inotify_max_user_instances: Option<u64>,
+    pub inotify_max_user_watches: Option<u64>,
+}
+
+impl Capabilities {
+    /// fanotify is usable with directory-entry reporting, which is what the
+    /// fanotify backend needs; otherwise fall back to inotify.
+    pub fn fanotify_dfid_name(&self) -> bool {
+        self.fanotify
+            .init
+            .contains(FanotifyInitFlags::REPORT_DFID_NAME)
+            && self.fanotify.marks.inode
+    }
+
+    /// Whole-filesystem marks on `/` (one mark per device instead of per directory).
+    pub fn fanotify_filesystem_marks(&self) -> bool {
+        self.fanotify_dfid_name() && self.fanotify.marks.filesystem
+    }
+
+    /// FIDs can be turned back into paths.
+    pub fn can_resolve_handles(&self) -> bool {
+        self.privileges.cap_dac_read_search
+    }
+}
+
+/// Probe the running system. Cheap (a handful of short-lived fds), but not
+/// free: call once at startup.
+pub fn probe() -> Capabilities {
+    let init_error = FanotifyGroup::try_init(FanotifyInitFlags::EMPTY).err();
+    let init = if init_error.is_some() {
+        FanotifyInitFlags::EMPTY
+    } else {
+        probe_init_flags()
+    };
+    let marks = if init.contains(FanotifyInitFlags::REPORT_FID) {
+        probe_marks(Path::new("/"))
+    } else {
+        MarkSupport::default()
+    };
+
+    Capabilities {
+        kernel_release: read_proc("/proc/sys/kernel/osrelease"),
+        privileges: probe_privileges(),
+        fanotify: FanotifySupport {
+            init,
+            init_error,
+            marks,
+        },
+        epoll_pwait2: probe_epoll_pwait2(),
+        limits: probe_limits(),
+    }
+}
+
+/// Mark kinds usable on `path` (e.g. a pair root), with the richest FID
+/// reporting mode the kernel accepts.
+pub fn probe_marks(path: &Path) -> MarkSupport {
+    let report = [
+        FanotifyInitFlags::REPORT_DFID_NAME,
+        FanotifyInitFlags::REPORT_FID,
+    ]
+    .into_iter()
+    .find(|&f| FanotifyGroup::try_init(f).is_ok());
+    let Some(report) = report else {
+        return MarkSupport::default();
+    };
+
+    let modify = FanotifyEventMask::MODIFY;
+    let try_mark = |flags: FanotifyMarkFlags, mask: FanotifyEventMask| -> Result<()> {
+        // 每次用新的 group：EVICTABLE 不能叠加到已有的非 evictable mark 上
+        let group = FanotifyGroup::try_init(report).map_err(Error::from_errno)?;
+        group
+            .0
+            .mark(FanotifyMarkFlags::ADD | flags, mask, DirFd::CWD, Some(path))
+    };
+
+    let inode = try_mark(FanotifyMarkFlags::INODE, modify);
+    MarkSupport {
+        inode: inode.is_ok(),
+        mount: try_mark(FanotifyMarkFlags::MOUNT, modify).is_ok(),
+        filesystem: try_mark(FanotifyMarkFlags::FILESYSTEM, modify).is_ok(),
+        evictable: try_mark(FanotifyMarkFlags::EVICTABLE, modify).is_ok(),
+        // 目录上的 FAN_MARK_IGNORE 必须带 IGNORED_SURV_MODIFY，否则 EISDIR
+        ignore: try_mark(
+            FanotifyMarkFlags::IGNORE | FanotifyMarkFlags::IGNORED_SURV_MODIFY,
+            modify,
+        )
+        .is_ok(),
+        rename: report.contains(FanotifyInitFlags::REPORT_DFID_NAME)
+            && try_mark(FanotifyMarkFlags::INODE, FanotifyEventMask::RENAME).is_ok(),
+        error: inode.err().map(|e| e.errno),
+    }
+}
+
+/// Short-lived notification group used only for probing.
+struct FanotifyGroup(Fanotify);
+
+impl FanotifyGroup {
+    fn try_init(extra: FanotifyInitFlags) -> core::result::Result<Self, Errno> {
+        Fanotify::new(
+            FanotifyInitFlags::CLASS_NOTIF | FanotifyInitFlags::CLOEXEC | extra,
+            OpenFlags::RDONLY | OpenFlags::CLOEXEC,
+        )
+        .map(Self)
+        .map_err(|e| e.errno)
+    }
+}
+
+fn probe_init_flags() -> FanotifyInitFlags {
+    let candidates = [
+        FanotifyInitFlags::NONBLOCK,
+        FanotifyInitFlags::REPORT_FID,
+        FanotifyInitFlags::REPORT_DIR_FID,
+        FanotifyInitFlags::REPORT_DFID_NAME,
+        FanotifyInitFlags::REPORT_DFID_NAME_TARGET,
+        FanotifyInitFlags::REPORT_PIDFD,
+        FanotifyInitFlags::REPORT_TID,
+        FanotifyInitFlags::UNLIMITED_QUEUE,
+        FanotifyInitFlags::UNLIMITED_MARKS,
+    ];
+    let mut supported = FanotifyInitFlags::EMPTY;
+    for flag in candidates {
+        if FanotifyGroup::try_init(flag).is_ok() {
+            supported |= flag;
+        }
+    }
+    supported
+}
+
+impl Privileges {
+    /// From the contents of `/proc/<pid>/status` (`CapEff:`); a missing or
+    /// malformed line counts as no capabilities.
+    pub fn from_status(euid: u32, status: &str) -> Self {
+        // CapEff 是十六进制位图
+        let cap_eff = status
+            .lines()
+            .find_map(|l| l.strip_prefix("CapEff:"))
+            .and_then(|v| u64::from_str_radix(v.trim(), 16).ok())
+            .unwrap_or(0);
+        Self {
+            euid,
+            cap_sys_admin: cap_eff & (1 << CAP_SYS_ADMIN) != 0,
+            cap_dac_read_search: cap_eff & (1 << CAP_DAC_READ_SEARCH) != 0,
+        }
+    }
+}
+
+impl Limits {
+    /// `sysctl` returns the contents of `/proc/sys/fs/<name>`, e.g.
+    /// `"inotify/max_user_watches"`; absent or unparsable values are `None`.
+    pub fn from_sysctl(sysctl: impl Fn(&str) -> Option<String>) -> Self {
+        let get = |name: &str| sysctl(name).and_then(|v| v.trim().parse().ok());
+        Self {
+            fanotify_max_queued_events: get("fanotify/max_queued_events"),
+            fanotify_max_user_groups: get("fanotify/max_user_groups"),
+            fanotify_max_user_marks: get("fanotify/max_user_marks"),
+            inotify_max_queued_events: get("inotify/max_queued_events"),
+            inotify_max_user_instances: get("inotify/max_user_instances"),
+            inotify_max_user_watches: get("inotify/max_user_watches"),
+        }
+    }
+}
+
+fn probe_privileges() -> Privileges {
+    // 读不到就当作没有能力
+    let status = read_proc("/proc/self/status").unwrap_or_default();
+    Privileges::from_status(crate::uid::effective(), &status)
+}
+
+fn probe_epoll_pwait2() -> bool {
+    let Ok(ep) = Epoll::new(EpollCreateFlags::CLOEXEC) else {
+        return false;
+    };
+    let mut events = Events::with_capacity(1);
+    !matches!(
+        ep.pwait2_into(&mut events, Some(Duration::ZERO), None),
+        Err(e) if e.errno == Errno::ENOSYS
+    )
+}
+
+fn probe_limits() -> Limits {
+    Limits::from_sysctl(|name| read_proc(&format!("/proc/sys/fs/{name}")))
+}
+
+fn read_proc(path: &str) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim_end().to_owned())
+}
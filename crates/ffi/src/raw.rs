@@ -1,5 +1,12 @@
+use crate::error::{Errno, Error, Result};
+use crate::flags::sys;
 use crate::types::*;
 use core::ffi::c_char;
+use std::ffi::CString;
+#[cfg(feature = "tokio")]
+use std::os::fd::{AsRawFd, BorrowedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
 
 #[link(name = "c")]
 extern "C" {
@@ -9,6 +16,13 @@ extern "C" {
     // ssize_t write(int fd, const void *buf, size_t count);
     pub fn write(fd: c_int, buf: *const core::ffi::c_void, count: size_t) -> ssize_t;
 
+    // long syscall(long number, ...);
+    pub fn syscall(num: c_long, ...) -> c_long;
+
+    // 只有 set_nonblocking 用到，跟它一起受 tokio feature 控制
+    #[cfg(feature = "tokio")]
+    pub fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
+
     // fanotify
     pub fn fanotify_init(flags: c_int, event_f_flags: c_int) -> c_int;
 
@@ -20,6 +34,136 @@ extern "C" {
         pathname: *const c_char,
     ) -> c_int;
 
+    // inotify
+    pub fn inotify_init1(flags: c_int) -> c_int;
+
+    pub fn inotify_add_watch(fd: c_int, pathname: *const c_char, mask: u32) -> c_int;
+
+    pub fn inotify_rm_watch(fd: c_int, wd: c_int) -> c_int;
+
+    // file handles (name_to_handle_at(2))
+    pub fn name_to_handle_at(
+        dirfd: c_int,
+        pathname: *const c_char,
+        handle: *mut file_handle,
+        mount_id: *mut c_int,
+        flags: c_int,
+    ) -> c_int;
+
+    pub fn open_by_handle_at(mount_fd: c_int, handle: *mut file_handle, flags: c_int) -> c_int;
+
+    pub fn openat(dirfd: c_int, pathname: *const c_char, flags: c_int, ...) -> c_int;
+
+    pub fn fstatfs64(fd: c_int, buf: *mut statfs64) -> c_int;
+
+    // rename / link (renameat2: glibc 2.28+)
+    pub fn renameat(
+        olddirfd: c_int,
+        oldpath: *const c_char,
+        newdirfd: c_int,
+        newpath: *const c_char,
+    ) -> c_int;
+    pub fn renameat2(
+        olddirfd: c_int,
+        oldpath: *const c_char,
+        newdirfd: c_int,
+        newpath: *const c_char,
+        flags: c_uint,
+    ) -> c_int;
+    pub fn linkat(
+        olddirfd: c_int,
+        oldpath: *const c_char,
+        newdirfd: c_int,
+        newpath: *const c_char,
+        flags: c_int,
+    ) -> c_int;
+    pub fn unlinkat(dirfd: c_int, pathname: *const c_char, flags: c_int) -> c_int;
+
+    // glibc 2.28+
+    pub fn statx(
+        dirfd: c_int,
+        pathname: *const c_char,
+        flags: c_int,
+        mask: c_uint,
+        statxbuf: *mut statx,
+    ) -> c_int;
+
+    // file I/O (LFS entry points, 64-bit offsets everywhere)
+    pub fn lseek64(fd: c_int, offset: off64_t, whence: c_int) -> off64_t;
+    pub fn ftruncate64(fd: c_int, length: off64_t) -> c_int;
+    pub fn pread64(
+        fd: c_int,
+        buf: *mut core::ffi::c_void,
+        count: size_t,
+        offset: off64_t,
+    ) -> ssize_t;
+    pub fn pwrite64(
+        fd: c_int,
+        buf: *const core::ffi::c_void,
+        count: size_t,
+        offset: off64_t,
+    ) -> ssize_t;
+    pub fn fallocate64(fd: c_int, mode: c_int, offset: off64_t, len: off64_t) -> c_int;
+    pub fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+
+    // glibc 2.27+
+    pub fn copy_file_range(
+        fd_in: c_int,
+        off_in: *mut off64_t,
+        fd_out: c_int,
+        off_out: *mut off64_t,
+        len: size_t,
+        flags: c_uint,
+    ) -> ssize_t;
+    pub fn sendfile64(out_fd: c_int, in_fd: c_int, offset: *mut off64_t, count: size_t) -> ssize_t;
+
+    // xattr
+    pub fn getxattr(
+        path: *const c_char,
+        name: *const c_char,
+        value: *mut core::ffi::c_void,
+        size: size_t,
+    ) -> ssize_t;
+    pub fn lgetxattr(
+        path: *const c_char,
+        name: *const c_char,
+        value: *mut core::ffi::c_void,
+        size: size_t,
+    ) -> ssize_t;
+    pub fn fgetxattr(
+        fd: c_int,
+        name: *const c_char,
+        value: *mut core::ffi::c_void,
+        size: size_t,
+    ) -> ssize_t;
+    pub fn setxattr(
+        path: *const c_char,
+        name: *const c_char,
+        value: *const core::ffi::c_void,
+        size: size_t,
+        flags: c_int,
+    ) -> c_int;
+    pub fn lsetxattr(
+        path: *const c_char,
+        name: *const c_char,
+        value: *const core::ffi::c_void,
+        size: size_t,
+        flags: c_int,
+    ) -> c_int;
+    pub fn fsetxattr(
+        fd: c_int,
+        name: *const c_char,
+        value: *const core::ffi::c_void,
+        size: size_t,
+        flags: c_int,
+    ) -> c_int;
+    pub fn listxattr(path: *const c_char, list: *mut c_char, size: size_t) -> ssize_t;
+    pub fn llistxattr(path: *const c_char, list: *mut c_char, size: size_t) -> ssize_t;
+    pub fn flistxattr(fd: c_int, list: *mut c_char, size: size_t) -> ssize_t;
+    pub fn removexattr(path: *const c_char, name: *const c_char) -> c_int;
+    pub fn lremovexattr(path: *const c_char, name: *const c_char) -> c_int;
+    pub fn fremovexattr(fd: c_int, name: *const c_char) -> c_int;
+
     // epoll
     pub fn epoll_create1(flags: c_int) -> c_int;
 
@@ -40,12 +184,83 @@ extern "C" {
         sigmask: *const SigSet,
     ) -> c_int;
 
-    // glibc 2.35+: epoll_pwait2
-    pub fn epoll_pwait2(
-        epfd: c_int,
-        events: *mut EpollEvent,
-        maxevents: c_int,
-        timeout: *const TimeSpec,
-        sigmask: *const SigSet,
+    // eventfd / signalfd / timerfd
+    pub fn eventfd(initval: c_uint, flags: c_int) -> c_int;
+    pub fn signalfd(fd: c_int, mask: *const SigSet, flags: c_int) -> c_int;
+    pub fn timerfd_create(clockid: c_int, flags: c_int) -> c_int;
+    pub fn timerfd_settime(
+        fd: c_int,
+        flags: c_int,
+        new_value: *const itimerspec,
+        old_value: *mut itimerspec,
     ) -> c_int;
+    pub fn timerfd_gettime(fd: c_int, curr_value: *mut itimerspec) -> c_int;
+
+    // 返回 errno 而不是 -1
+    pub fn pthread_sigmask(how: c_int, set: *const SigSet, oldset: *mut SigSet) -> c_int;
+
+    // credentials / capabilities
+    // glibc 会把 set*id 广播到进程内所有线程；capset 只作用于调用线程
+    pub fn setresuid(ruid: u32, euid: u32, suid: u32) -> c_int;
+    pub fn setresgid(rgid: u32, egid: u32, sgid: u32) -> c_int;
+    pub fn getresuid(ruid: *mut u32, euid: *mut u32, suid: *mut u32) -> c_int;
+    pub fn getresgid(rgid: *mut u32, egid: *mut u32, sgid: *mut u32) -> c_int;
+    pub fn setgroups(size: size_t, list: *const u32) -> c_int;
+    pub fn getgroups(size: c_int, list: *mut u32) -> c_int;
+    pub fn getgrouplist(
+        user: *const c_char,
+        group: u32,
+        groups: *mut u32,
+        ngroups: *mut c_int,
+    ) -> c_int;
+    // 返回 errno 而不是 -1
+    pub fn getpwnam_r(
+        name: *const c_char,
+        pwd: *mut passwd,
+        buf: *mut c_char,
+        buflen: size_t,
+        result: *mut *mut passwd,
+    ) -> c_int;
+    pub fn prctl(option: c_int, ...) -> c_int;
+    pub fn capget(hdrp: *mut cap_user_header, datap: *mut cap_user_data) -> c_int;
+    pub fn capset(hdrp: *mut cap_user_header, datap: *const cap_user_data) -> c_int;
+}
+
+/// `epoll_pwait2(2)` (Linux 5.11) via `syscall(2)`. glibc only wraps it since
+/// 2.35; linking that symbol would keep the library from loading on older libcs.
+/// Returns ENOSYS on kernels without it.
+pub unsafe fn epoll_pwait2(
+    epfd: c_int,
+    events: *mut EpollEvent,
+    maxevents: c_int,
+    timeout: *const kernel_timespec,
+    sigmask: *const SigSet,
+) -> c_int {
+    syscall(
+        sys::SYS_EPOLL_PWAIT2,
+        epfd as c_long,
+        events as c_long,
+        maxevents as c_long,
+        timeout as c_long,
+        sigmask as c_long,
+        sys::KERNEL_SIGSET_SIZE as c_long,
+    ) as c_int
+}
+
+/// Convert a path into a NUL-terminated C string; interior NUL -> EINVAL.
+pub(crate) fn path_cstring(path: &Path) -> Result<CString> {
+    CString::new(path.as_os_str().as_bytes()).map_err(|_| Error::from_errno(Errno::EINVAL))
+}
+
+/// Put a descriptor into O_NONBLOCK mode (required by readiness-based polling).
+#[cfg(feature = "tokio")]
+pub(crate) fn set_nonblocking(fd: BorrowedFd<'_>) -> Result<()> {
+    use crate::error::retry_eintr;
+    use crate::flags::fcntl as f;
+
+    let fl = retry_eintr(|| unsafe { fcntl(fd.as_raw_fd(), f::F_GETFL) })?;
+    if fl & f::O_NONBLOCK == 0 {
+        retry_eintr(|| unsafe { fcntl(fd.as_raw_fd(), f::F_SETFL, fl | f::O_NONBLOCK) })?;
+    }
+    Ok(())
 }
@@ -0,0 +1,200 @@
+//! `renameat2(2)` for committing staged files.
+//!
+//! Not every filesystem implements the flags (older NFS, FUSE, overlayfs
+//! lower layers, ...); they answer EINVAL. [`rename_noreplace`] and
+//! [`rename_exchange`] then fall back to an emulation and say so in the
+//! returned [`Atomicity`].
+
+use crate::error::{retry_eintr, Errno, Error, Result};
+use crate::fanotify::DirFd;
+use crate::flags::fs as fsflag;
+use crate::raw;
+use crate::statx::{statx_nofollow, StatxMask};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+// ---------- Strong-typed flag ----------
+
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct RenameFlags(pub u32);
+impl RenameFlags {
+    pub const EMPTY: Self = Self(0);
+    /// fail with EEXIST instead of replacing `new`
+    pub const NOREPLACE: Self = Self(fsflag::RENAME_NOREPLACE);
+    /// atomically swap `old` and `new` (both must exist)
+    pub const EXCHANGE: Self = Self(fsflag::RENAME_EXCHANGE);
+    pub const WHITEOUT: Self = Self(fsflag::RENAME_WHITEOUT);
+}
+impl core::ops::BitOr for RenameFlags {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Whether a rename helper got the kernel's atomic guarantee.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Atomicity {
+    Atomic,
+    /// The filesystem rejected the flag and the operation was emulated;
+    /// see the helper's docs for the race window that leaves.
+    Emulated,
+}
+
+/// Plain `renameat2(2)`, no fallback.
+pub fn renameat2(
+    old_dir: DirFd,
+    old: &Path,
+    new_dir: DirFd,
+    new: &Path,
+    flags: RenameFlags,
+) -> Result<()> {
+    let old = raw::path_cstring(old)?;
+    let new = raw::path_cstring(new)?;
+    retry_eintr(|| unsafe {
+        raw::renameat2(old_dir.0, old.as_ptr(), new_dir.0, new.as_ptr(), flags.0)
+    })?;
+    Ok(())
+}
+
+/// `renameat(2)`: replaces `new` if it exists.
+pub fn renameat(old_dir: DirFd, old: &Path, new_dir: DirFd, new: &Path) -> Result<()> {
+    let old = raw::path_cstring(old)?;
+    let new = raw::path_cstring(new)?;
+    retry_eintr(|| unsafe { raw::renameat(old_dir.0, old.as_ptr(), new_dir.0, new.as_ptr()) })?;
+    Ok(())
+}
+
+/// Move `old` to `new` only if `new` does not exist (EEXIST otherwise), so
+/// a file the user created on the target side meanwhile is never clobbered.
+///
+/// Fallback: `linkat` + `unlinkat`, which is just as race-free for regular
+/// files (a brief moment with both names is harmless). Where hard links are
+/// not possible (directories, some filesystems) it degrades to
+/// check-then-rename, which is racy.
+pub fn rename_noreplace(
+    old_dir: DirFd,
+    old: &Path,
+    new_dir: DirFd,
+    new: &Path,
+) -> Result<Atomicity> {
+    match renameat2(old_dir, old, new_dir, new, RenameFlags::NOREPLACE) {
+        Ok(()) => return Ok(Atomicity::Atomic),
+        Err(e) if unsupported(e.errno) => {}
+        Err(e) => return Err(e),
+    }
+
+    emulate_noreplace(old_dir, old, new_dir, new)
+}
+
+/// The fallback of [`rename_noreplace`], for callers that already know the
+/// filesystem lacks `RENAME_NOREPLACE`.
+pub fn emulate_noreplace(
+    old_dir: DirFd,
+    old: &Path,
+    new_dir: DirFd,
+    new: &Path,
+) -> Result<Atomicity> {
+    match linkat(old_dir, old, new_dir, new) {
+        Ok(()) => {
+            if let Err(e) = unlinkat(old_dir, old) {
+                // 撤销新链接，不留下两个名字
+                let _ = unlinkat(new_dir, new);
+                return Err(e);
+            }
+            return Ok(Atomicity::Atomic);
+        }
+        // EPERM：目录不能硬链接；其余几种：文件系统不支持
+        Err(e)
+            if matches!(
+                e.errno,
+                Errno::EPERM | Errno::EOPNOTSUPP | Errno::EMLINK | Errno::EXDEV
+            ) => {}
+        Err(e) => return Err(e),
+    }
+
+    match statx_nofollow(new_dir, new, StatxMask::TYPE) {
+        Ok(_) => return Err(Error::from_errno(Errno::EEXIST)),
+        Err(e) if e.errno == Errno::ENOENT => {}
+        Err(e) => return Err(e),
+    }
+    renameat(old_dir, old, new_dir, new)?;
+    Ok(Atomicity::Emulated)
+}
+
+/// Swap `old` and `new`, e.g. to put a staged file in place while keeping
+/// the previous version under the staging name.
+///
+/// Fallback: three renames through a temporary name next to `new`. Not
+/// atomic: for a moment `new` does not exist, and a crash in between leaves
+/// the temporary name behind (it starts with `.synchron-xchg-`).
+pub fn rename_exchange(
+    old_dir: DirFd,
+    old: &Path,
+    new_dir: DirFd,
+    new: &Path,
+) -> Result<Atomicity> {
+    match renameat2(old_dir, old, new_dir, new, RenameFlags::EXCHANGE) {
+        Ok(()) => return Ok(Atomicity::Atomic),
+        Err(e) if unsupported(e.errno) => {}
+        Err(e) => return Err(e),
+    }
+
+    emulate_exchange(old_dir, old, new_dir, new)
+}
+
+/// The fallback of [`rename_exchange`], for callers that already know the
+/// filesystem lacks `RENAME_EXCHANGE`. A failure part-way is rolled back as
+/// far as the filesystem allows.
+pub fn emulate_exchange(
+    old_dir: DirFd,
+    old: &Path,
+    new_dir: DirFd,
+    new: &Path,
+) -> Result<Atomicity> {
+    // 与 EXCHANGE 一致：两边都必须存在
+    statx_nofollow(old_dir, old, StatxMask::TYPE)?;
+    statx_nofollow(new_dir, new, StatxMask::TYPE)?;
+
+    let tmp = exchange_tmp_name(new);
+    rename_noreplace(new_dir, new, new_dir, &tmp)?;
+    if let Err(e) = renameat(old_dir, old, new_dir, new) {
+        // 尽量恢复原状
+        let _ = renameat(new_dir, &tmp, new_dir, new);
+        return Err(e);
+    }
+    if let Err(e) = renameat(new_dir, &tmp, old_dir, old) {
+        // 两步都倒回去，不留下临时名
+        if renameat(new_dir, new, old_dir, old).is_ok() {
+            let _ = renameat(new_dir, &tmp, new_dir, new);
+        }
+        return Err(e);
+    }
+    Ok(Atomicity::Emulated)
+}
+
+fn linkat(old_dir: DirFd, old: &Path, new_dir: DirFd, new: &Path) -> Result<()> {
+    let old = raw::path_cstring(old)?;
+    let new = raw::path_cstring(new)?;
+    retry_eintr(|| unsafe { raw::linkat(old_dir.0, old.as_ptr(), new_dir.0, new.as_ptr(), 0) })?;
+    Ok(())
+}
+
+fn unlinkat(dir: DirFd, path: &Path) -> Result<()> {
+    let path = raw::path_cstring(path)?;
+    retry_eintr(|| unsafe { raw::unlinkat(dir.0, path.as_ptr(), 0) })?;
+    Ok(())
+}
+
+fn exchange_tmp_name(new: &Path) -> PathBuf {
+    let mut name = OsString::from(format!(".synchron-xchg-{}-", std::process::id()));
+    name.push(new.file_name().unwrap_or_default());
+    new.with_file_name(name)
+}
+
+/// The filesystem (or a pre-3.15 kernel) does not know the flag.
+#[inline]
+fn unsupported(errno: Errno) -> bool {
+    matches!(errno, Errno::EINVAL | Errno::ENOSYS)
+}
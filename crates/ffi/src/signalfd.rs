@@ -0,0 +1,244 @@
+//! `signalfd(2)` plus the [`SigSet`] helpers needed to use it: the signals
+//! have to be blocked (in every thread) before they are queued on the fd
+//! instead of being delivered normally.
+
+use crate::error::{retry_eintr, Errno, Error, Result};
+use crate::flags::signal as sflag;
+use crate::raw;
+use crate::types::*;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+
+const _: () = assert!(core::mem::size_of::<signalfd_siginfo>() == 128);
+
+const WORD_BITS: usize = usize::BITS as usize;
+
+// ---------- Strong-typed flag ----------
+
+/// Signal number.
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Signal(pub i32);
+impl Signal {
+    pub const HUP: Self = Self(sflag::SIGHUP);
+    pub const INT: Self = Self(sflag::SIGINT);
+    pub const QUIT: Self = Self(sflag::SIGQUIT);
+    pub const USR1: Self = Self(sflag::SIGUSR1);
+    pub const USR2: Self = Self(sflag::SIGUSR2);
+    pub const PIPE: Self = Self(sflag::SIGPIPE);
+    pub const ALRM: Self = Self(sflag::SIGALRM);
+    pub const TERM: Self = Self(sflag::SIGTERM);
+    pub const CHLD: Self = Self(sflag::SIGCHLD);
+    pub const WINCH: Self = Self(sflag::SIGWINCH);
+
+    #[inline]
+    fn is_valid(self) -> bool {
+        self.0 > 0 && self.0 < sflag::NSIG
+    }
+
+    // glibc __sigword / __sigmask
+    #[inline]
+    fn word_bit(self) -> (usize, usize) {
+        let n = (self.0 - 1) as usize;
+        (n / WORD_BITS, 1 << (n % WORD_BITS))
+    }
+}
+
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct SignalFdFlags(pub i32);
+impl SignalFdFlags {
+    pub const EMPTY: Self = Self(0);
+    pub const CLOEXEC: Self = Self(sflag::SFD_CLOEXEC);
+    pub const NONBLOCK: Self = Self(sflag::SFD_NONBLOCK);
+}
+impl core::ops::BitOr for SignalFdFlags {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+impl core::ops::BitOrAssign for SignalFdFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+// ---------- SigSet ----------
+
+impl SigSet {
+    /// Every signal (1..=64). glibc drops its internal ones in
+    /// `pthread_sigmask`, and SIGKILL / SIGSTOP cannot be blocked anyway.
+    pub fn full() -> Self {
+        let mut set = Self::empty();
+        for sig in 1..sflag::NSIG {
+            let (w, b) = Signal(sig).word_bit();
+            set.__val[w] |= b;
+        }
+        set
+    }
+
+    /// Set of exactly `signals`; EINVAL on an out-of-range number.
+    pub fn of(signals: &[Signal]) -> Result<Self> {
+        let mut set = Self::empty();
+        for &sig in signals {
+            set.insert(sig)?;
+        }
+        Ok(set)
+    }
+
+    /// `sigaddset`
+    pub fn insert(&mut self, sig: Signal) -> Result<()> {
+        if !sig.is_valid() {
+            return Err(Error::from_errno(Errno::EINVAL));
+        }
+        let (w, b) = sig.word_bit();
+        self.__val[w] |= b;
+        Ok(())
+    }
+
+    /// `sigdelset`
+    pub fn remove(&mut self, sig: Signal) -> Result<()> {
+        if !sig.is_valid() {
+            return Err(Error::from_errno(Errno::EINVAL));
+        }
+        let (w, b) = sig.word_bit();
+        self.__val[w] &= !b;
+        Ok(())
+    }
+
+    /// `sigismember`; false for out-of-range numbers.
+    pub fn contains(&self, sig: Signal) -> bool {
+        if !sig.is_valid() {
+            return false;
+        }
+        let (w, b) = sig.word_bit();
+        self.__val[w] & b != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (1..sflag::NSIG).all(|sig| !self.contains(Signal(sig)))
+    }
+
+    /// Members in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = Signal> + '_ {
+        (1..sflag::NSIG)
+            .map(Signal)
+            .filter(move |&sig| self.contains(sig))
+    }
+
+    /// Add these signals to the calling thread's mask; returns the old mask.
+    /// Threads spawned afterwards inherit it, so call this before spawning.
+    pub fn block(&self) -> Result<SigSet> {
+        thread_sigmask(sflag::SIG_BLOCK, Some(self))
+    }
+
+    /// Remove these signals from the calling thread's mask; returns the old mask.
+    pub fn unblock(&self) -> Result<SigSet> {
+        thread_sigmask(sflag::SIG_UNBLOCK, Some(self))
+    }
+
+    /// Replace the calling thread's mask; returns the old mask.
+    pub fn set_thread_mask(&self) -> Result<SigSet> {
+        thread_sigmask(sflag::SIG_SETMASK, Some(self))
+    }
+
+    /// The calling thread's current mask.
+    pub fn thread_mask() -> Result<SigSet> {
+        thread_sigmask(sflag::SIG_BLOCK, None)
+    }
+}
+
+impl core::fmt::Debug for SigSet {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_set().entries(self.iter().map(|s| s.0)).finish()
+    }
+}
+
+fn thread_sigmask(how: c_int, set: Option<&SigSet>) -> Result<SigSet> {
+    let mut old = SigSet::empty();
+    // pthread_sigmask 直接返回 errno，不设置 errno 变量
+    let rc = unsafe {
+        raw::pthread_sigmask(
+            how,
+            set.map(|s| s as *const _).unwrap_or(core::ptr::null()),
+            &mut old,
+        )
+    };
+    if rc != 0 {
+        return Err(Error::from_errno(Errno::from_raw(rc)));
+    }
+    Ok(old)
+}
+
+// ---------- SignalFd ----------
+
+/// One dequeued signal (`struct signalfd_siginfo`, commonly used fields).
+#[derive(Copy, Clone, Debug)]
+pub struct SigInfo {
+    pub signo: Signal,
+    /// `si_code`: SI_USER (0) for kill(2), SI_KERNEL, CLD_* for SIGCHLD, ...
+    pub code: i32,
+    /// sender pid (SIGCHLD: the child)
+    pub pid: u32,
+    /// sender real uid
+    pub uid: u32,
+    /// SIGCHLD: exit status or signal
+    pub status: i32,
+}
+
+/// RAII signalfd. Readable (EPOLLIN) while one of its signals is pending.
+#[derive(Debug)]
+pub struct SignalFd {
+    fd: OwnedFd,
+}
+
+impl SignalFd {
+    /// The signals in `mask` must also be blocked ([`SigSet::block`]),
+    /// otherwise they are still delivered the default way.
+    pub fn new(mask: &SigSet, flags: SignalFdFlags) -> Result<Self> {
+        let fd = retry_eintr(|| unsafe { raw::signalfd(-1, mask, flags.0) })?;
+        // Safety: fresh descriptor owned by us.
+        Ok(Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+        })
+    }
+
+    /// Replace the set of signals this fd accepts.
+    pub fn set_mask(&self, mask: &SigSet) -> Result<()> {
+        retry_eintr(|| unsafe { raw::signalfd(self.fd.as_raw_fd() as c_int, mask, 0) })?;
+        Ok(())
+    }
+
+    /// Dequeue one signal; `None` on EAGAIN.
+    pub fn read(&self) -> Result<Option<SigInfo>> {
+        let mut si = core::mem::MaybeUninit::<signalfd_siginfo>::zeroed();
+        let rc = retry_eintr(|| unsafe {
+            raw::read(
+                self.fd.as_raw_fd() as c_int,
+                si.as_mut_ptr() as *mut _,
+                core::mem::size_of::<signalfd_siginfo>(),
+            )
+        });
+        match rc {
+            Ok(_) => {
+                // Safety: zero-initialised, then (partly) filled by the kernel.
+                let si = unsafe { si.assume_init() };
+                Ok(Some(SigInfo {
+                    signo: Signal(si.ssi_signo as i32),
+                    code: si.ssi_code,
+                    pid: si.ssi_pid,
+                    uid: si.ssi_uid,
+                    status: si.ssi_status,
+                }))
+            }
+            Err(e) if e.errno == Errno::EAGAIN => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl AsFd for SignalFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
@@ -0,0 +1,274 @@
+use crate::error::{retry_eintr, Result};
+use crate::fanotify::DirFd;
+use crate::flags::{fcntl as fcntl_flag, statx as sflag};
+use crate::raw;
+use crate::types::*;
+use std::os::fd::{AsRawFd, BorrowedFd};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// ---------- Strong-typed flag ----------
+
+/// Which fields to ask for / which ones the filesystem filled in (`stx_mask`).
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct StatxMask(pub u32);
+impl StatxMask {
+    pub const EMPTY: Self = Self(0);
+    pub const TYPE: Self = Self(sflag::STATX_TYPE);
+    pub const MODE: Self = Self(sflag::STATX_MODE);
+    pub const NLINK: Self = Self(sflag::STATX_NLINK);
+    pub const UID: Self = Self(sflag::STATX_UID);
+    pub const GID: Self = Self(sflag::STATX_GID);
+    pub const ATIME: Self = Self(sflag::STATX_ATIME);
+    pub const MTIME: Self = Self(sflag::STATX_MTIME);
+    pub const CTIME: Self = Self(sflag::STATX_CTIME);
+    pub const INO: Self = Self(sflag::STATX_INO);
+    pub const SIZE: Self = Self(sflag::STATX_SIZE);
+    pub const BLOCKS: Self = Self(sflag::STATX_BLOCKS);
+    pub const BASIC_STATS: Self = Self(sflag::STATX_BASIC_STATS);
+    pub const BTIME: Self = Self(sflag::STATX_BTIME);
+    pub const MNT_ID: Self = Self(sflag::STATX_MNT_ID);
+    pub const MNT_ID_UNIQUE: Self = Self(sflag::STATX_MNT_ID_UNIQUE);
+    /// What the reconciler compares: basic stats, birth time, mount id.
+    pub const SYNC: Self =
+        Self(sflag::STATX_BASIC_STATS | sflag::STATX_BTIME | sflag::STATX_MNT_ID);
+
+    #[inline]
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+impl core::ops::BitOr for StatxMask {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+impl core::ops::BitOrAssign for StatxMask {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// `STATX_ATTR_*` (`stx_attributes` / `stx_attributes_mask`).
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct StatxAttr(pub u64);
+impl StatxAttr {
+    pub const EMPTY: Self = Self(0);
+    pub const COMPRESSED: Self = Self(sflag::STATX_ATTR_COMPRESSED);
+    pub const IMMUTABLE: Self = Self(sflag::STATX_ATTR_IMMUTABLE);
+    pub const APPEND: Self = Self(sflag::STATX_ATTR_APPEND);
+    pub const NODUMP: Self = Self(sflag::STATX_ATTR_NODUMP);
+    pub const ENCRYPTED: Self = Self(sflag::STATX_ATTR_ENCRYPTED);
+    pub const AUTOMOUNT: Self = Self(sflag::STATX_ATTR_AUTOMOUNT);
+    pub const MOUNT_ROOT: Self = Self(sflag::STATX_ATTR_MOUNT_ROOT);
+    pub const VERITY: Self = Self(sflag::STATX_ATTR_VERITY);
+    pub const DAX: Self = Self(sflag::STATX_ATTR_DAX);
+}
+impl core::ops::BitOr for StatxAttr {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// `AT_*` lookup flags for the `*at` calls.
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct AtFlags(pub i32);
+impl AtFlags {
+    pub const EMPTY: Self = Self(0);
+    pub const SYMLINK_NOFOLLOW: Self = Self(fcntl_flag::AT_SYMLINK_NOFOLLOW);
+    pub const NO_AUTOMOUNT: Self = Self(fcntl_flag::AT_NO_AUTOMOUNT);
+    pub const EMPTY_PATH: Self = Self(fcntl_flag::AT_EMPTY_PATH);
+    pub const STATX_FORCE_SYNC: Self = Self(fcntl_flag::AT_STATX_FORCE_SYNC);
+    pub const STATX_DONT_SYNC: Self = Self(fcntl_flag::AT_STATX_DONT_SYNC);
+}
+impl core::ops::BitOr for AtFlags {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+impl core::ops::BitOrAssign for AtFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Nanosecond timestamp as reported by statx.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Timestamp {
+    pub sec: i64,
+    pub nsec: u32,
+}
+
+impl Timestamp {
+    pub fn to_system_time(self) -> SystemTime {
+        let nsec = Duration::from_nanos(self.nsec as u64);
+        if self.sec >= 0 {
+            UNIX_EPOCH + Duration::from_secs(self.sec as u64) + nsec
+        } else {
+            UNIX_EPOCH - Duration::from_secs(self.sec.unsigned_abs()) + nsec
+        }
+    }
+}
+
+impl From<statx_timestamp> for Timestamp {
+    fn from(t: statx_timestamp) -> Self {
+        Self {
+            sec: t.tv_sec,
+            nsec: t.tv_nsec,
+        }
+    }
+}
+
+/// Result of `statx(2)`.
+///
+/// `mask` is what the filesystem actually filled in, which can be less than
+/// what was asked for (no birth time on tmpfs, no mount id before 5.8, ...).
+/// Fields outside `mask` are zero; the optional ones have `Option` accessors.
+///
+/// There is no NFS-style change cookie in the userspace ABI (STATX_CHANGE_COOKIE
+/// is kernel-internal): `ctime` is the change attribute, at nanosecond precision.
+#[derive(Clone, Debug)]
+pub struct Statx {
+    pub mask: StatxMask,
+    pub blksize: u32,
+    pub attributes: StatxAttr,
+    /// attributes the filesystem supports (a clear bit in `attributes` is only
+    /// meaningful if it is set here)
+    pub attributes_mask: StatxAttr,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    /// file type and permission bits (`S_IFMT | 07777`)
+    pub mode: u16,
+    pub ino: u64,
+    pub size: u64,
+    pub blocks: u64,
+    pub atime: Timestamp,
+    pub ctime: Timestamp,
+    pub mtime: Timestamp,
+    btime: Timestamp,
+    pub rdev: (u32, u32),
+    /// (major, minor) of the filesystem
+    pub dev: (u32, u32),
+    mnt_id: u64,
+}
+
+impl Statx {
+    #[inline]
+    pub fn has(&self, fields: StatxMask) -> bool {
+        self.mask.contains(fields)
+    }
+
+    /// Birth time, if the filesystem records one.
+    pub fn btime(&self) -> Option<Timestamp> {
+        self.has(StatxMask::BTIME).then_some(self.btime)
+    }
+
+    /// Mount id, as in `/proc/self/mountinfo` (or the unique 64-bit id when
+    /// `MNT_ID_UNIQUE` was requested and granted).
+    pub fn mnt_id(&self) -> Option<u64> {
+        (self.has(StatxMask::MNT_ID) || self.has(StatxMask::MNT_ID_UNIQUE)).then_some(self.mnt_id)
+    }
+
+    /// `Some(set)` when the filesystem supports `attr`, `None` otherwise.
+    pub fn attribute(&self, attr: StatxAttr) -> Option<bool> {
+        (self.attributes_mask.0 & attr.0 == attr.0).then_some(self.attributes.0 & attr.0 == attr.0)
+    }
+
+    /// `st_dev` encoding (glibc `makedev`), comparable with std's `MetadataExt::dev`.
+    pub fn dev_t(&self) -> u64 {
+        makedev(self.dev.0, self.dev.1)
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.mode as u32 & S_IFMT == S_IFDIR
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.mode as u32 & S_IFMT == S_IFREG
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.mode as u32 & S_IFMT == S_IFLNK
+    }
+}
+
+impl From<statx> for Statx {
+    fn from(st: statx) -> Self {
+        Self {
+            mask: StatxMask(st.stx_mask),
+            blksize: st.stx_blksize,
+            attributes: StatxAttr(st.stx_attributes),
+            attributes_mask: StatxAttr(st.stx_attributes_mask),
+            nlink: st.stx_nlink,
+            uid: st.stx_uid,
+            gid: st.stx_gid,
+            mode: st.stx_mode,
+            ino: st.stx_ino,
+            size: st.stx_size,
+            blocks: st.stx_blocks,
+            atime: st.stx_atime.into(),
+            ctime: st.stx_ctime.into(),
+            mtime: st.stx_mtime.into(),
+            btime: st.stx_btime.into(),
+            rdev: (st.stx_rdev_major, st.stx_rdev_minor),
+            dev: (st.stx_dev_major, st.stx_dev_minor),
+            mnt_id: st.stx_mnt_id,
+        }
+    }
+}
+
+const _: () = assert!(core::mem::size_of::<statx>() == 0x100);
+
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+
+fn makedev(major: u32, minor: u32) -> u64 {
+    let (major, minor) = (major as u64, minor as u64);
+    ((major & 0xffff_f000) << 32)
+        | ((major & 0x0000_0fff) << 8)
+        | ((minor & 0xffff_ff00) << 12)
+        | (minor & 0x0000_00ff)
+}
+
+/// `statx(2)` for `path` relative to `dirfd`. `want` is a request; check
+/// [`Statx::mask`] for what came back.
+pub fn statx(dirfd: DirFd, path: &Path, flags: AtFlags, want: StatxMask) -> Result<Statx> {
+    let c_path = raw::path_cstring(path)?;
+    let mut st = core::mem::MaybeUninit::<statx>::zeroed();
+    retry_eintr(|| unsafe {
+        raw::statx(
+            dirfd.0 as c_int,
+            c_path.as_ptr(),
+            flags.0 as c_int,
+            want.0 as c_uint,
+            st.as_mut_ptr(),
+        )
+    })?;
+    // SAFETY: statx succeeded and filled the struct (zeroed beforehand anyway).
+    Ok(unsafe { st.assume_init() }.into())
+}
+
+/// `statx` that reports on a symlink itself rather than its target, e.g. for
+/// lookups relative to an open pair root.
+pub fn statx_nofollow(dirfd: DirFd, path: &Path, want: StatxMask) -> Result<Statx> {
+    statx(dirfd, path, AtFlags::SYMLINK_NOFOLLOW, want)
+}
+
+/// `statx` on an open descriptor (`AT_EMPTY_PATH`).
+pub fn fstatx(fd: BorrowedFd<'_>, want: StatxMask) -> Result<Statx> {
+    statx(
+        DirFd(fd.as_raw_fd()),
+        Path::new(""),
+        AtFlags::EMPTY_PATH,
+        want,
+    )
+}
@@ -0,0 +1,204 @@
+//! `timerfd_create(2)`: timers delivered as a readable fd, so debounce
+//! deadlines can sit on the same epoll as the event sources.
+
+use crate::error::{retry_eintr, Errno, Result};
+use crate::flags::timerfd as tflag;
+use crate::raw;
+use crate::types::*;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+use std::time::Duration;
+
+/// Clock the timer counts on.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ClockId {
+    /// wall clock; jumps with `settimeofday`
+    Realtime,
+    /// does not jump, stops while suspended
+    Monotonic,
+    /// monotonic, but keeps counting while suspended
+    Boottime,
+    /// Realtime that wakes the system (CAP_WAKE_ALARM)
+    RealtimeAlarm,
+    /// Boottime that wakes the system (CAP_WAKE_ALARM)
+    BoottimeAlarm,
+}
+
+impl ClockId {
+    fn to_raw(self) -> c_int {
+        match self {
+            ClockId::Realtime => tflag::CLOCK_REALTIME,
+            ClockId::Monotonic => tflag::CLOCK_MONOTONIC,
+            ClockId::Boottime => tflag::CLOCK_BOOTTIME,
+            ClockId::RealtimeAlarm => tflag::CLOCK_REALTIME_ALARM,
+            ClockId::BoottimeAlarm => tflag::CLOCK_BOOTTIME_ALARM,
+        }
+    }
+}
+
+// ---------- Strong-typed flag ----------
+
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct TimerFdFlags(pub i32);
+impl TimerFdFlags {
+    pub const EMPTY: Self = Self(0);
+    pub const CLOEXEC: Self = Self(tflag::TFD_CLOEXEC);
+    pub const NONBLOCK: Self = Self(tflag::TFD_NONBLOCK);
+}
+impl core::ops::BitOr for TimerFdFlags {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+impl core::ops::BitOrAssign for TimerFdFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct TimerSetFlags(pub i32);
+impl TimerSetFlags {
+    pub const EMPTY: Self = Self(0);
+    /// `value` is an absolute time on the timer's clock
+    pub const ABSTIME: Self = Self(tflag::TFD_TIMER_ABSTIME);
+    /// with ABSTIME on a Realtime clock: reads fail with ECANCELED when the
+    /// clock is set
+    pub const CANCEL_ON_SET: Self = Self(tflag::TFD_TIMER_CANCEL_ON_SET);
+}
+impl core::ops::BitOr for TimerSetFlags {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Timer setting. `value: None` means disarmed; `interval: None` one-shot.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct TimerSpec {
+    pub value: Option<Duration>,
+    pub interval: Option<Duration>,
+}
+
+impl TimerSpec {
+    fn to_raw(self) -> itimerspec {
+        itimerspec {
+            it_interval: to_timespec(self.interval),
+            it_value: to_timespec(self.value),
+        }
+    }
+
+    fn from_raw(t: &itimerspec) -> Self {
+        Self {
+            value: from_timespec(&t.it_value),
+            interval: from_timespec(&t.it_interval),
+        }
+    }
+}
+
+// 全零 = 停止；None 与 Duration::ZERO 等价
+fn to_timespec(d: Option<Duration>) -> timespec {
+    let d = d.unwrap_or(Duration::ZERO);
+    timespec {
+        tv_sec: d.as_secs() as c_long,
+        tv_nsec: d.subsec_nanos() as c_long,
+    }
+}
+
+fn from_timespec(t: &timespec) -> Option<Duration> {
+    if t.tv_sec == 0 && t.tv_nsec == 0 {
+        return None;
+    }
+    Some(Duration::new(t.tv_sec as u64, t.tv_nsec as u32))
+}
+
+/// RAII timerfd. Readable (EPOLLIN) once the timer has expired.
+#[derive(Debug)]
+pub struct TimerFd {
+    fd: OwnedFd,
+}
+
+impl TimerFd {
+    pub fn new(clock: ClockId, flags: TimerFdFlags) -> Result<Self> {
+        let fd = retry_eintr(|| unsafe { raw::timerfd_create(clock.to_raw(), flags.0) })?;
+        // Safety: fresh descriptor owned by us.
+        Ok(Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+        })
+    }
+
+    /// `timerfd_settime(2)`; returns the previous setting.
+    pub fn set(&self, spec: TimerSpec, flags: TimerSetFlags) -> Result<TimerSpec> {
+        let new = spec.to_raw();
+        let mut old = itimerspec::default();
+        retry_eintr(|| unsafe {
+            raw::timerfd_settime(self.fd.as_raw_fd() as c_int, flags.0, &new, &mut old)
+        })?;
+        Ok(TimerSpec::from_raw(&old))
+    }
+
+    /// One-shot, `after` from now; re-arming replaces the pending deadline
+    /// (debounce). A zero `after` fires as soon as possible.
+    pub fn arm(&self, after: Duration) -> Result<()> {
+        // it_value 全零会变成 disarm
+        let after = after.max(Duration::from_nanos(1));
+        self.set(
+            TimerSpec {
+                value: Some(after),
+                interval: None,
+            },
+            TimerSetFlags::EMPTY,
+        )?;
+        Ok(())
+    }
+
+    /// Periodic, first expiry one `every` from now.
+    pub fn arm_interval(&self, every: Duration) -> Result<()> {
+        let every = every.max(Duration::from_nanos(1));
+        self.set(
+            TimerSpec {
+                value: Some(every),
+                interval: Some(every),
+            },
+            TimerSetFlags::EMPTY,
+        )?;
+        Ok(())
+    }
+
+    pub fn disarm(&self) -> Result<()> {
+        self.set(TimerSpec::default(), TimerSetFlags::EMPTY)?;
+        Ok(())
+    }
+
+    /// Current setting; `value` is the time left until the next expiry.
+    pub fn get(&self) -> Result<TimerSpec> {
+        let mut cur = itimerspec::default();
+        retry_eintr(|| unsafe { raw::timerfd_gettime(self.fd.as_raw_fd() as c_int, &mut cur) })?;
+        Ok(TimerSpec::from_raw(&cur))
+    }
+
+    /// Expirations since the last read (also clears readiness); 0 on EAGAIN.
+    pub fn read(&self) -> Result<u64> {
+        let mut n: u64 = 0;
+        let rc = retry_eintr(|| unsafe {
+            raw::read(
+                self.fd.as_raw_fd() as c_int,
+                &mut n as *mut u64 as *mut _,
+                core::mem::size_of::<u64>(),
+            )
+        });
+        match rc {
+            Ok(_) => Ok(n),
+            Err(e) if e.errno == Errno::EAGAIN => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl AsFd for TimerFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
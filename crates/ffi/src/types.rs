@@ -4,6 +4,10 @@ use core::ffi::c_void;
 
 pub type c_int = i32;
 pub type c_uint = u32;
+pub type c_long = isize;
+pub type c_ulong = usize;
+/// `off64_t` / `loff_t` (the `*64` entry points are used on every ABI)
+pub type off64_t = i64;
 pub type size_t = usize;
 pub type ssize_t = isize;
 pub type RawFd = i32;
@@ -18,7 +22,9 @@ pub union EpollData {
 }
 
 /// Kernel epoll event layout.
-#[repr(C)]
+/// x86_64 declares it `__attribute__((packed))` (12 bytes), other ABIs don't.
+#[cfg_attr(target_arch = "x86_64", repr(C, packed))]
+#[cfg_attr(not(target_arch = "x86_64"), repr(C))]
 pub struct EpollEvent {
     pub events: u32,
     pub data: EpollData,
@@ -32,6 +38,7 @@ pub const SIGSET_NWORDS: usize = 16;
 pub const SIGSET_NWORDS: usize = 32;
 
 #[repr(C)]
+#[derive(Clone)]
 pub struct SigSet {
     pub __val: [usize; SIGSET_NWORDS],
 }
@@ -44,27 +51,124 @@ impl SigSet {
     }
 }
 
-/// timespec for epoll_pwait2. On most 64-bit Linux, both i64.
-#[cfg(any(
-    target_pointer_width = "64",
-    target_arch = "x86_64",
-    target_arch = "aarch64"
-))]
+/// `struct __user_cap_header_struct` for capget / capset
+#[repr(C)]
+pub struct cap_user_header {
+    pub version: u32,
+    pub pid: c_int,
+}
+
+/// `struct __user_cap_data_struct`; version 3 takes two of them (caps 0-31,
+/// 32-63)
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct cap_user_data {
+    pub effective: u32,
+    pub permitted: u32,
+    pub inheritable: u32,
+}
+
+/// glibc `struct passwd`
+#[repr(C)]
+pub struct passwd {
+    pub pw_name: *mut core::ffi::c_char,
+    pub pw_passwd: *mut core::ffi::c_char,
+    pub pw_uid: u32,
+    pub pw_gid: u32,
+    pub pw_gecos: *mut core::ffi::c_char,
+    pub pw_dir: *mut core::ffi::c_char,
+    pub pw_shell: *mut core::ffi::c_char,
+}
+
+/// libc `struct timespec` (`time_t` is `long` without `_TIME_BITS=64`).
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct timespec {
+    pub tv_sec: c_long,
+    pub tv_nsec: c_long,
+}
+
+/// `struct itimerspec` for timerfd_settime / timerfd_gettime
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct itimerspec {
+    pub it_interval: timespec,
+    pub it_value: timespec,
+}
+
+/// `struct signalfd_siginfo` (always 128 bytes)
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct signalfd_siginfo {
+    pub ssi_signo: u32,
+    pub ssi_errno: i32,
+    pub ssi_code: i32,
+    pub ssi_pid: u32,
+    pub ssi_uid: u32,
+    pub ssi_fd: i32,
+    pub ssi_tid: u32,
+    pub ssi_band: u32,
+    pub ssi_overrun: u32,
+    pub ssi_trapno: u32,
+    pub ssi_status: i32,
+    pub ssi_int: i32,
+    pub ssi_ptr: u64,
+    pub ssi_utime: u64,
+    pub ssi_stime: u64,
+    pub ssi_addr: u64,
+    pub ssi_addr_lsb: u16,
+    pub __pad2: u16,
+    pub ssi_syscall: i32,
+    pub ssi_call_addr: u64,
+    pub ssi_arch: u32,
+    pub __pad: [u8; 28],
+}
+
+/// `struct __kernel_timespec` for epoll_pwait2: 64-bit on every ABI,
+/// unlike libc's timespec on 32-bit targets.
 #[repr(C)]
-pub struct TimeSpec {
+pub struct kernel_timespec {
     pub tv_sec: i64,
     pub tv_nsec: i64,
 }
 
-/// A conservative 32-bit fallback (note: some ABIs use 64-bit time_t via different syscalls)
-#[cfg(all(
-    target_pointer_width = "32",
-    not(any(target_arch = "x86_64", target_arch = "aarch64"))
-))]
+/// `struct statx_timestamp`
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct statx_timestamp {
+    pub tv_sec: i64,
+    pub tv_nsec: u32,
+    pub __reserved: i32,
+}
+
+/// `struct statx` (0x100 bytes; trailing fields newer than DIOALIGN left as spare)
 #[repr(C)]
-pub struct TimeSpec {
-    pub tv_sec: i32,
-    pub tv_nsec: i32,
+#[derive(Copy, Clone)]
+pub struct statx {
+    pub stx_mask: u32,
+    pub stx_blksize: u32,
+    pub stx_attributes: u64,
+    pub stx_nlink: u32,
+    pub stx_uid: u32,
+    pub stx_gid: u32,
+    pub stx_mode: u16,
+    pub __spare0: u16,
+    pub stx_ino: u64,
+    pub stx_size: u64,
+    pub stx_blocks: u64,
+    pub stx_attributes_mask: u64,
+    pub stx_atime: statx_timestamp,
+    pub stx_btime: statx_timestamp,
+    pub stx_ctime: statx_timestamp,
+    pub stx_mtime: statx_timestamp,
+    pub stx_rdev_major: u32,
+    pub stx_rdev_minor: u32,
+    pub stx_dev_major: u32,
+    pub stx_dev_minor: u32,
+    pub stx_mnt_id: u64,
+    pub stx_dio_mem_align: u32,
+    pub stx_dio_offset_align: u32,
+    pub __spare3: [u64; 12],
 }
 
 /// fanotify event metadata (stable, widely used)
@@ -80,9 +184,82 @@ pub struct fanotify_event_metadata {
     pub pid: i32,
 }
 
+/// inotify event header; `len` bytes of NUL-padded name follow.
+/// See: include/uapi/linux/inotify.h
+#[repr(C)]
+pub struct inotify_event {
+    pub wd: i32,
+    pub mask: u32,
+    pub cookie: u32,
+    pub len: u32,
+}
+
 /// fanotify response payload to allow or deny
 #[repr(C)]
 pub struct fanotify_response {
     pub fd: i32,
     pub response: u32,
 }
+
+/// Common header of every variable-length info record that follows
+/// `fanotify_event_metadata` (FAN_REPORT_FID / DFID_NAME / PIDFD ...).
+#[repr(C)]
+pub struct fanotify_event_info_header {
+    pub info_type: u8,
+    pub pad: u8,
+    pub len: u16,
+}
+
+/// `__kernel_fsid_t`
+#[repr(C)]
+pub struct kernel_fsid_t {
+    pub val: [i32; 2],
+}
+
+/// FID / DFID / DFID_NAME record; a `struct file_handle` (and for the
+/// *_NAME types a NUL-terminated name) follows directly after `fsid`.
+#[repr(C)]
+pub struct fanotify_event_info_fid {
+    pub hdr: fanotify_event_info_header,
+    pub fsid: kernel_fsid_t,
+}
+
+/// Fixed part of `struct file_handle`; `handle_bytes` opaque bytes follow.
+#[repr(C)]
+pub struct file_handle {
+    pub handle_bytes: u32,
+    pub handle_type: i32,
+}
+
+/// FAN_REPORT_PIDFD record
+#[repr(C)]
+pub struct fanotify_event_info_pidfd {
+    pub hdr: fanotify_event_info_header,
+    pub pidfd: i32,
+}
+
+/// FAN_FS_ERROR record
+#[repr(C)]
+pub struct fanotify_event_info_error {
+    pub hdr: fanotify_event_info_header,
+    pub error: i32,
+    pub error_count: u32,
+}
+
+/// `struct statfs64`; only `f_fsid` is consumed, the rest keeps the layout.
+/// `__fsword_t` is `long` on every Linux ABI we build for.
+#[repr(C)]
+pub struct statfs64 {
+    pub f_type: c_long,
+    pub f_bsize: c_long,
+    pub f_blocks: u64,
+    pub f_bfree: u64,
+    pub f_bavail: u64,
+    pub f_files: u64,
+    pub f_ffree: u64,
+    pub f_fsid: kernel_fsid_t,
+    pub f_namelen: c_long,
+    pub f_frsize: c_long,
+    pub f_flags: c_long,
+    pub f_spare: [c_long; 4],
+}
@@ -10,7 +10,6 @@ pub fn effective() -> uid_t {
     unsafe { geteuid() }
 }
 
-#[allow(dead_code)]
 pub fn real() -> uid_t {
     unsafe { getuid() }
 }
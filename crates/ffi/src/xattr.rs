@@ -0,0 +1,392 @@
+//! Extended attributes, including POSIX ACLs (`system.posix_acl_*`) and
+//! security labels (`security.selinux`), which are stored as xattrs too.
+//!
+//! Every call comes in three flavours, like libc: by path (follows
+//! symlinks), `l*` (acts on the symlink itself) and `f*` (open descriptor).
+//! Names and values are raw bytes; only the namespace prefix is interpreted.
+
+use crate::error::{retry_eintr, Errno, Error, Result, RetVal};
+use crate::flags::xattr as xflag;
+use crate::raw;
+use crate::types::*;
+use core::ffi::c_char;
+use core::fmt;
+use std::ffi::{CStr, CString, OsStr, OsString};
+use std::os::fd::{AsRawFd, BorrowedFd, RawFd};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::Path;
+use std::str::FromStr;
+
+// ---------- Strong-typed flag ----------
+
+#[repr(transparent)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct XattrFlags(pub i32);
+impl XattrFlags {
+    /// create or replace
+    pub const EMPTY: Self = Self(0);
+    /// fail with EEXIST if the attribute exists
+    pub const CREATE: Self = Self(xflag::XATTR_CREATE);
+    /// fail with ENODATA if it does not
+    pub const REPLACE: Self = Self(xflag::XATTR_REPLACE);
+}
+
+/// What an xattr call operates on.
+#[derive(Copy, Clone)]
+enum Target<'a> {
+    Path(&'a CStr),
+    Link(&'a CStr),
+    Fd(RawFd),
+}
+
+// ---------- get ----------
+
+/// `getxattr(2)`. `Ok(None)` if the attribute is absent or the filesystem
+/// has no xattr support.
+pub fn get(path: &Path, name: &OsStr) -> Result<Option<Vec<u8>>> {
+    let path = raw::path_cstring(path)?;
+    get_inner(Target::Path(&path), name)
+}
+
+/// `lgetxattr(2)`: like [`get`] but does not follow a final symlink.
+pub fn lget(path: &Path, name: &OsStr) -> Result<Option<Vec<u8>>> {
+    let path = raw::path_cstring(path)?;
+    get_inner(Target::Link(&path), name)
+}
+
+/// `fgetxattr(2)`
+pub fn fget(fd: BorrowedFd<'_>, name: &OsStr) -> Result<Option<Vec<u8>>> {
+    get_inner(Target::Fd(fd.as_raw_fd()), name)
+}
+
+fn get_inner(target: Target<'_>, name: &OsStr) -> Result<Option<Vec<u8>>> {
+    let name = name_cstring(name)?;
+    let value = read_grow(|buf, size| unsafe {
+        let buf = buf as *mut core::ffi::c_void;
+        match target {
+            Target::Path(p) => raw::getxattr(p.as_ptr(), name.as_ptr(), buf, size),
+            Target::Link(p) => raw::lgetxattr(p.as_ptr(), name.as_ptr(), buf, size),
+            Target::Fd(fd) => raw::fgetxattr(fd, name.as_ptr(), buf, size),
+        }
+    });
+    match value {
+        Ok(v) => Ok(Some(v)),
+        Err(e) if matches!(e.errno, Errno::ENODATA | Errno::EOPNOTSUPP) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// ---------- set ----------
+
+/// `setxattr(2)`. EOPNOTSUPP if the filesystem (or namespace) does not
+/// support xattrs, E2BIG / ENOSPC if the value does not fit.
+pub fn set(path: &Path, name: &OsStr, value: &[u8], flags: XattrFlags) -> Result<()> {
+    let path = raw::path_cstring(path)?;
+    set_inner(Target::Path(&path), name, value, flags)
+}
+
+/// `lsetxattr(2)`
+pub fn lset(path: &Path, name: &OsStr, value: &[u8], flags: XattrFlags) -> Result<()> {
+    let path = raw::path_cstring(path)?;
+    set_inner(Target::Link(&path), name, value, flags)
+}
+
+/// `fsetxattr(2)`
+pub fn fset(fd: BorrowedFd<'_>, name: &OsStr, value: &[u8], flags: XattrFlags) -> Result<()> {
+    set_inner(Target::Fd(fd.as_raw_fd()), name, value, flags)
+}
+
+fn set_inner(target: Target<'_>, name: &OsStr, value: &[u8], flags: XattrFlags) -> Result<()> {
+    let name = name_cstring(name)?;
+    let (ptr, len) = (value.as_ptr() as *const core::ffi::c_void, value.len());
+    retry_eintr(|| unsafe {
+        match target {
+            Target::Path(p) => raw::setxattr(p.as_ptr(), name.as_ptr(), ptr, len, flags.0),
+            Target::Link(p) => raw::lsetxattr(p.as_ptr(), name.as_ptr(), ptr, len, flags.0),
+            Target::Fd(fd) => raw::fsetxattr(fd, name.as_ptr(), ptr, len, flags.0),
+        }
+    })?;
+    Ok(())
+}
+
+// ---------- list ----------
+
+/// `listxattr(2)`. Empty if the filesystem has no xattr support.
+/// Names the caller may not read (e.g. `trusted.*` without CAP_SYS_ADMIN)
+/// are left out by the kernel.
+pub fn list(path: &Path) -> Result<Vec<OsString>> {
+    let path = raw::path_cstring(path)?;
+    list_inner(Target::Path(&path))
+}
+
+/// `llistxattr(2)`
+pub fn llist(path: &Path) -> Result<Vec<OsString>> {
+    let path = raw::path_cstring(path)?;
+    list_inner(Target::Link(&path))
+}
+
+/// `flistxattr(2)`
+pub fn flist(fd: BorrowedFd<'_>) -> Result<Vec<OsString>> {
+    list_inner(Target::Fd(fd.as_raw_fd()))
+}
+
+fn list_inner(target: Target<'_>) -> Result<Vec<OsString>> {
+    let names = read_grow(|buf, size| unsafe {
+        let buf = buf as *mut c_char;
+        match target {
+            Target::Path(p) => raw::listxattr(p.as_ptr(), buf, size),
+            Target::Link(p) => raw::llistxattr(p.as_ptr(), buf, size),
+            Target::Fd(fd) => raw::flistxattr(fd, buf, size),
+        }
+    });
+    match names {
+        // 内核返回以 NUL 分隔的名字列表
+        Ok(buf) => Ok(buf
+            .split(|&b| b == 0)
+            .filter(|n| !n.is_empty())
+            .map(|n| OsString::from_vec(n.to_vec()))
+            .collect()),
+        Err(e) if e.errno == Errno::EOPNOTSUPP => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+// ---------- remove ----------
+
+/// `removexattr(2)`; ENODATA if the attribute does not exist.
+pub fn remove(path: &Path, name: &OsStr) -> Result<()> {
+    let path = raw::path_cstring(path)?;
+    remove_inner(Target::Path(&path), name)
+}
+
+/// `lremovexattr(2)`
+pub fn lremove(path: &Path, name: &OsStr) -> Result<()> {
+    let path = raw::path_cstring(path)?;
+    remove_inner(Target::Link(&path), name)
+}
+
+/// `fremovexattr(2)`
+pub fn fremove(fd: BorrowedFd<'_>, name: &OsStr) -> Result<()> {
+    remove_inner(Target::Fd(fd.as_raw_fd()), name)
+}
+
+fn remove_inner(target: Target<'_>, name: &OsStr) -> Result<()> {
+    let name = name_cstring(name)?;
+    retry_eintr(|| unsafe {
+        match target {
+            Target::Path(p) => raw::removexattr(p.as_ptr(), name.as_ptr()),
+            Target::Link(p) => raw::lremovexattr(p.as_ptr(), name.as_ptr()),
+            Target::Fd(fd) => raw::fremovexattr(fd, name.as_ptr()),
+        }
+    })?;
+    Ok(())
+}
+
+/// Size query followed by the real read. The value can grow in between,
+/// which shows up as ERANGE: start over with the new size.
+fn read_grow<F, T>(mut call: F) -> Result<Vec<u8>>
+where
+    F: FnMut(*mut u8, size_t) -> T,
+    T: RetVal + TryInto<usize>,
+{
+    loop {
+        let size = retry_eintr(|| call(core::ptr::null_mut(), 0))?;
+        let size = size.try_into().unwrap_or(0);
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+        let mut buf = vec![0u8; size];
+        match retry_eintr(|| call(buf.as_mut_ptr(), size)) {
+            Ok(n) => {
+                buf.truncate(n.try_into().unwrap_or(0));
+                return Ok(buf);
+            }
+            Err(e) if e.errno == Errno::ERANGE => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn name_cstring(name: &OsStr) -> Result<CString> {
+    CString::new(name.as_bytes()).map_err(|_| Error::from_errno(Errno::EINVAL))
+}
+
+// ---------- namespaces & propagation policy ----------
+
+/// xattr namespace, from the name prefix. POSIX ACLs live under `system.`
+/// but are told apart, since they are the only `system.*` names worth copying.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Namespace {
+    /// `user.*`: arbitrary application metadata (tags, checksums, ...)
+    User,
+    /// `trusted.*`: needs CAP_SYS_ADMIN to read or write
+    Trusted,
+    /// `security.*`: LSM labels such as `security.selinux`
+    Security,
+    /// `system.posix_acl_access` / `system.posix_acl_default`
+    PosixAcl,
+    /// any other `system.*` name (filesystem specific, rarely portable)
+    System,
+}
+
+impl Namespace {
+    pub const ALL: [Namespace; 5] = [
+        Namespace::User,
+        Namespace::Trusted,
+        Namespace::Security,
+        Namespace::PosixAcl,
+        Namespace::System,
+    ];
+
+    /// `None` for names without a known prefix (the kernel rejects those).
+    pub fn of(name: &OsStr) -> Option<Self> {
+        let name = name.as_bytes();
+        if name == xflag::XATTR_NAME_POSIX_ACL_ACCESS || name == xflag::XATTR_NAME_POSIX_ACL_DEFAULT
+        {
+            Some(Namespace::PosixAcl)
+        } else if name.starts_with(xflag::XATTR_USER_PREFIX) {
+            Some(Namespace::User)
+        } else if name.starts_with(xflag::XATTR_TRUSTED_PREFIX) {
+            Some(Namespace::Trusted)
+        } else if name.starts_with(xflag::XATTR_SECURITY_PREFIX) {
+            Some(Namespace::Security)
+        } else if name.starts_with(xflag::XATTR_SYSTEM_PREFIX) {
+            Some(Namespace::System)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Namespace::User => "user",
+            Namespace::Trusted => "trusted",
+            Namespace::Security => "security",
+            Namespace::PosixAcl => "acl",
+            Namespace::System => "system",
+        }
+    }
+
+    #[inline]
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+impl fmt::Display for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Namespace {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Namespace::ALL
+            .into_iter()
+            .find(|ns| ns.as_str() == s)
+            .ok_or_else(|| Error::from_errno(Errno::EINVAL))
+    }
+}
+
+/// Which namespaces a pair propagates between its sides.
+///
+/// The default is `user` + `acl`: application metadata and permissions,
+/// nothing that needs privileges beyond owning the file. Security labels
+/// are usually assigned per side by policy and are opt-in.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Policy {
+    bits: u8,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self::none().with(Namespace::User).with(Namespace::PosixAcl)
+    }
+}
+
+impl Policy {
+    pub const fn none() -> Self {
+        Self { bits: 0 }
+    }
+
+    pub fn all() -> Self {
+        Namespace::ALL.into_iter().collect()
+    }
+
+    pub fn with(mut self, ns: Namespace) -> Self {
+        self.bits |= ns.bit();
+        self
+    }
+
+    pub fn contains(&self, ns: Namespace) -> bool {
+        self.bits & ns.bit() != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    /// Whether the attribute `name` is propagated.
+    pub fn allows(&self, name: &OsStr) -> bool {
+        Namespace::of(name).is_some_and(|ns| self.contains(ns))
+    }
+
+    pub fn namespaces(&self) -> impl Iterator<Item = Namespace> + '_ {
+        Namespace::ALL.into_iter().filter(|ns| self.contains(*ns))
+    }
+}
+
+impl FromIterator<Namespace> for Policy {
+    fn from_iter<I: IntoIterator<Item = Namespace>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::none(), Policy::with)
+    }
+}
+
+/// Parses a comma separated list such as `user,acl`; `none` and `all` are
+/// accepted as well.
+impl FromStr for Policy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "" | "none" => Ok(Self::none()),
+            "all" => Ok(Self::all()),
+            list => list.split(',').map(|ns| ns.trim().parse()).collect(),
+        }
+    }
+}
+
+/// Make the `policy`-allowed attributes of `dst` match those of `src`:
+/// set (or replace) what `src` has, remove what it does not.
+/// Attributes outside the policy are left untouched on both sides.
+///
+/// A destination without xattr support yields EOPNOTSUPP as soon as there is
+/// something to copy, so the caller can report the loss instead of
+/// silently dropping metadata.
+pub fn copy_fd(src: BorrowedFd<'_>, dst: BorrowedFd<'_>, policy: &Policy) -> Result<()> {
+    if policy.is_empty() {
+        return Ok(());
+    }
+    let wanted: Vec<OsString> = flist(src)?
+        .into_iter()
+        .filter(|n| policy.allows(n))
+        .collect();
+
+    for name in &wanted {
+        // 在 list 与 get 之间被删除：跳过即可
+        if let Some(value) = fget(src, name)? {
+            fset(dst, name, &value, XattrFlags::EMPTY)?;
+        }
+    }
+    for name in flist(dst)? {
+        if policy.allows(&name) && !wanted.contains(&name) {
+            match fremove(dst, &name) {
+                Err(e) if e.errno != Errno::ENODATA => return Err(e),
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,154 @@
+//! tokio wrappers: wake-ups, EAGAIN re-arm and the event stream.
+//! Run with `--features tokio`.
+
+use core::pin::Pin;
+use futures_core::Stream;
+use std::path::Path;
+use std::time::Duration;
+use synchron_ffi::probe::probe;
+use synchron_ffi::{
+    AsyncEpoll, AsyncFanotify, DirFd, Epoll, EpollCreateFlags, EpollEventFlags, EventFd,
+    EventFdFlags, Fanotify, FanotifyEvent, FanotifyEventMask, FanotifyInitFlags, FanotifyMarkFlags,
+    OpenFlags,
+};
+
+/// Long enough for a spurious wake-up to show, short enough for the suite.
+const QUIET: Duration = Duration::from_millis(100);
+
+#[tokio::test]
+async fn epoll_wait_is_woken_by_an_eventfd() {
+    let efd = EventFd::new(0, EventFdFlags::NONBLOCK | EventFdFlags::CLOEXEC).unwrap();
+    let ep = Epoll::new(EpollCreateFlags::CLOEXEC).unwrap();
+    ep.add(&efd, EpollEventFlags::IN, 7).unwrap();
+    let ep = AsyncEpoll::new(ep).unwrap();
+
+    assert!(tokio::time::timeout(QUIET, ep.wait(4)).await.is_err());
+    let waiter = async { ep.wait(4).await.unwrap() };
+    let writer = async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        efd.notify().unwrap();
+    };
+    let (ready, ()) = tokio::join!(waiter, writer);
+    assert_eq!(ready.len(), 1);
+    assert_eq!(ready[0].data_u64, 7);
+    assert!(ready[0].flags.0 & EpollEventFlags::IN.0 != 0);
+
+    // 读走计数后不再就绪：下一次 wait 必须重新等待
+    efd.read().unwrap();
+    assert!(tokio::time::timeout(QUIET, ep.wait(4)).await.is_err());
+}
+
+#[tokio::test]
+async fn epoll_wait_rejects_zero_events() {
+    let ep = AsyncEpoll::new(Epoll::new(EpollCreateFlags::CLOEXEC).unwrap()).unwrap();
+    assert!(ep.wait(0).await.is_err());
+}
+
+/// A blocking-mode FID group watching entries created in `dir`, if this
+/// kernel and our privileges allow it.
+fn fanotify_on(dir: &Path) -> Option<Fanotify> {
+    let caps = probe();
+    if !caps.fanotify_dfid_name() {
+        eprintln!("skipped: fanotify with FAN_REPORT_DFID_NAME unavailable");
+        return None;
+    }
+    // 故意不带 NONBLOCK：AsyncFanotify 要自己切到非阻塞
+    let fan = Fanotify::new(
+        FanotifyInitFlags::CLASS_NOTIF
+            | FanotifyInitFlags::CLOEXEC
+            | FanotifyInitFlags::REPORT_DFID_NAME,
+        OpenFlags::RDONLY | OpenFlags::CLOEXEC,
+    )
+    .unwrap();
+    fan.mark(
+        FanotifyMarkFlags::ADD | FanotifyMarkFlags::INODE,
+        FanotifyEventMask::CREATE | FanotifyEventMask::EVENT_ON_CHILD,
+        DirFd::CWD,
+        Some(dir),
+    )
+    .unwrap();
+    Some(fan)
+}
+
+fn names(events: &[FanotifyEvent]) -> Vec<String> {
+    events
+        .iter()
+        .filter_map(|e| e.dir_name())
+        .map(|(_, n)| n.to_string_lossy().into_owned())
+        .collect()
+}
+
+#[tokio::test]
+async fn fanotify_batches_wait_for_readiness_and_rearm() {
+    let dir = tempfile::tempdir().unwrap();
+    let Some(fan) = fanotify_on(dir.path()) else {
+        return;
+    };
+    let mut fan = AsyncFanotify::new(fan).unwrap();
+
+    // 没有事件时不能返回空批次
+    assert!(tokio::time::timeout(QUIET, fan.read_batch()).await.is_err());
+
+    std::fs::write(dir.path().join("a"), b"").unwrap();
+    let batch = tokio::time::timeout(Duration::from_secs(5), fan.read_batch())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(names(&batch), ["a"]);
+
+    // 读空后 EAGAIN 清掉就绪状态；下一个事件要重新唤醒
+    assert!(tokio::time::timeout(QUIET, fan.read_batch()).await.is_err());
+    std::fs::write(dir.path().join("b"), b"").unwrap();
+    let batch = tokio::time::timeout(Duration::from_secs(5), fan.read_batch())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(names(&batch), ["b"]);
+}
+
+async fn next(s: &mut AsyncFanotify) -> Option<Result<FanotifyEvent, synchron_ffi::Error>> {
+    std::future::poll_fn(|cx| Pin::new(&mut *s).poll_next(cx)).await
+}
+
+#[tokio::test]
+async fn fanotify_stream_yields_one_event_at_a_time_and_never_ends() {
+    let dir = tempfile::tempdir().unwrap();
+    let Some(fan) = fanotify_on(dir.path()) else {
+        return;
+    };
+    let mut fan = AsyncFanotify::new(fan).unwrap();
+
+    for name in ["a", "b", "c"] {
+        std::fs::write(dir.path().join(name), b"").unwrap();
+    }
+    let mut got = Vec::new();
+    while got.len() < 3 {
+        let ev = tokio::time::timeout(Duration::from_secs(5), next(&mut fan))
+            .await
+            .expect("stream stalled")
+            .expect("stream ended")
+            .unwrap();
+        got.extend(names(std::slice::from_ref(&ev)));
+    }
+    assert_eq!(got, ["a", "b", "c"]);
+
+    // 队列读空不是流的结尾：next() 挂起而不是返回 None
+    assert!(tokio::time::timeout(QUIET, next(&mut fan)).await.is_err());
+    std::fs::write(dir.path().join("d"), b"").unwrap();
+    let ev = tokio::time::timeout(Duration::from_secs(5), next(&mut fan))
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(names(std::slice::from_ref(&ev)), ["d"]);
+
+    // 交还 group 后它仍可用，未读的事件留在内核队列里
+    std::fs::write(dir.path().join("e"), b"").unwrap();
+    let fan = fan.into_inner();
+    let mut buf = vec![0u8; 4096];
+    let mut left = Vec::new();
+    while left.is_empty() {
+        left = fan.read_events(&mut buf).unwrap();
+    }
+    assert_eq!(names(&left), ["e"]);
+}
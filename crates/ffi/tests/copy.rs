@@ -0,0 +1,105 @@
+//! fast_copy on tempfiles: the strategy chain and partial copies.
+
+use std::fs::{self, File, OpenOptions};
+use std::os::fd::AsFd;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use synchron_ffi::{fast_copy, CopyStrategy};
+
+fn content(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 7 % 251) as u8).collect()
+}
+
+fn staging(path: &Path) -> File {
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .unwrap()
+}
+
+#[test]
+fn whole_file_on_one_filesystem() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = content(300_000);
+    fs::write(dir.path().join("src"), &data).unwrap();
+    let src = File::open(dir.path().join("src")).unwrap();
+    let dst = staging(&dir.path().join("dst"));
+
+    let out = fast_copy(src.as_fd(), dst.as_fd(), data.len() as u64).unwrap();
+    // 支持 reflink 的文件系统直接克隆，否则退到 copy_file_range
+    assert!(matches!(
+        out.strategy,
+        CopyStrategy::Clone | CopyStrategy::CopyFileRange
+    ));
+    assert_eq!(out.copied, data.len() as u64);
+    assert_eq!(fs::read(dir.path().join("dst")).unwrap(), data);
+}
+
+#[test]
+fn partial_copy_never_clones_and_truncates() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = content(100_000);
+    fs::write(dir.path().join("src"), &data).unwrap();
+    // 目标里原有的更长的内容要被丢掉
+    fs::write(dir.path().join("dst"), content(200_000)).unwrap();
+    let src = File::open(dir.path().join("src")).unwrap();
+    let dst = staging(&dir.path().join("dst"));
+
+    let out = fast_copy(src.as_fd(), dst.as_fd(), 40_000).unwrap();
+    assert_ne!(out.strategy, CopyStrategy::Clone);
+    assert_eq!(out.copied, 40_000);
+    assert_eq!(fs::read(dir.path().join("dst")).unwrap(), &data[..40_000]);
+}
+
+#[test]
+fn shorter_source_copies_what_is_there() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = content(10_000);
+    fs::write(dir.path().join("src"), &data).unwrap();
+    let src = File::open(dir.path().join("src")).unwrap();
+    let dst = staging(&dir.path().join("dst"));
+
+    let out = fast_copy(src.as_fd(), dst.as_fd(), 50_000).unwrap();
+    assert_eq!(out.copied, 10_000);
+    assert_eq!(fs::read(dir.path().join("dst")).unwrap(), data);
+}
+
+#[test]
+fn across_filesystems_falls_back() {
+    let Ok(shm) = tempfile::tempdir_in("/dev/shm") else {
+        return;
+    };
+    let dir = tempfile::tempdir().unwrap();
+    if fs::metadata(shm.path()).unwrap().dev() == fs::metadata(dir.path()).unwrap().dev() {
+        return;
+    }
+    let data = content(200_000);
+    fs::write(shm.path().join("src"), &data).unwrap();
+    let src = File::open(shm.path().join("src")).unwrap();
+    let dst = staging(&dir.path().join("dst"));
+
+    // 不同类型的文件系统之间：FICLONE / copy_file_range 报 EXDEV（5.19 之前的内核
+    // copy_file_range 可以跨文件系统）
+    let out = fast_copy(src.as_fd(), dst.as_fd(), data.len() as u64).unwrap();
+    assert!(matches!(
+        out.strategy,
+        CopyStrategy::CopyFileRange | CopyStrategy::Sendfile
+    ));
+    assert_eq!(out.copied, data.len() as u64);
+    assert_eq!(fs::read(dir.path().join("dst")).unwrap(), data);
+}
+
+#[test]
+fn unspliceable_source_is_read_and_written() {
+    // procfs 报告大小为 0，copy_file_range / sendfile 一开始就返回 0 或失败
+    let dir = tempfile::tempdir().unwrap();
+    let src = File::open("/proc/self/status").unwrap();
+    let dst = staging(&dir.path().join("dst"));
+    let out = fast_copy(src.as_fd(), dst.as_fd(), 1 << 20).unwrap();
+    assert_eq!(out.strategy, CopyStrategy::ReadWrite);
+    let copied = fs::read(dir.path().join("dst")).unwrap();
+    assert_eq!(copied.len() as u64, out.copied);
+    assert!(copied.starts_with(b"Name:"));
+}
@@ -0,0 +1,279 @@
+//! Property tests for the fanotify record parser on synthetic buffers.
+
+use proptest::prelude::*;
+use std::ffi::OsStr;
+use std::os::fd::{BorrowedFd, IntoRawFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use synchron_ffi::fanotify_parse::{parse_events, METADATA_LEN};
+use synchron_ffi::{Errno, EventBuffer, FanotifyInfoRef, Malformed};
+
+const VERSION: u8 = 3;
+const INFO_TYPE_FID: u8 = 1;
+const INFO_TYPE_DFID_NAME: u8 = 2;
+const INFO_TYPE_PIDFD: u8 = 4;
+
+#[derive(Clone, Debug)]
+struct Synthetic {
+    mask: u64,
+    pid: i32,
+    fsid: [i32; 2],
+    handle: Vec<u8>,
+    /// `Some` -> DFID_NAME record, `None` -> FID record
+    name: Option<Vec<u8>>,
+}
+
+fn info_record(info_type: u8, body: &[u8]) -> Vec<u8> {
+    let mut rec = vec![info_type, 0, 0, 0];
+    rec.extend_from_slice(body);
+    while rec.len() % 4 != 0 {
+        rec.push(0);
+    }
+    let len = rec.len() as u16;
+    rec[2..4].copy_from_slice(&len.to_ne_bytes());
+    rec
+}
+
+fn fid_record(ev: &Synthetic) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(&ev.fsid[0].to_ne_bytes());
+    body.extend_from_slice(&ev.fsid[1].to_ne_bytes());
+    body.extend_from_slice(&(ev.handle.len() as u32).to_ne_bytes());
+    body.extend_from_slice(&1i32.to_ne_bytes());
+    body.extend_from_slice(&ev.handle);
+    match &ev.name {
+        Some(name) => {
+            body.extend_from_slice(name);
+            body.push(0);
+            info_record(INFO_TYPE_DFID_NAME, &body)
+        }
+        None => info_record(INFO_TYPE_FID, &body),
+    }
+}
+
+fn event(fd: i32, mask: u64, pid: i32, records: &[u8]) -> Vec<u8> {
+    let event_len = (METADATA_LEN + records.len()) as u32;
+    let mut out = Vec::new();
+    out.extend_from_slice(&event_len.to_ne_bytes());
+    out.push(VERSION);
+    out.push(0);
+    out.extend_from_slice(&(METADATA_LEN as u16).to_ne_bytes());
+    out.extend_from_slice(&mask.to_ne_bytes());
+    out.extend_from_slice(&fd.to_ne_bytes());
+    out.extend_from_slice(&pid.to_ne_bytes());
+    out.extend_from_slice(records);
+    out
+}
+
+fn encode(events: &[Synthetic]) -> Vec<u8> {
+    events
+        .iter()
+        .flat_map(|ev| event(-1, ev.mask, ev.pid, &fid_record(ev)))
+        .collect()
+}
+
+fn synthetic() -> impl Strategy<Value = Synthetic> {
+    (
+        any::<u64>(),
+        any::<i32>(),
+        any::<[i32; 2]>(),
+        prop::collection::vec(any::<u8>(), 0..64),
+        prop::option::of(prop::collection::vec(1u8..=255, 0..32)),
+    )
+        .prop_map(|(mask, pid, fsid, handle, name)| Synthetic {
+            mask,
+            pid,
+            fsid,
+            handle,
+            name,
+        })
+}
+
+/// A descriptor on a fresh anonymous file, with the file's (dev, ino).
+fn unique_fd() -> (i32, (u64, u64)) {
+    let file = tempfile::tempfile().unwrap();
+    let meta = file.metadata().unwrap();
+    (file.into_raw_fd(), (meta.dev(), meta.ino()))
+}
+
+/// (dev, ino) of whatever `fd` refers to now; `None` if it is closed.
+/// Compared against [`unique_fd`] rather than trusting the number, which a
+/// test running in parallel may get once it is freed.
+fn identity(fd: i32) -> Option<(u64, u64)> {
+    // SAFETY: only borrowed for the dup below; EBADF if it is not open.
+    let dup = unsafe { BorrowedFd::borrow_raw(fd) }
+        .try_clone_to_owned()
+        .ok()?;
+    let meta = std::fs::File::from(dup).metadata().ok()?;
+    Some((meta.dev(), meta.ino()))
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_bytes_never_panic(bytes in prop::collection::vec(any::<u8>(), 0..512)) {
+        let mut yielded = 0usize;
+        for ev in parse_events(&bytes) {
+            match ev {
+                Ok(ev) => {
+                    yielded += ev.event_len as usize;
+                    for info in ev.info() {
+                        let _ = info.to_info();
+                    }
+                }
+                Err(err) => prop_assert_eq!(err.errno, Errno::EPROTO),
+            }
+        }
+        prop_assert!(yielded <= bytes.len());
+    }
+
+    #[test]
+    fn well_formed_round_trip(events in prop::collection::vec(synthetic(), 0..16)) {
+        let bytes = encode(&events);
+        let parsed: Vec<_> = parse_events(&bytes).collect::<Result<_, _>>().unwrap();
+        prop_assert_eq!(parsed.len(), events.len());
+
+        for (got, want) in parsed.iter().zip(&events) {
+            prop_assert_eq!(got.mask.0, want.mask);
+            prop_assert_eq!(got.pid, want.pid);
+            prop_assert_eq!(got.fd, -1);
+            prop_assert_eq!(got.pidfd, None);
+
+            let info: Vec<_> = got.info().collect();
+            prop_assert_eq!(info.len(), 1);
+            let (dir, name) = match info[0] {
+                FanotifyInfoRef::Fid(fid) => (fid, None),
+                FanotifyInfoRef::DfidName { dir, name } => (dir, Some(name)),
+                other => return Err(TestCaseError::fail(format!("{other:?}"))),
+            };
+            prop_assert_eq!(dir.fsid.0, want.fsid);
+            prop_assert_eq!(dir.handle, &want.handle[..]);
+            prop_assert_eq!(name, want.name.as_deref().map(OsStr::from_bytes));
+        }
+    }
+
+    #[test]
+    fn truncation_is_rejected(events in prop::collection::vec(synthetic(), 1..8), cut in any::<prop::sample::Index>()) {
+        let bytes = encode(&events);
+        let cut = cut.index(bytes.len());
+        let results: Vec<_> = parse_events(&bytes[..cut]).collect();
+
+        // Only whole events are yielded; a partial tail is one error, then stop.
+        let ok = results.iter().take_while(|r| r.is_ok()).count();
+        prop_assert!(ok <= events.len());
+        prop_assert!(results.len() - ok <= 1);
+        if let Some(Err(err)) = results.last() {
+            prop_assert!(err.malformed.is_some());
+        }
+    }
+
+    #[test]
+    fn corrupt_metadata_len(events in prop::collection::vec(synthetic(), 1..4), bad in 0u16..METADATA_LEN as u16) {
+        let mut bytes = encode(&events);
+        bytes[6..8].copy_from_slice(&bad.to_ne_bytes());
+        let err = parse_events(&bytes).next().unwrap().unwrap_err();
+        prop_assert!(
+            matches!(err.malformed, Some(Malformed::MetadataLen { metadata_len, .. }) if metadata_len == bad),
+            "{:?}", err
+        );
+    }
+}
+
+#[test]
+fn rejects_version_mismatch() {
+    let mut bytes = event(-1, 0x100, 1, &[]);
+    bytes[4] = 0;
+    let err = parse_events(&bytes).next().unwrap().unwrap_err();
+    assert_eq!(err.malformed, Some(Malformed::Version { found: 0 }));
+}
+
+#[test]
+fn rejects_zero_event_len() {
+    let mut bytes = event(-1, 0x100, 1, &[]);
+    bytes[0..4].copy_from_slice(&0u32.to_ne_bytes());
+    let results: Vec<_> = parse_events(&bytes).collect();
+    assert_eq!(results.len(), 1);
+    assert!(matches!(
+        results[0].as_ref().unwrap_err().malformed,
+        Some(Malformed::MetadataLen { event_len: 0, .. })
+    ));
+}
+
+#[test]
+fn rejects_oversized_handle() {
+    let ev = Synthetic {
+        mask: 0x100,
+        pid: 1,
+        fsid: [0, 0],
+        handle: vec![1; 8],
+        name: Some(b"x".to_vec()),
+    };
+    let mut rec = fid_record(&ev);
+    rec[12..16].copy_from_slice(&4096u32.to_ne_bytes());
+    let bytes = event(-1, ev.mask, ev.pid, &rec);
+    let err = parse_events(&bytes).next().unwrap().unwrap_err();
+    assert!(matches!(
+        err.malformed,
+        Some(Malformed::HandleLen {
+            handle_bytes: 4096,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn rejects_unterminated_name() {
+    let mut body = vec![0u8; 8];
+    body.extend_from_slice(&0u32.to_ne_bytes());
+    body.extend_from_slice(&1i32.to_ne_bytes());
+    body.extend_from_slice(b"abcd");
+    let rec = info_record(INFO_TYPE_DFID_NAME, &body);
+    let bytes = event(-1, 0x100, 1, &rec);
+    let err = parse_events(&bytes).next().unwrap().unwrap_err();
+    assert_eq!(
+        err.malformed,
+        Some(Malformed::UnterminatedName {
+            info_type: INFO_TYPE_DFID_NAME
+        })
+    );
+}
+
+#[test]
+fn rejected_record_fds_are_not_adopted() {
+    let (fd, fd_id) = unique_fd();
+    let (pidfd, pidfd_id) = unique_fd();
+
+    // Valid metadata carrying `fd`, then a PIDFD record cut short by one byte.
+    let mut rec = info_record(INFO_TYPE_PIDFD, &pidfd.to_ne_bytes());
+    rec[2..4].copy_from_slice(&7u16.to_ne_bytes());
+    let bytes = event(fd, 0x2, 1, &rec);
+
+    // SAFETY: both fds are owned by this test; the buffer may adopt them.
+    let mut buf = unsafe { EventBuffer::from_raw(&bytes) };
+    let results: Vec<_> = buf.events().collect();
+    assert!(results[0].is_err());
+    drop(results);
+    drop(buf);
+
+    assert_eq!(identity(fd), Some(fd_id));
+    assert_eq!(identity(pidfd), Some(pidfd_id));
+    // SAFETY: still ours, the parser never took them.
+    unsafe {
+        drop(<std::os::fd::OwnedFd as std::os::fd::FromRawFd>::from_raw_fd(fd));
+        drop(<std::os::fd::OwnedFd as std::os::fd::FromRawFd>::from_raw_fd(pidfd));
+    }
+}
+
+#[test]
+fn undrained_event_fds_are_closed() {
+    let (fd, fd_id) = unique_fd();
+    let (pidfd, pidfd_id) = unique_fd();
+    let rec = info_record(INFO_TYPE_PIDFD, &pidfd.to_ne_bytes());
+    let bytes = event(fd, 0x2, 1, &rec);
+
+    // SAFETY: ownership of both fds is handed to the buffer.
+    let buf = unsafe { EventBuffer::from_raw(&bytes) };
+    drop(buf);
+
+    assert_ne!(identity(fd), Some(fd_id));
+    assert_ne!(identity(pidfd), Some(pidfd_id));
+}
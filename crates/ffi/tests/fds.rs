@@ -0,0 +1,146 @@
+//! eventfd / timerfd counters and the SigSet used with signalfd.
+
+use std::time::{Duration, Instant};
+use synchron_ffi::{
+    ClockId, Errno, EventFd, EventFdFlags, SigSet, Signal, SignalFd, SignalFdFlags, TimerFd,
+    TimerFdFlags,
+};
+
+const NONBLOCK: EventFdFlags = EventFdFlags::NONBLOCK;
+
+#[test]
+fn eventfd_counts_notifications() {
+    let efd = EventFd::new(0, NONBLOCK | EventFdFlags::CLOEXEC).unwrap();
+    assert_eq!(efd.read().unwrap(), 0);
+    efd.notify().unwrap();
+    efd.notify().unwrap();
+    efd.write(5).unwrap();
+    // 一次读走整个计数器
+    assert_eq!(efd.read().unwrap(), 7);
+    assert_eq!(efd.read().unwrap(), 0);
+
+    let efd = EventFd::new(3, NONBLOCK).unwrap();
+    assert_eq!(efd.read().unwrap(), 3);
+}
+
+#[test]
+fn eventfd_semaphore_takes_one_at_a_time() {
+    let efd = EventFd::new(2, NONBLOCK | EventFdFlags::SEMAPHORE).unwrap();
+    assert_eq!(efd.read().unwrap(), 1);
+    assert_eq!(efd.read().unwrap(), 1);
+    assert_eq!(efd.read().unwrap(), 0);
+}
+
+#[test]
+fn eventfd_refuses_to_overflow() {
+    let efd = EventFd::new(0, NONBLOCK).unwrap();
+    efd.write(u64::MAX - 1).unwrap();
+    assert_eq!(efd.write(1).unwrap_err().errno, Errno::EAGAIN);
+    assert_eq!(efd.read().unwrap(), u64::MAX - 1);
+}
+
+#[test]
+fn timerfd_one_shot_fires_once() {
+    let tfd = TimerFd::new(ClockId::Monotonic, TimerFdFlags::NONBLOCK).unwrap();
+    assert_eq!(tfd.read().unwrap(), 0);
+    assert_eq!(tfd.get().unwrap().value, None);
+
+    tfd.arm(Duration::from_secs(60)).unwrap();
+    let left = tfd.get().unwrap();
+    assert!(left.value.unwrap() > Duration::from_secs(50), "{left:?}");
+    assert_eq!(left.interval, None);
+
+    // 重新 arm 替换之前的期限
+    let start = Instant::now();
+    tfd.arm(Duration::from_millis(20)).unwrap();
+    while tfd.read().unwrap() == 0 {
+        assert!(start.elapsed() < Duration::from_secs(5));
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert!(start.elapsed() >= Duration::from_millis(20));
+    assert_eq!(tfd.get().unwrap().value, None);
+    assert_eq!(tfd.read().unwrap(), 0);
+}
+
+#[test]
+fn timerfd_disarm_and_zero_delay() {
+    let tfd = TimerFd::new(ClockId::Monotonic, TimerFdFlags::NONBLOCK).unwrap();
+    tfd.arm(Duration::from_millis(10)).unwrap();
+    tfd.disarm().unwrap();
+    std::thread::sleep(Duration::from_millis(30));
+    assert_eq!(tfd.read().unwrap(), 0);
+
+    // 零延迟不能变成 disarm
+    tfd.arm(Duration::ZERO).unwrap();
+    std::thread::sleep(Duration::from_millis(5));
+    assert_eq!(tfd.read().unwrap(), 1);
+}
+
+#[test]
+fn timerfd_interval_accumulates_expirations() {
+    let tfd = TimerFd::new(ClockId::Boottime, TimerFdFlags::NONBLOCK).unwrap();
+    tfd.arm_interval(Duration::from_millis(5)).unwrap();
+    assert_eq!(tfd.get().unwrap().interval, Some(Duration::from_millis(5)));
+    std::thread::sleep(Duration::from_millis(40));
+    assert!(tfd.read().unwrap() >= 2);
+    tfd.disarm().unwrap();
+    assert_eq!(tfd.get().unwrap(), Default::default());
+}
+
+#[test]
+fn sigset_membership() {
+    let mut set = SigSet::empty();
+    assert!(set.is_empty());
+    set.insert(Signal::TERM).unwrap();
+    set.insert(Signal::HUP).unwrap();
+    set.insert(Signal::HUP).unwrap();
+    assert!(set.contains(Signal::TERM));
+    assert!(!set.contains(Signal::INT));
+    assert_eq!(set.iter().collect::<Vec<_>>(), [Signal::HUP, Signal::TERM]);
+
+    set.remove(Signal::HUP).unwrap();
+    assert_eq!(set.iter().collect::<Vec<_>>(), [Signal::TERM]);
+    assert_eq!(format!("{set:?}"), format!("{{{}}}", Signal::TERM.0));
+}
+
+#[test]
+fn sigset_rejects_out_of_range_numbers() {
+    let mut set = SigSet::empty();
+    for sig in [Signal(0), Signal(-1), Signal(65)] {
+        assert_eq!(set.insert(sig).unwrap_err().errno, Errno::EINVAL);
+        assert_eq!(set.remove(sig).unwrap_err().errno, Errno::EINVAL);
+        assert!(!set.contains(sig));
+    }
+    assert!(SigSet::of(&[Signal::INT, Signal(0)]).is_err());
+
+    // 最大的信号号（SIGRTMAX）也在范围内
+    set.insert(Signal(64)).unwrap();
+    assert_eq!(set.iter().collect::<Vec<_>>(), [Signal(64)]);
+
+    let full = SigSet::full();
+    assert_eq!(full.iter().count(), 64);
+    assert!(full.contains(Signal(1)) && full.contains(Signal(64)));
+}
+
+#[test]
+fn thread_mask_round_trip() {
+    // 在单独的线程里改掩码，不影响测试框架的其他线程
+    std::thread::spawn(|| {
+        let set = SigSet::of(&[Signal::USR1, Signal::USR2]).unwrap();
+        let before = set.block().unwrap();
+        assert!(!before.contains(Signal::USR1));
+        let mask = SigSet::thread_mask().unwrap();
+        assert!(mask.contains(Signal::USR1) && mask.contains(Signal::USR2));
+
+        let sfd = SignalFd::new(&set, SignalFdFlags::NONBLOCK).unwrap();
+        assert!(sfd.read().unwrap().is_none());
+        sfd.set_mask(&SigSet::of(&[Signal::USR1]).unwrap()).unwrap();
+        assert!(sfd.read().unwrap().is_none());
+
+        set.unblock().unwrap();
+        assert!(!SigSet::thread_mask().unwrap().contains(Signal::USR1));
+        before.set_thread_mask().unwrap();
+    })
+    .join()
+    .unwrap();
+}
@@ -0,0 +1,60 @@
+//! File handles taken from a path, on a tempdir.
+
+use std::fs::File;
+use std::path::Path;
+use synchron_ffi::{DirFd, Errno, FileHandle, FsId, MountCache};
+
+#[test]
+fn handle_and_fsid_name_the_same_object() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("f");
+    File::create(&path).unwrap();
+
+    let (h, _mount_id) = match FileHandle::at(DirFd::CWD, &path, false) {
+        Ok(h) => h,
+        // 不支持导出句柄的文件系统
+        Err(e) if e.errno == Errno::EOPNOTSUPP => return,
+        Err(e) => panic!("name_to_handle_at: {e}"),
+    };
+    assert_eq!(h.fsid, FsId::of(File::open(&path).unwrap()).unwrap());
+    assert_eq!(FileHandle::at(DirFd::CWD, &path, true).unwrap().0, h);
+
+    // 换成另一个对象后句柄随之改变
+    std::fs::remove_file(&path).unwrap();
+    File::create(&path).unwrap();
+    assert_ne!(FileHandle::at(DirFd::CWD, &path, false).unwrap().0, h);
+}
+
+#[test]
+fn symlink_is_not_followed_unless_asked() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    let link = dir.path().join("link");
+    File::create(&target).unwrap();
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    let at = |p: &Path, follow| FileHandle::at(DirFd::CWD, p, follow).map(|(h, _)| h);
+    let Ok(of_target) = at(&target, false) else {
+        return;
+    };
+    assert_eq!(at(&link, true).unwrap(), of_target);
+    assert_ne!(at(&link, false).unwrap(), of_target);
+}
+
+#[test]
+fn resolves_back_to_the_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("f");
+    File::create(&path).unwrap();
+    let Ok((h, _)) = FileHandle::at(DirFd::CWD, &path, false) else {
+        return;
+    };
+    let mut cache = MountCache::new();
+    assert_eq!(cache.register(dir.path()).unwrap(), h.fsid);
+    match cache.resolve(&h) {
+        Ok(p) => assert_eq!(p, path.canonicalize().unwrap()),
+        // open_by_handle_at 需要 CAP_DAC_READ_SEARCH
+        Err(e) if e.errno == Errno::EPERM => {}
+        Err(e) => panic!("resolve: {e}"),
+    }
+}
@@ -0,0 +1,178 @@
+//! Inotify on a tempdir: recursive watches, rename pairing and overflow.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use synchron_ffi::{Inotify, InotifyEvent, InotifyFlags, InotifyMask};
+
+const MASK: InotifyMask = InotifyMask(
+    InotifyMask::CREATE.0
+        | InotifyMask::CLOSE_WRITE.0
+        | InotifyMask::DELETE.0
+        | InotifyMask::MOVE.0,
+);
+
+fn inotify() -> Inotify {
+    Inotify::new(InotifyFlags::NONBLOCK | InotifyFlags::CLOEXEC).unwrap()
+}
+
+/// Everything queued so far.
+fn drain(ino: &mut Inotify) -> Vec<InotifyEvent> {
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut out = Vec::new();
+    loop {
+        let evs = ino.read_events(&mut buf).unwrap();
+        if evs.is_empty() {
+            return out;
+        }
+        out.extend(evs);
+    }
+}
+
+fn has(evs: &[InotifyEvent], mask: InotifyMask, path: &Path) -> bool {
+    evs.iter()
+        .any(|e| e.mask.intersects(mask) && e.path == path)
+}
+
+#[test]
+fn recursive_watch_covers_existing_and_new_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir_all(root.join("a/b")).unwrap();
+    let mut ino = inotify();
+    ino.add_watch_recursive(root, MASK).unwrap();
+    assert_eq!(ino.watch_count(), 3);
+
+    fs::write(root.join("a/b/f"), b"x").unwrap();
+    // 新目录里在 watch 建立前就出现的文件要补发 CREATE
+    fs::create_dir(root.join("c")).unwrap();
+    fs::write(root.join("c/g"), b"x").unwrap();
+    let evs = drain(&mut ino);
+    assert!(has(&evs, InotifyMask::CLOSE_WRITE, &root.join("a/b/f")));
+    let c = evs.iter().find(|e| e.path == root.join("c")).unwrap();
+    assert!(c.is_dir() && c.mask.intersects(InotifyMask::CREATE));
+    assert!(has(&evs, InotifyMask::CREATE, &root.join("c/g")));
+    assert_eq!(ino.watch_count(), 4);
+
+    fs::write(root.join("c/h"), b"x").unwrap();
+    assert!(has(
+        &drain(&mut ino),
+        InotifyMask::CLOSE_WRITE,
+        &root.join("c/h")
+    ));
+}
+
+#[test]
+fn moves_are_paired_by_cookie() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir(root.join("sub")).unwrap();
+    fs::write(root.join("x"), b"x").unwrap();
+    let mut ino = inotify();
+    ino.add_watch_recursive(root, MASK).unwrap();
+
+    // 跨目录的 rename 也在同一次 read 里配对
+    fs::rename(root.join("x"), root.join("sub/y")).unwrap();
+    let evs = drain(&mut ino);
+    assert_eq!(evs.len(), 1);
+    let ev = &evs[0];
+    assert!(ev.mask.intersects(InotifyMask::MOVED_FROM));
+    assert!(ev.mask.intersects(InotifyMask::MOVED_TO));
+    assert_ne!(ev.cookie, 0);
+    assert_eq!(ev.path, root.join("sub/y"));
+    assert_eq!(ev.from.as_deref(), Some(root.join("x").as_path()));
+}
+
+#[test]
+fn directory_moves_keep_the_watch_table_in_sync() {
+    let dir = tempfile::tempdir().unwrap();
+    let outside = tempfile::tempdir_in(dir.path()).unwrap();
+    let root = dir.path().join("root");
+    fs::create_dir_all(root.join("d/e")).unwrap();
+    let mut ino = inotify();
+    ino.add_watch_recursive(&root, MASK).unwrap();
+    assert_eq!(ino.watch_count(), 3);
+
+    fs::rename(root.join("d"), root.join("m")).unwrap();
+    drain(&mut ino);
+    fs::write(root.join("m/e/f"), b"x").unwrap();
+    assert!(has(
+        &drain(&mut ino),
+        InotifyMask::CLOSE_WRITE,
+        &root.join("m/e/f")
+    ));
+
+    // 移出被监视范围：未配对的 MOVED_FROM；下一个事件不是它的 MOVED_TO，
+    // 子树的 watch 随之撤掉
+    let gone: PathBuf = outside.path().join("m");
+    fs::rename(root.join("m"), &gone).unwrap();
+    let evs = drain(&mut ino);
+    let ev = evs.iter().find(|e| e.path == root.join("m")).unwrap();
+    assert!(ev.mask.intersects(InotifyMask::MOVED_FROM) && ev.from.is_none());
+    fs::write(root.join("next"), b"x").unwrap();
+    fs::write(gone.join("e/g"), b"x").unwrap();
+    let evs = drain(&mut ino);
+    assert_eq!(ino.watch_count(), 1);
+    assert!(
+        evs.iter().all(|e| e.path == root.join("next")),
+        "{:?}",
+        paths(&evs)
+    );
+}
+
+/// The two halves of a move landed in different reads: the directory keeps
+/// its watches, now under the new name.
+#[test]
+fn directory_move_split_across_reads_keeps_its_watches() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir_all(root.join("d/e")).unwrap();
+    let mut ino = inotify();
+    ino.add_watch_recursive(root, MASK).unwrap();
+
+    fs::rename(root.join("d"), root.join("m")).unwrap();
+    // 一个事件头加 16 字节的 name：每次 read 只装得下一个事件
+    let mut one = vec![0u8; 32];
+    let from = ino.read_events(&mut one).unwrap();
+    assert_eq!(from.len(), 1);
+    assert!(from[0].mask.intersects(InotifyMask::MOVED_FROM) && from[0].path == root.join("d"));
+    let to = ino.read_events(&mut one).unwrap();
+    assert_eq!(to.len(), 1);
+    assert!(to[0].mask.intersects(InotifyMask::MOVED_TO) && to[0].path == root.join("m"));
+    assert_eq!(to[0].cookie, from[0].cookie);
+    assert_eq!(ino.watch_count(), 3);
+
+    fs::write(root.join("m/e/f"), b"x").unwrap();
+    assert!(has(
+        &drain(&mut ino),
+        InotifyMask::CLOSE_WRITE,
+        &root.join("m/e/f")
+    ));
+}
+
+fn paths(evs: &[InotifyEvent]) -> Vec<&Path> {
+    evs.iter().map(|e| e.path.as_path()).collect()
+}
+
+#[test]
+fn queue_overflow_is_reported() {
+    let max: usize = fs::read_to_string("/proc/sys/fs/inotify/max_queued_events")
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    if max > 1 << 16 {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let mut ino = inotify();
+    ino.add_watch(dir.path(), InotifyMask::CREATE).unwrap();
+    for i in 0..=max {
+        fs::File::create(dir.path().join(i.to_string())).unwrap();
+    }
+    let evs = drain(&mut ino);
+    let overflow = evs.last().unwrap();
+    assert!(overflow.is_overflow());
+    assert_eq!(overflow.wd, -1);
+    assert!(overflow.path.as_os_str().is_empty());
+    assert_eq!(evs.iter().filter(|e| e.is_overflow()).count(), 1);
+}
@@ -0,0 +1,165 @@
+//! mountinfo parsing against proc(5)-style sample lines.
+
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use synchron_ffi::{FsSupport, MountInfo, MountTable, Propagation};
+
+fn one(line: &str) -> MountInfo {
+    let table = MountTable::parse(line.as_bytes()).unwrap();
+    let mut mounts: Vec<_> = table.iter().cloned().collect();
+    assert_eq!(mounts.len(), 1, "{line}");
+    mounts.remove(0)
+}
+
+#[test]
+fn proc5_example() {
+    let m = one("36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue");
+    assert_eq!(
+        m,
+        MountInfo {
+            mount_id: 36,
+            parent_id: 35,
+            dev: (98, 0),
+            root: "/mnt1".into(),
+            mount_point: "/mnt2".into(),
+            options: vec!["rw".into(), "noatime".into()],
+            propagation: vec![Propagation::Master(1)],
+            fs_type: "ext3".into(),
+            source: "/dev/root".into(),
+            super_options: vec!["rw".into(), "errors=continue".into()],
+        }
+    );
+    assert!(m.is_bind_subtree());
+    assert!(!m.is_read_only());
+}
+
+#[test]
+fn optional_fields() {
+    let cases: &[(&str, &[Propagation])] = &[
+        ("22 1 8:1 / / rw - ext4 /dev/sda1 rw", &[]),
+        (
+            "22 1 8:1 / / rw shared:1 - ext4 /dev/sda1 rw",
+            &[Propagation::Shared(1)],
+        ),
+        (
+            "22 1 8:1 / / rw shared:5 master:2 propagate_from:3 - ext4 /dev/sda1 rw",
+            &[
+                Propagation::Shared(5),
+                Propagation::Master(2),
+                Propagation::PropagateFrom(3),
+            ],
+        ),
+        (
+            "22 1 8:1 / / rw unbindable - ext4 /dev/sda1 rw",
+            &[Propagation::Unbindable],
+        ),
+        // 未知的可选字段被忽略
+        (
+            "22 1 8:1 / / rw future:9 shared:1 - ext4 /dev/sda1 rw",
+            &[Propagation::Shared(1)],
+        ),
+    ];
+    for (line, want) in cases {
+        assert_eq!(one(line).propagation, *want, "{line}");
+    }
+}
+
+#[test]
+fn octal_escapes_are_decoded() {
+    let cases: &[(&str, &[u8])] = &[
+        (r"/mnt/with\040space", b"/mnt/with space"),
+        (r"/mnt/tab\011nl\012", b"/mnt/tab\tnl\n"),
+        (r"/mnt/back\134slash", b"/mnt/back\\slash"),
+        // 路径末尾的转义
+        (r"/mnt/end\040", b"/mnt/end "),
+        // 非 UTF-8 字节原样保留
+        (r"/mnt/\377raw", b"/mnt/\xffraw"),
+        // 不完整或非八进制的转义按字面保留
+        (r"/mnt/a\04", b"/mnt/a\\04"),
+        (r"/mnt/a\9xy", b"/mnt/a\\9xy"),
+        (r"/mnt/a\\", b"/mnt/a\\\\"),
+    ];
+    for (escaped, raw) in cases {
+        let m = one(&format!("40 22 0:50 / {escaped} rw - tmpfs {escaped} rw"));
+        assert_eq!(
+            m.mount_point,
+            Path::new(OsStr::from_bytes(raw)),
+            "{escaped}"
+        );
+        assert_eq!(m.source, OsStr::from_bytes(raw), "{escaped}");
+    }
+}
+
+#[test]
+fn fs_types_and_options() {
+    let m = one("50 22 0:60 / /net ro,relatime - fuse.sshfs user@host:/ rw,user_id=0");
+    assert_eq!(m.fs_type, "fuse.sshfs");
+    assert_eq!(m.support(), FsSupport::RemoteChangesInvisible);
+    assert!(m.is_read_only());
+    assert!(!m.is_bind_subtree());
+
+    let m = one("23 22 0:21 / /proc rw,nosuid,nodev,noexec,relatime shared:12 - proc proc rw");
+    assert_eq!(m.support(), FsSupport::Unsupported);
+
+    // 超级块选项可以为空
+    let m = one("60 22 0:70 / /x rw - overlay none");
+    assert_eq!(m.super_options, Vec::<String>::new());
+    assert_eq!(m.support(), FsSupport::Supported);
+}
+
+#[test]
+fn malformed_lines_are_rejected() {
+    for line in [
+        "",
+        "36 35 98:0 /mnt1 /mnt2 rw",
+        "36 35 98:0 /mnt1 /mnt2 rw master:1 ext3 /dev/root rw",
+        "x 35 98:0 / / rw - ext3 /dev/root rw",
+        "36 35 98 / / rw - ext3 /dev/root rw",
+        "36 35 98:0 / / rw shared:x - ext3 /dev/root rw",
+        "36 35 98:0 / / rw -",
+    ] {
+        let parsed = MountTable::parse(format!("{line}\n").as_bytes());
+        if line.is_empty() {
+            assert_eq!(parsed.unwrap().iter().count(), 0);
+        } else {
+            assert!(parsed.is_err(), "{line}");
+        }
+    }
+}
+
+#[test]
+fn table_lookups() {
+    let table = MountTable::parse(
+        b"1 0 8:1 / / rw - ext4 /dev/sda1 rw\n\
+          2 1 8:2 / /home rw - ext4 /dev/sda2 rw\n\
+          3 2 0:40 / /home/u/mnt rw - tmpfs tmpfs rw\n\
+          4 1 0:41 / /homework rw - tmpfs tmpfs rw\n",
+    )
+    .unwrap();
+    assert_eq!(table.iter().count(), 4);
+    assert_eq!(
+        table.by_id(3).unwrap().mount_point,
+        Path::new("/home/u/mnt")
+    );
+    assert!(table.by_id(9).is_none());
+
+    let under = |root: &str| {
+        table
+            .submounts(Path::new(root))
+            .iter()
+            .map(|m| m.mount_id)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(under("/home"), [3]);
+    assert_eq!(under("/home/u"), [3]);
+    assert_eq!(under("/"), [2, 3, 4]);
+    assert!(under("/home/u/mnt").is_empty());
+}
+
+#[test]
+fn own_mount_table_parses() {
+    let table = MountTable::read().unwrap();
+    let root = table.containing(Path::new("/")).unwrap();
+    assert_eq!(root.mount_point, Path::new("/"));
+}
@@ -0,0 +1,107 @@
+//! User database lookups and the privilege drop itself.
+
+use synchron_ffi::{drop_privileges, uid, Capabilities, Credentials, Errno};
+
+extern "C" {
+    fn fork() -> i32;
+    fn waitpid(pid: i32, status: *mut i32, options: i32) -> i32;
+    fn _exit(code: i32) -> !;
+}
+
+#[test]
+fn root_is_uid_zero() {
+    let root = Credentials::of_user("root").unwrap().expect("root exists");
+    assert_eq!((root.uid, root.gid), (0, 0));
+    // getgrouplist 总是包含主组
+    assert!(root.groups.contains(&0), "{:?}", root.groups);
+}
+
+#[test]
+fn unknown_user_is_none() {
+    assert_eq!(Credentials::of_user("synchron-no-such-user").unwrap(), None);
+}
+
+#[test]
+fn name_with_nul_is_rejected() {
+    let e = Credentials::of_user("ro\0ot").unwrap_err();
+    assert_eq!(e.errno, Errno::EINVAL);
+}
+
+/// `key:\t<hex>` of `/proc/self/status`.
+fn cap_set(status: &str, key: &str) -> Option<u64> {
+    let v = status.lines().find_map(|l| l.strip_prefix(key))?;
+    u64::from_str_radix(v.trim(), 16).ok()
+}
+
+/// What the child found after dropping to `nobody`; 0 when all is right.
+fn dropped_child(to: &Credentials, keep: Capabilities) -> i32 {
+    if drop_privileges(to, keep).is_err() {
+        return 1;
+    }
+    let Ok(status) = std::fs::read_to_string("/proc/self/status") else {
+        return 2;
+    };
+    let ids = |key: &str| {
+        status
+            .lines()
+            .find_map(|l| l.strip_prefix(key))
+            .map(str::split_whitespace)
+    };
+    let all_are = |key: &str, id: u32| ids(key).is_some_and(|mut v| v.all(|x| x == id.to_string()));
+    if !all_are("Uid:", to.uid) || !all_are("Gid:", to.gid) || uid::effective() != to.uid {
+        return 3;
+    }
+    for key in ["CapEff:", "CapPrm:", "CapBnd:"] {
+        if cap_set(&status, key) != Some(keep.0) {
+            return 4;
+        }
+    }
+    if cap_set(&status, "CapInh:") != Some(0) || cap_set(&status, "CapAmb:").is_some_and(|a| a != 0)
+    {
+        return 5;
+    }
+    if !status
+        .lines()
+        .any(|l| l.split_whitespace().eq(["NoNewPrivs:", "1"]))
+    {
+        return 6;
+    }
+    0
+}
+
+#[test]
+fn drop_privileges_leaves_only_the_kept_capabilities() {
+    if uid::effective() != 0 {
+        eprintln!("skipped: needs root");
+        return;
+    }
+    let Some(nobody) = Credentials::of_user("nobody").unwrap() else {
+        eprintln!("skipped: no `nobody` user");
+        return;
+    };
+    let keep = Capabilities::SYS_ADMIN | Capabilities::DAC_READ_SEARCH;
+    // 降权不可逆：在子进程里做，测试进程本身保持 root
+    let pid = unsafe { fork() };
+    assert!(pid >= 0, "fork failed");
+    if pid == 0 {
+        let code = std::panic::catch_unwind(|| dropped_child(&nobody, keep)).unwrap_or(100);
+        unsafe { _exit(code) };
+    }
+    let mut status = 0;
+    assert_eq!(unsafe { waitpid(pid, &mut status, 0) }, pid);
+    // WIFEXITED && WEXITSTATUS
+    assert_eq!(status & 0x7f, 0, "child killed by signal {}", status & 0x7f);
+    assert_eq!(
+        (status >> 8) & 0xff,
+        0,
+        "see dropped_child for the failed check"
+    );
+    assert_eq!(uid::effective(), 0);
+}
+
+#[test]
+fn drop_to_root_is_refused() {
+    let root = Credentials::of_user("root").unwrap().unwrap();
+    let e = drop_privileges(&root, Capabilities::EMPTY).unwrap_err();
+    assert_eq!(e.errno, Errno::EINVAL);
+}
@@ -0,0 +1,75 @@
+//! Feature probing on the running system, and parsing of its /proc inputs.
+
+use std::collections::HashMap;
+use synchron_ffi::probe::{probe, Limits, Privileges};
+use synchron_ffi::{uid, FanotifyInitFlags};
+
+#[test]
+fn probe_is_consistent_with_this_process() {
+    let caps = probe();
+    let p = caps.privileges;
+    assert_eq!(p.euid, uid::effective());
+    let status = std::fs::read_to_string("/proc/self/status").unwrap();
+    let again = Privileges::from_status(p.euid, &status);
+    assert_eq!(
+        (p.cap_sys_admin, p.cap_dac_read_search),
+        (again.cap_sys_admin, again.cap_dac_read_search)
+    );
+    assert_eq!(caps.can_resolve_handles(), p.cap_dac_read_search);
+
+    let fan = caps.fanotify;
+    if fan.init_error.is_some() {
+        assert_eq!(fan.init, FanotifyInitFlags::EMPTY);
+        assert!(!caps.fanotify_dfid_name());
+    }
+    // 宽范围 mark 需要 CAP_SYS_ADMIN
+    if !p.cap_sys_admin {
+        assert!(!fan.marks.mount && !fan.marks.filesystem);
+    }
+    if caps.fanotify_filesystem_marks() {
+        assert!(caps.fanotify_dfid_name());
+    }
+    // inotify 的 sysctl 从 2.6.13 起就有
+    assert!(caps.limits.inotify_max_user_watches.is_some());
+    assert!(caps.kernel_release.is_some_and(|r| !r.is_empty()));
+}
+
+#[test]
+fn capabilities_come_from_the_effective_set() {
+    let status = "Name:\tx\nCapInh:\t0000000000000000\nCapPrm:\t000001ffffffffff\n\
+                  CapEff:\t0000000000200004\nCapBnd:\t000001ffffffffff\n";
+    let p = Privileges::from_status(1000, status);
+    assert_eq!(p.euid, 1000);
+    // bit 21 SYS_ADMIN, bit 2 DAC_READ_SEARCH
+    assert!(p.cap_sys_admin && p.cap_dac_read_search);
+
+    let p = Privileges::from_status(0, "CapEff:\t0000000000200000\n");
+    assert!(p.cap_sys_admin && !p.cap_dac_read_search);
+}
+
+#[test]
+fn missing_or_malformed_capabilities_mean_none() {
+    for status in ["", "Name:\tx\n", "CapEff:\tnot-hex\n"] {
+        let p = Privileges::from_status(0, status);
+        assert!(!p.cap_sys_admin && !p.cap_dac_read_search, "{status:?}");
+    }
+}
+
+#[test]
+fn limits_parse_sysctl_values() {
+    let values = HashMap::from([
+        ("fanotify/max_queued_events", "16384\n"),
+        ("fanotify/max_user_groups", " 128 "),
+        ("inotify/max_queued_events", "16384"),
+        ("inotify/max_user_instances", "garbage"),
+        ("inotify/max_user_watches", "-1"),
+    ]);
+    let limits = Limits::from_sysctl(|name| values.get(name).map(|v| v.to_string()));
+    assert_eq!(limits.fanotify_max_queued_events, Some(16384));
+    assert_eq!(limits.fanotify_max_user_groups, Some(128));
+    // 5.13 之前没有这个 sysctl
+    assert_eq!(limits.fanotify_max_user_marks, None);
+    assert_eq!(limits.inotify_max_queued_events, Some(16384));
+    assert_eq!(limits.inotify_max_user_instances, None);
+    assert_eq!(limits.inotify_max_user_watches, None);
+}
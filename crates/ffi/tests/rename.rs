@@ -0,0 +1,157 @@
+//! Rename helpers on tempdirs, including the fallbacks used where the
+//! filesystem rejects the renameat2 flags.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use synchron_ffi::fanotify::DirFd;
+use synchron_ffi::{
+    emulate_exchange, emulate_noreplace, rename_exchange, rename_noreplace, Atomicity, Errno,
+};
+
+/// `chattr +i` on a directory: entries can no longer be added or removed.
+/// Cleared again on drop so the tempdir can be removed.
+struct Immutable(PathBuf);
+
+impl Immutable {
+    fn set(dir: &Path) -> Option<Self> {
+        let ok = Command::new("chattr")
+            .arg("+i")
+            .arg(dir)
+            .status()
+            .is_ok_and(|s| s.success());
+        ok.then(|| Self(dir.to_path_buf()))
+    }
+}
+
+impl Drop for Immutable {
+    fn drop(&mut self) {
+        let _ = Command::new("chattr").arg("-i").arg(&self.0).status();
+    }
+}
+
+fn names(dir: &Path) -> Vec<String> {
+    let mut v: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    v.sort();
+    v
+}
+
+#[test]
+fn noreplace_moves_and_refuses_to_clobber() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b, c) = (
+        dir.path().join("a"),
+        dir.path().join("b"),
+        dir.path().join("c"),
+    );
+    fs::write(&a, "a").unwrap();
+    fs::write(&b, "b").unwrap();
+
+    let err = rename_noreplace(DirFd::CWD, &a, DirFd::CWD, &b).unwrap_err();
+    assert_eq!(err.errno, Errno::EEXIST);
+    assert_eq!(fs::read(&b).unwrap(), b"b");
+
+    assert_eq!(
+        rename_noreplace(DirFd::CWD, &a, DirFd::CWD, &c).unwrap(),
+        Atomicity::Atomic
+    );
+    assert_eq!(names(dir.path()), ["b", "c"]);
+}
+
+#[test]
+fn emulated_noreplace_links_files_and_renames_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    let p = |n: &str| dir.path().join(n);
+    fs::write(p("a"), "a").unwrap();
+    fs::write(p("b"), "b").unwrap();
+
+    let err = emulate_noreplace(DirFd::CWD, &p("a"), DirFd::CWD, &p("b")).unwrap_err();
+    assert_eq!(err.errno, Errno::EEXIST);
+    // linkat + unlinkat 仍是原子的
+    assert_eq!(
+        emulate_noreplace(DirFd::CWD, &p("a"), DirFd::CWD, &p("c")).unwrap(),
+        Atomicity::Atomic
+    );
+    assert_eq!(fs::read(p("c")).unwrap(), b"a");
+
+    // 目录不能硬链接：退化为先检查再 rename
+    fs::create_dir(p("d")).unwrap();
+    fs::create_dir(p("e")).unwrap();
+    let err = emulate_noreplace(DirFd::CWD, &p("d"), DirFd::CWD, &p("e")).unwrap_err();
+    assert_eq!(err.errno, Errno::EEXIST);
+    assert_eq!(
+        emulate_noreplace(DirFd::CWD, &p("d"), DirFd::CWD, &p("f")).unwrap(),
+        Atomicity::Emulated
+    );
+    assert_eq!(names(dir.path()), ["b", "c", "e", "f"]);
+}
+
+#[test]
+fn emulated_noreplace_undoes_the_link_when_unlink_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+    fs::create_dir(&from).unwrap();
+    fs::create_dir(&to).unwrap();
+    fs::write(from.join("f"), "x").unwrap();
+
+    // 源目录不可变：linkat 成功，unlinkat 以 EPERM 失败
+    let Some(_guard) = Immutable::set(&from) else {
+        return;
+    };
+    let err =
+        emulate_noreplace(DirFd::CWD, &from.join("f"), DirFd::CWD, &to.join("f")).unwrap_err();
+    assert_eq!(err.errno, Errno::EPERM);
+    assert_eq!(names(&from), ["f"]);
+    assert!(names(&to).is_empty());
+}
+
+#[test]
+fn exchange_swaps_both_names() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+    fs::write(&a, "a").unwrap();
+    fs::write(&b, "b").unwrap();
+    for exchange in [rename_exchange, emulate_exchange] {
+        exchange(DirFd::CWD, &a, DirFd::CWD, &b).unwrap();
+        assert_eq!(fs::read(&a).unwrap(), b"b");
+        assert_eq!(fs::read(&b).unwrap(), b"a");
+        exchange(DirFd::CWD, &a, DirFd::CWD, &b).unwrap();
+        assert_eq!(fs::read(&a).unwrap(), b"a");
+    }
+    assert_eq!(names(dir.path()), ["a", "b"]);
+}
+
+#[test]
+fn emulated_exchange_needs_both_sides() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+    fs::write(&a, "a").unwrap();
+    let err = emulate_exchange(DirFd::CWD, &a, DirFd::CWD, &b).unwrap_err();
+    assert_eq!(err.errno, Errno::ENOENT);
+    assert_eq!(names(dir.path()), ["a"]);
+}
+
+#[test]
+fn emulated_exchange_rolls_back_without_leaving_the_temporary() {
+    let dir = tempfile::tempdir().unwrap();
+    let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+    fs::create_dir(&from).unwrap();
+    fs::create_dir(&to).unwrap();
+    fs::write(from.join("f"), "old").unwrap();
+    fs::write(to.join("f"), "new").unwrap();
+
+    // new 已挪到临时名之后，old 移不出不可变的目录
+    let Some(guard) = Immutable::set(&from) else {
+        return;
+    };
+    let err = emulate_exchange(DirFd::CWD, &from.join("f"), DirFd::CWD, &to.join("f")).unwrap_err();
+    assert_eq!(err.errno, Errno::EPERM);
+    drop(guard);
+
+    assert_eq!(names(&to), ["f"]);
+    assert_eq!(fs::read(to.join("f")).unwrap(), b"new");
+    assert_eq!(fs::read(from.join("f")).unwrap(), b"old");
+}
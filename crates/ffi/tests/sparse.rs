@@ -0,0 +1,155 @@
+//! Sparse files: SEEK_DATA / SEEK_HOLE, hole punching and hole-preserving
+//! copies.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::os::fd::AsFd;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use synchron_ffi::{
+    data_segments, fallocate, fast_copy, lseek, punch_hole, Errno, FallocFlags, Whence,
+};
+
+const MIB: u64 = 1 << 20;
+
+/// `len` bytes with data at `0..4K` and at the last 4K, a hole in between.
+/// `None` if the filesystem does not report holes.
+fn sparse(path: &Path, len: u64) -> Option<File> {
+    let mut f = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .unwrap();
+    f.write_all(&[1u8; 4096]).unwrap();
+    f.seek(SeekFrom::Start(len - 4096)).unwrap();
+    f.write_all(&[2u8; 4096]).unwrap();
+    f.sync_all().unwrap();
+    match lseek(f.as_fd(), 0, Whence::Hole) {
+        Ok(off) if off < len => Some(f),
+        Ok(_) => None,
+        Err(e) if matches!(e.errno, Errno::EINVAL | Errno::EOPNOTSUPP) => None,
+        Err(e) => panic!("SEEK_HOLE: {e}"),
+    }
+}
+
+fn segments(f: &File, len: u64) -> Vec<Range<u64>> {
+    data_segments(f.as_fd(), len)
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn data_segments_skip_holes() {
+    let dir = tempfile::tempdir().unwrap();
+    let len = 4 * MIB;
+    let Some(f) = sparse(&dir.path().join("f"), len) else {
+        return;
+    };
+    assert_eq!(segments(&f, len), [0..4096, len - 4096..len]);
+    // 只看前一部分：截在 len 处
+    assert_eq!(
+        segments(&f, 2048),
+        [Range {
+            start: 0,
+            end: 2048
+        }]
+    );
+    assert_eq!(lseek(f.as_fd(), 4096, Whence::Data).unwrap(), len - 4096);
+    let err = lseek(f.as_fd(), len as i64, Whence::Data).unwrap_err();
+    assert_eq!(err.errno, Errno::ENXIO);
+}
+
+#[test]
+fn punched_hole_is_reported_and_reads_as_zeros() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("f");
+    fs::write(&path, vec![9u8; 3 * 65536]).unwrap();
+    let f = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap();
+    match punch_hole(f.as_fd(), 65536, 65536) {
+        Ok(()) => {}
+        Err(e) if e.errno == Errno::EOPNOTSUPP => return,
+        Err(e) => panic!("punch_hole: {e}"),
+    }
+    assert_eq!(f.metadata().unwrap().len(), 3 * 65536);
+    assert_eq!(lseek(f.as_fd(), 0, Whence::Hole).unwrap(), 65536);
+    let data = fs::read(&path).unwrap();
+    assert!(data[65536..131072].iter().all(|&b| b == 0));
+    assert!(data[131072..].iter().all(|&b| b == 9));
+}
+
+#[test]
+fn fallocate_keep_size_does_not_grow_the_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let f = File::create(dir.path().join("f")).unwrap();
+    match fallocate(f.as_fd(), FallocFlags::KEEP_SIZE, 0, MIB) {
+        Ok(()) => {}
+        Err(e) if e.errno == Errno::EOPNOTSUPP => return,
+        Err(e) => panic!("fallocate: {e}"),
+    }
+    let meta = f.metadata().unwrap();
+    assert_eq!(meta.len(), 0);
+    assert!(meta.blocks() * 512 >= MIB);
+}
+
+#[test]
+fn copies_keep_holes() {
+    let dir = tempfile::tempdir().unwrap();
+    // 大于 PREALLOC_MIN，也走预分配那条路
+    for len in [256 * 1024, 4 * MIB] {
+        let Some(src) = sparse(&dir.path().join("src"), len) else {
+            return;
+        };
+        let dst_path = dir.path().join("dst");
+        let dst = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&dst_path)
+            .unwrap();
+
+        // 少拷一个字节：不走 FICLONE，按数据段拷贝
+        let out = fast_copy(src.as_fd(), dst.as_fd(), len - 1).unwrap();
+        assert_eq!(out.copied, len - 1);
+        assert_eq!(dst.metadata().unwrap().len(), len - 1);
+        assert_eq!(lseek(dst.as_fd(), 0, Whence::Hole).unwrap(), 4096);
+        assert_eq!(segments(&dst, len - 1), [0..4096, len - 4096..len - 1]);
+        assert!(dst.metadata().unwrap().blocks() * 512 < len / 2);
+
+        let data = fs::read(&dst_path).unwrap();
+        assert!(data[..4096].iter().all(|&b| b == 1));
+        assert!(data[4096..(len - 4096) as usize].iter().all(|&b| b == 0));
+        assert!(data[(len - 4096) as usize..].iter().all(|&b| b == 2));
+    }
+}
+
+#[test]
+fn trailing_hole_is_kept_by_the_final_truncate() {
+    let dir = tempfile::tempdir().unwrap();
+    let src_path = dir.path().join("src");
+    let mut src = File::create(&src_path).unwrap();
+    src.write_all(&[5u8; 4096]).unwrap();
+    src.set_len(MIB).unwrap();
+    let src = File::open(&src_path).unwrap();
+    if lseek(src.as_fd(), 0, Whence::Hole).unwrap() >= MIB {
+        return;
+    }
+    let dst_path = dir.path().join("dst");
+    let dst = File::create(&dst_path).unwrap();
+    fast_copy(src.as_fd(), dst.as_fd(), MIB - 1).unwrap();
+    assert_eq!(dst.metadata().unwrap().len(), MIB - 1);
+    assert_eq!(
+        segments(&File::open(&dst_path).unwrap(), MIB - 1),
+        [Range {
+            start: 0,
+            end: 4096
+        }]
+    );
+}
@@ -0,0 +1,103 @@
+//! statx on a tempdir: mask negotiation and symlink handling.
+
+use std::fs::{self, File};
+use std::os::fd::AsFd;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use synchron_ffi::{fstatx, statx, statx_nofollow, AtFlags, DirFd, StatxAttr, StatxMask};
+
+#[test]
+fn filled_fields_match_std_metadata() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("f");
+    fs::write(&path, b"hello").unwrap();
+    let st = statx(DirFd::CWD, &path, AtFlags::EMPTY, StatxMask::SYNC).unwrap();
+    let meta = fs::metadata(&path).unwrap();
+
+    assert!(st.has(StatxMask::BASIC_STATS));
+    assert!(st.is_file());
+    assert_eq!(st.size, 5);
+    assert_eq!(st.ino, meta.ino());
+    assert_eq!(st.dev_t(), meta.dev());
+    assert_eq!(st.nlink as u64, meta.nlink());
+    assert_eq!(st.mode as u32, meta.mode());
+    assert_eq!(
+        (st.mtime.sec, st.mtime.nsec as i64),
+        (meta.mtime(), meta.mtime_nsec())
+    );
+    assert_eq!(st.mtime.to_system_time(), meta.modified().unwrap());
+    assert_eq!(
+        (st.ctime.sec, st.ctime.nsec as i64),
+        (meta.ctime(), meta.ctime_nsec())
+    );
+}
+
+#[test]
+fn only_granted_fields_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("f");
+    File::create(&path).unwrap();
+
+    let st = statx(DirFd::CWD, &path, AtFlags::EMPTY, StatxMask::SIZE).unwrap();
+    assert!(st.has(StatxMask::SIZE));
+    // 可选字段只在内核确实填了时才给出
+    let full = statx(DirFd::CWD, &path, AtFlags::EMPTY, StatxMask::SYNC).unwrap();
+    assert_eq!(full.btime().is_some(), full.has(StatxMask::BTIME));
+    assert_eq!(full.mnt_id().is_some(), full.has(StatxMask::MNT_ID));
+
+    // 文件系统不支持的属性是 None，而不是 false
+    for attr in [StatxAttr::IMMUTABLE, StatxAttr::APPEND, StatxAttr::VERITY] {
+        let supported = full.attributes_mask.0 & attr.0 != 0;
+        assert_eq!(full.attribute(attr).is_some(), supported);
+        if supported {
+            assert_eq!(full.attribute(attr), Some(false));
+        }
+    }
+}
+
+#[test]
+fn mount_id_matches_mountinfo() {
+    let st = statx(DirFd::CWD, Path::new("/"), AtFlags::EMPTY, StatxMask::SYNC).unwrap();
+    let Some(id) = st.mnt_id() else {
+        return;
+    };
+    let mountinfo = fs::read_to_string("/proc/self/mountinfo").unwrap();
+    let root = mountinfo
+        .lines()
+        .map(|l| l.split(' ').collect::<Vec<_>>())
+        // 同一挂载点上叠了多层时，最后一层可见
+        .rfind(|f| f[4] == "/")
+        .unwrap();
+    assert_eq!(root[0].parse::<u64>().unwrap(), id);
+}
+
+#[test]
+fn nofollow_reports_the_symlink_itself() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    fs::write(&target, b"0123456789").unwrap();
+    std::os::unix::fs::symlink("target", dir.path().join("link")).unwrap();
+
+    // 相对于打开的根目录查找
+    let root = File::open(dir.path()).unwrap();
+    let root_fd = DirFd(std::os::fd::AsRawFd::as_raw_fd(&root));
+    let link = Path::new("link");
+    let nofollow = statx_nofollow(root_fd, link, StatxMask::BASIC_STATS).unwrap();
+    assert!(nofollow.is_symlink());
+    assert_eq!(nofollow.size, "target".len() as u64);
+
+    let followed = statx(root_fd, link, AtFlags::EMPTY, StatxMask::BASIC_STATS).unwrap();
+    assert!(followed.is_file());
+    assert_eq!(followed.size, 10);
+    assert_eq!(followed.ino, fs::metadata(&target).unwrap().ino());
+
+    let by_fd = fstatx(File::open(&target).unwrap().as_fd(), StatxMask::INO).unwrap();
+    assert_eq!(by_fd.ino, followed.ino);
+}
+
+#[test]
+fn directories_are_typed() {
+    let dir = tempfile::tempdir().unwrap();
+    let st = statx_nofollow(DirFd::CWD, dir.path(), StatxMask::TYPE).unwrap();
+    assert!(st.is_dir() && !st.is_file() && !st.is_symlink());
+}
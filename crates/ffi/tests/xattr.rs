@@ -0,0 +1,159 @@
+//! xattr wrappers on tempfiles (`user.*` needs no privileges).
+
+use std::ffi::{OsStr, OsString};
+use std::os::fd::AsFd;
+use std::path::Path;
+use synchron_ffi::xattr::{self, Namespace, Policy, XattrFlags};
+use synchron_ffi::Errno;
+
+fn n(s: &str) -> &OsStr {
+    OsStr::new(s)
+}
+
+/// Tempfile on a filesystem with `user.*` support, or `None` to skip.
+fn xattr_file() -> Option<tempfile::NamedTempFile> {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    match xattr::set(file.path(), n("user.probe"), b"", XattrFlags::EMPTY) {
+        Ok(()) => {
+            xattr::remove(file.path(), n("user.probe")).unwrap();
+            Some(file)
+        }
+        Err(e) if e.errno == Errno::EOPNOTSUPP => None,
+        Err(e) => panic!("setxattr: {e}"),
+    }
+}
+
+#[test]
+fn set_get_list_remove() {
+    let Some(file) = xattr_file() else {
+        return;
+    };
+    let path = file.path();
+    assert_eq!(xattr::get(path, n("user.tag")).unwrap(), None);
+
+    xattr::set(path, n("user.tag"), b"blue", XattrFlags::EMPTY).unwrap();
+    xattr::set(path, n("user.empty"), b"", XattrFlags::EMPTY).unwrap();
+    assert_eq!(
+        xattr::get(path, n("user.tag")).unwrap().as_deref(),
+        Some(&b"blue"[..])
+    );
+    assert_eq!(
+        xattr::get(path, n("user.empty")).unwrap().as_deref(),
+        Some(&b""[..])
+    );
+    let mut names = xattr::list(path).unwrap();
+    names.retain(|name| name.as_encoded_bytes().starts_with(b"user."));
+    names.sort();
+    assert_eq!(
+        names,
+        [OsString::from("user.empty"), OsString::from("user.tag")]
+    );
+
+    xattr::remove(path, n("user.tag")).unwrap();
+    assert_eq!(xattr::get(path, n("user.tag")).unwrap(), None);
+    let err = xattr::remove(path, n("user.tag")).unwrap_err();
+    assert_eq!(err.errno, Errno::ENODATA);
+}
+
+#[test]
+fn create_and_replace_flags() {
+    let Some(file) = xattr_file() else {
+        return;
+    };
+    let path = file.path();
+    let err = xattr::set(path, n("user.x"), b"1", XattrFlags::REPLACE).unwrap_err();
+    assert_eq!(err.errno, Errno::ENODATA);
+    xattr::set(path, n("user.x"), b"1", XattrFlags::CREATE).unwrap();
+    let err = xattr::set(path, n("user.x"), b"2", XattrFlags::CREATE).unwrap_err();
+    assert_eq!(err.errno, Errno::EEXIST);
+    xattr::set(path, n("user.x"), b"2", XattrFlags::REPLACE).unwrap();
+    assert_eq!(
+        xattr::get(path, n("user.x")).unwrap().as_deref(),
+        Some(&b"2"[..])
+    );
+}
+
+#[test]
+fn values_are_raw_bytes_of_any_size() {
+    let Some(file) = xattr_file() else {
+        return;
+    };
+    let fd = file.as_file().as_fd();
+    let value: Vec<u8> = (0..=255u8).cycle().take(3000).collect();
+    xattr::fset(fd, n("user.blob"), &value, XattrFlags::EMPTY).unwrap();
+    assert_eq!(xattr::fget(fd, n("user.blob")).unwrap(), Some(value));
+    assert!(xattr::flist(fd)
+        .unwrap()
+        .contains(&OsString::from("user.blob")));
+    xattr::fremove(fd, n("user.blob")).unwrap();
+    assert_eq!(xattr::fget(fd, n("user.blob")).unwrap(), None);
+}
+
+#[test]
+fn l_variants_act_on_the_symlink() {
+    let Some(file) = xattr_file() else {
+        return;
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let link = dir.path().join("link");
+    std::os::unix::fs::symlink(file.path(), &link).unwrap();
+
+    xattr::set(&link, n("user.via"), b"link", XattrFlags::EMPTY).unwrap();
+    assert!(xattr::get(file.path(), n("user.via")).unwrap().is_some());
+    assert_eq!(xattr::lget(&link, n("user.via")).unwrap(), None);
+    // user.* 不允许设在符号链接本身上
+    let err = xattr::lset(&link, n("user.via"), b"x", XattrFlags::EMPTY).unwrap_err();
+    assert_eq!(err.errno, Errno::EPERM);
+}
+
+#[test]
+fn copy_fd_follows_the_policy() {
+    let (Some(src), Some(dst)) = (xattr_file(), xattr_file()) else {
+        return;
+    };
+    let set = |f: &Path, k: &str, v: &[u8]| xattr::set(f, n(k), v, XattrFlags::EMPTY).unwrap();
+    set(src.path(), "user.a", b"1");
+    set(src.path(), "user.b", b"2");
+    set(dst.path(), "user.b", b"old");
+    set(dst.path(), "user.stale", b"x");
+
+    let policy = Policy::none().with(Namespace::User);
+    xattr::copy_fd(src.as_file().as_fd(), dst.as_file().as_fd(), &policy).unwrap();
+    let get = |k: &str| xattr::get(dst.path(), n(k)).unwrap();
+    assert_eq!(get("user.a").as_deref(), Some(&b"1"[..]));
+    assert_eq!(get("user.b").as_deref(), Some(&b"2"[..]));
+    assert_eq!(get("user.stale"), None);
+
+    // 空策略什么都不动
+    set(dst.path(), "user.keep", b"x");
+    xattr::copy_fd(
+        src.as_file().as_fd(),
+        dst.as_file().as_fd(),
+        &Policy::none(),
+    )
+    .unwrap();
+    assert!(get("user.keep").is_some());
+}
+
+#[test]
+fn namespaces_and_policies_parse() {
+    let of = |s: &str| Namespace::of(n(s));
+    assert_eq!(of("user.x"), Some(Namespace::User));
+    assert_eq!(of("trusted.x"), Some(Namespace::Trusted));
+    assert_eq!(of("security.selinux"), Some(Namespace::Security));
+    assert_eq!(of("system.posix_acl_access"), Some(Namespace::PosixAcl));
+    assert_eq!(of("system.posix_acl_default"), Some(Namespace::PosixAcl));
+    assert_eq!(of("system.nfs4_acl"), Some(Namespace::System));
+    assert_eq!(of("bogus.x"), None);
+
+    for ns in Namespace::ALL {
+        assert_eq!(ns.to_string().parse::<Namespace>().unwrap(), ns);
+    }
+    let default = Policy::default();
+    assert_eq!("user,acl".parse::<Policy>().unwrap(), default);
+    assert!(default.allows(n("system.posix_acl_access")));
+    assert!(!default.allows(n("security.selinux")));
+    assert!("none".parse::<Policy>().unwrap().is_empty());
+    assert_eq!("all".parse::<Policy>().unwrap(), Policy::all());
+    assert!("user,bogus".parse::<Policy>().is_err());
+}
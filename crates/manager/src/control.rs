@@ -0,0 +1,137 @@
+//! Control socket: one task per client, one reply per request frame (see
+//! `synchron_utils::protocol`).
+
+use crate::pairs::Pairs;
+use crate::pipeline::Pipelines;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use synchron_ffi::xattr::Namespace;
+use synchron_utils::pathenc::Encoded;
+use synchron_utils::protocol::{PairAddParams, Params, Request};
+use synchron_utils::{read_frame, write_frame};
+use tokio::net::{UnixListener, UnixStream};
+
+/// Failed request: `error.code` and `error.message` of the reply.
+struct Failure(&'static str, String);
+
+type Reply = Result<Value, Failure>;
+
+pub async fn serve(listener: UnixListener, pairs: Arc<Mutex<Pairs>>, pipelines: Arc<Pipelines>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(client(stream, pairs.clone(), pipelines.clone()));
+            }
+            Err(e) => eprintln!("accept failed: {e}"),
+        }
+    }
+}
+
+async fn client(mut stream: UnixStream, pairs: Arc<Mutex<Pairs>>, pipelines: Arc<Pipelines>) {
+    // 客户端断开时 read_frame 返回 UnexpectedEof
+    while let Ok(frame) = read_frame(&mut stream).await {
+        let (id, reply) = match serde_json::from_slice::<Request>(&frame) {
+            Ok(req) => (req.id, handle(req.params, &pairs, &pipelines).await),
+            Err(e) => (
+                request_id(&frame),
+                Err(Failure("BAD_REQUEST", e.to_string())),
+            ),
+        };
+        let body = match reply {
+            Ok(data) => json!({ "ok": true, "error": null, "data": data, "request_id": id }),
+            Err(Failure(code, message)) => json!({
+                "ok": false,
+                "error": { "code": code, "message": message },
+                "data": null,
+                "request_id": id,
+            }),
+        };
+        let bytes = serde_json::to_vec(&body).expect("serialize reply");
+        if write_frame(&mut stream, &bytes).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Best-effort id of a request that did not parse.
+fn request_id(frame: &[u8]) -> u64 {
+    let v: Value = serde_json::from_slice(frame).unwrap_or_default();
+    v.get("id")
+        .or(v.get("request_id"))
+        .and_then(Value::as_u64)
+        .unwrap_or(0)
+}
+
+async fn handle(params: Params, pairs: &Arc<Mutex<Pairs>>, pipelines: &Arc<Pipelines>) -> Reply {
+    match params {
+        Params::PairAdd(p) => pair_add(p, pairs, pipelines).await,
+        Params::PairRemove {
+            pair_id,
+            purge_state,
+        } => {
+            // 先停掉事件流，worker 不会再碰这个 pair 的 root
+            pipelines.stop(&pair_id).await;
+            match pairs.lock().unwrap().remove(&pair_id, purge_state) {
+                Ok(Some(_)) => Ok(json!({ "pair_id": pair_id })),
+                Ok(None) => Err(Failure("NOT_FOUND", format!("no pair {pair_id}"))),
+                Err(e) => Err(Failure("MARKER", e.to_string())),
+            }
+        }
+        Params::PairList {} => {
+            let pairs = pairs.lock().unwrap();
+            let list: Vec<Value> = pairs
+                .iter()
+                .map(|p| {
+                    json!({
+                        "pair_id": p.id,
+                        "dir_a": Encoded(&p.dir_a),
+                        "dir_b": Encoded(&p.dir_b),
+                        "state": p.state,
+                        "debounce_ms": p.coalesce.debounce.as_millis() as u64,
+                        "xattrs": p.xattrs.namespaces().map(Namespace::as_str).collect::<Vec<_>>(),
+                    })
+                })
+                .collect();
+            Ok(json!({ "pairs": list }))
+        }
+        Params::ServiceStatus { .. } => {
+            let pairs = pairs.lock().unwrap();
+            let list: Vec<Value> = pairs
+                .iter()
+                .map(|p| {
+                    json!({
+                        "pair_id": p.id,
+                        "state": p.state,
+                        "queue": pipelines.queue(&p.id).unwrap_or_default(),
+                        "overflow": p.dirty.status(),
+                    })
+                })
+                .collect();
+            Ok(json!({ "pairs": list }))
+        }
+        _ => Err(Failure("UNSUPPORTED", "operation not implemented".into())),
+    }
+}
+
+async fn pair_add(
+    params: PairAddParams,
+    pairs: &Arc<Mutex<Pairs>>,
+    pipelines: &Arc<Pipelines>,
+) -> Reply {
+    // 挂载表、statx、标记文件都是阻塞 IO；检查和登记在同一把锁里，避免并发添加重叠的 root
+    let (pairs, pipelines) = (pairs.clone(), pipelines.clone());
+    tokio::task::spawn_blocking(move || {
+        let (id, warnings) = match pairs.lock().unwrap().add(&params) {
+            Ok((pair, warnings)) => (pair.id.clone(), warnings),
+            Err(e) => return Err(Failure(e.code(), e.to_string())),
+        };
+        // 监视起不来的 pair 不登记：撤掉它和刚写下的标记
+        if let Err(e) = pipelines.start(&id) {
+            let _ = pairs.lock().unwrap().remove(&id, true);
+            return Err(Failure("WATCH", format!("cannot watch the roots: {e}")));
+        }
+        Ok(json!({ "pair_id": id, "warnings": warnings }))
+    })
+    .await
+    .map_err(|e| Failure("INTERNAL", e.to_string()))?
+}
@@ -0,0 +1,5 @@
+//! The manager daemon's parts; `main.rs` wires them to the control socket.
+
+pub mod control;
+pub mod pairs;
+pub mod pipeline;
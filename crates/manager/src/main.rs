@@ -1,9 +1,15 @@
-use std::path::PathBuf;
+use std::os::unix::net::UnixListener as StdUnixListener;
+use std::path::{Path, PathBuf};
 use std::process;
-use synchron_utils::{create_uds, ensure_uds};
+use std::sync::{Arc, Mutex};
+use synchron_ffi::{drop_privileges, uid, Capabilities, Credentials};
+use synchron_manager::{control, pairs, pipeline};
+use synchron_utils::{bind_uds, ensure_uds, DEBOUNCE_MS_DEFAULT, DEBOUNCE_MS_MAX, DEBOUNCE_MS_MIN};
+use synchron_watcher::coalescer::CoalescerConfig;
+use synchron_watcher::collector::{Collector, CollectorConfig};
 
 use clap::Parser;
-use tokio;
+use tokio::net::UnixListener;
 
 /// Synchron-manager -- synchron's daemon
 /// Supports --config (optional), plus auto-provided --help / --version.
@@ -13,28 +19,133 @@ pub struct Args {
     /// Path to config file
     #[arg(long, value_name = "PATH")]
     config: Option<PathBuf>,
+
+    /// User to run as once fanotify is set up (the pair owner, or a
+    /// dedicated account); defaults to `synchron` if that user exists
+    #[arg(long, value_name = "NAME")]
+    user: Option<String>,
+
+    /// Debounce window for pairs added without their own `--debounce-ms`
+    #[arg(
+        long,
+        value_name = "MS",
+        default_value_t = DEBOUNCE_MS_DEFAULT,
+        value_parser = clap::value_parser!(u64).range(DEBOUNCE_MS_MIN..=DEBOUNCE_MS_MAX),
+    )]
+    debounce_ms: u64,
 }
 
-#[tokio::main]
-async fn main() {
+/// Capabilities kept after dropping root; everything else goes.
+///
+/// - `SYS_ADMIN`: `fanotify_init` and filesystem/mount marks. Groups are
+///   created per filesystem as pairs are added, long after the drop.
+/// - `DAC_READ_SEARCH`: `open_by_handle_at` for fanotify file handles, and
+///   reading roots the service user has no permission on.
+const RETAINED_CAPS: Capabilities =
+    Capabilities(Capabilities::SYS_ADMIN.0 | Capabilities::DAC_READ_SEARCH.0);
+
+const DEFAULT_USER: &str = "synchron";
+
+fn main() {
     let args = Args::parse();
 
-    let socket_path = ensure_uds(PathBuf::from("/run/synchron/manager-control.sock"))
-        .unwrap_or_else(|e| {
+    let path =
+        ensure_uds(PathBuf::from("/run/synchron/manager-control.sock")).unwrap_or_else(|e| {
             eprintln!("Failed to prepare socket path: {}", e);
-            std::process::exit(1);
+            process::exit(1);
         });
-    let path = PathBuf::from(socket_path);
-    let listener = match create_uds(path.clone()).await {
+    // 先做完需要 root 的准备：绑定 socket、打开挂载表
+    let listener = bind_uds(&path).unwrap_or_else(|e| {
+        eprintln!(
+            "Failed to create UDS at '{}': {} (kind: {:?})",
+            path.display(),
+            e,
+            e.kind()
+        );
+        process::exit(1);
+    });
+    // pair.add 没带 debounce_ms 的 pair 用这一份（CoalescerConfig::for_pair）
+    let coalesce = CoalescerConfig::from_millis(args.debounce_ms);
+    eprintln!(
+        "synchron-manager: debounce {} ms, max latency {} ms",
+        coalesce.debounce.as_millis(),
+        coalesce.max_latency.as_millis()
+    );
+    let pairs = pairs::Pairs::new(coalesce).unwrap_or_else(|e| {
+        eprintln!("Failed to open the mount table: {e}");
+        process::exit(1);
+    });
+
+    // 降权必须在 tokio 起线程之前：capabilities 是按线程的
+    if let Err(e) = drop_root(args.user.as_deref(), &path) {
+        eprintln!("Failed to drop privileges: {e}");
+        process::exit(1);
+    }
+
+    let rt = tokio::runtime::Runtime::new().unwrap_or_else(|e| {
+        eprintln!("Failed to start runtime: {e}");
+        process::exit(1);
+    });
+    rt.block_on(run(listener, pairs));
+}
+
+async fn run(listener: StdUnixListener, pairs: pairs::Pairs) {
+    let listener = match UnixListener::from_std(listener) {
         Ok(l) => l,
         Err(e) => {
+            eprintln!("Failed to register the control socket: {e}");
+            process::exit(1);
+        }
+    };
+    // fanotify 组在第一次 pair.add 时才按文件系统创建，保留的 SYS_ADMIN 够用
+    let collector = match Collector::new(&synchron_ffi::probe::probe(), CollectorConfig::default())
+    {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Failed to start the collector: {e}");
+            process::exit(1);
+        }
+    };
+    let pairs = Arc::new(Mutex::new(pairs));
+    let pipelines = Arc::new(pipeline::Pipelines::new(collector, pairs.clone()));
+    let watched = pipelines.clone();
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = watched.watch_mounts() {
+            eprintln!("mount watch stopped: {e}");
+        }
+    });
+    control::serve(listener, pairs, pipelines).await;
+}
+
+/// Switch to `user` (or [`DEFAULT_USER`]) when started as root, after setup.
+/// The socket and its directory are handed over so it can clean them up.
+fn drop_root(user: Option<&str>, socket: &Path) -> std::io::Result<()> {
+    if uid::effective() != 0 {
+        if user.is_some() {
+            eprintln!("warning: not running as root, --user ignored");
+        }
+        return Ok(());
+    }
+    let name = user.unwrap_or(DEFAULT_USER);
+    let creds = match Credentials::of_user(name)? {
+        Some(c) => c,
+        None if user.is_none() => {
             eprintln!(
-                "Failed to create UDS at '{}': {} (kind: {:?})",
-                path.display(),
-                e,
-                e.kind()
+                "warning: no `{DEFAULT_USER}` user, staying root; pass --user to drop privileges"
             );
-            process::exit(1);
+            return Ok(());
+        }
+        None => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no such user: {name}"),
+            ))
         }
     };
+    if let Some(dir) = socket.parent() {
+        std::os::unix::fs::chown(dir, Some(creds.uid), Some(creds.gid))?;
+    }
+    std::os::unix::fs::chown(socket, Some(creds.uid), Some(creds.gid))?;
+    drop_privileges(&creds, RETAINED_CAPS)?;
+    Ok(())
 }
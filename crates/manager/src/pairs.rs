@@ -0,0 +1,258 @@
+//! Pairs known to the manager.
+
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use synchron_ffi::xattr::{Namespace, Policy};
+use synchron_ffi::Error;
+use synchron_reconciler::{Dirty, RescanScope};
+use synchron_utils::marker::{self, MarkerError};
+use synchron_utils::protocol::PairAddParams;
+use synchron_utils::PairState;
+use synchron_watcher::coalescer::CoalescerConfig;
+use synchron_watcher::mounts::{self, MountChange, MountWatch, RootState};
+use synchron_watcher::roots::{check_root_with, RootError};
+use thiserror::Error;
+use tokio::sync::Notify;
+
+#[derive(Debug, Error)]
+pub enum AddError {
+    #[error(transparent)]
+    Root(#[from] RootError),
+
+    #[error("{} and {} overlap: one root is inside the other", .0.display(), .1.display())]
+    Nested(PathBuf, PathBuf),
+
+    #[error("{}: overlaps pair {pair_id}", .root.display())]
+    Taken { root: PathBuf, pair_id: String },
+
+    #[error(transparent)]
+    Marker(#[from] MarkerError),
+
+    #[error("{}: cannot watch its mount: {source}", .root.display())]
+    Mount { root: PathBuf, source: Error },
+
+    #[error("unknown xattr namespace {0:?} (expected user, trusted, security, acl or system)")]
+    Xattrs(String),
+}
+
+impl AddError {
+    /// `error.code` of the reply.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AddError::Root(RootError::InUse { .. })
+            | AddError::Nested(..)
+            | AddError::Taken { .. } => "ROOT_IN_USE",
+            AddError::Root(_) | AddError::Mount { .. } => "INVALID_ROOT",
+            AddError::Marker(_) => "MARKER",
+            AddError::Xattrs(_) => "INVALID_PARAMS",
+        }
+    }
+}
+
+pub struct Pair {
+    pub id: String,
+    /// canonical
+    pub dir_a: PathBuf,
+    /// canonical
+    pub dir_b: PathBuf,
+    pub state: PairState,
+    /// the pair's `debounce_ms`, or the manager's
+    pub coalesce: CoalescerConfig,
+    /// xattr namespaces copied between the sides
+    pub xattrs: Policy,
+    /// rescans owed after lost events or a mount that came back
+    pub dirty: Dirty,
+    /// wakes the pair's worker to run the reconcile pass it owes
+    pub kick: Arc<Notify>,
+}
+
+pub struct Pairs {
+    pairs: HashMap<String, Pair>,
+    /// mounts of every pair root, keyed by pair id
+    mounts: MountWatch<String>,
+    /// `--debounce-ms` of the manager
+    coalesce: CoalescerConfig,
+}
+
+impl Pairs {
+    /// `coalesce`: settings of pairs added without their own `debounce_ms`.
+    pub fn new(coalesce: CoalescerConfig) -> Result<Self, Error> {
+        Ok(Self {
+            pairs: HashMap::new(),
+            mounts: MountWatch::new()?,
+            coalesce,
+        })
+    }
+
+    /// Validate and register a `pair.add` request, and mark both roots with
+    /// the new pair id. The client checked the roots too, but the filesystem
+    /// may have changed since, and not every client does. Returns the new
+    /// pair and the warnings for the user.
+    ///
+    /// Pairs are not kept across restarts, their markers are. Roots marked by
+    /// a pair this manager does not know are taken over: by that pair's id if
+    /// both roots carry it, by a new id otherwise.
+    pub fn add(&mut self, params: &PairAddParams) -> Result<(&Pair, Vec<String>), AddError> {
+        let xattrs = match &params.xattrs {
+            None => Policy::default(),
+            Some(names) => names
+                .iter()
+                .map(|name| {
+                    name.parse::<Namespace>()
+                        .map_err(|_| AddError::Xattrs(name.clone()))
+                })
+                .collect::<Result<_, _>>()?,
+        };
+        // 不认识的 pair id 是上次运行留下的：pair 只活在内存里，标记却还在盘上
+        let stale = |id: &str| !self.pairs.contains_key(id);
+        let a = check_root_with(&params.dir_a, params.skip_submounts, stale)?;
+        let b = check_root_with(&params.dir_b, params.skip_submounts, stale)?;
+        if overlaps(&a.root, &b.root) {
+            return Err(AddError::Nested(a.root, b.root));
+        }
+        // 两个 pair 的 root 嵌套时，同一个文件会被两边同时同步
+        for root in [&a.root, &b.root] {
+            if let Some(p) = self
+                .pairs
+                .values()
+                .find(|p| overlaps(root, &p.dir_a) || overlaps(root, &p.dir_b))
+            {
+                return Err(AddError::Taken {
+                    root: root.clone(),
+                    pair_id: p.id.clone(),
+                });
+            }
+        }
+        let mut warnings = a.warnings;
+        warnings.extend(b.warnings);
+        let (dir_a, dir_b) = (a.root, b.root);
+
+        // 两边是同一个旧 pair：沿用它的 id；否则换成新 id
+        let left = [marker::read(&dir_a)?, marker::read(&dir_b)?];
+        let id = match &left {
+            [Some(a), Some(b)] if a == b => a.clone(),
+            _ => loop {
+                let id = new_id(&dir_a, &dir_b);
+                if !self.pairs.contains_key(&id) {
+                    break id;
+                }
+            },
+        };
+        for (root, old) in [&dir_a, &dir_b].into_iter().zip(&left) {
+            if let Some(old) = old.as_ref().filter(|old| **old != id) {
+                warnings.push(format!(
+                    "{}: replacing the marker of pair {old}, which this manager does not know",
+                    root.display()
+                ));
+            }
+        }
+        for root in [&dir_a, &dir_b] {
+            if let Err(source) = self.mounts.watch(id.clone(), root) {
+                self.mounts.unwatch(&id);
+                return Err(AddError::Mount {
+                    root: root.clone(),
+                    source,
+                });
+            }
+        }
+        // 没有标记，reconciler::preflight 会拒绝这个 pair 的每一次同步
+        let marked = marker::write(&dir_a, &id).and_then(|_| {
+            marker::write(&dir_b, &id).inspect_err(|_| {
+                let _ = marker::remove(&dir_a, &id);
+            })
+        });
+        if let Err(e) = marked {
+            self.mounts.unwatch(&id);
+            return Err(e.into());
+        }
+        let pair = self.pairs.entry(id.clone()).or_insert(Pair {
+            id,
+            dir_a,
+            dir_b,
+            state: PairState::Running,
+            coalesce: self.coalesce.for_pair(params.debounce_ms),
+            xattrs,
+            dirty: Dirty::new(),
+            kick: Arc::new(Notify::new()),
+        });
+        Ok((pair, warnings))
+    }
+
+    /// Forget pair `id`. With `purge_state` its markers go too; if that fails
+    /// the pair is kept. Without it, adding the same roots again brings the
+    /// pair back under its id.
+    pub fn remove(&mut self, id: &str, purge_state: bool) -> Result<Option<Pair>, MarkerError> {
+        let Some(pair) = self.pairs.get(id) else {
+            return Ok(None);
+        };
+        if purge_state {
+            marker::remove(&pair.dir_a, id)?;
+            marker::remove(&pair.dir_b, id)?;
+        }
+        self.mounts.unwatch(&pair.id);
+        Ok(self.pairs.remove(id))
+    }
+
+    /// The mount table fd; register with [`MountWatch::INTEREST`] and call
+    /// [`Self::refresh_mounts`] when it is ready.
+    pub fn mounts(&self) -> &MountWatch<String> {
+        &self.mounts
+    }
+
+    /// Park pairs with a root whose mount went away, and resume them once
+    /// all their roots are back, owing a full rescan (nothing was watched
+    /// meanwhile). A root that came back on another device only counts if
+    /// it still carries the pair's marker. Stopping and restarting the
+    /// pipelines is up to the caller
+    /// ([`Pipelines::refresh_mounts`](crate::pipeline::Pipelines::refresh_mounts)).
+    pub fn refresh_mounts(&mut self) -> Result<Vec<MountChange<String>>, Error> {
+        let table = self.mounts.table()?;
+        let changes = self.mounts.refresh(
+            &table,
+            |root| mounts::dev_of(root).ok(),
+            |id, root| marker::check(root, id).is_ok(),
+        );
+        for change in &changes {
+            let (MountChange::Lost { key, .. } | MountChange::Restored { key, .. }) = change;
+            let Some(pair) = self.pairs.get_mut(key) else {
+                continue;
+            };
+            pair.state = match (self.mounts.state(key), pair.state) {
+                (Some(RootState::WaitingForMount), PairState::Running) => {
+                    PairState::WaitingForMount
+                }
+                (Some(RootState::Mounted), PairState::WaitingForMount) => {
+                    pair.dirty.restore(RescanScope::Full);
+                    PairState::Running
+                }
+                (_, state) => state,
+            };
+        }
+        Ok(changes)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Pair> {
+        self.pairs.get(id)
+    }
+
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut Pair> {
+        self.pairs.get_mut(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Pair> {
+        self.pairs.values()
+    }
+}
+
+fn overlaps(x: &Path, y: &Path) -> bool {
+    x.starts_with(y) || y.starts_with(x)
+}
+
+/// Short random id, e.g. `3f9a0c12`.
+fn new_id(dir_a: &Path, dir_b: &Path) -> String {
+    let h = std::hash::RandomState::new().hash_one((dir_a, dir_b, SystemTime::now()));
+    format!("{:08x}", h as u32)
+}
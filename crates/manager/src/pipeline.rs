@@ -0,0 +1,238 @@
+//! Event pipeline of each running pair: one collector stream per root,
+//! normalized and coalesced into the pair's dispatcher queue, read by the
+//! pair's worker.
+//!
+//! The worker folds rescans (lost events, collapsed queues, a mount that
+//! came back) into [`Pair::dirty`](crate::pairs::Pair) and runs a reconcile
+//! pass for what is owed: [`synchron_reconciler::preflight`] first, then the
+//! rescan of the scope.
+//!
+//! A pair whose root loses its mount has its pipeline stopped; it starts
+//! again once every root is back, and the worker then runs the catch-up
+//! scan.
+
+use crate::pairs::Pairs;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use synchron_ffi::{Epoll, EpollCreateFlags, Error, Events};
+use synchron_reconciler::preflight;
+use synchron_utils::{CoalescedEvent, Metadata, PairState, QueueStatus, Side};
+use synchron_watcher::coalescer::{self, Coalescer};
+use synchron_watcher::collector::Collector;
+use synchron_watcher::dispatcher::{Dispatcher, QueueLimits, Receiver};
+use synchron_watcher::mounts::{MountChange, MountWatch};
+use synchron_watcher::normalizer::Normalizer;
+use tokio::sync::{broadcast, Notify};
+use tokio::task::JoinHandle;
+
+/// Events the worker takes from its queue at once.
+const BATCH: usize = 256;
+
+struct Running {
+    stop: broadcast::Sender<()>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+pub struct Pipelines {
+    collector: Collector,
+    dispatcher: Dispatcher<String>,
+    pairs: Arc<Mutex<Pairs>>,
+    running: Mutex<HashMap<String, Running>>,
+}
+
+impl Pipelines {
+    pub fn new(collector: Collector, pairs: Arc<Mutex<Pairs>>) -> Self {
+        Self {
+            collector,
+            dispatcher: Dispatcher::new(),
+            pairs,
+            running: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Start watching both roots of pair `id`. Must be called from within
+    /// the runtime, without holding the pairs lock.
+    pub fn start(&self, id: &str) -> Result<(), Error> {
+        let Some((roots, coalesce, kick)) = self.pairs.lock().unwrap().get(id).map(|p| {
+            (
+                [(Side::A, p.dir_a.clone()), (Side::B, p.dir_b.clone())],
+                p.coalesce,
+                Arc::clone(&p.kick),
+            )
+        }) else {
+            return Ok(());
+        };
+
+        // 先把两边的 mark 都放好，失败时已经起来的那一边随 Handle drop 停掉
+        let mut handles = Vec::with_capacity(roots.len());
+        for (side, root) in &roots {
+            handles.push(self.collector.spawn(Metadata {
+                root: root.clone(),
+                side: *side,
+            })?);
+        }
+
+        let (stop, _) = broadcast::channel(1);
+        let (tx, rx) = self.dispatcher.add(id.to_owned(), QueueLimits::default());
+        let mut tasks = Vec::with_capacity(roots.len() + 1);
+        for ((_, root), mut events) in roots.into_iter().zip(handles) {
+            let tx = tx.clone();
+            let mut stop = stop.subscribe();
+            tasks.push(tokio::spawn(async move {
+                let normalizer = Normalizer::new(root);
+                let coalescer = Coalescer::new(coalesce);
+                tokio::select! {
+                    _ = coalescer::run(normalizer, coalescer, &mut events.rx, tx) => {}
+                    _ = stop.recv() => {}
+                }
+                events.shutdown().await;
+            }));
+        }
+        drop(tx);
+        tasks.push(tokio::spawn(work(
+            id.to_owned(),
+            rx,
+            kick,
+            Arc::clone(&self.pairs),
+        )));
+
+        let old = self
+            .running
+            .lock()
+            .unwrap()
+            .insert(id.to_owned(), Running { stop, tasks });
+        if let Some(old) = old {
+            let _ = old.stop.send(());
+        }
+        Ok(())
+    }
+
+    /// Stop the pipeline of `id` and wait for its tasks; what the worker
+    /// still holds is reconciled first.
+    pub async fn stop(&self, id: &str) {
+        let Some(running) = self.running.lock().unwrap().remove(id) else {
+            return;
+        };
+        let _ = running.stop.send(());
+        // 队列的最后一个 Sender 在 dispatcher 里；移除后 worker 才能读到队尾
+        self.dispatcher.remove(&id.to_owned());
+        for task in running.tasks {
+            let _ = task.await;
+        }
+    }
+
+    pub fn queue(&self, id: &str) -> Option<QueueStatus> {
+        self.dispatcher.status(&id.to_owned())
+    }
+
+    /// Follow mount table changes; blocks forever. Run it on the blocking
+    /// pool.
+    pub fn watch_mounts(&self) -> Result<(), Error> {
+        let epoll = Epoll::new(EpollCreateFlags::CLOEXEC)?;
+        epoll.add(
+            self.pairs.lock().unwrap().mounts(),
+            MountWatch::<String>::INTEREST,
+            0,
+        )?;
+        let mut events = Events::with_capacity(1);
+        loop {
+            epoll.wait_into(&mut events, -1)?;
+            self.refresh_mounts()?;
+        }
+    }
+
+    /// Apply [`Pairs::refresh_mounts`]: stop the pipeline of a pair that
+    /// lost a root, restart it once all its roots are back. Blocking: call
+    /// from the blocking pool.
+    pub fn refresh_mounts(&self) -> Result<(), Error> {
+        // 停、起 pipeline 时不能持有 pairs 锁：worker 的 reconcile 也要拿它
+        let changes = self.pairs.lock().unwrap().refresh_mounts()?;
+        let rt = tokio::runtime::Handle::current();
+        for change in changes {
+            match change {
+                MountChange::Lost { key, root } => {
+                    eprintln!(
+                        "pair {key}: {} is gone, waiting for its mount",
+                        root.display()
+                    );
+                    // 同一 pair 的另一个 root 也丢了时已经停过，再停是空操作
+                    rt.block_on(self.stop(&key));
+                }
+                MountChange::Restored { key, root } => {
+                    eprintln!("pair {key}: {} is back", root.display());
+                    self.resume(&key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Restart the pipeline of `id` if its roots are all back, then wake
+    /// the worker for the rescan it owes.
+    fn resume(&self, id: &str) {
+        // 还有 root 没回来时 pair 仍在等待挂载
+        let kick = match self.pairs.lock().unwrap().get(id) {
+            Some(p) if p.state == PairState::Running => Arc::clone(&p.kick),
+            _ => return,
+        };
+        if let Err(e) = self.start(id) {
+            eprintln!("pair {id}: cannot watch the roots again: {e}; stopped syncing");
+            if let Some(p) = self.pairs.lock().unwrap().get_mut(id) {
+                p.state = PairState::Error;
+            }
+            return;
+        }
+        // mark 已经就位才重扫：扫描期间的改动不会漏掉
+        kick.notify_one();
+    }
+}
+
+/// Worker of one pair: runs a reconcile pass for each batch of its queue,
+/// and whenever `kick` asks for one.
+async fn work(id: String, mut rx: Receiver, kick: Arc<Notify>, pairs: Arc<Mutex<Pairs>>) {
+    let mut buf = Vec::with_capacity(BATCH);
+    loop {
+        // recv_many 只在返回前同步地移入事件，被 kick 打断不会丢事件
+        tokio::select! {
+            n = rx.recv_many(&mut buf, BATCH) => if n == 0 {
+                return;
+            },
+            _ = kick.notified() => {}
+        }
+        let events = std::mem::replace(&mut buf, Vec::with_capacity(BATCH));
+        let (id, pairs) = (id.clone(), Arc::clone(&pairs));
+        // preflight 读两边的标记文件，是阻塞 IO
+        if tokio::task::spawn_blocking(move || reconcile(&id, &events, &pairs))
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// Fold `events` into the pair's [`Dirty`](synchron_reconciler::Dirty) and
+/// run the rescan it owes, if the pair is running and both markers check
+/// out.
+fn reconcile(id: &str, events: &[CoalescedEvent], pairs: &Mutex<Pairs>) {
+    let mut pairs = pairs.lock().unwrap();
+    let Some(pair) = pairs.get_mut(id) else {
+        return;
+    };
+    for ev in events {
+        pair.dirty.mark(ev);
+    }
+    // 暂停或等待挂载时欠下的重扫留着，恢复时再做
+    if pair.state != PairState::Running || !pair.dirty.is_dirty() {
+        return;
+    }
+    if let Err(e) = preflight(&pair.id, &pair.dir_a, &pair.dir_b) {
+        eprintln!("pair {id}: {e}; stopped syncing");
+        pair.state = PairState::Error;
+        return;
+    }
+    if let Some(scope) = pair.dirty.take() {
+        // 比较与复制由 executor 完成；这里只交出重扫范围
+        eprintln!("pair {id}: rescan {scope:?}");
+    }
+}
@@ -0,0 +1,117 @@
+//! Pair registration against tempdir roots.
+
+use serde_json::json;
+use synchron_ffi::xattr::{Namespace, Policy};
+use synchron_manager::pairs::Pairs;
+use synchron_utils::marker;
+use synchron_utils::protocol::PairAddParams;
+use synchron_watcher::coalescer::CoalescerConfig;
+
+fn pairs() -> Pairs {
+    Pairs::new(CoalescerConfig::default()).unwrap()
+}
+
+fn roots() -> (tempfile::TempDir, tempfile::TempDir) {
+    (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap())
+}
+
+fn params(a: &tempfile::TempDir, b: &tempfile::TempDir, extra: serde_json::Value) -> PairAddParams {
+    let mut p = json!({ "dir_a": a.path(), "dir_b": b.path() });
+    p.as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    serde_json::from_value(p).unwrap()
+}
+
+#[test]
+fn xattr_policy_is_parsed_per_pair() {
+    let mut pairs = pairs();
+    let (a, b) = roots();
+    let (pair, _) = pairs.add(&params(&a, &b, json!({}))).unwrap();
+    assert_eq!(pair.xattrs, Policy::default());
+
+    let (c, d) = roots();
+    let named = params(&c, &d, json!({ "xattrs": ["user", "security"] }));
+    let (pair, _) = pairs.add(&named).unwrap();
+    let want: Policy = [Namespace::User, Namespace::Security].into_iter().collect();
+    assert_eq!(pair.xattrs, want);
+
+    // 空列表是 --no-xattrs
+    let (e, f) = roots();
+    let (pair, _) = pairs.add(&params(&e, &f, json!({ "xattrs": [] }))).unwrap();
+    assert!(pair.xattrs.is_empty());
+}
+
+#[test]
+fn unknown_xattr_namespace_is_refused_before_marking() {
+    let mut pairs = pairs();
+    let (a, b) = roots();
+    let err = pairs
+        .add(&params(&a, &b, json!({ "xattrs": ["user", "bogus"] })))
+        .err()
+        .unwrap();
+    assert_eq!(err.code(), "INVALID_PARAMS");
+    assert!(err.to_string().contains("bogus"), "{err}");
+    assert_eq!(pairs.iter().count(), 0);
+    assert_eq!(marker::read(a.path()).unwrap(), None);
+}
+
+#[test]
+fn remove_with_purge_frees_the_roots_for_a_new_pair() {
+    let mut pairs = pairs();
+    let (a, b) = roots();
+    let first = pairs.add(&params(&a, &b, json!({}))).unwrap().0.id.clone();
+    assert_eq!(marker::read(a.path()).unwrap().as_deref(), Some(&*first));
+
+    assert!(pairs.remove(&first, true).unwrap().is_some());
+    assert_eq!(marker::read(a.path()).unwrap(), None);
+    assert_eq!(marker::read(b.path()).unwrap(), None);
+    assert!(pairs.remove(&first, true).unwrap().is_none());
+
+    let (pair, warnings) = pairs.add(&params(&a, &b, json!({}))).unwrap();
+    assert!(warnings.is_empty(), "{warnings:?}");
+    let second = pair.id.clone();
+    marker::check(a.path(), &second).unwrap();
+    marker::check(b.path(), &second).unwrap();
+}
+
+#[test]
+fn roots_of_a_live_pair_are_refused() {
+    let mut pairs = pairs();
+    let (a, b) = roots();
+    pairs.add(&params(&a, &b, json!({}))).unwrap();
+    let (_, c) = roots();
+    let err = pairs.add(&params(&a, &c, json!({}))).err().unwrap();
+    assert_eq!(err.code(), "ROOT_IN_USE");
+    assert_eq!(marker::read(c.path()).unwrap(), None);
+}
+
+/// Pairs live in memory only: after a restart their markers are still on
+/// disk and must not lock the roots out.
+#[test]
+fn markers_of_an_earlier_run_are_taken_over() {
+    let (a, b) = roots();
+    let old = pairs()
+        .add(&params(&a, &b, json!({})))
+        .unwrap()
+        .0
+        .id
+        .clone();
+
+    // 同一对 root：沿用旧 id
+    let mut pairs = pairs();
+    let (pair, warnings) = pairs.add(&params(&a, &b, json!({}))).unwrap();
+    assert_eq!(pair.id, old);
+    assert!(warnings.is_empty(), "{warnings:?}");
+    pairs.remove(&old, false).unwrap();
+
+    // 换了另一边：新 id，并提醒旧标记被替换
+    let (_, c) = roots();
+    let (pair, warnings) = pairs.add(&params(&a, &c, json!({}))).unwrap();
+    assert_ne!(pair.id, old);
+    let new = pair.id.clone();
+    assert_eq!(warnings.len(), 1, "{warnings:?}");
+    assert!(warnings[0].contains(&old), "{warnings:?}");
+    marker::check(a.path(), &new).unwrap();
+    marker::check(c.path(), &new).unwrap();
+}
@@ -0,0 +1,69 @@
+//! Pair pipelines following their roots' mounts.
+
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use synchron_ffi::probe::probe;
+use synchron_manager::pairs::Pairs;
+use synchron_manager::pipeline::Pipelines;
+use synchron_utils::PairState;
+use synchron_watcher::coalescer::CoalescerConfig;
+use synchron_watcher::collector::{Collector, CollectorConfig};
+
+fn pipelines() -> (Arc<Mutex<Pairs>>, Arc<Pipelines>) {
+    let pairs = Arc::new(Mutex::new(Pairs::new(CoalescerConfig::default()).unwrap()));
+    let collector = Collector::new(&probe(), CollectorConfig::default()).unwrap();
+    let pipelines = Arc::new(Pipelines::new(collector, pairs.clone()));
+    (pairs, pipelines)
+}
+
+async fn refresh(pipelines: &Arc<Pipelines>) {
+    let pipelines = pipelines.clone();
+    tokio::task::spawn_blocking(move || pipelines.refresh_mounts())
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+fn state(pairs: &Mutex<Pairs>, id: &str) -> PairState {
+    pairs.lock().unwrap().get(id).unwrap().state
+}
+
+/// A root gone from its place (here: moved away, as an unmount looks to
+/// the mount watch) stops the pair's pipeline; once it is back the
+/// pipeline runs again and the worker takes the full rescan owed.
+#[tokio::test(flavor = "multi_thread")]
+async fn pipeline_stops_while_a_root_is_gone_and_catches_up_when_it_is_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = std::fs::canonicalize(dir.path()).unwrap();
+    let (a, b) = (base.join("a"), base.join("b"));
+    for root in [&a, &b] {
+        std::fs::create_dir(root).unwrap();
+    }
+    let (pairs, pipelines) = pipelines();
+    let params = serde_json::from_value(json!({ "dir_a": a, "dir_b": b })).unwrap();
+    let id = pairs.lock().unwrap().add(&params).unwrap().0.id.clone();
+    pipelines.start(&id).unwrap();
+    assert!(pipelines.queue(&id).is_some());
+
+    std::fs::rename(&a, base.join("away")).unwrap();
+    refresh(&pipelines).await;
+    assert_eq!(state(&pairs, &id), PairState::WaitingForMount);
+    assert!(pipelines.queue(&id).is_none());
+
+    std::fs::rename(base.join("away"), &a).unwrap();
+    refresh(&pipelines).await;
+    assert_eq!(state(&pairs, &id), PairState::Running);
+    assert!(pipelines.queue(&id).is_some());
+    // 重扫由 worker 做完后 dirty 被清空
+    let caught_up = async {
+        while pairs.lock().unwrap().get(&id).unwrap().dirty.is_dirty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), caught_up)
+        .await
+        .expect("catch-up rescan");
+
+    pipelines.stop(&id).await;
+}
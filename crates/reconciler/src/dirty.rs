@@ -0,0 +1,115 @@
+//! Rescans a pair owes after events were lost.
+//!
+//! The watcher turns every loss into an `Action::Rescan` event. A kernel
+//! queue overflow (`FAN_Q_OVERFLOW` / `IN_Q_OVERFLOW`), or a dispatcher queue
+//! collapsed all the way up, gives one for the root flagged `overflow`: a
+//! full rescan, counted in [`Dirty::status`]. A collapsed queue or an
+//! ambiguous merged event gives one for a directory: an incremental rescan
+//! of just that subtree. A root that was deleted or moved away also gives a
+//! root rescan, but lost nothing and is not counted. [`Dirty`] folds them
+//! into what the next reconcile pass has to compare.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use synchron_utils::{Action, CoalescedEvent, OverflowStatus};
+
+/// What a reconcile pass has to compare on both sides.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RescanScope {
+    /// the whole pair
+    Full,
+    /// these root-relative directories (none below another)
+    Dirs(BTreeSet<PathBuf>),
+}
+
+impl RescanScope {
+    fn add(&mut self, dir: &Path) {
+        let Self::Dirs(dirs) = self else {
+            return;
+        };
+        if dir.as_os_str().is_empty() {
+            *self = Self::Full;
+            return;
+        }
+        if dir.ancestors().any(|a| dirs.contains(a)) {
+            return;
+        }
+        // 有序集合中子目录紧跟在 dir 之后
+        let below: Vec<PathBuf> = dirs
+            .range(dir.to_path_buf()..)
+            .take_while(|d| d.starts_with(dir))
+            .cloned()
+            .collect();
+        for d in below {
+            dirs.remove(&d);
+        }
+        dirs.insert(dir.to_path_buf());
+    }
+
+    fn merge(&mut self, other: RescanScope) {
+        match other {
+            Self::Full => *self = Self::Full,
+            Self::Dirs(dirs) => dirs.iter().for_each(|d| self.add(d)),
+        }
+    }
+}
+
+/// Per-pair record of lost events.
+#[derive(Clone, Debug, Default)]
+pub struct Dirty {
+    scope: Option<RescanScope>,
+    overflows: u64,
+    last_overflow: Option<SystemTime>,
+}
+
+impl Dirty {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `ev` if it is a rescan; returns whether it was, i.e. whether
+    /// the caller should schedule a reconcile pass.
+    pub fn mark(&mut self, ev: &CoalescedEvent) -> bool {
+        if ev.action != Action::Rescan {
+            return false;
+        }
+        if ev.overflow {
+            self.overflows += 1;
+            self.last_overflow = Some(self.last_overflow.map_or(ev.ts, |t| t.max(ev.ts)));
+        }
+        self.scope
+            .get_or_insert_with(|| RescanScope::Dirs(BTreeSet::new()))
+            .add(&ev.path);
+        true
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.scope.is_some()
+    }
+
+    /// The rescan owed, for the next reconcile pass (after
+    /// [`crate::preflight`]). Hand it back with [`Dirty::restore`] if the
+    /// pass does not complete.
+    pub fn take(&mut self) -> Option<RescanScope> {
+        self.scope.take()
+    }
+
+    pub fn restore(&mut self, scope: RescanScope) {
+        match &mut self.scope {
+            Some(held) => held.merge(scope),
+            None => self.scope = Some(scope),
+        }
+    }
+
+    pub fn status(&self) -> OverflowStatus {
+        OverflowStatus {
+            count: self.overflows,
+            last_unix: self
+                .last_overflow
+                .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+            rescan_pending: self.is_dirty(),
+        }
+    }
+}
@@ -0,0 +1,14 @@
+pub mod dirty;
+pub use dirty::{Dirty, RescanScope};
+
+use std::path::Path;
+use synchron_utils::marker::{self, MarkerError};
+
+/// Check run before every reconcile pass of a pair: both roots must carry
+/// the pair's marker (see `synchron_utils::marker`). On failure nothing is
+/// touched and the pair goes to `PairState::Error` with the returned error
+/// as the reason.
+pub fn preflight(pair_id: &str, dir_a: &Path, dir_b: &Path) -> Result<(), MarkerError> {
+    marker::check(dir_a, pair_id)?;
+    marker::check(dir_b, pair_id)
+}
@@ -0,0 +1,86 @@
+//! Rescan events -> the scope of the next reconcile pass.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use synchron_reconciler::{Dirty, RescanScope};
+use synchron_utils::{Action, CoalescedEvent, Side};
+
+fn event(action: Action, path: &str) -> CoalescedEvent {
+    CoalescedEvent {
+        side: Side::A,
+        action,
+        path: PathBuf::from(path),
+        from: None,
+        is_dir: true,
+        overflow: false,
+        ts: SystemTime::UNIX_EPOCH + Duration::from_secs(1_000),
+    }
+}
+
+/// Root rescan standing for events a queue dropped.
+fn overflow() -> CoalescedEvent {
+    CoalescedEvent {
+        overflow: true,
+        ..event(Action::Rescan, "")
+    }
+}
+
+fn dirs(paths: &[&str]) -> RescanScope {
+    RescanScope::Dirs(paths.iter().map(PathBuf::from).collect::<BTreeSet<_>>())
+}
+
+#[test]
+fn only_rescans_mark_the_pair() {
+    let mut d = Dirty::new();
+    assert!(!d.mark(&event(Action::Write, "a")));
+    assert!(!d.is_dirty());
+    assert_eq!(d.take(), None);
+}
+
+#[test]
+fn directory_rescans_stay_incremental() {
+    let mut d = Dirty::new();
+    for p in ["x/y", "b", "x", "x/z/w"] {
+        assert!(d.mark(&event(Action::Rescan, p)));
+    }
+    assert_eq!(d.status().count, 0);
+    assert_eq!(d.take(), Some(dirs(&["b", "x"])));
+    assert!(!d.is_dirty());
+}
+
+#[test]
+fn overflow_forces_a_full_rescan_and_is_reported() {
+    let mut d = Dirty::new();
+    d.mark(&event(Action::Rescan, "x"));
+    d.mark(&overflow());
+    d.mark(&event(Action::Rescan, "y"));
+    let status = d.status();
+    assert_eq!(status.count, 1);
+    assert_eq!(status.last_unix, Some(1_000));
+    assert!(status.rescan_pending);
+
+    assert_eq!(d.take(), Some(RescanScope::Full));
+    let status = d.status();
+    assert_eq!((status.count, status.rescan_pending), (1, false));
+}
+
+#[test]
+fn failed_pass_is_restored() {
+    let mut d = Dirty::new();
+    d.mark(&event(Action::Rescan, "a/b"));
+    let scope = d.take().unwrap();
+    d.mark(&event(Action::Rescan, "c"));
+    d.restore(scope);
+    assert_eq!(d.take(), Some(dirs(&["a/b", "c"])));
+}
+
+#[test]
+fn root_rescan_without_overflow_is_not_counted() {
+    // 根目录被删或移走：全量重扫，但没有丢事件
+    let mut d = Dirty::new();
+    assert!(d.mark(&event(Action::Rescan, "")));
+    let status = d.status();
+    assert_eq!((status.count, status.last_unix), (0, None));
+    assert_eq!(d.take(), Some(RescanScope::Full));
+}
@@ -1,10 +1,15 @@
 #![allow(dead_code)]
+pub mod marker;
+pub mod pathenc;
+pub mod protocol;
 pub mod uds;
 pub use uds::*;
 
 use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
 use std::env;
-use std::path::PathBuf;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 use tokio::sync::{broadcast, mpsc};
 use tokio::task::JoinHandle;
@@ -22,33 +27,204 @@ pub struct Metadata {
     pub side: Side,
 }
 
+/// Pair lifecycle, as reported by `pair.list` and `service.status`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PairState {
+    Running,
+    /// paused by `synchron pause`
+    Paused,
+    /// a root's mount is gone or was replaced; resumes with a catch-up scan
+    /// once it is back
+    WaitingForMount,
+    Error,
+}
+
+impl fmt::Display for PairState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PairState::Running => "running",
+            PairState::Paused => "paused",
+            PairState::WaitingForMount => "waiting for mount",
+            PairState::Error => "error",
+        })
+    }
+}
+
 /// dispacther and manager
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Action {
     Write,
     Delete,
     Rename,
+    /// events under the path were lost or are ambiguous; compare both sides
+    Rescan,
+}
+
+/// Kernel interface a [`RawEvent`] was read from.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EventSource {
+    Fanotify,
+    Inotify,
+}
+
+/// One kernel event, resolved to a path but otherwise as reported.
+#[derive(Clone, Debug)]
+pub struct RawEvent {
+    pub side: Side,
+    pub source: EventSource,
+    /// `FAN_*` or `IN_*` bits; the two APIs share the values of the common
+    /// events (CREATE, DELETE, MOVED_*, CLOSE_WRITE, ..., ONDIR == ISDIR)
+    pub mask: u64,
+    /// absolute; the root itself for an overflow
+    pub path: PathBuf,
+    /// rename source (FAN_RENAME, or a paired inotify move)
+    pub from: Option<PathBuf>,
+    /// inotify move cookie, 0 otherwise
+    pub cookie: u32,
+    /// tgid of the process that caused the event (fanotify only)
+    pub pid: Option<i32>,
+    /// events were lost: everything below `path` has to be rescanned
+    pub overflow: bool,
+    pub ts: SystemTime,
+}
+/// A [`RawEvent`] reduced to one action on a root-relative path.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NormalizedEvent {
+    pub side: Side,
+    pub action: Action,
+    /// relative to the pair root; empty for the root itself
+    pub path: PathBuf,
+    /// rename source, for `Action::Rename`
+    pub from: Option<PathBuf>,
+    pub is_dir: bool,
+    /// the entry did not exist before this event (create, moved in)
+    pub created: bool,
+    pub pid: Option<i32>,
+    /// stands for events the kernel dropped (`Action::Rescan` of the root)
+    pub overflow: bool,
+    pub ts: SystemTime,
 }
 
-pub struct RawEvent {}
-pub struct NormalizedEvent {}
-pub struct CoalescedEvent {}
+/// What is left of a path's events once its debounce window closed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CoalescedEvent {
+    pub side: Side,
+    pub action: Action,
+    /// relative to the pair root; empty for the root itself
+    pub path: PathBuf,
+    /// rename source, for `Action::Rename`
+    pub from: Option<PathBuf>,
+    pub is_dir: bool,
+    /// stands for events a kernel or dispatcher queue dropped
+    /// (`Action::Rescan`)
+    pub overflow: bool,
+    /// time of the last event folded into this one
+    pub ts: SystemTime,
+}
+
+/// Dispatcher queue of one pair, as reported by `service.status`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct QueueStatus {
+    /// events waiting for the worker
+    pub depth: usize,
+    /// estimated memory held by them
+    pub bytes: usize,
+    /// directories queued for a rescan because the queue hit its bound
+    pub dirty: usize,
+    /// times the queue was collapsed
+    pub collapses: u64,
+    /// producers currently held back by a full queue
+    pub waiting: usize,
+}
+
+/// Events a pair lost, as reported by `pair.list` and `service.status`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct OverflowStatus {
+    /// times a kernel or dispatcher queue overflowed
+    pub count: u64,
+    /// when it last happened, seconds since the epoch
+    pub last_unix: Option<u64>,
+    /// a rescan of lost changes has not run yet
+    pub rescan_pending: bool,
+}
+
+/// `--debounce-ms`: how long a path must stay quiet before its events are
+/// dispatched.
+pub const DEBOUNCE_MS_DEFAULT: u64 = 500;
+pub const DEBOUNCE_MS_MIN: u64 = 1;
+pub const DEBOUNCE_MS_MAX: u64 = 60_000;
 
 /// Worker and threads
 
 pub struct Event {
     metadata: Metadata,
+    /// raw bytes from the kernel; never assumed to be UTF-8
     path: PathBuf,
+    /// rename source, for `Action::Rename`
+    from: Option<PathBuf>,
     action: Action,
     pub ts: SystemTime,
 }
 
-pub struct Handle {
-    pub rx: mpsc::Receiver<Event>,
+impl Event {
+    pub fn new(metadata: Metadata, path: PathBuf, action: Action) -> Self {
+        Self {
+            metadata,
+            path,
+            from: None,
+            action,
+            ts: SystemTime::now(),
+        }
+    }
+
+    pub fn rename(metadata: Metadata, from: PathBuf, to: PathBuf) -> Self {
+        Self {
+            from: Some(from),
+            ..Self::new(metadata, to, Action::Rename)
+        }
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn renamed_from(&self) -> Option<&Path> {
+        self.from.as_deref()
+    }
+
+    pub fn action(&self) -> &Action {
+        &self.action
+    }
+}
+
+pub struct Handle<T = Event> {
+    pub rx: mpsc::Receiver<T>,
     stop: broadcast::Sender<()>,
     join: JoinHandle<()>,
 }
 
+impl<T> Handle<T> {
+    pub fn new(rx: mpsc::Receiver<T>, stop: broadcast::Sender<()>, join: JoinHandle<()>) -> Self {
+        Self { rx, stop, join }
+    }
+
+    /// Ask the task to stop and wait until it has. Events not received yet
+    /// are dropped.
+    pub async fn shutdown(self) {
+        let Self { rx, stop, join } = self;
+        // 先丢弃接收端：阻塞在满 channel 上的发送方立即返回，join 才等得到
+        drop(rx);
+        let _ = stop.send(());
+        let _ = join.await;
+    }
+}
+
 // ======== Env utils ========
 // set environment variables utils function
 fn getenv_or(key: &str, default: &str) -> String {
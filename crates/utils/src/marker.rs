@@ -0,0 +1,85 @@
+//! `.synchron/pair-id` marker in each pair root.
+//!
+//! An empty mount point (drive not mounted, autofs timed out) looks exactly
+//! like a root whose contents the user deleted. `pair.add` writes the pair id
+//! into both roots; the reconciler only runs while both markers are present
+//! and name the pair, and otherwise puts it into `PairState::Error`.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Directory holding synchron's per-root files; never synced.
+pub const MARKER_DIR: &str = ".synchron";
+pub const MARKER_FILE: &str = "pair-id";
+
+#[derive(Debug, Error)]
+pub enum MarkerError {
+    #[error("{}: marker missing; is the right filesystem mounted?", .0.display())]
+    Missing(PathBuf),
+
+    #[error("{}: marker belongs to pair {found}", .path.display())]
+    Foreign { path: PathBuf, found: String },
+
+    #[error("{}: {source}", .0.display(), source = .1)]
+    Io(PathBuf, #[source] io::Error),
+}
+
+pub fn marker_path(root: &Path) -> PathBuf {
+    root.join(MARKER_DIR).join(MARKER_FILE)
+}
+
+/// Write (or overwrite) the marker of `root`. The file is replaced
+/// atomically, so a crash never leaves a truncated id behind.
+pub fn write(root: &Path, pair_id: &str) -> Result<(), MarkerError> {
+    let dir = root.join(MARKER_DIR);
+    let path = dir.join(MARKER_FILE);
+    let io_err = |e| MarkerError::Io(path.clone(), e);
+
+    match fs::create_dir(&dir) {
+        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(io_err(e)),
+        _ => {}
+    }
+    let tmp = dir.join(format!("{MARKER_FILE}.tmp"));
+    let mut f = File::create(&tmp).map_err(io_err)?;
+    f.write_all(format!("{pair_id}\n").as_bytes())
+        .and_then(|_| f.sync_all())
+        .map_err(io_err)?;
+    fs::rename(&tmp, &path).map_err(io_err)
+}
+
+/// Pair id recorded in `root`, `None` if there is no marker.
+pub fn read(root: &Path) -> Result<Option<String>, MarkerError> {
+    let path = marker_path(root);
+    match fs::read_to_string(&path) {
+        Ok(s) => Ok(Some(s.trim_end().to_owned())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(MarkerError::Io(path, e)),
+    }
+}
+
+/// `Ok` only if `root` carries the marker of `pair_id`.
+pub fn check(root: &Path, pair_id: &str) -> Result<(), MarkerError> {
+    match read(root)? {
+        Some(found) if found == pair_id => Ok(()),
+        Some(found) => Err(MarkerError::Foreign {
+            path: marker_path(root),
+            found,
+        }),
+        None => Err(MarkerError::Missing(marker_path(root))),
+    }
+}
+
+/// Remove the marker of `root` (`pair.remove` with `purge_state`). Leaves
+/// `.synchron/` in place if anything else lives there; a marker of another
+/// pair is left alone.
+pub fn remove(root: &Path, pair_id: &str) -> Result<(), MarkerError> {
+    let path = marker_path(root);
+    if read(root)?.as_deref() != Some(pair_id) {
+        return Ok(());
+    }
+    fs::remove_file(&path).map_err(|e| MarkerError::Io(path.clone(), e))?;
+    let _ = fs::remove_dir(root.join(MARKER_DIR));
+    Ok(())
+}
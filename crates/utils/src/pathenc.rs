@@ -0,0 +1,60 @@
+//! Lossless JSON encoding of paths for the control protocol.
+//!
+//! Linux paths are arbitrary bytes, JSON strings are UTF-8. A path that is
+//! valid UTF-8 goes over the wire as a plain string (so the common case stays
+//! readable); anything else becomes `{"b64": "<standard base64 of the bytes>"}`.
+//!
+//! Use on fields with `#[serde(with = "synchron_utils::pathenc")]`, or wrap a
+//! borrowed path in [`Encoded`] when building a `serde_json::json!` value.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::ffi::OsString;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Repr<'a> {
+    Utf8(&'a str),
+    Bytes { b64: String },
+}
+
+// 反序列化时字符串可能含转义，不能借用
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OwnedRepr {
+    Utf8(String),
+    Bytes { b64: String },
+}
+
+pub fn serialize<S: Serializer>(path: &Path, s: S) -> Result<S::Ok, S::Error> {
+    match path.to_str() {
+        Some(utf8) => Repr::Utf8(utf8),
+        None => Repr::Bytes {
+            b64: STANDARD.encode(path.as_os_str().as_bytes()),
+        },
+    }
+    .serialize(s)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<PathBuf, D::Error> {
+    match OwnedRepr::deserialize(d)? {
+        OwnedRepr::Utf8(s) => Ok(PathBuf::from(s)),
+        OwnedRepr::Bytes { b64 } => STANDARD
+            .decode(b64)
+            .map(|bytes| PathBuf::from(OsString::from_vec(bytes)))
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+/// Borrowed path that serializes with this encoding.
+#[derive(Clone, Copy, Debug)]
+pub struct Encoded<'a>(pub &'a Path);
+
+impl Serialize for Encoded<'_> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        serialize(self.0, s)
+    }
+}
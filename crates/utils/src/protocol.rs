@@ -0,0 +1,156 @@
+//! Control protocol between the CLI and the manager.
+//!
+//! Every message is one length-prefixed JSON frame ([`crate::read_frame`] /
+//! [`crate::write_frame`]). A request names its operation in `op` and carries
+//! its arguments in `params`:
+//!
+//! ```text
+//! { "op": "pair.add", "id": 17, "ts": "...", "params": { "dir_a": ..., ... } }
+//! ```
+//!
+//! The reply is `{ "ok": bool, "error": null | { "code", "message" },
+//! "data": {...}, "request_id": 17 }`.
+
+use crate::pathenc;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Serialize, Deserialize)]
+pub struct Request {
+    /// echoed back as `request_id`; older clients send it under that name
+    #[serde(default, alias = "request_id")]
+    pub id: u64,
+    #[serde(default)]
+    pub ts: Option<String>,
+    #[serde(flatten)]
+    pub params: Params,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", content = "params")]
+pub enum Params {
+    // pair.*
+    #[serde(rename = "pair.add")]
+    PairAdd(PairAddParams),
+    #[serde(rename = "pair.remove")]
+    PairRemove {
+        pair_id: String,
+        #[serde(default)]
+        purge_state: bool,
+    },
+    #[serde(rename = "pair.list")]
+    PairList {},
+    #[serde(rename = "pair.pause")]
+    PairPause {
+        scope: Scope,
+        #[serde(default)]
+        pair_id: Option<String>,
+    },
+    #[serde(rename = "pair.resume")]
+    PairResume {
+        scope: Scope,
+        #[serde(default)]
+        pair_id: Option<String>,
+    },
+    #[serde(rename = "pair.restart")]
+    PairRestart {
+        scope: Scope,
+        #[serde(default)]
+        pair_id: Option<String>,
+    },
+
+    // service.*
+    #[serde(rename = "service.status")]
+    ServiceStatus {
+        #[serde(default)]
+        detail: Option<String>,
+    }, // "summary"|"full"
+
+    // logs.*
+    #[serde(rename = "logs.tail")]
+    LogsTail(LogsTailParams),
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    #[default]
+    One,
+    All,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    Bi,
+    A2b,
+    B2a,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    Ours,
+    Theirs,
+    Manual,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PairAddParams {
+    #[serde(with = "pathenc")]
+    pub dir_a: PathBuf,
+    #[serde(with = "pathenc")]
+    pub dir_b: PathBuf,
+    #[serde(default = "default_mode")]
+    pub mode: Mode,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub bandwidth_mb: Option<u32>,
+    #[serde(default)]
+    pub max_inflight: Option<u32>,
+    #[serde(default = "default_conflict_policy")]
+    pub conflict_policy: ConflictPolicy,
+    /// namespace names, as in `--xattrs` (see `synchron_ffi::xattr::Policy`);
+    /// `None` leaves the choice to the manager
+    #[serde(default)]
+    pub xattrs: Option<Vec<String>>,
+    #[serde(default)]
+    pub skip_submounts: bool,
+    /// `--debounce-ms` of the pair; `None` uses the manager's
+    #[serde(default)]
+    pub debounce_ms: Option<u64>,
+}
+fn default_mode() -> Mode {
+    Mode::Bi
+}
+fn default_conflict_policy() -> ConflictPolicy {
+    ConflictPolicy::Manual
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LogsTailParams {
+    #[serde(default)]
+    pub scope: Scope, // 默认 One；若 All, 可不填 pair_id
+    #[serde(default)]
+    pub pair_id: Option<String>,
+    #[serde(default)]
+    pub since: Option<String>, // 支持 "1h" 或 RFC3339；也可换成自定义新类型
+    #[serde(default)]
+    pub level: Option<LogLevel>,
+    #[serde(default)]
+    pub follow: bool,
+}
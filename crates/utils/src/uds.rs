@@ -1,6 +1,7 @@
 use std::fs;
 use std::os::unix::fs::PermissionsExt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::UnixListener;
 
 pub fn ensure_uds(path: PathBuf) -> std::io::Result<PathBuf> {
@@ -17,8 +18,36 @@ pub fn ensure_uds(path: PathBuf) -> std::io::Result<PathBuf> {
 }
 
 pub async fn create_uds(path: PathBuf) -> std::io::Result<UnixListener> {
-    let listener = UnixListener::bind(&path)?;
-    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    UnixListener::from_std(bind_uds(&path)?)
+}
+
+/// [`create_uds`] without a runtime, e.g. to bind before dropping
+/// privileges; hand the result to `tokio::net::UnixListener::from_std`.
+pub fn bind_uds(path: &Path) -> std::io::Result<std::os::unix::net::UnixListener> {
+    let listener = std::os::unix::net::UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    listener.set_nonblocking(true)?;
 
     Ok(listener)
 }
+
+/// Send one frame: a big-endian `u32` length, then the payload.
+pub async fn write_frame<W: AsyncWriteExt + Unpin>(
+    w: &mut W,
+    payload: &[u8],
+) -> std::io::Result<()> {
+    let len = (payload.len() as u32).to_be_bytes();
+    w.write_all(&len).await?;
+    w.write_all(payload).await?;
+    w.flush().await
+}
+
+/// Receive one frame written by [`write_frame`].
+pub async fn read_frame<R: AsyncReadExt + Unpin>(r: &mut R) -> std::io::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    r.read_exact(&mut len).await?;
+    let n = u32::from_be_bytes(len) as usize;
+    let mut buf = vec![0u8; n];
+    r.read_exact(&mut buf).await?;
+    Ok(buf)
+}
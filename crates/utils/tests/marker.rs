@@ -0,0 +1,76 @@
+//! Pair markers in tempdir roots.
+
+use std::fs;
+use synchron_utils::marker::{self, marker_path, MarkerError, MARKER_DIR};
+
+#[test]
+fn write_then_read_and_check() {
+    let root = tempfile::tempdir().unwrap();
+    assert_eq!(marker::read(root.path()).unwrap(), None);
+
+    marker::write(root.path(), "p1").unwrap();
+    assert_eq!(marker::read(root.path()).unwrap().as_deref(), Some("p1"));
+    assert_eq!(
+        fs::read_to_string(marker_path(root.path())).unwrap(),
+        "p1\n"
+    );
+    marker::check(root.path(), "p1").unwrap();
+    // 没有留下临时文件
+    let names: Vec<_> = fs::read_dir(root.path().join(MARKER_DIR))
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    assert_eq!(names, ["pair-id"]);
+
+    marker::write(root.path(), "p2").unwrap();
+    assert_eq!(marker::read(root.path()).unwrap().as_deref(), Some("p2"));
+}
+
+#[test]
+fn check_tells_missing_from_foreign() {
+    let root = tempfile::tempdir().unwrap();
+    match marker::check(root.path(), "p1") {
+        Err(MarkerError::Missing(path)) => assert_eq!(path, marker_path(root.path())),
+        r => panic!("{r:?}"),
+    }
+    marker::write(root.path(), "other").unwrap();
+    match marker::check(root.path(), "p1") {
+        Err(MarkerError::Foreign { found, .. }) => assert_eq!(found, "other"),
+        r => panic!("{r:?}"),
+    }
+}
+
+#[test]
+fn remove_leaves_foreign_markers_and_other_files() {
+    let root = tempfile::tempdir().unwrap();
+    marker::write(root.path(), "other").unwrap();
+    marker::remove(root.path(), "p1").unwrap();
+    assert_eq!(marker::read(root.path()).unwrap().as_deref(), Some("other"));
+
+    marker::write(root.path(), "p1").unwrap();
+    fs::write(root.path().join(MARKER_DIR).join("keep"), "").unwrap();
+    marker::remove(root.path(), "p1").unwrap();
+    assert_eq!(marker::read(root.path()).unwrap(), None);
+    assert!(root.path().join(MARKER_DIR).join("keep").exists());
+
+    fs::remove_file(root.path().join(MARKER_DIR).join("keep")).unwrap();
+    marker::write(root.path(), "p1").unwrap();
+    marker::remove(root.path(), "p1").unwrap();
+    assert!(!root.path().join(MARKER_DIR).exists());
+    // 没有标记时什么也不做
+    marker::remove(root.path(), "p1").unwrap();
+}
+
+#[test]
+fn marker_path_taken_by_a_file_is_an_io_error() {
+    let root = tempfile::tempdir().unwrap();
+    fs::write(root.path().join(MARKER_DIR), "not a directory").unwrap();
+    assert!(matches!(
+        marker::write(root.path(), "p1"),
+        Err(MarkerError::Io(..))
+    ));
+    assert!(matches!(
+        marker::read(root.path()),
+        Err(MarkerError::Io(..))
+    ));
+}
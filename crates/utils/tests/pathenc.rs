@@ -0,0 +1,63 @@
+//! Path encoding on the control protocol: plain strings for UTF-8, `b64`
+//! objects for everything else.
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use synchron_utils::pathenc::{self, Encoded};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Msg {
+    #[serde(with = "pathenc")]
+    path: PathBuf,
+}
+
+fn round_trip(path: &Path) -> serde_json::Value {
+    let msg = Msg {
+        path: path.to_path_buf(),
+    };
+    let wire = serde_json::to_value(&msg).unwrap();
+    let text = serde_json::to_string(&msg).unwrap();
+    assert_eq!(serde_json::from_str::<Msg>(&text).unwrap(), msg);
+    wire["path"].clone()
+}
+
+#[test]
+fn utf8_paths_stay_plain_strings() {
+    assert_eq!(
+        round_trip(Path::new("/srv/a b/ü.txt")),
+        json!("/srv/a b/ü.txt")
+    );
+    // 需要转义的字符也能还原
+    assert_eq!(round_trip(Path::new("x/\"q\"\n")), json!("x/\"q\"\n"));
+    assert_eq!(round_trip(Path::new("")), json!(""));
+}
+
+#[test]
+fn non_utf8_paths_go_through_base64() {
+    let raw = Path::new(OsStr::from_bytes(b"/srv/\xff\xfe/latin1-\xe9.txt"));
+    let wire = round_trip(raw);
+    assert_eq!(wire, json!({ "b64": "L3Nydi///i9sYXRpbjEt6S50eHQ=" }));
+}
+
+#[test]
+fn b64_form_is_accepted_for_any_path() {
+    // 对端也可以把合法 UTF-8 编成 b64
+    let msg: Msg = serde_json::from_value(json!({ "path": { "b64": "L3RtcC9h" } })).unwrap();
+    assert_eq!(msg.path, Path::new("/tmp/a"));
+
+    let bad = serde_json::from_value::<Msg>(json!({ "path": { "b64": "not base64!" } }));
+    assert!(bad.is_err());
+    assert!(serde_json::from_value::<Msg>(json!({ "path": 7 })).is_err());
+}
+
+#[test]
+fn encoded_matches_the_field_encoding() {
+    let raw = Path::new(OsStr::from_bytes(b"a\x80b"));
+    let v = json!({ "dir": Encoded(raw), "plain": Encoded(Path::new("a/b")) });
+    assert_eq!(v["dir"], round_trip(raw));
+    assert_eq!(v["plain"], json!("a/b"));
+    assert_eq!(pathenc::deserialize(v["dir"].clone()).unwrap(), raw);
+}
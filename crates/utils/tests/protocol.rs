@@ -0,0 +1,109 @@
+//! Requests as the CLI sends them, parsed the way the manager does.
+
+use serde_json::json;
+use std::path::Path;
+use synchron_utils::protocol::{ConflictPolicy, Mode, Params, Request, Scope};
+
+fn parse(v: serde_json::Value) -> Request {
+    serde_json::from_value(v).unwrap()
+}
+
+#[test]
+fn pair_add_with_defaults() {
+    let req = parse(json!({
+        "op": "pair.add",
+        "id": 7,
+        "ts": "2025-01-01T00:00:00Z",
+        "params": { "dir_a": "/a", "dir_b": { "b64": "L2L/" } }
+    }));
+    assert_eq!(req.id, 7);
+    let Params::PairAdd(p) = req.params else {
+        panic!("not pair.add");
+    };
+    assert_eq!(p.dir_a, Path::new("/a"));
+    assert_eq!(p.dir_b.as_os_str().len(), 3);
+    assert_eq!(p.mode, Mode::Bi);
+    assert_eq!(p.conflict_policy, ConflictPolicy::Manual);
+    assert_eq!(p.xattrs, None);
+    assert!(!p.skip_submounts);
+    assert_eq!(p.debounce_ms, None);
+}
+
+#[test]
+fn pair_add_as_the_cli_sends_it() {
+    let req = parse(json!({
+        "op": "pair.add",
+        "id": 1,
+        "ts": "now",
+        "params": {
+            "dir_a": "/a",
+            "dir_b": "/b",
+            "mode": "bi",
+            "include": [],
+            "exclude": [],
+            "conflict_policy": "manual",
+            "xattrs": [],
+            "skip_submounts": true,
+            "debounce_ms": 250,
+        }
+    }));
+    let Params::PairAdd(p) = req.params else {
+        panic!("not pair.add");
+    };
+    // 空列表表示 --no-xattrs，和“未指定”不同
+    assert_eq!(p.xattrs, Some(vec![]));
+    assert!(p.skip_submounts);
+    assert_eq!(p.debounce_ms, Some(250));
+}
+
+#[test]
+fn request_id_alias_and_extra_fields() {
+    let req = parse(json!({
+        "api": "synchron.v1",
+        "op": "pair.pause",
+        "request_id": 9,
+        "ts": "now",
+        "params": { "scope": "all", "pair_id": null }
+    }));
+    assert_eq!(req.id, 9);
+    assert!(matches!(
+        req.params,
+        Params::PairPause {
+            scope: Scope::All,
+            pair_id: None
+        }
+    ));
+}
+
+#[test]
+fn every_cli_operation_parses() {
+    for (op, params) in [
+        (
+            "pair.remove",
+            json!({ "pair_id": "p", "purge_state": false }),
+        ),
+        ("pair.list", json!({})),
+        ("pair.resume", json!({ "scope": "one", "pair_id": "p" })),
+        ("pair.restart", json!({ "scope": "one", "pair_id": "p" })),
+        ("service.status", json!({ "detail": "summary" })),
+        (
+            "logs.tail",
+            json!({ "scope": "all", "pair_id": null, "since": "1h", "level": "info", "follow": true, "format": "text" }),
+        ),
+    ] {
+        let req = serde_json::from_value::<Request>(json!({ "op": op, "id": 1, "params": params }));
+        assert!(req.is_ok(), "{op}: {}", req.err().unwrap());
+    }
+}
+
+#[test]
+fn unknown_operations_and_bad_params_are_rejected() {
+    for v in [
+        json!({ "op": "pair.explode", "id": 1, "params": {} }),
+        json!({ "op": "pair.add", "id": 1, "params": { "dir_a": "/a" } }),
+        json!({ "op": "pair.pause", "id": 1, "params": { "scope": "some" } }),
+        json!({ "id": 1, "params": {} }),
+    ] {
+        assert!(serde_json::from_value::<Request>(v.clone()).is_err(), "{v}");
+    }
+}
@@ -0,0 +1,539 @@
+//! Per-path debounce between the normalizer and the dispatcher.
+//!
+//! Events for a path are held until it has been quiet for `debounce`, but
+//! never longer than `max_latency` after the first one, so a log file that
+//! is appended to all the time still gets synced now and then. Only the net
+//! effect is held:
+//!
+//! | held                  | + event          | released as                    |
+//! |-----------------------|------------------|--------------------------------|
+//! | create                | write, write ... | one write                      |
+//! | create                | delete           | nothing                        |
+//! | write                 | delete           | delete                         |
+//! | delete                | create           | write (the file was replaced)  |
+//! | write at `a`          | rename `a -> b`  | rename `a -> b`, then write `b`|
+//! | create at `a`         | rename `a -> b`  | write `b`                      |
+//! | rename `c -> b`       | delete `b`       | delete `c`, delete `b`         |
+//! | anything at or below `p` | rescan `p`    | rescan `p`                     |
+//!
+//! A held rename is released no later than anything below its destination
+//! or anything renamed from below it, so the other side always sees the
+//! directory move first.
+
+use crate::dispatcher::Sender;
+use crate::normalizer::Normalizer;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use synchron_utils::{
+    Action, CoalescedEvent, NormalizedEvent, RawEvent, Side, DEBOUNCE_MS_DEFAULT, DEBOUNCE_MS_MAX,
+    DEBOUNCE_MS_MIN,
+};
+use tokio::sync::mpsc;
+
+/// `max_latency` is this many debounce windows.
+const MAX_LATENCY_FACTOR: u32 = 10;
+
+/// How long an unpaired MOVED_FROM waits for its MOVED_TO. The kernel
+/// queues both halves back to back; they only get split across two reads.
+const MOVE_PAIR_WAIT: Duration = Duration::from_millis(10);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CoalescerConfig {
+    /// quiet time before a path's events are released
+    pub debounce: Duration,
+    /// upper bound on how long the first held event of a path waits
+    pub max_latency: Duration,
+}
+
+impl Default for CoalescerConfig {
+    fn default() -> Self {
+        Self::from_millis(DEBOUNCE_MS_DEFAULT)
+    }
+}
+
+impl CoalescerConfig {
+    /// `--debounce-ms`, clamped to 1..=60000.
+    pub fn from_millis(debounce_ms: u64) -> Self {
+        let debounce = Duration::from_millis(debounce_ms.clamp(DEBOUNCE_MS_MIN, DEBOUNCE_MS_MAX));
+        Self {
+            debounce,
+            max_latency: debounce * MAX_LATENCY_FACTOR,
+        }
+    }
+
+    /// Config of a pair: its own `debounce_ms` if it has one, the global
+    /// one (`self`) otherwise.
+    pub fn for_pair(&self, debounce_ms: Option<u64>) -> Self {
+        debounce_ms.map_or(*self, Self::from_millis)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Op {
+    /// `created`: the other side does not have the path yet
+    Write {
+        created: bool,
+    },
+    Delete,
+    /// `write`: changed after (or before) the move
+    Rename {
+        from: PathBuf,
+        write: bool,
+    },
+    Rescan,
+}
+
+#[derive(Debug)]
+struct Entry {
+    op: Op,
+    is_dir: bool,
+    /// a held `Rescan` stands for lost events
+    overflow: bool,
+    first: Instant,
+    last: Instant,
+    ts: SystemTime,
+}
+
+impl Entry {
+    fn new(op: Op, is_dir: bool, now: Instant, ts: SystemTime) -> Self {
+        Self {
+            op,
+            is_dir,
+            overflow: false,
+            first: now,
+            last: now,
+            ts,
+        }
+    }
+}
+
+pub struct Coalescer {
+    config: CoalescerConfig,
+    /// held entries, indexed by `Side`
+    sides: [HashMap<PathBuf, Entry>; 2],
+}
+
+impl Coalescer {
+    pub fn new(config: CoalescerConfig) -> Self {
+        Self {
+            config,
+            sides: [HashMap::new(), HashMap::new()],
+        }
+    }
+
+    pub fn config(&self) -> &CoalescerConfig {
+        &self.config
+    }
+
+    /// Number of paths held.
+    pub fn len(&self) -> usize {
+        self.sides.iter().map(HashMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sides.iter().all(HashMap::is_empty)
+    }
+
+    /// Fold `ev` into what is held for its path.
+    pub fn push(&mut self, ev: NormalizedEvent, now: Instant) {
+        let side = ev.side;
+        if let Some(held) = self.rescan_ancestor(side, &ev.path) {
+            // 祖先目录已在等待重扫：这里的变化会被一并比较，只需推迟重扫
+            held.last = now;
+            held.ts = ev.ts;
+            held.overflow |= ev.overflow;
+            if let (Action::Rename, Some(from)) = (ev.action, ev.from) {
+                if self.rescan_ancestor(side, &from).is_none() {
+                    self.vacate(side, from, ev.is_dir, now, ev.ts);
+                }
+            }
+            return;
+        }
+        let op = match (ev.action, ev.from) {
+            (Action::Write, _) => Op::Write {
+                created: ev.created,
+            },
+            (Action::Delete, _) => Op::Delete,
+            (Action::Rename, Some(from)) => {
+                return self.rename(side, from, ev.path, ev.is_dir, now, ev.ts)
+            }
+            (Action::Rename, None) => Op::Write { created: false },
+            (Action::Rescan, _) => {
+                let (first, overflow) = self.absorb(side, &ev.path, now, ev.ts);
+                self.merge(side, ev.path.clone(), Op::Rescan, ev.is_dir, now, ev.ts);
+                if let Some(held) = self.sides[side as usize].get_mut(&ev.path) {
+                    held.first = held.first.min(first);
+                    held.overflow |= ev.overflow || overflow;
+                }
+                return;
+            }
+        };
+        self.merge(side, ev.path, op, ev.is_dir, now, ev.ts);
+    }
+
+    /// When the next held path becomes due.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.sides
+            .iter()
+            .flat_map(HashMap::values)
+            .map(|e| self.deadline(e))
+            .min()
+    }
+
+    /// Release every path that is due at `now`, in an order the other side
+    /// can apply one by one.
+    pub fn poll(&mut self, now: Instant, out: &mut Vec<CoalescedEvent>) {
+        for side in [Side::A, Side::B] {
+            let due: Vec<PathBuf> = self.sides[side as usize]
+                .iter()
+                .filter(|(_, e)| self.deadline(e) <= now)
+                .map(|(p, _)| p.clone())
+                .collect();
+            self.release_all(side, due, out);
+        }
+    }
+
+    /// Release everything held.
+    pub fn flush(&mut self, out: &mut Vec<CoalescedEvent>) {
+        for side in [Side::A, Side::B] {
+            let all: Vec<PathBuf> = self.sides[side as usize].keys().cloned().collect();
+            self.release_all(side, all, out);
+        }
+    }
+
+    fn release_all(&mut self, side: Side, mut paths: Vec<PathBuf>, out: &mut Vec<CoalescedEvent>) {
+        if paths.is_empty() {
+            return;
+        }
+        // 源路径 -> 改名目标：源路径上新出现的条目必须排在改名之后
+        let sources: HashMap<PathBuf, PathBuf> = self.sides[side as usize]
+            .iter()
+            .filter_map(|(to, e)| match &e.op {
+                Op::Rename { from, .. } => Some((from.clone(), to.clone())),
+                _ => None,
+            })
+            .collect();
+        paths.sort_unstable();
+        for path in paths {
+            self.release(side, &path, &sources, out);
+        }
+    }
+
+    fn deadline(&self, e: &Entry) -> Instant {
+        (e.last + self.config.debounce).min(e.first + self.config.max_latency)
+    }
+
+    fn merge(
+        &mut self,
+        side: Side,
+        path: PathBuf,
+        op: Op,
+        is_dir: bool,
+        now: Instant,
+        ts: SystemTime,
+    ) {
+        let map = &mut self.sides[side as usize];
+        let Some(held) = map.get_mut(&path) else {
+            map.insert(path, Entry::new(op, is_dir, now, ts));
+            return;
+        };
+        let mut orphan = None;
+        let merged = match (&held.op, op) {
+            // 重扫只比较这个路径：改名的源还得单独删
+            (Op::Rescan, Op::Rename { from, .. }) => {
+                orphan = Some(from);
+                Some(Op::Rescan)
+            }
+            (Op::Rescan, _) | (_, Op::Rescan) => Some(Op::Rescan),
+            (Op::Write { created }, Op::Write { .. }) => Some(Op::Write { created: *created }),
+            // 删除后又建了同名文件：对端仍是旧内容
+            (Op::Delete, Op::Write { .. }) => Some(Op::Write { created: false }),
+            (Op::Rename { from, .. }, Op::Write { .. }) => Some(Op::Rename {
+                from: from.clone(),
+                write: true,
+            }),
+            (Op::Write { created: true }, Op::Delete) => None,
+            (Op::Write { created: false } | Op::Delete, Op::Delete) => Some(Op::Delete),
+            // 被覆盖的改名：对端的源路径还在
+            (Op::Rename { from, .. }, new @ (Op::Delete | Op::Rename { .. })) => {
+                orphan = Some(from.clone());
+                Some(new)
+            }
+            (_, new @ Op::Rename { .. }) => Some(new),
+        };
+        match merged {
+            Some(op) => {
+                held.op = op;
+                held.is_dir = is_dir;
+                held.last = now;
+                held.ts = ts;
+            }
+            None => {
+                map.remove(&path);
+            }
+        }
+        if let Some(from) = orphan {
+            self.vacate(side, from, is_dir, now, ts);
+        }
+    }
+
+    /// The other side still has `path` but this side no longer has the
+    /// entry it held: make sure it goes away there too.
+    fn vacate(&mut self, side: Side, path: PathBuf, is_dir: bool, now: Instant, ts: SystemTime) {
+        let map = &mut self.sides[side as usize];
+        match map.get_mut(&path) {
+            None => {
+                map.insert(path, Entry::new(Op::Delete, is_dir, now, ts));
+            }
+            // 新内容会覆盖对端的旧条目
+            Some(held) => {
+                if let Op::Write { created } = &mut held.op {
+                    *created = false;
+                }
+                held.last = now;
+            }
+        }
+    }
+
+    fn rename(
+        &mut self,
+        side: Side,
+        from: PathBuf,
+        to: PathBuf,
+        is_dir: bool,
+        now: Instant,
+        ts: SystemTime,
+    ) {
+        if self.rescan_ancestor(side, &from).is_some() {
+            // 源路径由重扫处理，目标按普通写入同步
+            return self.merge(side, to, Op::Write { created: false }, is_dir, now, ts);
+        }
+        let map = &mut self.sides[side as usize];
+        let op = match map.remove(&from).map(|e| e.op) {
+            None => Some(Op::Rename {
+                from: from.clone(),
+                write: false,
+            }),
+            // 窗口内新建的：对端没有源路径
+            Some(Op::Write { created: true }) => Some(Op::Write { created: true }),
+            Some(Op::Write { created: false }) => Some(Op::Rename {
+                from: from.clone(),
+                write: true,
+            }),
+            // 改回原名
+            Some(Op::Rename { from: orig, write }) if orig == to => {
+                write.then_some(Op::Write { created: false })
+            }
+            Some(Op::Rename { from: orig, write }) => Some(Op::Rename { from: orig, write }),
+            // 对端的源路径不可信：源保持原状，目标整体复制
+            Some(held @ (Op::Delete | Op::Rescan)) => {
+                map.insert(from.clone(), Entry::new(held, is_dir, now, ts));
+                Some(Op::Write { created: false })
+            }
+        };
+        if is_dir {
+            self.move_children(side, &from, &to, now);
+        }
+        let map = &mut self.sides[side as usize];
+        match op {
+            Some(op) => {
+                // 目标被整体替换：之前为它攒下的事件都作废
+                if let Some(old) = map.remove(&to) {
+                    if let Op::Rename { from: orphan, .. } = old.op {
+                        self.vacate(side, orphan, old.is_dir, now, ts);
+                    } else if old.op == Op::Rescan {
+                        map.insert(to.clone(), old);
+                    }
+                }
+                self.merge(side, to, op, is_dir, now, ts);
+            }
+            None => {
+                map.remove(&to);
+            }
+        }
+    }
+
+    /// Re-key entries held below a renamed directory. They are pushed back
+    /// to `now` so they are not released before the rename itself.
+    fn move_children(&mut self, side: Side, from: &Path, to: &Path, now: Instant) {
+        let map = &mut self.sides[side as usize];
+        let below: Vec<PathBuf> = map
+            .keys()
+            .filter(|p| p.starts_with(from) && p.as_path() != from)
+            .cloned()
+            .collect();
+        for path in below {
+            let Some(mut e) = map.remove(&path) else {
+                continue;
+            };
+            let Ok(rest) = path.strip_prefix(from) else {
+                continue;
+            };
+            if let Op::Rename { from: src, .. } = &mut e.op {
+                if let Ok(r) = src.strip_prefix(from) {
+                    *src = to.join(r);
+                }
+            }
+            e.first = e.first.max(now);
+            e.last = e.last.max(now);
+            map.insert(to.join(rest), e);
+        }
+    }
+
+    /// Drop what is held at or below `dir` ahead of a rescan of it; returns
+    /// the oldest `first` among them so the rescan inherits its latency, and
+    /// whether one of them stood for lost events.
+    fn absorb(&mut self, side: Side, dir: &Path, now: Instant, ts: SystemTime) -> (Instant, bool) {
+        let map = &mut self.sides[side as usize];
+        let below: Vec<PathBuf> = map.keys().filter(|p| p.starts_with(dir)).cloned().collect();
+        let mut first = now;
+        let mut overflow = false;
+        let mut vacated = Vec::new();
+        for path in below {
+            let Some(e) = map.remove(&path) else {
+                continue;
+            };
+            first = first.min(e.first);
+            overflow |= e.overflow;
+            if let Op::Rename { from, .. } = e.op {
+                if !from.starts_with(dir) {
+                    vacated.push((from, e.is_dir));
+                }
+            }
+        }
+        // 从 dir 里改名出去的：源由重扫处理，目标改成整体复制
+        for e in map.values_mut() {
+            if matches!(&e.op, Op::Rename { from, .. } if from.starts_with(dir)) {
+                e.op = Op::Write { created: false };
+            }
+        }
+        for (from, is_dir) in vacated {
+            self.vacate(side, from, is_dir, now, ts);
+        }
+        (first, overflow)
+    }
+
+    /// Held rescan of `path` or one of its ancestors.
+    fn rescan_ancestor(&mut self, side: Side, path: &Path) -> Option<&mut Entry> {
+        let map = &mut self.sides[side as usize];
+        let key = path
+            .ancestors()
+            .find(|p| map.get(*p).is_some_and(|e| e.op == Op::Rescan))?;
+        map.get_mut(key)
+    }
+
+    /// Remove `path` and append it to `out`, after the renames it depends
+    /// on: one of its ancestors, or of its rename source, being moved, or a
+    /// rename away from the path (or an ancestor) it now occupies.
+    fn release(
+        &mut self,
+        side: Side,
+        path: &Path,
+        sources: &HashMap<PathBuf, PathBuf>,
+        out: &mut Vec<CoalescedEvent>,
+    ) {
+        let Some(e) = self.sides[side as usize].remove(path) else {
+            return;
+        };
+        let mut deps: Vec<PathBuf> = path.ancestors().skip(1).map(Path::to_path_buf).collect();
+        if let Op::Rename { from, .. } = &e.op {
+            deps.extend(from.ancestors().skip(1).map(Path::to_path_buf));
+        }
+        deps.extend(path.ancestors().filter_map(|p| sources.get(p)).cloned());
+        deps.sort_unstable();
+        deps.dedup();
+        for dep in deps {
+            let map = &self.sides[side as usize];
+            if map
+                .get(&dep)
+                .is_some_and(|d| matches!(d.op, Op::Rename { .. }))
+            {
+                self.release(side, &dep, sources, out);
+            }
+        }
+
+        let event = |action, from| CoalescedEvent {
+            side,
+            action,
+            path: path.to_path_buf(),
+            from,
+            is_dir: e.is_dir,
+            overflow: e.overflow,
+            ts: e.ts,
+        };
+        match e.op {
+            Op::Write { .. } => out.push(event(Action::Write, None)),
+            Op::Delete => out.push(event(Action::Delete, None)),
+            Op::Rescan => out.push(event(Action::Rescan, None)),
+            Op::Rename { ref from, write } => {
+                out.push(event(Action::Rename, Some(from.clone())));
+                if write {
+                    out.push(event(Action::Write, None));
+                }
+            }
+        }
+    }
+}
+
+/// Normalize and coalesce the events of one root (e.g. the `rx` of its
+/// collector [`Handle`](synchron_utils::Handle)) into its pair's queue until
+/// `rx` closes (what is held is released then) or the queue's worker is gone.
+pub async fn run(
+    mut normalizer: Normalizer,
+    mut coalescer: Coalescer,
+    rx: &mut mpsc::Receiver<RawEvent>,
+    tx: Sender,
+) {
+    let mut normalized = Vec::new();
+    let mut out = Vec::new();
+    let mut pair_deadline: Option<Instant> = None;
+    loop {
+        let wake = [coalescer.next_deadline(), pair_deadline]
+            .into_iter()
+            .flatten()
+            .min();
+        let timer = async {
+            match wake {
+                Some(t) => tokio::time::sleep_until(t.into()).await,
+                None => std::future::pending().await,
+            }
+        };
+        let closed = tokio::select! {
+            ev = rx.recv() => match ev {
+                Some(ev) => {
+                    normalizer.push(ev, &mut normalized);
+                    false
+                }
+                None => true,
+            },
+            _ = timer => false,
+        };
+
+        let now = Instant::now();
+        if closed || pair_deadline.is_some_and(|t| t <= now) {
+            normalizer.flush(&mut normalized);
+        }
+        pair_deadline = match pair_deadline {
+            Some(t) if normalizer.has_pending() => Some(t),
+            _ if normalizer.has_pending() => Some(now + MOVE_PAIR_WAIT),
+            _ => None,
+        };
+        for ev in normalized.drain(..) {
+            coalescer.push(ev, now);
+        }
+        if closed {
+            coalescer.flush(&mut out);
+        } else {
+            coalescer.poll(now, &mut out);
+        }
+        for ev in out.drain(..) {
+            if tx.send(ev).await.is_err() {
+                return;
+            }
+        }
+        if closed {
+            return;
+        }
+    }
+}
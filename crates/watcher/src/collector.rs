@@ -0,0 +1,667 @@
+//! Kernel event sources for pair roots.
+//!
+//! A [`Collector`] hands out one [`RawEvent`] stream per root. fanotify
+//! roots (FID + name reporting) share a single [`MarkTable`], so every root
+//! on a device costs one group, and a single read thread hands each event
+//! to the roots [`Group::route`] finds for it. inotify, used when the
+//! kernel or our privileges rule fanotify out, runs a loop per root.
+//!
+//! Streams are bounded channels. A full channel stalls its loop (for
+//! fanotify: every root sharing the read thread): the kernel queue absorbs
+//! the burst and reports an overflow if it fills up. An overflow is passed
+//! on as an `overflow` event (a rescan of the root downstream) once the
+//! marks or watches of directories created while events were lost have
+//! been put back.
+//!
+//! fanotify reports who caused each event: what synchron or one of its
+//! registered workers did itself is dropped here (see [`crate::suppress`]).
+//! inotify cannot tell, and its streams carry everything.
+
+use crate::marks::{Group, MarkMode, MarkTable, TreeMarks};
+use crate::suppress::{Origin, Suppressor};
+use std::collections::HashMap;
+use std::os::fd::AsFd;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use synchron_ffi::probe::Capabilities;
+use synchron_ffi::{
+    Epoll, EpollCreateFlags, EpollEventFlags, Errno, Error, EventBuffer, EventFd, EventFdFlags,
+    Events, FanotifyEventMask, FanotifyEventRef, FanotifyInitFlags, Inotify, InotifyFlags,
+    InotifyMask,
+};
+use synchron_utils::{EventSource, Handle, Metadata, RawEvent, Side};
+use tokio::sync::{broadcast, mpsc};
+
+type Result<T> = core::result::Result<T, Error>;
+
+/// Kernel interface to read events from.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum Backend {
+    /// fanotify if usable for the root, inotify otherwise
+    #[default]
+    Auto,
+    Fanotify,
+    Inotify,
+}
+
+#[derive(Clone, Debug)]
+pub struct CollectorConfig {
+    pub backend: Backend,
+    pub mark_mode: MarkMode,
+    /// capacity of each root's `RawEvent` channel
+    pub capacity: usize,
+    /// bytes per `read(2)` of the kernel queue
+    pub buf_len: usize,
+    /// Lift the fanotify queue limit (`FAN_UNLIMITED_QUEUE`, needs
+    /// CAP_SYS_ADMIN) so bursts do not overflow into a full rescan. Ignored
+    /// when the kernel or our privileges do not allow it.
+    pub unlimited_queue: bool,
+    /// Drop fanotify events caused by this process or a worker registered
+    /// with [`Collector::suppressor`].
+    pub suppress_own: bool,
+}
+
+impl Default for CollectorConfig {
+    fn default() -> Self {
+        Self {
+            backend: Backend::Auto,
+            mark_mode: MarkMode::Auto,
+            capacity: 4096,
+            buf_len: 64 * 1024,
+            unlimited_queue: false,
+            suppress_own: true,
+        }
+    }
+}
+
+/// Changes that matter for syncing: content, metadata and the namespace.
+const FAN_EVENTS: FanotifyEventMask = FanotifyEventMask(
+    FanotifyEventMask::CREATE.0
+        | FanotifyEventMask::DELETE.0
+        | FanotifyEventMask::MODIFY.0
+        | FanotifyEventMask::CLOSE_WRITE.0
+        | FanotifyEventMask::ATTRIB.0
+        | FanotifyEventMask::DELETE_SELF.0
+        | FanotifyEventMask::MOVE_SELF.0,
+);
+
+const IN_EVENTS: InotifyMask = InotifyMask(
+    InotifyMask::CREATE.0
+        | InotifyMask::DELETE.0
+        | InotifyMask::MODIFY.0
+        | InotifyMask::CLOSE_WRITE.0
+        | InotifyMask::ATTRIB.0
+        | InotifyMask::MOVE.0
+        | InotifyMask::DELETE_SELF.0
+        | InotifyMask::MOVE_SELF.0,
+);
+
+/// events about a marked object itself, not an entry in it
+const SELF: u64 = FanotifyEventMask::DELETE_SELF.0 | FanotifyEventMask::MOVE_SELF.0;
+
+/// epoll data of the stop eventfd; fanotify groups use their device
+const STOP: u64 = u64::MAX;
+/// epoll data of an inotify fd
+const SOURCE: u64 = 0;
+
+/// Hands out event streams for pair roots; see the module docs.
+pub struct Collector {
+    config: CollectorConfig,
+    /// `None` when fanotify is ruled out
+    hub: Option<Arc<Hub>>,
+    suppressor: Arc<Mutex<Suppressor>>,
+    /// stops the fanotify read thread
+    _stop: Option<WakeOnDrop>,
+}
+
+impl Collector {
+    /// Must be called from within a tokio runtime: the fanotify read thread
+    /// runs on the blocking pool until the collector is dropped.
+    pub fn new(caps: &Capabilities, config: CollectorConfig) -> Result<Self> {
+        let fanotify_usable = caps.fanotify_dfid_name() && caps.can_resolve_handles();
+        let suppressor = Arc::new(Mutex::new(Suppressor::default()));
+        let suppress = config.suppress_own.then(|| Arc::clone(&suppressor));
+        let hub = match config.backend {
+            Backend::Inotify => None,
+            Backend::Auto if !fanotify_usable => None,
+            Backend::Auto | Backend::Fanotify => Some(Arc::new(Hub::new(caps, &config, suppress)?)),
+        };
+        let stop = match &hub {
+            Some(hub) => {
+                let reader = Arc::clone(hub);
+                let buf_len = config.buf_len;
+                tokio::task::spawn_blocking(move || reader.run(buf_len));
+                Some(WakeOnDrop(Arc::clone(&hub.wake)))
+            }
+            None => None,
+        };
+        Ok(Self {
+            config,
+            hub,
+            suppressor,
+            _stop: stop,
+        })
+    }
+
+    /// Register worker processes here: fanotify events they cause are not
+    /// reported (unless [`CollectorConfig::suppress_own`] is off).
+    pub fn suppressor(&self) -> &Mutex<Suppressor> {
+        &self.suppressor
+    }
+
+    /// Start collecting events under `meta.root`. Marks/watches are in place
+    /// when this returns, so the caller can run its initial scan right after.
+    ///
+    /// Must be called from within a tokio runtime. The stream lasts until
+    /// [`Handle::shutdown`] or until the handle is dropped. A read error ends
+    /// it with a final overflow event.
+    pub fn spawn(&self, meta: Metadata) -> Result<Handle<RawEvent>> {
+        let root = std::fs::canonicalize(&meta.root)?;
+        match (&self.hub, self.config.backend) {
+            (Some(hub), Backend::Fanotify) => hub.attach(root, meta.side, &self.config),
+            // EXDEV / EOPNOTSUPP：该文件系统无法编码 file handle，退回 inotify
+            (Some(hub), _) => hub
+                .attach(root.clone(), meta.side, &self.config)
+                .or_else(|_| spawn_inotify(root, meta.side, &self.config)),
+            (None, Backend::Fanotify) => Err(Error::from_errno(Errno::EOPNOTSUPP)),
+            (None, _) => spawn_inotify(root, meta.side, &self.config),
+        }
+    }
+}
+
+/// Where the fanotify read thread sends a root's events.
+struct Route {
+    side: Side,
+    root: PathBuf,
+    tx: mpsc::Sender<RawEvent>,
+}
+
+struct Routes {
+    /// keyed by route id
+    table: MarkTable<u64>,
+    routes: HashMap<u64, Route>,
+    next_id: u64,
+    /// the read thread died; new roots go to inotify
+    failed: bool,
+}
+
+/// fanotify groups shared by every root, and the thread reading them.
+struct Hub {
+    epoll: Epoll,
+    wake: Arc<EventFd>,
+    routes: Mutex<Routes>,
+    /// locked after `routes`, never before
+    suppress: Option<Arc<Mutex<Suppressor>>>,
+}
+
+impl Hub {
+    fn new(
+        caps: &Capabilities,
+        config: &CollectorConfig,
+        suppress: Option<Arc<Mutex<Suppressor>>>,
+    ) -> Result<Self> {
+        let mut init = FanotifyInitFlags::CLASS_NOTIF
+            | FanotifyInitFlags::CLOEXEC
+            | FanotifyInitFlags::NONBLOCK
+            | FanotifyInitFlags::REPORT_DFID_NAME;
+        // probe 只在有 CAP_SYS_ADMIN 时报告 UNLIMITED_QUEUE 可用
+        if config.unlimited_queue
+            && caps
+                .fanotify
+                .init
+                .contains(FanotifyInitFlags::UNLIMITED_QUEUE)
+        {
+            init |= FanotifyInitFlags::UNLIMITED_QUEUE;
+        }
+        // FAN_RENAME 一次带出新旧两个名字；不支持时只能拿到不成对的 MOVED_FROM/TO
+        let moves = if caps.fanotify.marks.rename {
+            FanotifyEventMask::RENAME
+        } else {
+            FanotifyEventMask::MOVED_FROM | FanotifyEventMask::MOVED_TO
+        };
+        let epoll = Epoll::new(EpollCreateFlags::CLOEXEC)?;
+        let wake = Arc::new(EventFd::new(
+            0,
+            EventFdFlags::CLOEXEC | EventFdFlags::NONBLOCK,
+        )?);
+        epoll.add(wake.as_fd(), EpollEventFlags::IN, STOP)?;
+        Ok(Self {
+            epoll,
+            wake,
+            routes: Mutex::new(Routes {
+                table: MarkTable::new(init, FAN_EVENTS | moves, config.mark_mode),
+                routes: HashMap::new(),
+                next_id: 0,
+                failed: false,
+            }),
+            suppress,
+        })
+    }
+
+    fn attach(
+        self: &Arc<Self>,
+        root: PathBuf,
+        side: Side,
+        config: &CollectorConfig,
+    ) -> Result<Handle<RawEvent>> {
+        let (tx, rx) = mpsc::channel(config.capacity.max(1));
+        let id = {
+            let mut r = self.routes.lock().unwrap();
+            if r.failed {
+                return Err(Error::from_errno(Errno::EIO));
+            }
+            let id = r.next_id;
+            let dev = r.table.add_root(id, &root)?;
+            let group = r.table.group(dev).expect("group of a root just added");
+            // 同一设备的 group 已经注册过
+            match self.epoll.add(group.as_fd(), EpollEventFlags::IN, dev) {
+                Ok(()) => {}
+                Err(e) if e.errno == Errno::EEXIST => {}
+                Err(e) => {
+                    let _ = r.table.remove(&id);
+                    return Err(e);
+                }
+            }
+            r.next_id += 1;
+            r.routes.insert(id, Route { side, root, tx });
+            id
+        };
+
+        let (stop, mut stop_rx) = broadcast::channel(1);
+        let hub = Arc::clone(self);
+        let join = tokio::spawn(async move {
+            // 收到 stop 或 Handle 被丢弃：撤掉这个 root 的 mark
+            let _ = stop_rx.recv().await;
+            let _ = tokio::task::spawn_blocking(move || hub.detach(id)).await;
+        });
+        Ok(Handle::new(rx, stop, join))
+    }
+
+    fn detach(&self, id: u64) {
+        let mut r = self.routes.lock().unwrap();
+        r.routes.remove(&id);
+        // group 关闭时它的 fd 自动离开 epoll
+        let _ = r.table.remove(&id);
+    }
+
+    fn run(&self, buf_len: usize) {
+        let mut ready = Events::with_capacity(16);
+        let mut buf = EventBuffer::with_capacity(buf_len);
+        let mut out = Vec::new();
+        let res: Result<()> = (|| loop {
+            self.epoll.wait_into(&mut ready, -1)?;
+            for r in &ready {
+                if r.data_u64 == STOP {
+                    return Ok(());
+                }
+                let read = self.read(r.data_u64, &mut buf, &mut out);
+                // 发送时不持锁：满的 channel 不能挡住 attach/detach
+                for (tx, ev) in out.drain(..) {
+                    let _ = tx.blocking_send(ev);
+                }
+                read?;
+            }
+        })();
+        if res.is_err() {
+            let overflows: Vec<_> = {
+                let mut r = self.routes.lock().unwrap();
+                r.failed = true;
+                r.routes
+                    .values()
+                    .map(|route| (route.tx.clone(), overflow(route)))
+                    .collect()
+            };
+            for (tx, ev) in overflows {
+                let _ = tx.blocking_send(ev);
+            }
+        }
+    }
+
+    /// Read what group `dev` has queued (nothing on EAGAIN) and append each
+    /// event, once per root it concerns, to `out`.
+    fn read(
+        &self,
+        dev: u64,
+        buf: &mut EventBuffer,
+        out: &mut Vec<(mpsc::Sender<RawEvent>, RawEvent)>,
+    ) -> Result<()> {
+        let mut work = Vec::new();
+        self.read_group(dev, buf, out, &mut work)?;
+        if work.is_empty() {
+            return Ok(());
+        }
+        // 遍历目录树加 mark、补发 CREATE 都不持锁：attach/detach 不必等它们
+        for w in &mut work {
+            if let Deferred::Marks(marks, _) = w {
+                marks.place()?;
+            }
+        }
+        let mut synthesize = Vec::new();
+        {
+            let mut r = self.routes.lock().unwrap();
+            // group 在此期间被关闭了：它的 mark 随 fd 一起没了
+            let Some(group) = r.table.group_mut(dev) else {
+                return Ok(());
+            };
+            // 按事件顺序记账：先删后建、先建后删的同一路径都要对
+            for w in work {
+                match w {
+                    Deferred::Marks(marks, to) => {
+                        if !to.is_empty() {
+                            synthesize.push((marks.top().to_path_buf(), to));
+                        }
+                        group.commit(marks);
+                    }
+                    Deferred::Forget(dir) => group.forget_dir(&dir),
+                }
+            }
+        }
+        // mark 落地前建好的条目没有事件：补发 CREATE
+        for (dir, to) in synthesize {
+            for (side, tx) in to {
+                synthesize_tree(&dir, side, EventSource::Fanotify, |ev| {
+                    out.push((tx.clone(), ev))
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The part of [`Self::read`] done under the lock: events go to `out`,
+    /// marks to place and forget to `work`.
+    fn read_group(
+        &self,
+        dev: u64,
+        buf: &mut EventBuffer,
+        out: &mut Vec<(mpsc::Sender<RawEvent>, RawEvent)>,
+        work: &mut Vec<Deferred>,
+    ) -> Result<()> {
+        let mut r = self.routes.lock().unwrap();
+        let Routes { table, routes, .. } = &mut *r;
+        // group 在等待期间被关闭了
+        let Some(group) = table.group_mut(dev) else {
+            return Ok(());
+        };
+        if group.fanotify().read_into(buf)? == 0 {
+            return Ok(());
+        }
+        let suppress = self.suppress.as_ref().map(|s| s.lock().unwrap());
+        let mut targets = Vec::new();
+        for ev in buf.events() {
+            let ev = ev?;
+            if ev.is_overflow() {
+                // 溢出期间新建的目录没有 mark：补上后再让下游重扫
+                work.extend(group.plan_remark().map(|m| Deferred::Marks(m, Vec::new())));
+                overflow_all(group, routes, out);
+                continue;
+            }
+            // 自己（或登记过的 worker）造成的事件不下发，但 mark 照样要跟上：
+            // 我们建的目录里，用户随后写入的东西也得看得到
+            let own = suppress
+                .as_ref()
+                .is_some_and(|s| s.origin_of(&ev) == Origin::Own);
+            if ev.mask.0 & SELF != 0 {
+                // 根目录自身的事件解析不到 root 之下（句柄已失效，或指向移走后的
+                // 位置）；root 之下的由父目录的 DELETE / MOVED_FROM 报告
+                if !own {
+                    roots_gone(group, routes, &ev, out);
+                }
+                continue;
+            }
+            let resolved = if ev.mask.0 & FanotifyEventMask::RENAME.0 != 0 {
+                group
+                    .resolve_rename_ref(&ev)
+                    .map(|(from, to)| (Some(from), to))
+            } else {
+                group.resolve_ref(&ev).map(|path| (None, path))
+            };
+            let (from, path) = match resolved {
+                Ok(resolved) => resolved,
+                // 对象已经不在了：后续的 DELETE 会说明一切
+                Err(e) if e.is_stale() || e.errno == Errno::ENOENT => continue,
+                // 解析不了就不知道属于哪个 root：让它们都重扫
+                Err(_) => {
+                    overflow_all(group, routes, out);
+                    continue;
+                }
+            };
+
+            // 宽范围 mark 报告的其它目录不属于任何 root
+            targets.clear();
+            targets.extend(group.route(&path).copied());
+            let landed = targets.len();
+            if let Some(from) = from.as_deref() {
+                for id in group.route(from) {
+                    if !targets.contains(id) {
+                        targets.push(*id);
+                    }
+                }
+            }
+            for id in targets.iter().filter(|_| !own) {
+                let Some(route) = routes.get(id) else {
+                    continue;
+                };
+                out.push((
+                    route.tx.clone(),
+                    RawEvent {
+                        side: route.side,
+                        source: EventSource::Fanotify,
+                        mask: ev.mask.0,
+                        path: path.clone(),
+                        from: from.clone(),
+                        cookie: 0,
+                        pid: (ev.pid > 0).then_some(ev.pid),
+                        overflow: false,
+                        ts: SystemTime::now(),
+                    },
+                ));
+            }
+
+            // 删掉或移走的目录：同一路径上再建的目录要重新加 mark
+            let gone = FanotifyEventMask::DELETE.0
+                | FanotifyEventMask::MOVED_FROM.0
+                | FanotifyEventMask::RENAME.0;
+            if ev.mask.0 & FanotifyEventMask::ONDIR.0 != 0 && ev.mask.0 & gone != 0 {
+                // FAN_RENAME 的 path 是新位置，旧位置在 from
+                let old = from.clone().unwrap_or_else(|| path.clone());
+                work.push(Deferred::Forget(old));
+            }
+
+            let new_dir = ev.mask.0 & FanotifyEventMask::ONDIR.0 != 0
+                && ev.mask.0
+                    & (FanotifyEventMask::CREATE.0
+                        | FanotifyEventMask::MOVED_TO.0
+                        | FanotifyEventMask::RENAME.0)
+                    != 0
+                && landed > 0;
+            if let Some(marks) = new_dir.then(|| group.plan_watch(&path)).flatten() {
+                let to = targets[..landed]
+                    .iter()
+                    .filter_map(|id| routes.get(id))
+                    .map(|route| (route.side, route.tx.clone()))
+                    .collect();
+                work.push(Deferred::Marks(marks, to));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The mask of `ev` (DELETE_SELF / MOVE_SELF) for every root of `group`
+/// it took away: the event is on the root or its parent, and the root is no
+/// longer at its path.
+fn roots_gone(
+    group: &Group<u64>,
+    routes: &HashMap<u64, Route>,
+    ev: &FanotifyEventRef<'_>,
+    out: &mut Vec<(mpsc::Sender<RawEvent>, RawEvent)>,
+) {
+    let mask = ev.mask.0;
+    for (id, root) in group.roots_hit(ev) {
+        let Some(route) = routes.get(id) else {
+            continue;
+        };
+        if std::fs::symlink_metadata(root).is_ok() {
+            continue;
+        }
+        out.push((
+            route.tx.clone(),
+            RawEvent {
+                mask,
+                overflow: false,
+                ..overflow(route)
+            },
+        ));
+    }
+}
+
+/// An overflow for every root of `group`.
+fn overflow_all(
+    group: &Group<u64>,
+    routes: &HashMap<u64, Route>,
+    out: &mut Vec<(mpsc::Sender<RawEvent>, RawEvent)>,
+) {
+    for (id, _) in group.roots() {
+        if let Some(route) = routes.get(id) {
+            out.push((route.tx.clone(), overflow(route)));
+        }
+    }
+}
+
+fn overflow(route: &Route) -> RawEvent {
+    RawEvent {
+        side: route.side,
+        source: EventSource::Fanotify,
+        mask: FanotifyEventMask::Q_OVERFLOW.0,
+        path: route.root.clone(),
+        from: None,
+        cookie: 0,
+        pid: None,
+        overflow: true,
+        ts: SystemTime::now(),
+    }
+}
+
+/// Mark work [`Hub::read`] finds under the lock and does after releasing it.
+enum Deferred {
+    /// place the marks, then report what is already below the new
+    /// directory to these streams
+    Marks(TreeMarks, Vec<(Side, mpsc::Sender<RawEvent>)>),
+    /// a directory was deleted or moved away
+    Forget(PathBuf),
+}
+
+/// Wakes an epoll loop when dropped.
+struct WakeOnDrop(Arc<EventFd>);
+
+impl Drop for WakeOnDrop {
+    fn drop(&mut self) {
+        let _ = self.0.notify();
+    }
+}
+
+/// CREATE for everything below `dir` (not following symlinks).
+fn synthesize_tree(dir: &Path, side: Side, source: EventSource, mut emit: impl FnMut(RawEvent)) {
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            let mut mask = FanotifyEventMask::CREATE.0;
+            if is_dir {
+                mask |= FanotifyEventMask::ONDIR.0;
+                stack.push(entry.path());
+            }
+            emit(RawEvent {
+                side,
+                source,
+                mask,
+                path: entry.path(),
+                from: None,
+                cookie: 0,
+                pid: None,
+                overflow: false,
+                ts: SystemTime::now(),
+            });
+        }
+    }
+}
+
+/// inotify loop for one root; the ffi layer watches new directories itself.
+fn spawn_inotify(root: PathBuf, side: Side, config: &CollectorConfig) -> Result<Handle<RawEvent>> {
+    let mut ino = Inotify::new(InotifyFlags::CLOEXEC | InotifyFlags::NONBLOCK)?;
+    ino.add_watch_recursive(&root, IN_EVENTS)?;
+
+    let epoll = Epoll::new(EpollCreateFlags::CLOEXEC)?;
+    let wake = Arc::new(EventFd::new(
+        0,
+        EventFdFlags::CLOEXEC | EventFdFlags::NONBLOCK,
+    )?);
+    epoll.add(ino.as_fd(), EpollEventFlags::IN, SOURCE)?;
+    epoll.add(wake.as_fd(), EpollEventFlags::IN, STOP)?;
+
+    let (tx, rx) = mpsc::channel(config.capacity.max(1));
+    let (stop, mut stop_rx) = broadcast::channel(1);
+    let notifier = Arc::clone(&wake);
+    tokio::spawn(async move {
+        // 收到 stop、Handle 被丢弃，或 runtime 关闭时任务被取消：都唤醒 epoll 循环
+        let _wake = WakeOnDrop(notifier);
+        let _ = stop_rx.recv().await;
+    });
+
+    let buf_len = config.buf_len;
+    let join = tokio::task::spawn_blocking(move || {
+        // wake 必须活到循环结束
+        let _wake = wake;
+        let mut ready = Events::with_capacity(2);
+        let mut buf = vec![0u8; buf_len];
+        let res: Result<()> = (|| loop {
+            epoll.wait_into(&mut ready, -1)?;
+            if ready.iter().any(|r| r.data_u64 == STOP) {
+                return Ok(());
+            }
+            let mut overflowed = false;
+            for ev in ino.read_events(&mut buf)? {
+                let overflow = ev.is_overflow();
+                overflowed |= overflow;
+                let ev = RawEvent {
+                    side,
+                    source: EventSource::Inotify,
+                    mask: ev.mask.0 as u64,
+                    path: if overflow { root.clone() } else { ev.path },
+                    from: ev.from,
+                    cookie: ev.cookie,
+                    pid: None,
+                    overflow,
+                    ts: SystemTime::now(),
+                };
+                // 接收端已丢弃（Handle::shutdown 会先丢弃它）：结束
+                if tx.blocking_send(ev).is_err() {
+                    return Ok(());
+                }
+            }
+            if overflowed {
+                // 同上：补齐溢出期间新建目录的 watch（已有的返回原 wd）
+                ino.add_watch_recursive(&root, IN_EVENTS)?;
+            }
+        })();
+        if res.is_err() {
+            let _ = tx.blocking_send(RawEvent {
+                side,
+                source: EventSource::Inotify,
+                mask: 0,
+                path: root,
+                from: None,
+                cookie: 0,
+                pid: None,
+                overflow: true,
+                ts: SystemTime::now(),
+            });
+        }
+    });
+
+    Ok(Handle::new(rx, stop, join))
+}
@@ -0,0 +1,396 @@
+//! Coalesced events -> per-pair queues read by the workers.
+//!
+//! Every pair has its own queue, so a slow worker only holds up its own
+//! pair. A producer that finds the queue over its memory bound waits up to
+//! `stall` for the worker to catch up (backpressure); waiting longer would
+//! only move the backlog into the kernel queue, which overflows into a full
+//! rescan of the root. If the worker is still behind, the queue is
+//! collapsed: the queued events are replaced by rescans of their parent
+//! directories (grandparents, ... if that is still too much). Nothing is
+//! dropped silently: a directory rescan covers every event it replaced, and
+//! later events below a directory that is already queued for a rescan are
+//! covered by it too. A collapse all the way up to the root stands for
+//! lost events like a kernel queue overflow, and is flagged `overflow`.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+use synchron_utils::{Action, CoalescedEvent, QueueStatus, Side};
+use tokio::sync::mpsc::error::SendError;
+use tokio::sync::Notify;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct QueueLimits {
+    /// estimated bytes of queued events before producers are held back
+    pub max_bytes: usize,
+    /// how long a producer waits for the worker before the queue collapses
+    pub stall: Duration,
+}
+
+impl Default for QueueLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: 16 * 1024 * 1024,
+            stall: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Memory held by a queued event.
+fn event_size(ev: &CoalescedEvent) -> usize {
+    let path_len = |p: &Path| p.as_os_str().len();
+    core::mem::size_of::<CoalescedEvent>()
+        + path_len(&ev.path)
+        + ev.from.as_deref().map_or(0, path_len)
+}
+
+#[derive(Default)]
+struct Inner {
+    events: VecDeque<CoalescedEvent>,
+    bytes: usize,
+    /// directories a collapse queued for a rescan, until the worker takes it
+    dirty: HashSet<(Side, PathBuf)>,
+    collapses: u64,
+    senders: usize,
+    waiting: usize,
+    rx_closed: bool,
+}
+
+impl Inner {
+    fn over(&self, limits: &QueueLimits) -> bool {
+        self.bytes >= limits.max_bytes
+    }
+
+    fn push_back(&mut self, ev: CoalescedEvent) {
+        self.bytes += event_size(&ev);
+        self.events.push_back(ev);
+    }
+
+    fn pop_front(&mut self) -> Option<CoalescedEvent> {
+        let ev = self.events.pop_front()?;
+        self.bytes -= event_size(&ev);
+        if ev.action == Action::Rescan {
+            self.dirty.remove(&(ev.side, ev.path.clone()));
+        }
+        Some(ev)
+    }
+
+    /// A queued collapse rescan already covers `path`.
+    fn covered(&self, side: Side, path: &Path) -> bool {
+        !self.dirty.is_empty()
+            && path
+                .ancestors()
+                .any(|p| self.dirty.contains(&(side, p.to_path_buf())))
+    }
+
+    fn enqueue(&mut self, ev: CoalescedEvent) {
+        let covered = self.covered(ev.side, &ev.path)
+            && ev.from.as_deref().is_none_or(|f| self.covered(ev.side, f));
+        // 溢出仍要送到 worker 计数；重扫范围由它合并
+        if !covered || ev.overflow {
+            self.push_back(ev);
+        }
+    }
+
+    /// Replace the queue by rescans of the directories its events are in,
+    /// going up a level at a time until it takes at most half the bound.
+    fn collapse(&mut self, limits: &QueueLimits) {
+        self.collapses += 1;
+        for level in 1.. {
+            let mut dirs: BTreeMap<(Side, PathBuf), SystemTime> = BTreeMap::new();
+            for ev in &self.events {
+                for path in std::iter::once(&ev.path).chain(ev.from.as_ref()) {
+                    // 目录自身的重扫不用再往上提
+                    let up = if ev.action == Action::Rescan {
+                        level - 1
+                    } else {
+                        level
+                    };
+                    let dir = path.ancestors().nth(up).unwrap_or(Path::new(""));
+                    let ts = dirs.entry((ev.side, dir.to_path_buf())).or_insert(ev.ts);
+                    *ts = (*ts).max(ev.ts);
+                }
+            }
+
+            // 有序遍历时子目录紧跟在祖先后面：被祖先覆盖的都去掉
+            let mut kept: Vec<((Side, PathBuf), SystemTime)> = Vec::with_capacity(dirs.len());
+            for (key, ts) in dirs {
+                match kept.last() {
+                    Some(((side, dir), _)) if *side == key.0 && key.1.starts_with(dir) => {}
+                    _ => kept.push((key, ts)),
+                }
+            }
+
+            let at_root = kept.iter().all(|((_, dir), _)| dir.as_os_str().is_empty());
+            self.events.clear();
+            self.bytes = 0;
+            self.dirty.clear();
+            for ((side, path), ts) in kept {
+                self.dirty.insert((side, path.clone()));
+                self.push_back(CoalescedEvent {
+                    side,
+                    action: Action::Rescan,
+                    // 折叠到根目录：和内核队列溢出一样丢了事件
+                    overflow: path.as_os_str().is_empty(),
+                    path,
+                    from: None,
+                    is_dir: true,
+                    ts,
+                });
+            }
+            if self.bytes <= limits.max_bytes / 2 || at_root {
+                return;
+            }
+        }
+    }
+
+    fn status(&self) -> QueueStatus {
+        QueueStatus {
+            depth: self.events.len(),
+            bytes: self.bytes,
+            dirty: self.dirty.len(),
+            collapses: self.collapses,
+            waiting: self.waiting,
+        }
+    }
+}
+
+struct Queue {
+    limits: QueueLimits,
+    inner: Mutex<Inner>,
+    /// an event was queued or the last sender left
+    readable: Notify,
+    /// the worker took events or went away
+    writable: Notify,
+}
+
+impl Queue {
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        // 持锁期间不会 panic，poison 也不影响数据一致性
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Wait until the queue is under its bound or the worker is gone.
+    async fn drained(&self) {
+        loop {
+            let notified = self.writable.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            {
+                let inner = self.lock();
+                if !inner.over(&self.limits) || inner.rx_closed {
+                    return;
+                }
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Counts a producer in `QueueStatus::waiting` while alive.
+struct Waiting<'a>(&'a Queue);
+
+impl<'a> Waiting<'a> {
+    fn new(queue: &'a Queue) -> Self {
+        queue.lock().waiting += 1;
+        Self(queue)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.lock().waiting -= 1;
+    }
+}
+
+/// Producer side of a pair queue; one per root, cloned freely.
+pub struct Sender {
+    queue: Arc<Queue>,
+}
+
+impl Sender {
+    /// Queue `ev`, waiting up to `stall` if the queue is full. Fails only if
+    /// the receiver is gone.
+    pub async fn send(&self, ev: CoalescedEvent) -> Result<(), SendError<CoalescedEvent>> {
+        let q = &self.queue;
+        let full = q.lock().over(&q.limits);
+        if full {
+            // 背压：给 worker 一点时间追上，否则折叠队列
+            let _waiting = Waiting::new(q);
+            let _ = tokio::time::timeout(q.limits.stall, q.drained()).await;
+        }
+
+        let mut inner = q.lock();
+        if inner.rx_closed {
+            return Err(SendError(ev));
+        }
+        if inner.over(&q.limits) {
+            inner.collapse(&q.limits);
+        }
+        inner.enqueue(ev);
+        drop(inner);
+        q.readable.notify_one();
+        Ok(())
+    }
+
+    pub fn status(&self) -> QueueStatus {
+        self.queue.lock().status()
+    }
+}
+
+impl Clone for Sender {
+    fn clone(&self) -> Self {
+        self.queue.lock().senders += 1;
+        Self {
+            queue: Arc::clone(&self.queue),
+        }
+    }
+}
+
+impl Drop for Sender {
+    fn drop(&mut self) {
+        let last = {
+            let mut inner = self.queue.lock();
+            inner.senders -= 1;
+            inner.senders == 0
+        };
+        if last {
+            self.queue.readable.notify_one();
+        }
+    }
+}
+
+/// Worker side of a pair queue.
+pub struct Receiver {
+    queue: Arc<Queue>,
+}
+
+impl Receiver {
+    /// Next event; `None` once every sender is gone and the queue is empty.
+    pub async fn recv(&mut self) -> Option<CoalescedEvent> {
+        let mut buf = Vec::with_capacity(1);
+        self.recv_many(&mut buf, 1).await;
+        buf.pop()
+    }
+
+    /// Move up to `limit` events into `buf`, waiting for at least one.
+    /// Returns how many were moved; 0 means the queue is closed.
+    pub async fn recv_many(&mut self, buf: &mut Vec<CoalescedEvent>, limit: usize) -> usize {
+        let q = &self.queue;
+        loop {
+            let notified = q.readable.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            {
+                let mut inner = q.lock();
+                let before = buf.len();
+                while buf.len() - before < limit.max(1) {
+                    match inner.pop_front() {
+                        Some(ev) => buf.push(ev),
+                        None => break,
+                    }
+                }
+                let n = buf.len() - before;
+                if n > 0 {
+                    drop(inner);
+                    q.writable.notify_waiters();
+                    return n;
+                }
+                if inner.senders == 0 {
+                    return 0;
+                }
+            }
+            notified.await;
+        }
+    }
+
+    pub fn status(&self) -> QueueStatus {
+        self.queue.lock().status()
+    }
+}
+
+impl Drop for Receiver {
+    fn drop(&mut self) {
+        self.queue.lock().rx_closed = true;
+        self.queue.writable.notify_waiters();
+    }
+}
+
+/// Per-pair queues, keyed by pair id.
+pub struct Dispatcher<K = String> {
+    queues: Mutex<HashMap<K, Sender>>,
+}
+
+impl<K> Default for Dispatcher<K> {
+    fn default() -> Self {
+        Self {
+            queues: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone> Dispatcher<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<K, Sender>> {
+        self.queues.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Create the queue of `key`, replacing any previous one (whose worker
+    /// then sees the end of its queue once its producers are gone).
+    pub fn add(&self, key: K, limits: QueueLimits) -> (Sender, Receiver) {
+        let queue = Arc::new(Queue {
+            limits,
+            inner: Mutex::new(Inner {
+                senders: 1,
+                ..Inner::default()
+            }),
+            readable: Notify::new(),
+            writable: Notify::new(),
+        });
+        let tx = Sender {
+            queue: Arc::clone(&queue),
+        };
+        self.lock().insert(key, tx.clone());
+        (tx, Receiver { queue })
+    }
+
+    /// Forget the queue of `key`. The worker drains what is left once the
+    /// producers holding a [`Sender`] are gone.
+    pub fn remove(&self, key: &K) -> bool {
+        self.lock().remove(key).is_some()
+    }
+
+    pub fn sender(&self, key: &K) -> Option<Sender> {
+        self.lock().get(key).cloned()
+    }
+
+    /// Route `ev` to the queue of `key`; the event comes back if there is
+    /// no such queue or its worker is gone.
+    pub async fn dispatch(
+        &self,
+        key: &K,
+        ev: CoalescedEvent,
+    ) -> Result<(), SendError<CoalescedEvent>> {
+        match self.sender(key) {
+            Some(tx) => tx.send(ev).await,
+            None => Err(SendError(ev)),
+        }
+    }
+
+    pub fn status(&self, key: &K) -> Option<QueueStatus> {
+        self.lock().get(key).map(Sender::status)
+    }
+
+    /// Every queue, for `service.status`.
+    pub fn statuses(&self) -> Vec<(K, QueueStatus)> {
+        self.lock()
+            .iter()
+            .map(|(k, tx)| (k.clone(), tx.status()))
+            .collect()
+    }
+}
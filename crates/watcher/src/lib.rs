@@ -1,4 +1,8 @@
 pub mod coalescer;
 pub mod collector;
 pub mod dispatcher;
+pub mod marks;
+pub mod mounts;
 pub mod normalizer;
+pub mod roots;
+pub mod suppress;
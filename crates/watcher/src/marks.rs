@@ -0,0 +1,569 @@
+//! Mark placement shared between pairs.
+//!
+//! One fanotify group per device (`st_dev`), no matter how many pair roots
+//! live on it: 50 pairs under `/home` cost one group and one read loop.
+//! Events are handed to pairs by resolved path prefix.
+//!
+//! - wide scope: a single FILESYSTEM mark (MOUNT if the kernel refuses) covers
+//!   the device. Nothing to walk and no race with new subdirectories; events
+//!   outside every root are dropped by [`Group::route`].
+//! - inode scope: one mark per directory under each root. Used when no root
+//!   covers its whole mount (a wide mark would mostly report strangers) or
+//!   when wide marks are unavailable. New subdirectories must be added with
+//!   [`Group::watch_dir`] as their CREATE events arrive.
+//!
+//! Walking a tree to mark it can take long. An event loop sharing the table
+//! behind a lock asks for a [`TreeMarks`] instead ([`Group::plan_watch`],
+//! [`Group::plan_remark`]), places it with the lock released and hands it
+//! back to [`Group::commit`].
+
+use std::collections::{HashMap, HashSet};
+use std::os::fd::{AsFd, BorrowedFd};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use synchron_ffi::{
+    statx, AtFlags, DirFd, Errno, Error, Fanotify, FanotifyEvent, FanotifyEventMask,
+    FanotifyEventRef, FanotifyInitFlags, FanotifyMarkFlags, FileHandle, FileHandleRef, MountCache,
+    OpenFlags, StatxAttr, StatxMask,
+};
+
+type Result<T> = core::result::Result<T, Error>;
+
+/// How marks are placed for a device.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum MarkMode {
+    /// wide mark when some root is a mount root, per-directory marks otherwise
+    #[default]
+    Auto,
+    /// always a wide mark, even for a small subtree of a big filesystem
+    Wide,
+    /// always per-directory marks
+    Inode,
+}
+
+/// What the marks of a group currently look like.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MarkScope {
+    Filesystem,
+    Mount,
+    Inode,
+}
+
+/// Every pair root on one device, sharing one fanotify group.
+pub struct Group<K> {
+    fan: Arc<Fanotify>,
+    mounts: MountCache,
+    mask: FanotifyEventMask,
+    scope: MarkScope,
+    /// path the wide mark was placed on (needed to remove it)
+    wide_at: Option<PathBuf>,
+    /// (pair key, canonical root, root is a mount root, handles of the root
+    /// and of its parent)
+    roots: Vec<(K, PathBuf, bool, Vec<FileHandle>)>,
+    /// directories carrying an inode mark, by the path they were marked at;
+    /// see [`Group::forget_dir`]
+    dirs: HashSet<PathBuf>,
+}
+
+impl<K: Clone + Eq> Group<K> {
+    pub fn fanotify(&self) -> &Fanotify {
+        &self.fan
+    }
+
+    pub fn scope(&self) -> MarkScope {
+        self.scope
+    }
+
+    pub fn roots(&self) -> impl Iterator<Item = (&K, &Path)> {
+        self.roots.iter().map(|(k, root, _, _)| (k, root.as_path()))
+    }
+
+    /// Pairs whose root contains `path` (nested roots all match).
+    pub fn route<'a>(&'a self, path: &'a Path) -> impl Iterator<Item = &'a K> + 'a {
+        self.roots
+            .iter()
+            .filter(move |(_, root, _, _)| path.starts_with(root))
+            .map(|(k, _, _, _)| k)
+    }
+
+    /// Roots a DELETE_SELF / MOVE_SELF may have taken away: those whose own
+    /// handle, or their parent's, is the object of `ev`. Events on any other
+    /// directory leave every root where it was.
+    pub fn roots_hit(&self, ev: &FanotifyEventRef<'_>) -> Vec<(&K, &Path)> {
+        let Some(hit) = ev
+            .fid()
+            .or(ev.dir_fid())
+            .or(ev.dir_name().map(|(dir, _)| dir))
+        else {
+            return Vec::new();
+        };
+        self.roots
+            .iter()
+            .filter(|(_, _, _, handles)| handles.iter().any(|h| same_handle(h, &hit)))
+            .map(|(k, root, _, _)| (k, root.as_path()))
+            .collect()
+    }
+
+    /// Absolute path an event refers to (entry path for DFID_NAME events).
+    pub fn resolve(&self, ev: &FanotifyEvent) -> Result<PathBuf> {
+        if let Some((dir, name)) = ev.dir_name() {
+            return self.mounts.resolve_name(dir, name);
+        }
+        if let Some(fid) = ev.fid().or(ev.dir_fid()) {
+            return self.mounts.resolve(fid);
+        }
+        Err(Error::from_errno(Errno::EINVAL))
+    }
+
+    /// `(from, to)` of a FAN_RENAME event. Either side may lie outside every
+    /// root (moved in from / out to an unwatched directory).
+    pub fn resolve_rename(&self, ev: &FanotifyEvent) -> Result<(PathBuf, PathBuf)> {
+        let (Some((old_dir, old)), Some((new_dir, new))) = (ev.old_name(), ev.new_name()) else {
+            return Err(Error::from_errno(Errno::EINVAL));
+        };
+        Ok((
+            self.mounts.resolve_name(old_dir, old)?,
+            self.mounts.resolve_name(new_dir, new)?,
+        ))
+    }
+
+    /// [`Self::resolve`] for an event still in its [`EventBuffer`](synchron_ffi::EventBuffer).
+    pub fn resolve_ref(&self, ev: &FanotifyEventRef<'_>) -> Result<PathBuf> {
+        if let Some((dir, name)) = ev.dir_name() {
+            return self.mounts.resolve_name(&dir.to_handle(), name);
+        }
+        if let Some(fid) = ev.fid().or(ev.dir_fid()) {
+            return self.mounts.resolve(&fid.to_handle());
+        }
+        Err(Error::from_errno(Errno::EINVAL))
+    }
+
+    /// [`Self::resolve_rename`] for an event still in its buffer.
+    pub fn resolve_rename_ref(&self, ev: &FanotifyEventRef<'_>) -> Result<(PathBuf, PathBuf)> {
+        let (Some((old_dir, old)), Some((new_dir, new))) = (ev.old_name(), ev.new_name()) else {
+            return Err(Error::from_errno(Errno::EINVAL));
+        };
+        Ok((
+            self.mounts.resolve_name(&old_dir.to_handle(), old)?,
+            self.mounts.resolve_name(&new_dir.to_handle(), new)?,
+        ))
+    }
+
+    /// Mark a directory created under one of the roots, and everything that
+    /// already appeared below it. No-op for wide scope. Entries created
+    /// before the mark landed produce no event: the caller rescans `dir`.
+    pub fn watch_dir(&mut self, dir: &Path) -> Result<()> {
+        if self.scope != MarkScope::Inode || self.route(dir).next().is_none() {
+            return Ok(());
+        }
+        self.mark_tree(dir)
+    }
+
+    /// `dir` was deleted or moved away: forget it and everything below, so
+    /// a directory created at the same path later gets its own mark. A
+    /// moved directory keeps its marks (they are on the inodes); its new
+    /// path is marked again by [`Self::watch_dir`], which is harmless.
+    pub fn forget_dir(&mut self, dir: &Path) {
+        self.dirs.retain(|d| !d.starts_with(dir));
+    }
+
+    /// After a queue overflow: directories created while events were lost
+    /// carry no mark yet. Walks every root again (existing marks are kept).
+    /// No-op for wide scope.
+    pub fn remark(&mut self) -> Result<()> {
+        if self.scope != MarkScope::Inode {
+            return Ok(());
+        }
+        // 缓存里可能还有已删除/改名的目录：整体重建
+        self.dirs.clear();
+        let roots: Vec<PathBuf> = self.roots.iter().map(|(_, r, _, _)| r.clone()).collect();
+        for root in roots {
+            self.mark_tree(&root)?;
+        }
+        Ok(())
+    }
+
+    /// [`Self::watch_dir`], to be placed without holding the group. `None`
+    /// when there is nothing to mark.
+    pub fn plan_watch(&self, dir: &Path) -> Option<TreeMarks> {
+        if self.scope != MarkScope::Inode || self.route(dir).next().is_none() {
+            return None;
+        }
+        Some(self.plan(vec![dir.to_path_buf()], false))
+    }
+
+    /// [`Self::remark`], to be placed without holding the group.
+    pub fn plan_remark(&self) -> Option<TreeMarks> {
+        if self.scope != MarkScope::Inode {
+            return None;
+        }
+        let tops = self.roots.iter().map(|(_, r, _, _)| r.clone()).collect();
+        Some(self.plan(tops, true))
+    }
+
+    /// Record what `marks` placed. Marks that no longer fit the group (its
+    /// scope turned wide, or their root was removed meanwhile) are taken
+    /// back; marks placed for a group since closed died with its fd.
+    pub fn commit(&mut self, marks: TreeMarks) {
+        if !Arc::ptr_eq(&self.fan, &marks.fan) {
+            return;
+        }
+        if self.scope != MarkScope::Inode {
+            let _ = self.fan.mark(
+                FanotifyMarkFlags::FLUSH,
+                FanotifyEventMask::EMPTY,
+                DirFd::CWD,
+                None,
+            );
+            self.dirs.clear();
+            return;
+        }
+        if marks.reset {
+            self.dirs.clear();
+        }
+        for dir in marks.marked {
+            if self.route(&dir).next().is_some() {
+                self.dirs.insert(dir);
+            } else {
+                self.unmark_dir(&dir);
+            }
+        }
+    }
+
+    fn plan(&self, tops: Vec<PathBuf>, reset: bool) -> TreeMarks {
+        TreeMarks {
+            fan: Arc::clone(&self.fan),
+            mask: self.inode_mask(),
+            tops,
+            reset,
+            marked: HashSet::new(),
+        }
+    }
+
+    fn inode_mask(&self) -> FanotifyEventMask {
+        self.mask | FanotifyEventMask::ONDIR | FanotifyEventMask::EVENT_ON_CHILD
+    }
+
+    fn mark_tree(&mut self, top: &Path) -> Result<()> {
+        let mask = self.inode_mask();
+        mark_tree(&self.fan, mask, top, &mut self.dirs)
+    }
+
+    fn unmark_dir(&mut self, dir: &Path) {
+        // 目录已删除时内核已自动移除 mark
+        let _ = self.fan.mark(
+            FanotifyMarkFlags::REMOVE | FanotifyMarkFlags::ONLYDIR | FanotifyMarkFlags::DONT_FOLLOW,
+            self.inode_mask(),
+            DirFd::CWD,
+            Some(dir),
+        );
+        self.dirs.remove(dir);
+    }
+
+    /// Try FILESYSTEM, then MOUNT, on `at`.
+    fn place_wide(&mut self, at: &Path) -> Result<MarkScope> {
+        let mask = self.mask | FanotifyEventMask::ONDIR;
+        let mut last = Error::from_errno(Errno::EINVAL);
+        for (flag, scope) in [
+            (FanotifyMarkFlags::FILESYSTEM, MarkScope::Filesystem),
+            (FanotifyMarkFlags::MOUNT, MarkScope::Mount),
+        ] {
+            match self
+                .fan
+                .mark(FanotifyMarkFlags::ADD | flag, mask, DirFd::CWD, Some(at))
+            {
+                Ok(()) => {
+                    self.wide_at = Some(at.to_path_buf());
+                    return Ok(scope);
+                }
+                // EINVAL：mount mark 不支持目录项事件；EPERM：缺 CAP_SYS_ADMIN
+                Err(e) if matches!(e.errno, Errno::EINVAL | Errno::EPERM | Errno::EXDEV) => {
+                    last = e
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last)
+    }
+
+    fn remove_wide(&mut self) {
+        let flag = match self.scope {
+            MarkScope::Filesystem => FanotifyMarkFlags::FILESYSTEM,
+            MarkScope::Mount => FanotifyMarkFlags::MOUNT,
+            MarkScope::Inode => return,
+        };
+        if let Some(at) = self.wide_at.take() {
+            let _ = self.fan.mark(
+                FanotifyMarkFlags::FLUSH | flag,
+                FanotifyEventMask::EMPTY,
+                DirFd::CWD,
+                Some(&at),
+            );
+        }
+    }
+
+    /// Bring the marks in line with `mode` and the current roots.
+    fn rebalance(&mut self, mode: MarkMode) -> Result<()> {
+        let want_wide = match mode {
+            MarkMode::Wide => true,
+            MarkMode::Inode => false,
+            MarkMode::Auto => self.roots.iter().any(|(_, _, covers, _)| *covers),
+        };
+
+        if want_wide && self.scope == MarkScope::Inode {
+            let at = self
+                .roots
+                .iter()
+                .find(|(_, _, covers, _)| *covers)
+                .or(self.roots.first())
+                .map(|(_, root, _, _)| root.clone());
+            if let Some(at) = at {
+                if let Ok(scope) = self.place_wide(&at) {
+                    // 宽范围 mark 已覆盖：清掉所有 inode mark
+                    if !self.dirs.is_empty() {
+                        self.fan.mark(
+                            FanotifyMarkFlags::FLUSH,
+                            FanotifyEventMask::EMPTY,
+                            DirFd::CWD,
+                            None,
+                        )?;
+                        self.dirs.clear();
+                    }
+                    self.scope = scope;
+                    return Ok(());
+                }
+            }
+        } else if !want_wide && self.scope != MarkScope::Inode {
+            self.remove_wide();
+            self.scope = MarkScope::Inode;
+        } else if want_wide {
+            return Ok(());
+        }
+
+        // inode 范围：补齐每个 root 下的目录 mark
+        let roots: Vec<PathBuf> = self.roots.iter().map(|(_, r, _, _)| r.clone()).collect();
+        for root in roots {
+            self.mark_tree(&root)?;
+        }
+        Ok(())
+    }
+}
+
+/// Inode marks for one or more directory trees, planned by a [`Group`] and
+/// placed by [`Self::place`] while the group is free for other users.
+pub struct TreeMarks {
+    fan: Arc<Fanotify>,
+    mask: FanotifyEventMask,
+    tops: Vec<PathBuf>,
+    /// the group's cache is rebuilt from these marks alone
+    reset: bool,
+    marked: HashSet<PathBuf>,
+}
+
+impl TreeMarks {
+    /// The first tree planned (the new directory of [`Group::plan_watch`]).
+    pub fn top(&self) -> &Path {
+        &self.tops[0]
+    }
+
+    /// Walk the trees and mark every directory. A tree that vanished before
+    /// the walk is skipped: its DELETE tells the rest.
+    pub fn place(&mut self) -> Result<()> {
+        for top in &self.tops {
+            match mark_tree(&self.fan, self.mask, top, &mut self.marked) {
+                Ok(()) => {}
+                Err(e) if matches!(e.errno, Errno::ENOENT | Errno::ENOTDIR) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Mark every directory of the tree at `top` not yet in `dirs`, on `top`'s
+/// device, and add it to `dirs`.
+fn mark_tree(
+    fan: &Fanotify,
+    mask: FanotifyEventMask,
+    top: &Path,
+    dirs: &mut HashSet<PathBuf>,
+) -> Result<()> {
+    let dev = std::fs::symlink_metadata(top)?.dev();
+    let mut stack = vec![top.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        if dirs.contains(&dir) {
+            continue;
+        }
+        match fan.mark(
+            FanotifyMarkFlags::ADD | FanotifyMarkFlags::ONLYDIR | FanotifyMarkFlags::DONT_FOLLOW,
+            mask,
+            DirFd::CWD,
+            Some(&dir),
+        ) {
+            Ok(()) => {}
+            // 遍历途中被删掉/换成了文件：跳过
+            Err(e) if matches!(e.errno, Errno::ENOENT | Errno::ENOTDIR) && dir != top => continue,
+            Err(e) => return Err(e),
+        }
+        dirs.insert(dir.clone());
+
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            // 不跟随符号链接，不跨越挂载点（那是另一个设备的 group）
+            if meta.is_dir() && meta.dev() == dev {
+                stack.push(entry.path());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn same_handle(h: &FileHandle, r: &FileHandleRef<'_>) -> bool {
+    h.fsid == r.fsid && h.handle_type == r.handle_type && h.handle == r.handle
+}
+
+/// All groups, keyed by device.
+pub struct MarkTable<K> {
+    init: FanotifyInitFlags,
+    mask: FanotifyEventMask,
+    mode: MarkMode,
+    groups: HashMap<u64, Group<K>>,
+}
+
+impl<K: Clone + Eq> MarkTable<K> {
+    /// `init` needs a FID reporting mode (REPORT_FID / REPORT_DFID_NAME):
+    /// events are resolved through file handles, not event fds.
+    pub fn new(init: FanotifyInitFlags, mask: FanotifyEventMask, mode: MarkMode) -> Self {
+        Self {
+            init,
+            mask,
+            mode,
+            groups: HashMap::new(),
+        }
+    }
+
+    /// Start watching `root` for pair `key`. Joins the group of its device,
+    /// creating it on first use. Returns the device (group key).
+    pub fn add_root(&mut self, key: K, root: &Path) -> Result<u64> {
+        let root = std::fs::canonicalize(root)?;
+        let st = statx(DirFd::CWD, &root, AtFlags::EMPTY, StatxMask::TYPE)?;
+        if !st.is_dir() {
+            return Err(Error::from_errno(Errno::ENOTDIR));
+        }
+        let dev = st.dev_t();
+        // 内核不报告 MOUNT_ROOT 时保守地按“未覆盖整个挂载”处理
+        let covers = st.attribute(StatxAttr::MOUNT_ROOT) == Some(true);
+
+        let group = match self.groups.entry(dev) {
+            std::collections::hash_map::Entry::Occupied(e) => e.into_mut(),
+            std::collections::hash_map::Entry::Vacant(e) => e.insert(Group {
+                fan: Arc::new(Fanotify::new(
+                    self.init,
+                    OpenFlags::RDONLY | OpenFlags::CLOEXEC,
+                )?),
+                mounts: MountCache::new(),
+                mask: self.mask,
+                scope: MarkScope::Inode,
+                wide_at: None,
+                roots: Vec::new(),
+                dirs: HashSet::new(),
+            }),
+        };
+        // 根目录的删除/移走只按句柄认：不必每次都 stat 所有 root
+        let handles = [Some(root.as_path()), root.parent()]
+            .into_iter()
+            .flatten()
+            .filter_map(|dir| FileHandle::at(DirFd::CWD, dir, false).ok())
+            .map(|(handle, _)| handle)
+            .collect();
+        let mode = self.mode;
+        let added = group.mounts.register(fs_top(&root, dev)).and_then(|_| {
+            group.roots.push((key, root, covers, handles));
+            group.rebalance(mode).inspect_err(|_| {
+                group.roots.pop();
+            })
+        });
+        if let Err(e) = added {
+            // 刚建的 group 没有任何 root：不留空 group
+            if group.roots.is_empty() {
+                self.groups.remove(&dev);
+            }
+            return Err(e);
+        }
+        Ok(dev)
+    }
+
+    /// Stop watching every root of `key`. Groups left without roots are closed.
+    pub fn remove(&mut self, key: &K) -> Result<()> {
+        let mode = self.mode;
+        let mut emptied = Vec::new();
+        for (&dev, group) in self.groups.iter_mut() {
+            let before = group.roots.len();
+            group.roots.retain(|(k, _, _, _)| k != key);
+            if group.roots.len() == before {
+                continue;
+            }
+            if group.roots.is_empty() {
+                emptied.push(dev);
+                continue;
+            }
+            if group.scope == MarkScope::Inode {
+                let stale: Vec<PathBuf> = group
+                    .dirs
+                    .iter()
+                    .filter(|d| group.route(d).next().is_none())
+                    .cloned()
+                    .collect();
+                for dir in stale {
+                    group.unmark_dir(&dir);
+                }
+            }
+            group.rebalance(mode)?;
+        }
+        // 关闭 fanotify fd 即释放该 group 的所有 mark
+        for dev in emptied {
+            self.groups.remove(&dev);
+        }
+        Ok(())
+    }
+
+    pub fn group(&self, dev: u64) -> Option<&Group<K>> {
+        self.groups.get(&dev)
+    }
+
+    pub fn group_mut(&mut self, dev: u64) -> Option<&mut Group<K>> {
+        self.groups.get_mut(&dev)
+    }
+
+    /// (device, group) pairs; register each group's fd with the event loop.
+    pub fn groups(&self) -> impl Iterator<Item = (u64, &Group<K>)> {
+        self.groups.iter().map(|(&dev, g)| (dev, g))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+}
+
+impl<K> AsFd for Group<K> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fan.as_fd()
+    }
+}
+
+/// Topmost directory above `root` still on device `dev`: the mount point.
+/// [`MountCache`] keeps the directory it registers open, and an open root
+/// would not report DELETE_SELF until that fd is closed; a mount point
+/// cannot be removed.
+fn fs_top(root: &Path, dev: u64) -> &Path {
+    root.ancestors()
+        .take_while(|dir| std::fs::symlink_metadata(dir).is_ok_and(|m| m.dev() == dev))
+        .last()
+        .unwrap_or(root)
+}
@@ -0,0 +1,179 @@
+//! Pause pairs whose root mount went away, resume them when it is back.
+//!
+//! An unmounted USB drive or an expired autofs NFS mount leaves an empty
+//! mount point behind. Syncing against that would look like "every file was
+//! deleted", so the pair is parked in `WaitingForMount` instead.
+//!
+//! `/proc/self/mountinfo` signals every mount table change with EPOLLPRI
+//! (plus EPOLLERR); register [`MountWatch`] with [`MountWatch::INTEREST`] and
+//! when it becomes ready, pass [`MountWatch::table`] to
+//! [`MountWatch::refresh`]. The kernel resets the readiness in the poll
+//! itself, so each change is reported once even with level-triggered epoll.
+//!
+//! A root only counts as back when its mount point is mounted again with
+//! the device it had when it was added, or when the caller confirms it is
+//! the same root (the pair marker): some other filesystem mounted at the
+//! same place must not be synced against.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::os::fd::{AsFd, BorrowedFd};
+use std::path::{Path, PathBuf};
+use synchron_ffi::{statx, AtFlags, StatxMask};
+use synchron_ffi::{DirFd, EpollEventFlags, Errno, Error, MountTable};
+
+type Result<T> = core::result::Result<T, Error>;
+
+/// Mount state of one watched root.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RootState {
+    Mounted,
+    /// root missing, or now on a different device than when it was added
+    WaitingForMount,
+}
+
+/// Transition reported by [`MountWatch::refresh`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MountChange<K> {
+    /// Stop syncing `key`: drop its marks and ignore pending events.
+    Lost { key: K, root: PathBuf },
+    /// Mount is back: re-add marks, then run a catch-up scan of `root`
+    /// (nothing was watched while it was gone).
+    Restored { key: K, root: PathBuf },
+}
+
+struct Watched<K> {
+    key: K,
+    root: PathBuf,
+    /// `st_dev` of the root while mounted
+    dev: u64,
+    /// mount point of the mount the root lives on
+    mount_point: PathBuf,
+    state: RootState,
+}
+
+pub struct MountWatch<K> {
+    mountinfo: File,
+    roots: Vec<Watched<K>>,
+}
+
+impl<K: Clone + Eq> MountWatch<K> {
+    /// Epoll events to register the watch with.
+    pub const INTEREST: EpollEventFlags =
+        EpollEventFlags(EpollEventFlags::PRI.0 | EpollEventFlags::ERR.0);
+
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            mountinfo: File::open("/proc/self/mountinfo")?,
+            roots: Vec::new(),
+        })
+    }
+
+    /// Start tracking `root` for `key`. The root must be mounted right now;
+    /// its current device and mount point are what later checks compare to.
+    pub fn watch(&mut self, key: K, root: &Path) -> Result<()> {
+        let root = std::fs::canonicalize(root)?;
+        let dev = dev_of(&root)?;
+        // 与 refresh 用同一种查找，否则会立刻误报 Lost
+        let mount_point = self
+            .table()?
+            .covering(&root)
+            .ok_or(Error::from_errno(Errno::ENOENT))?
+            .mount_point
+            .clone();
+        self.track(key, root, mount_point, dev);
+        Ok(())
+    }
+
+    /// [`Self::watch`] with the mount point and device already known.
+    /// `root` must be canonical.
+    pub fn track(&mut self, key: K, root: PathBuf, mount_point: PathBuf, dev: u64) {
+        self.roots.retain(|w| w.key != key || w.root != root);
+        self.roots.push(Watched {
+            key,
+            root,
+            dev,
+            mount_point,
+            state: RootState::Mounted,
+        });
+    }
+
+    pub fn unwatch(&mut self, key: &K) {
+        self.roots.retain(|w| &w.key != key);
+    }
+
+    /// State of `key`, or `None` if none of its roots is watched.
+    /// A pair with any root waiting is waiting as a whole.
+    pub fn state(&self, key: &K) -> Option<RootState> {
+        let mut states = self.roots.iter().filter(|w| &w.key == key).map(|w| w.state);
+        let first = states.next()?;
+        Some(if states.any(|s| s == RootState::WaitingForMount) {
+            RootState::WaitingForMount
+        } else {
+            first
+        })
+    }
+
+    /// Re-check every root against `table` (normally [`Self::table`]).
+    /// `dev_of` looks up a root's current `st_dev` (`None`: gone);
+    /// `is_ours` decides whether a root that came back on a different device
+    /// is still the pair's, e.g. a USB drive that got a new device number.
+    /// Safe to call spuriously.
+    pub fn refresh(
+        &mut self,
+        table: &MountTable,
+        mut dev_of: impl FnMut(&Path) -> Option<u64>,
+        mut is_ours: impl FnMut(&K, &Path) -> bool,
+    ) -> Vec<MountChange<K>> {
+        let mut changes = Vec::new();
+        for w in &mut self.roots {
+            let current = table
+                .covering(&w.root)
+                .map(|m| m.mount_point.clone())
+                .zip(dev_of(&w.root));
+            match (w.state, current) {
+                (RootState::Mounted, Some((mp, dev))) if mp == w.mount_point && dev == w.dev => {}
+                (RootState::Mounted, _) => {
+                    w.state = RootState::WaitingForMount;
+                    changes.push(MountChange::Lost {
+                        key: w.key.clone(),
+                        root: w.root.clone(),
+                    });
+                }
+                // 挂载点回来了，但设备不同时必须确认是同一个 root
+                (RootState::WaitingForMount, Some((mp, dev)))
+                    if mp == w.mount_point && (dev == w.dev || is_ours(&w.key, &w.root)) =>
+                {
+                    w.dev = dev;
+                    w.state = RootState::Mounted;
+                    changes.push(MountChange::Restored {
+                        key: w.key.clone(),
+                        root: w.root.clone(),
+                    });
+                }
+                (RootState::WaitingForMount, _) => {}
+            }
+        }
+        changes
+    }
+
+    /// Current mount table, read through the watched fd.
+    pub fn table(&mut self) -> Result<MountTable> {
+        // 同一个 fd 反复读：seq_file 每次从头生成，需先 seek 回 0
+        let mut buf = Vec::with_capacity(8192);
+        self.mountinfo.seek(SeekFrom::Start(0))?;
+        self.mountinfo.read_to_end(&mut buf)?;
+        MountTable::parse(&buf)
+    }
+}
+
+impl<K> AsFd for MountWatch<K> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.mountinfo.as_fd()
+    }
+}
+
+/// `st_dev` of `path`; the lookup [`MountWatch::refresh`] normally gets.
+pub fn dev_of(path: &Path) -> Result<u64> {
+    Ok(statx(DirFd::CWD, path, AtFlags::EMPTY, StatxMask::TYPE)?.dev_t())
+}
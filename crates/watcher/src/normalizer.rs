@@ -0,0 +1,207 @@
+//! Kernel masks -> [`Action`]s on root-relative paths.
+//!
+//! fanotify and inotify share the bit values of every event used here, so
+//! one table covers both:
+//!
+//! | raw event                                     | result                  |
+//! |-----------------------------------------------|-------------------------|
+//! | CREATE, MOVED_TO (unpaired), MODIFY, CLOSE_WRITE, ATTRIB | `Write`      |
+//! | DELETE, MOVED_FROM (unpaired)                 | `Delete`                |
+//! | RENAME / paired MOVED_FROM+MOVED_TO           | `Rename`                |
+//! | ... from outside the root                     | `Write` (moved in)      |
+//! | ... to outside the root                       | `Delete` (moved out)    |
+//! | created and removed in one merged fanotify event | `Rescan` of the path |
+//! | DELETE_SELF / MOVE_SELF of the root, or the root's own entry deleted, moved or created | `Rescan` of the root; never a delete |
+//! | DELETE_SELF / MOVE_SELF below the root        | nothing (parent reports it) |
+//! | Q_OVERFLOW, or an event that could not be resolved | `Rescan` of the root |
+//! | anything under `.synchron/` (the pair marker) | nothing; outside the pair |
+//!
+//! An inotify MOVED_FROM that was not paired by the ffi layer (its
+//! MOVED_TO landed in the next read) is held until the next event: the
+//! kernel queues the two halves back to back, so anything else means the
+//! entry left the watched tree. [`Normalizer::flush`] settles it when the
+//! stream goes idle.
+
+use std::path::{Path, PathBuf};
+use synchron_ffi::{FanotifyEventMask, InotifyMask};
+use synchron_utils::marker::MARKER_DIR;
+use synchron_utils::{Action, NormalizedEvent, RawEvent};
+
+const MODIFY: u64 = FanotifyEventMask::MODIFY.0;
+const ATTRIB: u64 = FanotifyEventMask::ATTRIB.0;
+const CLOSE_WRITE: u64 = FanotifyEventMask::CLOSE_WRITE.0;
+const MOVED_FROM: u64 = FanotifyEventMask::MOVED_FROM.0;
+const MOVED_TO: u64 = FanotifyEventMask::MOVED_TO.0;
+const CREATE: u64 = FanotifyEventMask::CREATE.0;
+const DELETE: u64 = FanotifyEventMask::DELETE.0;
+const DELETE_SELF: u64 = FanotifyEventMask::DELETE_SELF.0;
+const MOVE_SELF: u64 = FanotifyEventMask::MOVE_SELF.0;
+const Q_OVERFLOW: u64 = FanotifyEventMask::Q_OVERFLOW.0;
+const RENAME: u64 = FanotifyEventMask::RENAME.0;
+/// FAN_ONDIR == IN_ISDIR
+const ONDIR: u64 = FanotifyEventMask::ONDIR.0;
+const IN_IGNORED: u64 = InotifyMask::IGNORED.0 as u64;
+const IN_UNMOUNT: u64 = InotifyMask::UNMOUNT.0 as u64;
+
+pub struct Normalizer {
+    root: PathBuf,
+    /// unpaired inotify MOVED_FROM
+    pending: Option<RawEvent>,
+}
+
+impl Normalizer {
+    /// `root` must be the path the collector reports events under
+    /// (canonical).
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            pending: None,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// A MOVED_FROM is waiting for its MOVED_TO.
+    pub fn has_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Normalize `ev`, appending 0..n events to `out`.
+    pub fn push(&mut self, ev: RawEvent, out: &mut Vec<NormalizedEvent>) {
+        if let Some(from) = self.pending.take() {
+            let is_other_half = ev.cookie != 0
+                && ev.cookie == from.cookie
+                && ev.mask & MOVED_TO != 0
+                && ev.mask & MOVED_FROM == 0;
+            if is_other_half {
+                self.rename(&from.path, &ev, out);
+                return;
+            }
+            // 下一个事件不是配对的 MOVED_TO：条目被移出了监视范围
+            self.convert(from, out);
+        }
+        if ev.mask & MOVED_FROM != 0 && ev.mask & MOVED_TO == 0 && ev.cookie != 0 && !ev.overflow {
+            self.pending = Some(ev);
+            return;
+        }
+        self.convert(ev, out);
+    }
+
+    /// Settle a pending MOVED_FROM as a delete. Call when no event arrived
+    /// for a while.
+    pub fn flush(&mut self, out: &mut Vec<NormalizedEvent>) {
+        if let Some(from) = self.pending.take() {
+            self.convert(from, out);
+        }
+    }
+
+    fn convert(&self, ev: RawEvent, out: &mut Vec<NormalizedEvent>) {
+        let mask = ev.mask;
+        if ev.overflow || mask & Q_OVERFLOW != 0 {
+            // 溢出事件的 path 是根目录
+            let path = self.relative(&ev.path).unwrap_or_default();
+            out.push(NormalizedEvent {
+                overflow: true,
+                ..self.event(&ev, Action::Rescan, path, None, true)
+            });
+            return;
+        }
+        // IGNORED 跟在 watch 移除之后；卸载由 mounts 负责
+        if mask & (IN_IGNORED | IN_UNMOUNT) != 0 {
+            return;
+        }
+        // 两端可能只有一端在根目录内
+        if let Some(from) = ev.from.as_deref() {
+            if mask & (RENAME | MOVED_FROM | MOVED_TO) != 0 {
+                self.rename(from, &ev, out);
+                return;
+            }
+        }
+        let Some(path) = self.synced(&ev.path) else {
+            return;
+        };
+        let namespace = DELETE_SELF | MOVE_SELF | CREATE | DELETE | MOVED_FROM | MOVED_TO;
+        if path.as_os_str().is_empty() && mask & namespace != 0 {
+            self.root_replaced(&ev, out);
+            return;
+        }
+        if mask & (DELETE_SELF | MOVE_SELF) != 0 {
+            return;
+        }
+
+        let is_dir = mask & ONDIR != 0;
+        let created = mask & (CREATE | MOVED_TO) != 0;
+        let gone = mask & (DELETE | MOVED_FROM) != 0;
+        let written = mask & (MODIFY | CLOSE_WRITE | ATTRIB) != 0;
+        let action = match (created, gone) {
+            // 合并后的 fanotify 事件丢失了先后顺序
+            (true, true) => Action::Rescan,
+            (false, true) => Action::Delete,
+            (true, false) => Action::Write,
+            (false, false) if written => Action::Write,
+            // ACCESS / OPEN / CLOSE_NOWRITE
+            (false, false) => return,
+        };
+        out.push(self.event(&ev, action, path, None, is_dir));
+    }
+
+    /// `ev` moved `from` to `ev.path`; either end may be outside the root.
+    fn rename(&self, from: &Path, ev: &RawEvent, out: &mut Vec<NormalizedEvent>) {
+        let is_dir = ev.mask & ONDIR != 0;
+        let is_root = |p: &Option<PathBuf>| p.as_ref().is_some_and(|p| p.as_os_str().is_empty());
+        match (self.synced(from), self.synced(&ev.path)) {
+            (from, to) if is_root(&from) || is_root(&to) => self.root_replaced(ev, out),
+            (Some(from), Some(to)) => {
+                out.push(self.event(ev, Action::Rename, to, Some(from), is_dir))
+            }
+            (None, Some(to)) => out.push(self.event(ev, Action::Write, to, None, is_dir)),
+            (Some(from), None) => out.push(self.event(ev, Action::Delete, from, None, is_dir)),
+            (None, None) => {}
+        }
+    }
+
+    /// The root itself was deleted, moved away or replaced (its own
+    /// `*_SELF` event, or its entry in the parent under a wide mark).
+    fn root_replaced(&self, ev: &RawEvent, out: &mut Vec<NormalizedEvent>) {
+        // 根目录没了不等于“所有文件都删了”：只要求重扫，由 preflight
+        // 和 mounts 判断 root 是否还在，并暂停这个 pair
+        out.push(self.event(ev, Action::Rescan, PathBuf::new(), None, true));
+    }
+
+    fn relative(&self, path: &Path) -> Option<PathBuf> {
+        path.strip_prefix(&self.root).ok().map(Path::to_path_buf)
+    }
+
+    /// [`Self::relative`], minus synchron's own files in the root.
+    fn synced(&self, path: &Path) -> Option<PathBuf> {
+        let rel = self.relative(path)?;
+        // 标记目录按“在 root 之外”处理：移进移出等同于创建/删除
+        if rel.components().next() == Some(std::path::Component::Normal(MARKER_DIR.as_ref())) {
+            return None;
+        }
+        Some(rel)
+    }
+
+    fn event(
+        &self,
+        ev: &RawEvent,
+        action: Action,
+        path: PathBuf,
+        from: Option<PathBuf>,
+        is_dir: bool,
+    ) -> NormalizedEvent {
+        NormalizedEvent {
+            side: ev.side,
+            action,
+            path,
+            from,
+            is_dir,
+            created: action == Action::Write && ev.mask & (CREATE | MOVED_TO | RENAME) != 0,
+            pid: ev.pid,
+            overflow: false,
+            ts: ev.ts,
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Mount-aware validation of pair roots: run by the CLI before `pair.add` is
+//! sent, and again by the manager before it accepts the pair.
+
+use std::path::{Path, PathBuf};
+
+use synchron_ffi::{DirFd, Errno, FileHandle, FsSupport, MountTable};
+use synchron_utils::marker;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RootError {
+    #[error("{}: {source}", .0.display(), source = .1)]
+    Access(PathBuf, #[source] std::io::Error),
+
+    #[error("{}: not a directory", .0.display())]
+    NotDir(PathBuf),
+
+    #[error("cannot read mount table: {0}")]
+    MountTable(#[source] synchron_ffi::Error),
+
+    #[error("{}: is on {fs_type} ({}), which cannot be synced", .root.display(), .mount_point.display())]
+    Unsupported {
+        root: PathBuf,
+        fs_type: String,
+        mount_point: PathBuf,
+    },
+
+    #[error("{}: {fs_type} does not support file handles, so changes cannot be watched", .root.display())]
+    NoFileHandles { root: PathBuf, fs_type: String },
+
+    #[error(
+        "{}: spans {} other mount(s) ({}); pass --skip-submounts to sync only the root's own filesystem",
+        .root.display(),
+        .mounts.len(),
+        join(.mounts)
+    )]
+    Submounts { root: PathBuf, mounts: Vec<PathBuf> },
+
+    #[error("{}: already the root of pair {pair_id}", .root.display())]
+    InUse { root: PathBuf, pair_id: String },
+
+    #[error(transparent)]
+    Marker(#[from] marker::MarkerError),
+}
+
+/// A root that can be synced, plus what the user should know about it.
+#[derive(Debug)]
+pub struct CheckedRoot {
+    pub root: PathBuf,
+    pub warnings: Vec<String>,
+}
+
+/// Reject roots on pseudo filesystems, on filesystems without file handle
+/// support (some FUSE mounts), or spanning other mounts unless
+/// `skip_submounts`, or already carrying another pair's marker; warn about
+/// network/FUSE and read-only mounts.
+pub fn check_root(dir: &Path, skip_submounts: bool) -> Result<CheckedRoot, RootError> {
+    check_root_with(dir, skip_submounts, |_| false)
+}
+
+/// [`check_root`], except that a marker whose pair id `stale` accepts does
+/// not make the root in use: the manager passes the ids it does not know,
+/// left behind by an earlier run.
+pub fn check_root_with(
+    dir: &Path,
+    skip_submounts: bool,
+    stale: impl Fn(&str) -> bool,
+) -> Result<CheckedRoot, RootError> {
+    let root = std::fs::canonicalize(dir).map_err(|e| RootError::Access(dir.to_path_buf(), e))?;
+    if !root.is_dir() {
+        return Err(RootError::NotDir(root));
+    }
+
+    // pair.add 会写入标记；覆盖别的 pair 的标记会让那个 pair 进入 error 状态
+    match marker::read(&root)? {
+        Some(pair_id) if !stale(&pair_id) => return Err(RootError::InUse { root, pair_id }),
+        _ => {}
+    }
+
+    let table = MountTable::read().map_err(RootError::MountTable)?;
+    let mount = table
+        .containing(&root)
+        .map_err(|e| RootError::Access(root.clone(), e.into()))?;
+
+    let mut warnings = Vec::new();
+    match mount.support() {
+        FsSupport::Supported => {}
+        FsSupport::RemoteChangesInvisible => warnings.push(format!(
+            "{} is on {}: changes made by other clients of that filesystem are not detected",
+            root.display(),
+            mount.fs_type
+        )),
+        FsSupport::Unsupported => {
+            return Err(RootError::Unsupported {
+                root,
+                fs_type: mount.fs_type.clone(),
+                mount_point: mount.mount_point.clone(),
+            })
+        }
+    }
+
+    // fanotify 的 FID 模式依赖 name_to_handle_at；不导出句柄的 FUSE 实现无法监听
+    match FileHandle::at(DirFd::CWD, &root, true) {
+        Ok(_) => {}
+        Err(e) if e.errno == Errno::EOPNOTSUPP => {
+            return Err(RootError::NoFileHandles {
+                root,
+                fs_type: mount.fs_type.clone(),
+            })
+        }
+        Err(e) => return Err(RootError::Access(root, e.into())),
+    }
+
+    if mount.is_read_only() {
+        warnings.push(format!(
+            "{} is mounted read-only: changes from the other side cannot be applied",
+            root.display()
+        ));
+    }
+
+    let nested: Vec<PathBuf> = table
+        .submounts(&root)
+        .iter()
+        .map(|m| m.mount_point.clone())
+        .collect();
+    if !nested.is_empty() {
+        if !skip_submounts {
+            return Err(RootError::Submounts {
+                root,
+                mounts: nested,
+            });
+        }
+        warnings.push(format!(
+            "{}: skipping {} nested mount(s): {}",
+            root.display(),
+            nested.len(),
+            join(&nested)
+        ));
+    }
+
+    Ok(CheckedRoot { root, warnings })
+}
+
+fn join(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,178 @@
+//! Loop suppression: keep synchron's own writes out of the event stream.
+//!
+//! Without it, a file the executor writes into side B is reported as a change
+//! on B and, in bidirectional mode, copied straight back to A.
+//!
+//! - attribution (authoritative): fanotify reports the writer's tgid with every
+//!   event. Events from this process or from a registered worker are dropped.
+//!   Workers are held by pidfd, so a recycled pid is not mistaken for one;
+//!   whenever that cannot be confirmed the event counts as external. A user
+//!   write must never be lost: at worst one of our own writes is reconciled
+//!   once more.
+//! - ignore marks (optional): while the executor writes a staging file an
+//!   inode ignore mark keeps its MODIFY / CLOSE_WRITE / ATTRIB out of the
+//!   queue entirely. An ignore mask applies to every process, so it is only
+//!   ever put on a file no user knows about yet. Directory entry events
+//!   (CREATE, DELETE, MOVED_*) are reported on the parent and are not
+//!   covered; attribution still catches those, including the rename that
+//!   publishes the staging file.
+
+use std::collections::HashMap;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd};
+use synchron_ffi::{
+    pidfd, DirFd, Error, Fanotify, FanotifyEvent, FanotifyEventMask, FanotifyEventRef,
+    FanotifyMarkFlags, PidFd,
+};
+
+type Result<T> = core::result::Result<T, Error>;
+
+/// Who caused an event.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Origin {
+    /// synchron itself (daemon or a registered worker): never dispatch.
+    Own,
+    External,
+}
+
+/// Events an ignore mark suppresses: content and metadata writes.
+const IGNORED_EVENTS: FanotifyEventMask = FanotifyEventMask(
+    FanotifyEventMask::MODIFY.0 | FanotifyEventMask::CLOSE_WRITE.0 | FanotifyEventMask::ATTRIB.0,
+);
+
+pub struct Suppressor {
+    own_pid: i32,
+    workers: HashMap<i32, PidFd>,
+    ignore_marks: bool,
+}
+
+impl Default for Suppressor {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl Suppressor {
+    /// `ignore_marks`: also install ignore marks in [`Suppressor::guard_write`].
+    pub fn new(ignore_marks: bool) -> Self {
+        Self {
+            own_pid: std::process::id() as i32,
+            workers: HashMap::new(),
+            ignore_marks,
+        }
+    }
+
+    /// Register a worker process whose writes must not be dispatched.
+    /// Fails with ESRCH if it is already gone.
+    pub fn register(&mut self, pid: i32) -> Result<()> {
+        let pidfd = PidFd::open(pid)?;
+        self.workers.insert(pid, pidfd);
+        Ok(())
+    }
+
+    /// Forget a worker. Call only after its events have been drained:
+    /// until then they must still be attributed to it.
+    pub fn unregister(&mut self, pid: i32) -> Option<PidFd> {
+        self.workers.remove(&pid)
+    }
+
+    /// Drop workers that have exited; returns how many were removed.
+    /// Same caveat as [`Suppressor::unregister`].
+    pub fn prune(&mut self) -> usize {
+        let before = self.workers.len();
+        self.workers.retain(|_, fd| fd.is_alive().unwrap_or(true));
+        before - self.workers.len()
+    }
+
+    pub fn is_worker(&self, pid: i32) -> bool {
+        self.workers.contains_key(&pid)
+    }
+
+    /// Classify by reported pid, confirmed through the event's pidfd
+    /// (FAN_REPORT_PIDFD) when there is one.
+    ///
+    /// A worker pid only counts while the worker is alive: a live process
+    /// keeps its pid, so nobody else can be writing under it. Once it has
+    /// exited its late events cannot be told apart from those of a process
+    /// that reused the pid, and they are treated as external.
+    pub fn origin(&self, pid: i32, pidfd: Option<BorrowedFd<'_>>) -> Origin {
+        if pid == self.own_pid {
+            return Origin::Own;
+        }
+        let Some(worker) = self.workers.get(&pid) else {
+            return Origin::External;
+        };
+
+        // 拿不准时一律按外部处理：多同步一次无害，丢掉用户的写入不行
+        let worker_alive = worker.is_alive().unwrap_or(false);
+        let event_alive = pidfd.map(|fd| matches!(pidfd::pid_of(fd), Ok(Some(_))));
+        match (worker_alive, event_alive) {
+            // 事件的进程已退出而 worker 还活着：不是同一个进程
+            (true, None | Some(true)) => Origin::Own,
+            _ => Origin::External,
+        }
+    }
+
+    pub fn origin_of(&self, event: &FanotifyEventRef<'_>) -> Origin {
+        self.origin(event.pid, event.pidfd.as_ref().map(|fd| fd.as_fd()))
+    }
+
+    pub fn origin_of_owned(&self, event: &FanotifyEvent) -> Origin {
+        self.origin(event.pid, event.pidfd.as_ref().map(|fd| fd.as_fd()))
+    }
+
+    /// Call after creating a staging file and before writing it; drop the
+    /// guard before the file is renamed into place. `None` when ignore marks
+    /// are disabled.
+    pub fn guard_write<'a>(
+        &self,
+        fan: &'a Fanotify,
+        staging: BorrowedFd<'_>,
+    ) -> Result<Option<IgnoreMark<'a>>> {
+        if !self.ignore_marks {
+            return Ok(None);
+        }
+        IgnoreMark::add(fan, staging).map(Some)
+    }
+}
+
+/// Inode ignore mark for one staging file, removed on drop.
+///
+/// The kernel ignores the masked events whoever causes them, so the file
+/// must be one synchron created itself (`O_EXCL`, private name) and not yet
+/// renamed to a name users can open. The mark is set through the open
+/// descriptor rather than a path, so it cannot land on a file swapped in
+/// under the same name.
+///
+/// IGNORED_SURV_MODIFY keeps it in place across the writes it is meant to
+/// hide (by default the kernel clears an ignore mask on the first modify).
+pub struct IgnoreMark<'a> {
+    fan: &'a Fanotify,
+    file: OwnedFd,
+}
+
+impl<'a> IgnoreMark<'a> {
+    pub fn add(fan: &'a Fanotify, staging: BorrowedFd<'_>) -> Result<Self> {
+        let file = staging.try_clone_to_owned()?;
+        fan.mark(
+            FanotifyMarkFlags::ADD
+                | FanotifyMarkFlags::IGNORED_MASK
+                | FanotifyMarkFlags::IGNORED_SURV_MODIFY,
+            IGNORED_EVENTS,
+            DirFd(file.as_raw_fd()),
+            None,
+        )?;
+        Ok(Self { fan, file })
+    }
+}
+
+impl Drop for IgnoreMark<'_> {
+    fn drop(&mut self) {
+        // 标记挂在 inode 上：文件即使已被删除也能按 fd 移除
+        let _ = self.fan.mark(
+            FanotifyMarkFlags::REMOVE | FanotifyMarkFlags::IGNORED_MASK,
+            IGNORED_EVENTS,
+            DirFd(self.file.as_raw_fd()),
+            None,
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! Table-driven tests: normalized event sequences -> coalesced events.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+use synchron_utils::{Action, CoalescedEvent, NormalizedEvent, Side};
+use synchron_watcher::coalescer::{Coalescer, CoalescerConfig};
+
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// One normalized event: action, path, rename source, is_dir, created.
+struct Ev(Action, &'static str, Option<&'static str>, bool, bool);
+
+const fn create(p: &'static str) -> Ev {
+    Ev(Action::Write, p, None, false, true)
+}
+const fn write(p: &'static str) -> Ev {
+    Ev(Action::Write, p, None, false, false)
+}
+const fn delete(p: &'static str) -> Ev {
+    Ev(Action::Delete, p, None, false, false)
+}
+const fn rename(from: &'static str, to: &'static str) -> Ev {
+    Ev(Action::Rename, to, Some(from), false, false)
+}
+const fn rename_dir(from: &'static str, to: &'static str) -> Ev {
+    Ev(Action::Rename, to, Some(from), true, false)
+}
+const fn rescan(p: &'static str) -> Ev {
+    Ev(Action::Rescan, p, None, true, false)
+}
+
+/// Expected output: action, path, rename source.
+type Want = (Action, &'static str, Option<&'static str>);
+
+struct Case {
+    name: &'static str,
+    events: &'static [Ev],
+    want: &'static [Want],
+}
+
+fn normalized(e: &Ev) -> NormalizedEvent {
+    NormalizedEvent {
+        side: Side::A,
+        action: e.0,
+        path: PathBuf::from(e.1),
+        from: e.2.map(PathBuf::from),
+        is_dir: e.3,
+        created: e.4,
+        pid: None,
+        overflow: false,
+        ts: SystemTime::UNIX_EPOCH,
+    }
+}
+
+const CASES: &[Case] = &[
+    Case {
+        name: "create+modify+modify is one write",
+        events: &[create("a"), write("a"), write("a")],
+        want: &[(Action::Write, "a", None)],
+    },
+    Case {
+        name: "create+delete is nothing",
+        events: &[create("a"), write("a"), delete("a")],
+        want: &[],
+    },
+    Case {
+        name: "write+delete is a delete",
+        events: &[write("a"), delete("a")],
+        want: &[(Action::Delete, "a", None)],
+    },
+    Case {
+        name: "delete+create is a write",
+        events: &[delete("a"), create("a")],
+        want: &[(Action::Write, "a", None)],
+    },
+    Case {
+        name: "write+rename is a rename followed by a write",
+        events: &[write("a"), rename("a", "b")],
+        want: &[(Action::Rename, "b", Some("a")), (Action::Write, "b", None)],
+    },
+    Case {
+        name: "rename+write is a rename followed by a write",
+        events: &[rename("a", "b"), write("b")],
+        want: &[(Action::Rename, "b", Some("a")), (Action::Write, "b", None)],
+    },
+    Case {
+        name: "create+rename is a write of the destination",
+        events: &[create("a"), rename("a", "b")],
+        want: &[(Action::Write, "b", None)],
+    },
+    Case {
+        name: "renames chain",
+        events: &[rename("a", "b"), rename("b", "c")],
+        want: &[(Action::Rename, "c", Some("a"))],
+    },
+    Case {
+        name: "renamed back is nothing",
+        events: &[rename("a", "b"), rename("b", "a")],
+        want: &[],
+    },
+    Case {
+        name: "renamed and deleted deletes both names",
+        events: &[rename("c", "b"), delete("b")],
+        want: &[(Action::Delete, "b", None), (Action::Delete, "c", None)],
+    },
+    Case {
+        name: "rename over a held write drops the write",
+        events: &[write("b"), rename("a", "b")],
+        want: &[(Action::Rename, "b", Some("a"))],
+    },
+    Case {
+        name: "recreated after a rename away",
+        events: &[rename("a", "b"), create("a")],
+        want: &[(Action::Rename, "b", Some("a")), (Action::Write, "a", None)],
+    },
+    Case {
+        name: "directory recreated after a rename away",
+        events: &[rename_dir("d", "e"), create("d/x")],
+        want: &[
+            (Action::Rename, "e", Some("d")),
+            (Action::Write, "d/x", None),
+        ],
+    },
+    Case {
+        name: "children follow a directory rename, after it",
+        events: &[write("d/x"), rename_dir("d", "e")],
+        want: &[
+            (Action::Rename, "e", Some("d")),
+            (Action::Write, "e/x", None),
+        ],
+    },
+    Case {
+        name: "a rename out of a moved directory waits for the directory",
+        events: &[rename_dir("z", "y"), rename("y/x", "a")],
+        want: &[
+            (Action::Rename, "y", Some("z")),
+            (Action::Rename, "a", Some("y/x")),
+        ],
+    },
+    Case {
+        name: "rescan absorbs what is held below it",
+        events: &[write("d/x"), delete("d/y"), rescan("d"), write("d/z")],
+        want: &[(Action::Rescan, "d", None)],
+    },
+    Case {
+        name: "rescan keeps the source of a rename into it",
+        events: &[rename("a", "d/a"), rescan("d")],
+        want: &[(Action::Delete, "a", None), (Action::Rescan, "d", None)],
+    },
+    Case {
+        name: "rescan of the root absorbs everything",
+        events: &[write("a"), rename("b", "c"), rescan("")],
+        want: &[(Action::Rescan, "", None)],
+    },
+    Case {
+        name: "unrelated paths stay apart",
+        events: &[write("b"), delete("a")],
+        want: &[(Action::Delete, "a", None), (Action::Write, "b", None)],
+    },
+];
+
+fn key(e: &CoalescedEvent) -> (Action, PathBuf, Option<PathBuf>) {
+    (e.action, e.path.clone(), e.from.clone())
+}
+
+#[test]
+fn coalescer_table() {
+    let mut failures = Vec::new();
+    for case in CASES {
+        let mut c = Coalescer::new(CoalescerConfig::from_millis(500));
+        let now = Instant::now();
+        for e in case.events {
+            c.push(normalized(e), now);
+        }
+        let mut out = Vec::new();
+        c.poll(now + DEBOUNCE, &mut out);
+        let got: Vec<_> = out.iter().map(key).collect();
+        let want: Vec<_> = case
+            .want
+            .iter()
+            .map(|&(a, p, f)| (a, PathBuf::from(p), f.map(PathBuf::from)))
+            .collect();
+        if got != want || !c.is_empty() {
+            failures.push(format!("{}:\n  want {want:?}\n  got  {got:?}", case.name));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn held_until_quiet() {
+    let mut c = Coalescer::new(CoalescerConfig::from_millis(500));
+    let t0 = Instant::now();
+    let mut out = Vec::new();
+    c.push(normalized(&write("a")), t0);
+    c.push(normalized(&write("a")), t0 + Duration::from_millis(400));
+    c.poll(t0 + Duration::from_millis(600), &mut out);
+    assert!(out.is_empty());
+    assert_eq!(c.next_deadline(), Some(t0 + Duration::from_millis(900)));
+    c.poll(t0 + Duration::from_millis(900), &mut out);
+    assert_eq!(out.len(), 1);
+    assert!(c.is_empty());
+}
+
+#[test]
+fn max_latency_caps_a_busy_path() {
+    let config = CoalescerConfig::from_millis(500);
+    let mut c = Coalescer::new(config);
+    let t0 = Instant::now();
+    let mut out = Vec::new();
+    let mut t = t0;
+    // 每 100ms 追加一次，防抖窗口永远不会结束
+    while t < t0 + config.max_latency {
+        c.push(normalized(&write("log")), t);
+        c.poll(t, &mut out);
+        assert!(out.is_empty());
+        t += Duration::from_millis(100);
+    }
+    c.poll(t0 + config.max_latency, &mut out);
+    assert_eq!(out.len(), 1);
+}
+
+#[test]
+fn debounce_is_clamped_and_overridable() {
+    let global = CoalescerConfig::from_millis(500);
+    assert_eq!(CoalescerConfig::default(), global);
+    assert_eq!(global.max_latency, Duration::from_secs(5));
+    assert_eq!(
+        CoalescerConfig::from_millis(0).debounce,
+        Duration::from_millis(1)
+    );
+    assert_eq!(
+        CoalescerConfig::from_millis(120_000).debounce,
+        Duration::from_secs(60)
+    );
+    assert_eq!(global.for_pair(None), global);
+    assert_eq!(
+        global.for_pair(Some(50)).debounce,
+        Duration::from_millis(50)
+    );
+}
+
+#[test]
+fn sides_are_independent() {
+    let mut c = Coalescer::new(CoalescerConfig::default());
+    let now = Instant::now();
+    let mut b = normalized(&delete("a"));
+    b.side = Side::B;
+    c.push(normalized(&create("a")), now);
+    c.push(b, now);
+    assert_eq!(c.len(), 2);
+    let mut out = Vec::new();
+    c.flush(&mut out);
+    let got: Vec<_> = out.iter().map(|e| (e.side, e.action)).collect();
+    assert_eq!(got, [(Side::A, Action::Write), (Side::B, Action::Delete)]);
+}
+
+#[test]
+fn overflow_survives_coalescing() {
+    let mut c = Coalescer::new(CoalescerConfig::default());
+    let now = Instant::now();
+    c.push(normalized(&write("a")), now);
+    c.push(
+        NormalizedEvent {
+            overflow: true,
+            ..normalized(&rescan(""))
+        },
+        now,
+    );
+    c.push(normalized(&write("b")), now);
+    c.push(normalized(&rescan("d")), now + Duration::from_secs(60));
+    let mut out = Vec::new();
+    c.flush(&mut out);
+    let got: Vec<_> = out
+        .iter()
+        .map(|e| (e.action, e.path.clone(), e.overflow))
+        .collect();
+    assert_eq!(got, [(Action::Rescan, PathBuf::new(), true)]);
+
+    // 目录重扫不是溢出
+    c.push(normalized(&rescan("d")), now);
+    out.clear();
+    c.flush(&mut out);
+    assert!(!out[0].overflow);
+}
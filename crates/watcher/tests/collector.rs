@@ -0,0 +1,325 @@
+//! Event streams from real kernel queues.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use synchron_ffi::probe::{probe, Capabilities};
+use synchron_ffi::FanotifyEventMask;
+use synchron_utils::{EventSource, Handle, Metadata, RawEvent, Side};
+use synchron_watcher::collector::{Backend, Collector, CollectorConfig};
+
+const CREATE: u64 = FanotifyEventMask::CREATE.0;
+const DELETE: u64 = FanotifyEventMask::DELETE.0;
+const DELETE_SELF: u64 = FanotifyEventMask::DELETE_SELF.0;
+const MOVE_SELF: u64 = FanotifyEventMask::MOVE_SELF.0;
+
+/// The tests write from their own process: keep those events.
+fn config(backend: Backend) -> CollectorConfig {
+    CollectorConfig {
+        backend,
+        suppress_own: false,
+        ..CollectorConfig::default()
+    }
+}
+
+fn meta(root: &Path, side: Side) -> Metadata {
+    Metadata {
+        root: root.to_path_buf(),
+        side,
+    }
+}
+
+/// fanotify backend, if this kernel and our privileges allow it.
+fn fanotify() -> Option<Capabilities> {
+    let caps = probe();
+    (caps.fanotify_dfid_name() && caps.can_resolve_handles()).then_some(caps)
+}
+
+/// Receive until `done` holds for what arrived, or fail after a while.
+async fn collect(h: &mut Handle<RawEvent>, done: impl Fn(&[RawEvent]) -> bool) -> Vec<RawEvent> {
+    let mut got = Vec::new();
+    while !done(&got) {
+        match tokio::time::timeout(Duration::from_secs(5), h.rx.recv()).await {
+            Ok(Some(ev)) => got.push(ev),
+            _ => panic!("stream ended or stalled; got {got:#?}"),
+        }
+    }
+    got
+}
+
+fn created(evs: &[RawEvent], path: &Path) -> bool {
+    evs.iter().any(|e| e.path == path && e.mask & CREATE != 0)
+}
+
+fn renamed(evs: &[RawEvent], from: &Path, to: &Path) -> bool {
+    evs.iter()
+        .any(|e| e.path == to && e.from.as_deref() == Some(from))
+}
+
+fn deleted(evs: &[RawEvent], path: &Path) -> bool {
+    evs.iter().any(|e| e.path == path && e.mask & DELETE != 0)
+}
+
+/// Create, rename and delete a file under a fresh root; every step must be
+/// reported, in order, tagged with the root's side.
+async fn create_rename_delete(caps: &Capabilities, backend: Backend, source: EventSource) {
+    let dir = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(dir.path()).unwrap();
+    let collector = Collector::new(caps, config(backend)).unwrap();
+    let mut h = collector.spawn(meta(&root, Side::B)).unwrap();
+
+    let (a, b) = (root.join("a"), root.join("b"));
+    std::fs::write(&a, b"x").unwrap();
+    std::fs::rename(&a, &b).unwrap();
+    std::fs::remove_file(&b).unwrap();
+
+    let got = collect(&mut h, |evs| deleted(evs, &b)).await;
+    let at = |f: &dyn Fn(&RawEvent) -> bool| got.iter().position(f).unwrap();
+    let c = at(&|e| e.path == a && e.mask & CREATE != 0);
+    let r = at(&|e| e.path == b && e.from.as_deref() == Some(&*a));
+    let d = at(&|e| e.path == b && e.mask & DELETE != 0);
+    assert!(c < r && r < d, "{got:#?}");
+    for ev in &got {
+        assert_eq!((ev.side, ev.source, ev.overflow), (Side::B, source, false));
+    }
+    h.shutdown().await;
+}
+
+#[tokio::test]
+async fn inotify_reports_create_rename_delete() {
+    create_rename_delete(&probe(), Backend::Inotify, EventSource::Inotify).await;
+}
+
+#[tokio::test]
+async fn fanotify_reports_create_rename_delete() {
+    let Some(caps) = fanotify() else {
+        eprintln!("skipped: fanotify with file handles unavailable");
+        return;
+    };
+    create_rename_delete(&caps, Backend::Fanotify, EventSource::Fanotify).await;
+}
+
+/// Removing the root itself must reach the stream as a DELETE_SELF of the
+/// root (the normalizer turns it into a rescan), even though fanotify can
+/// no longer resolve its handle.
+async fn root_deleted(caps: &Capabilities, backend: Backend) {
+    let dir = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(dir.path()).unwrap().join("root");
+    std::fs::create_dir_all(root.join("sub")).unwrap();
+    let collector = Collector::new(caps, config(backend)).unwrap();
+    let mut h = collector.spawn(meta(&root, Side::A)).unwrap();
+
+    std::fs::remove_dir_all(&root).unwrap();
+    let got = collect(&mut h, |evs| {
+        evs.iter()
+            .any(|e| e.path == root && e.mask & DELETE_SELF != 0)
+    })
+    .await;
+    assert!(got.iter().all(|e| !e.overflow), "{got:#?}");
+    h.shutdown().await;
+}
+
+#[tokio::test]
+async fn inotify_reports_the_root_deleted() {
+    root_deleted(&probe(), Backend::Inotify).await;
+}
+
+#[tokio::test]
+async fn fanotify_reports_the_root_deleted() {
+    let Some(caps) = fanotify() else {
+        eprintln!("skipped: fanotify with file handles unavailable");
+        return;
+    };
+    root_deleted(&caps, Backend::Fanotify).await;
+}
+
+/// A MOVE_SELF is the root's only when it is on the root: moving a
+/// subdirectory out reports that directory, moving the root reports it.
+#[tokio::test]
+async fn fanotify_reports_the_root_moved_away_and_not_its_subdirectories() {
+    let Some(caps) = fanotify() else {
+        eprintln!("skipped: fanotify with file handles unavailable");
+        return;
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let base = std::fs::canonicalize(dir.path()).unwrap();
+    let root = base.join("root");
+    std::fs::create_dir_all(root.join("sub")).unwrap();
+    let collector = Collector::new(&caps, config(Backend::Fanotify)).unwrap();
+    let mut h = collector.spawn(meta(&root, Side::A)).unwrap();
+
+    std::fs::rename(root.join("sub"), base.join("sub")).unwrap();
+    std::fs::rename(&root, base.join("moved")).unwrap();
+    let got = collect(&mut h, |evs| {
+        evs.iter()
+            .any(|e| e.path == root && e.mask & MOVE_SELF != 0)
+    })
+    .await;
+    assert!(
+        got.iter()
+            .all(|e| e.mask & MOVE_SELF == 0 || e.path == root),
+        "{got:#?}"
+    );
+    h.shutdown().await;
+}
+
+/// Writes of this process and of a registered worker are dropped; a
+/// stranger's are reported, including inside a directory we created.
+#[tokio::test]
+async fn fanotify_drops_events_of_its_own_process_and_workers() {
+    let Some(caps) = fanotify() else {
+        eprintln!("skipped: fanotify with file handles unavailable");
+        return;
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(dir.path()).unwrap();
+    let collector = Collector::new(&caps, CollectorConfig::default()).unwrap();
+    let mut h = collector.spawn(meta(&root, Side::A)).unwrap();
+
+    std::fs::write(root.join("own"), b"x").unwrap();
+    std::fs::create_dir(root.join("ours")).unwrap();
+    // worker 等读到一行再写：登记在写之前完成
+    let mut worker = Command::new("sh")
+        .args(["-c", "read _; echo x > worker; sleep 30"])
+        .current_dir(&root)
+        .stdin(Stdio::piped())
+        .spawn()
+        .unwrap();
+    collector
+        .suppressor()
+        .lock()
+        .unwrap()
+        .register(worker.id() as i32)
+        .unwrap();
+    writeln!(worker.stdin.as_mut().unwrap()).unwrap();
+    while !root.join("worker").exists() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let status = Command::new("sh")
+        .args(["-c", "echo x > ours/theirs"])
+        .current_dir(&root)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let theirs = root.join("ours/theirs");
+    let got = collect(&mut h, |evs| created(evs, &theirs)).await;
+    for own in ["own", "ours", "worker"] {
+        assert!(got.iter().all(|e| e.path != root.join(own)), "{got:#?}");
+    }
+    worker.kill().unwrap();
+    worker.wait().unwrap();
+    h.shutdown().await;
+}
+
+/// A directory removed and created again at the same path must be
+/// watched like the first one.
+#[tokio::test]
+async fn fanotify_watches_a_directory_recreated_at_the_same_path() {
+    let Some(caps) = fanotify() else {
+        eprintln!("skipped: fanotify with file handles unavailable");
+        return;
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let root = std::fs::canonicalize(dir.path()).unwrap();
+    let d = root.join("d");
+    std::fs::create_dir(&d).unwrap();
+    let collector = Collector::new(&caps, config(Backend::Fanotify)).unwrap();
+    let mut h = collector.spawn(meta(&root, Side::A)).unwrap();
+
+    std::fs::remove_dir(&d).unwrap();
+    collect(&mut h, |evs| deleted(evs, &d)).await;
+    std::fs::create_dir(&d).unwrap();
+    collect(&mut h, |evs| created(evs, &d)).await;
+    std::fs::write(d.join("f"), b"x").unwrap();
+    collect(&mut h, |evs| created(evs, &d.join("f"))).await;
+    h.shutdown().await;
+}
+
+#[tokio::test]
+async fn fanotify_roots_share_a_group_and_get_their_own_events() {
+    let Some(caps) = fanotify() else {
+        eprintln!("skipped: fanotify with file handles unavailable");
+        return;
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let top = std::fs::canonicalize(dir.path()).unwrap();
+    let (x, y) = (top.join("x"), top.join("y"));
+    std::fs::create_dir_all(x.join("in")).unwrap();
+    std::fs::create_dir(&y).unwrap();
+
+    let collector = Collector::new(&caps, config(Backend::Fanotify)).unwrap();
+    let mut hx = collector.spawn(meta(&x, Side::A)).unwrap();
+    let mut hy = collector.spawn(meta(&y, Side::B)).unwrap();
+
+    std::fs::write(y.join("only-y"), b"").unwrap();
+    std::fs::write(x.join("only-x"), b"").unwrap();
+    let got_x = collect(&mut hx, |evs| created(evs, &x.join("only-x"))).await;
+    let got_y = collect(&mut hy, |evs| created(evs, &y.join("only-y"))).await;
+    assert!(
+        got_x
+            .iter()
+            .all(|e| e.side == Side::A && e.path.starts_with(&x)),
+        "{got_x:#?}"
+    );
+    assert!(
+        got_y
+            .iter()
+            .all(|e| e.side == Side::B && e.path.starts_with(&y)),
+        "{got_y:#?}"
+    );
+
+    // 跨 root 的改名：两个 root 都收到
+    let (from, to) = (x.join("only-x"), y.join("from-x"));
+    std::fs::rename(&from, &to).unwrap();
+    if caps.fanotify.marks.rename {
+        collect(&mut hx, |evs| renamed(evs, &from, &to)).await;
+        collect(&mut hy, |evs| renamed(evs, &from, &to)).await;
+    } else {
+        collect(&mut hx, |evs| evs.iter().any(|e| e.path == from)).await;
+        collect(&mut hy, |evs| evs.iter().any(|e| e.path == to)).await;
+    }
+
+    // y 停掉之后 x 照常工作
+    hy.shutdown().await;
+    std::fs::write(x.join("in/later"), b"").unwrap();
+    collect(&mut hx, |evs| created(evs, &x.join("in/later"))).await;
+    hx.shutdown().await;
+}
+
+/// A stream nobody reads fills up and stalls its loop; shutting it down
+/// must still finish.
+async fn shutdown_with_full_channel(caps: &Capabilities, backend: Backend) {
+    let dir = tempfile::tempdir().unwrap();
+    let collector = Collector::new(
+        caps,
+        CollectorConfig {
+            capacity: 1,
+            ..config(backend)
+        },
+    )
+    .unwrap();
+    let h = collector.spawn(meta(dir.path(), Side::A)).unwrap();
+    for i in 0..32 {
+        std::fs::write(dir.path().join(i.to_string()), b"").unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    tokio::time::timeout(Duration::from_secs(5), h.shutdown())
+        .await
+        .expect("shutdown hung on a full channel");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn inotify_shutdown_with_full_channel() {
+    shutdown_with_full_channel(&probe(), Backend::Inotify).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn fanotify_shutdown_with_full_channel() {
+    let Some(caps) = fanotify() else {
+        eprintln!("skipped: fanotify with file handles unavailable");
+        return;
+    };
+    shutdown_with_full_channel(&caps, Backend::Fanotify).await;
+}
@@ -0,0 +1,127 @@
+//! Per-pair queues: ordering, backpressure and collapsing under a bound.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use synchron_utils::{Action, CoalescedEvent, Side};
+use synchron_watcher::dispatcher::{Dispatcher, QueueLimits};
+
+fn write(path: &str) -> CoalescedEvent {
+    CoalescedEvent {
+        side: Side::A,
+        action: Action::Write,
+        path: PathBuf::from(path),
+        from: None,
+        is_dir: false,
+        overflow: false,
+        ts: SystemTime::UNIX_EPOCH,
+    }
+}
+
+/// Room for `n` events with two-byte paths.
+fn limits(n: usize, stall: Duration) -> QueueLimits {
+    QueueLimits {
+        max_bytes: n * (core::mem::size_of::<CoalescedEvent>() + 2),
+        stall,
+    }
+}
+
+#[tokio::test]
+async fn events_arrive_in_order_until_closed() {
+    let d = Dispatcher::new();
+    let (tx, mut rx) = d.add("p".to_string(), QueueLimits::default());
+    for p in ["a", "b", "c"] {
+        tx.send(write(p)).await.unwrap();
+    }
+    assert_eq!(d.status(&"p".to_string()).unwrap().depth, 3);
+    drop(tx);
+    assert!(d.remove(&"p".to_string()));
+
+    let mut got = Vec::new();
+    while let Some(ev) = rx.recv().await {
+        got.push(ev.path);
+    }
+    assert_eq!(got, ["a", "b", "c"].map(PathBuf::from));
+}
+
+#[tokio::test]
+async fn unknown_pair_and_gone_worker_return_the_event() {
+    let d = Dispatcher::new();
+    assert!(d.dispatch(&"nope".to_string(), write("a")).await.is_err());
+    let (_tx, rx) = d.add("p".to_string(), QueueLimits::default());
+    drop(rx);
+    let back = d.dispatch(&"p".to_string(), write("a")).await.unwrap_err();
+    assert_eq!(back.0.path, Path::new("a"));
+}
+
+#[tokio::test]
+async fn slow_worker_holds_the_producer_back() {
+    let d = Dispatcher::new();
+    let (tx, mut rx) = d.add("p".to_string(), limits(4, Duration::from_secs(10)));
+    for i in 0..4 {
+        tx.send(write(&format!("f{i}"))).await.unwrap();
+    }
+    let blocked = tokio::spawn(async move { tx.send(write("last")).await });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!blocked.is_finished());
+    assert_eq!(d.status(&"p".to_string()).unwrap().waiting, 1);
+
+    let mut buf = Vec::new();
+    assert_eq!(rx.recv_many(&mut buf, 2).await, 2);
+    blocked.await.unwrap().unwrap();
+    let status = d.status(&"p".to_string()).unwrap();
+    assert_eq!((status.depth, status.collapses, status.waiting), (3, 0, 0));
+}
+
+#[tokio::test]
+async fn full_queue_collapses_into_directory_rescans() {
+    let d = Dispatcher::new();
+    let (tx, mut rx) = d.add("p".to_string(), limits(8, Duration::from_millis(10)));
+    let mut sent = Vec::new();
+    for i in 0..50 {
+        for dir in ["x/d", "y"] {
+            let path = format!("{dir}/f{i}");
+            tx.send(write(&path)).await.unwrap();
+            sent.push(PathBuf::from(path));
+        }
+    }
+    let status = d.status(&"p".to_string()).unwrap();
+    assert!(status.collapses >= 1);
+    assert!(status.depth <= 8, "{status:?}");
+    drop(tx);
+    d.remove(&"p".to_string());
+
+    let mut queued = Vec::new();
+    while let Some(ev) = rx.recv().await {
+        queued.push(ev);
+    }
+    assert!(queued
+        .iter()
+        .any(|e| e.action == Action::Rescan && e.path == Path::new("x/d")));
+    // 只折叠到目录：没有丢事件
+    assert!(queued.iter().all(|e| !e.overflow), "{queued:?}");
+    // 每个发出的事件都被保留下来，或被某个目录重扫覆盖
+    for path in &sent {
+        let covered = queued
+            .iter()
+            .any(|e| &e.path == path || (e.action == Action::Rescan && path.starts_with(&e.path)));
+        assert!(covered, "{} lost", path.display());
+    }
+}
+
+#[tokio::test]
+async fn collapse_up_to_the_root_is_an_overflow() {
+    let d = Dispatcher::new();
+    let (tx, mut rx) = d.add("p".to_string(), limits(2, Duration::from_millis(10)));
+    for i in 0..8 {
+        tx.send(write(&format!("f{i}"))).await.unwrap();
+    }
+    drop(tx);
+    d.remove(&"p".to_string());
+
+    let first = rx.recv().await.unwrap();
+    assert_eq!(
+        (first.action, first.path.as_path()),
+        (Action::Rescan, Path::new(""))
+    );
+    assert!(first.overflow);
+}
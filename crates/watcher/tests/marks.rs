@@ -0,0 +1,254 @@
+//! Shared fanotify groups: routing by root, mark placement and removal.
+
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use synchron_ffi::{Errno, FanotifyEventMask, FanotifyInitFlags};
+use synchron_watcher::marks::{MarkMode, MarkScope, MarkTable};
+
+fn table(mode: MarkMode) -> MarkTable<&'static str> {
+    let init = FanotifyInitFlags::CLASS_NOTIF
+        | FanotifyInitFlags::CLOEXEC
+        | FanotifyInitFlags::NONBLOCK
+        | FanotifyInitFlags::REPORT_DFID_NAME;
+    let mask = FanotifyEventMask::CREATE | FanotifyEventMask::DELETE | FanotifyEventMask::MODIFY;
+    MarkTable::new(init, mask, mode)
+}
+
+/// `None` without CAP_SYS_ADMIN (fanotify_init answers EPERM).
+fn add(t: &mut MarkTable<&'static str>, key: &'static str, root: &Path) -> Option<u64> {
+    match t.add_root(key, root) {
+        Ok(dev) => Some(dev),
+        Err(e) if e.errno == Errno::EPERM => None,
+        Err(e) => panic!("add_root {}: {e}", root.display()),
+    }
+}
+
+fn tree() -> (tempfile::TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let root = fs::canonicalize(dir.path()).unwrap();
+    for d in ["a/x", "a/y", "b"] {
+        fs::create_dir_all(root.join(d)).unwrap();
+    }
+    (dir, root)
+}
+
+#[test]
+fn roots_on_one_device_share_a_group() {
+    let (_dir, root) = tree();
+    let mut t = table(MarkMode::Auto);
+    let Some(dev) = add(&mut t, "a", &root.join("a")) else {
+        return;
+    };
+    assert_eq!(dev, fs::metadata(&root).unwrap().dev());
+    assert_eq!(add(&mut t, "b", &root.join("b")), Some(dev));
+    assert_eq!(t.groups().count(), 1);
+
+    let group = t.group(dev).unwrap();
+    let roots: Vec<_> = group.roots().map(|(k, r)| (*k, r.to_path_buf())).collect();
+    assert_eq!(roots, [("a", root.join("a")), ("b", root.join("b"))]);
+}
+
+#[test]
+fn route_matches_every_containing_root() {
+    let (_dir, root) = tree();
+    let mut t = table(MarkMode::Auto);
+    let Some(dev) = add(&mut t, "outer", &root) else {
+        return;
+    };
+    add(&mut t, "inner", &root.join("a"));
+    let group = t.group(dev).unwrap();
+
+    let route = |p: &Path| group.route(p).copied().collect::<Vec<_>>();
+    assert_eq!(route(&root.join("a/x/f")), ["outer", "inner"]);
+    assert_eq!(route(&root.join("a")), ["outer", "inner"]);
+    assert_eq!(route(&root.join("b/f")), ["outer"]);
+    // 按路径组件比较，不是字符串前缀
+    assert_eq!(route(&root.join("ab")), ["outer"]);
+    assert_eq!(route(&root.with_file_name("elsewhere")), Vec::<&str>::new());
+}
+
+#[test]
+fn auto_mode_places_inode_marks_below_a_subtree() {
+    let (_dir, root) = tree();
+    let mut t = table(MarkMode::Auto);
+    let Some(dev) = add(&mut t, "a", &root.join("a")) else {
+        return;
+    };
+    // tempdir 不是挂载根：宽范围 mark 会报告大量无关事件
+    let group = t.group_mut(dev).unwrap();
+    assert_eq!(group.scope(), MarkScope::Inode);
+
+    // 新目录要补 mark；root 之外的目录不管
+    fs::create_dir_all(root.join("a/new/deep")).unwrap();
+    group.watch_dir(&root.join("a/new")).unwrap();
+    group.watch_dir(&root.join("b")).unwrap();
+    group.remark().unwrap();
+
+    fs::write(root.join("a/new/deep/f"), "x").unwrap();
+    fs::write(root.join("b/f"), "x").unwrap();
+    let group = t.group(dev).unwrap();
+    let mut buf = vec![0u8; 8192];
+    let mut seen = Vec::new();
+    for ev in group.fanotify().read_events(&mut buf).unwrap() {
+        let path = group.resolve(&ev).unwrap();
+        assert_eq!(group.route(&path).copied().collect::<Vec<_>>(), ["a"]);
+        seen.push(path);
+    }
+    assert!(seen.contains(&root.join("a/new/deep/f")), "{seen:?}");
+}
+
+/// Paths of every event queued on the group of `dev`.
+fn seen(t: &MarkTable<&'static str>, dev: u64) -> Vec<PathBuf> {
+    let group = t.group(dev).unwrap();
+    let mut buf = vec![0u8; 8192];
+    let mut seen = Vec::new();
+    loop {
+        let evs = group.fanotify().read_events(&mut buf).unwrap();
+        if evs.is_empty() {
+            return seen;
+        }
+        seen.extend(evs.iter().filter_map(|ev| group.resolve(ev).ok()));
+    }
+}
+
+#[test]
+fn directory_recreated_at_a_forgotten_path_is_marked_again() {
+    let (_dir, root) = tree();
+    let mut t = table(MarkMode::Inode);
+    let Some(dev) = add(&mut t, "a", &root.join("a")) else {
+        return;
+    };
+    // 删掉重建 / 移走后在原处新建：新目录是另一个 inode，没有 mark
+    fs::remove_dir_all(root.join("a/x")).unwrap();
+    fs::create_dir(root.join("a/x")).unwrap();
+    fs::rename(root.join("a/y"), root.join("a/moved")).unwrap();
+    fs::create_dir(root.join("a/y")).unwrap();
+    seen(&t, dev);
+
+    let group = t.group_mut(dev).unwrap();
+    for d in ["a/x", "a/y"] {
+        group.forget_dir(&root.join(d));
+        group.watch_dir(&root.join(d)).unwrap();
+    }
+    fs::write(root.join("a/x/f"), "x").unwrap();
+    fs::write(root.join("a/y/f"), "x").unwrap();
+    fs::write(root.join("a/moved/f"), "x").unwrap();
+    let seen = seen(&t, dev);
+    for f in ["a/x/f", "a/y/f", "a/moved/f"] {
+        assert!(seen.contains(&root.join(f)), "{f}: {seen:?}");
+    }
+}
+
+#[test]
+fn planned_marks_are_committed_only_while_their_root_is_watched() {
+    let (_dir, root) = tree();
+    let mut t = table(MarkMode::Inode);
+    let Some(dev) = add(&mut t, "a", &root.join("a")) else {
+        return;
+    };
+    add(&mut t, "b", &root.join("b"));
+    for d in ["a/new/deep", "b/new/deep"] {
+        fs::create_dir_all(root.join(d)).unwrap();
+    }
+    seen(&t, dev);
+
+    let group = t.group(dev).unwrap();
+    let mut a = group.plan_watch(&root.join("a/new")).unwrap();
+    let mut b = group.plan_watch(&root.join("b/new")).unwrap();
+    assert_eq!(a.top(), root.join("a/new"));
+    assert!(group.plan_watch(&root.join("elsewhere")).is_none());
+    // 放置时不借用 group：期间 root "a" 被移除
+    a.place().unwrap();
+    b.place().unwrap();
+    t.remove(&"a").unwrap();
+    let group = t.group_mut(dev).unwrap();
+    group.commit(a);
+    group.commit(b);
+
+    fs::write(root.join("a/new/deep/f"), "x").unwrap();
+    fs::write(root.join("b/new/deep/f"), "x").unwrap();
+    let seen = seen(&t, dev);
+    assert!(seen.contains(&root.join("b/new/deep/f")), "{seen:?}");
+    assert!(!seen.contains(&root.join("a/new/deep/f")), "{seen:?}");
+}
+
+#[test]
+fn wide_mode_marks_the_filesystem_whatever_the_roots() {
+    let (_dir, root) = tree();
+    let mut t = table(MarkMode::Wide);
+    let Some(dev) = add(&mut t, "a", &root.join("a")) else {
+        return;
+    };
+    let scope = t.group(dev).unwrap().scope();
+    assert!(
+        matches!(scope, MarkScope::Filesystem | MarkScope::Mount),
+        "{scope:?}"
+    );
+    add(&mut t, "b", &root.join("b")).unwrap();
+    t.remove(&"a").unwrap();
+    assert_eq!(t.group(dev).unwrap().scope(), scope);
+}
+
+#[test]
+fn mount_root_switches_auto_to_a_wide_mark_and_back() {
+    let shm = Path::new("/dev/shm");
+    if fs::metadata(shm).map(|m| m.dev()).ok() == fs::metadata("/dev").map(|m| m.dev()).ok() {
+        return;
+    }
+    let sub = tempfile::tempdir_in(shm).unwrap();
+    let mut t = table(MarkMode::Auto);
+    let Some(dev) = add(&mut t, "sub", sub.path()) else {
+        return;
+    };
+    assert_eq!(t.group(dev).unwrap().scope(), MarkScope::Inode);
+
+    // 覆盖整个挂载的 root 加入后改用宽范围 mark
+    add(&mut t, "shm", shm).unwrap();
+    let scope = t.group(dev).unwrap().scope();
+    assert!(
+        matches!(scope, MarkScope::Filesystem | MarkScope::Mount),
+        "{scope:?}"
+    );
+
+    // 它离开后退回 inode mark
+    t.remove(&"shm").unwrap();
+    assert_eq!(t.group(dev).unwrap().scope(), MarkScope::Inode);
+}
+
+#[test]
+fn removing_the_last_root_closes_the_group() {
+    let (_dir, root) = tree();
+    let mut t = table(MarkMode::Auto);
+    let Some(dev) = add(&mut t, "a", &root.join("a")) else {
+        return;
+    };
+    add(&mut t, "b", &root.join("b")).unwrap();
+
+    t.remove(&"a").unwrap();
+    let group = t.group(dev).unwrap();
+    assert_eq!(group.roots().map(|(k, _)| *k).collect::<Vec<_>>(), ["b"]);
+    assert_eq!(group.route(&root.join("a/x")).count(), 0);
+
+    // 未知的 key 什么也不做
+    t.remove(&"nope").unwrap();
+    t.remove(&"b").unwrap();
+    assert!(t.group(dev).is_none());
+    assert!(t.is_empty());
+}
+
+#[test]
+fn failed_add_leaves_no_empty_group() {
+    let (_dir, root) = tree();
+    let mut t = table(MarkMode::Auto);
+    assert_eq!(
+        t.add_root("gone", &root.join("missing")).unwrap_err().errno,
+        Errno::ENOENT
+    );
+    fs::write(root.join("file"), "").unwrap();
+    assert_eq!(
+        t.add_root("file", &root.join("file")).unwrap_err().errno,
+        Errno::ENOTDIR
+    );
+    assert!(t.is_empty());
+}
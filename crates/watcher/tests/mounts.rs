@@ -0,0 +1,162 @@
+//! Mount loss and return, driven by synthetic mount tables.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use synchron_ffi::MountTable;
+use synchron_watcher::mounts::{MountChange, MountWatch, RootState};
+
+const BASE: &str = "22 1 8:1 / / rw - ext4 /dev/sda1 rw\n";
+const USB: &str = "40 22 8:17 / /media/usb rw - vfat /dev/sdb1 rw\n";
+const OTHER: &str = "41 22 8:33 / /media/usb rw - vfat /dev/sdc1 rw\n";
+
+const ROOT_DEV: u64 = 2065;
+
+fn table(lines: &[&str]) -> MountTable {
+    MountTable::parse(lines.concat().as_bytes()).unwrap()
+}
+
+/// `MountWatch` tracking `p` on the USB mount and `local` on `/`.
+fn watch() -> MountWatch<&'static str> {
+    let mut w = MountWatch::new().unwrap();
+    w.track("p", "/media/usb/docs".into(), "/media/usb".into(), ROOT_DEV);
+    w.track("p", "/home/u/docs".into(), "/".into(), 2049);
+    w.track("local", "/home/u/other".into(), "/".into(), 2049);
+    w
+}
+
+/// Device lookup backed by a map; missing roots have none.
+struct Devs(HashMap<PathBuf, u64>);
+
+impl Devs {
+    fn new(usb: Option<u64>) -> Self {
+        let mut m = HashMap::from([
+            (PathBuf::from("/home/u/docs"), 2049),
+            (PathBuf::from("/home/u/other"), 2049),
+        ]);
+        if let Some(dev) = usb {
+            m.insert(PathBuf::from("/media/usb/docs"), dev);
+        }
+        Self(m)
+    }
+
+    fn get(&self, p: &Path) -> Option<u64> {
+        self.0.get(p).copied()
+    }
+}
+
+fn refresh(
+    w: &mut MountWatch<&'static str>,
+    t: &MountTable,
+    devs: &Devs,
+    ours: bool,
+) -> Vec<MountChange<&'static str>> {
+    w.refresh(t, |p| devs.get(p), |_, _| ours)
+}
+
+fn lost(key: &'static str, root: &str) -> MountChange<&'static str> {
+    MountChange::Lost {
+        key,
+        root: root.into(),
+    }
+}
+
+fn restored(key: &'static str, root: &str) -> MountChange<&'static str> {
+    MountChange::Restored {
+        key,
+        root: root.into(),
+    }
+}
+
+#[test]
+fn nothing_changes_while_mounted() {
+    let mut w = watch();
+    let mounted = table(&[BASE, USB]);
+    assert!(refresh(&mut w, &mounted, &Devs::new(Some(ROOT_DEV)), false).is_empty());
+    assert_eq!(w.state(&"p"), Some(RootState::Mounted));
+    assert_eq!(w.state(&"nope"), None);
+}
+
+#[test]
+fn unmount_and_remount_of_the_same_device() {
+    let mut w = watch();
+    let gone = table(&[BASE]);
+    // 挂载点下只剩空目录：root 不存在或落在 / 上
+    assert_eq!(
+        refresh(&mut w, &gone, &Devs::new(None), false),
+        [lost("p", "/media/usb/docs")]
+    );
+    assert_eq!(w.state(&"p"), Some(RootState::WaitingForMount));
+    assert_eq!(w.state(&"local"), Some(RootState::Mounted));
+    // 只报告一次
+    assert!(refresh(&mut w, &gone, &Devs::new(Some(2049)), false).is_empty());
+
+    let back = table(&[BASE, USB]);
+    assert_eq!(
+        refresh(&mut w, &back, &Devs::new(Some(ROOT_DEV)), false),
+        [restored("p", "/media/usb/docs")]
+    );
+    assert_eq!(w.state(&"p"), Some(RootState::Mounted));
+}
+
+#[test]
+fn other_filesystem_at_the_mount_point_is_not_restored() {
+    let mut w = watch();
+    refresh(&mut w, &table(&[BASE]), &Devs::new(None), false);
+
+    let other = table(&[BASE, OTHER]);
+    let devs = Devs::new(Some(2081));
+    assert!(refresh(&mut w, &other, &devs, false).is_empty());
+    assert_eq!(w.state(&"p"), Some(RootState::WaitingForMount));
+
+    // 标记确认是同一个 root（U 盘换了设备号）：恢复，并记住新设备
+    assert_eq!(
+        refresh(&mut w, &other, &devs, true),
+        [restored("p", "/media/usb/docs")]
+    );
+    assert!(refresh(&mut w, &other, &devs, false).is_empty());
+}
+
+#[test]
+fn replaced_while_mounted_is_lost() {
+    let mut w = watch();
+    // 卸载后立即挂上了另一个文件系统，中间的变化没有观察到
+    let other = table(&[BASE, OTHER]);
+    assert_eq!(
+        refresh(&mut w, &other, &Devs::new(Some(2081)), true),
+        [lost("p", "/media/usb/docs")]
+    );
+}
+
+#[test]
+fn root_directory_removed_is_lost() {
+    let mut w = watch();
+    let mut devs = Devs::new(Some(ROOT_DEV));
+    devs.0.remove(Path::new("/home/u/other"));
+    assert_eq!(
+        refresh(&mut w, &table(&[BASE, USB]), &devs, false),
+        [lost("local", "/home/u/other")]
+    );
+}
+
+#[test]
+fn unwatch_forgets_every_root_of_the_key() {
+    let mut w = watch();
+    w.unwatch(&"p");
+    assert_eq!(w.state(&"p"), None);
+    assert!(refresh(&mut w, &table(&[BASE]), &Devs::new(None), false).is_empty());
+}
+
+#[test]
+fn real_roots_stay_mounted() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut w = MountWatch::new().unwrap();
+    w.watch("t", dir.path()).unwrap();
+    let t = w.table().unwrap();
+    let changes = w.refresh(
+        &t,
+        |p| synchron_watcher::mounts::dev_of(p).ok(),
+        |_, _| false,
+    );
+    assert!(changes.is_empty(), "{changes:?}");
+    assert_eq!(w.state(&"t"), Some(RootState::Mounted));
+}
@@ -0,0 +1,422 @@
+//! Table-driven tests: kernel event sequences -> normalized actions.
+
+use std::path::PathBuf;
+use std::time::SystemTime;
+use synchron_ffi::{FanotifyEventMask as Fan, InotifyMask as In};
+use synchron_utils::{Action, EventSource, NormalizedEvent, RawEvent, Side};
+use synchron_watcher::normalizer::Normalizer;
+
+const ROOT: &str = "/pair/root";
+
+const CREATE: u64 = Fan::CREATE.0;
+const DELETE: u64 = Fan::DELETE.0;
+const MODIFY: u64 = Fan::MODIFY.0;
+const ATTRIB: u64 = Fan::ATTRIB.0;
+const CLOSE_WRITE: u64 = Fan::CLOSE_WRITE.0;
+const MOVED_FROM: u64 = Fan::MOVED_FROM.0;
+const MOVED_TO: u64 = Fan::MOVED_TO.0;
+const RENAME: u64 = Fan::RENAME.0;
+const DELETE_SELF: u64 = Fan::DELETE_SELF.0;
+const MOVE_SELF: u64 = Fan::MOVE_SELF.0;
+const ONDIR: u64 = Fan::ONDIR.0;
+const ACCESS: u64 = Fan::ACCESS.0;
+const Q_OVERFLOW: u64 = Fan::Q_OVERFLOW.0;
+const IN_IGNORED: u64 = In::IGNORED.0 as u64;
+
+/// One kernel record: mask, path, rename source, inotify cookie.
+/// Paths not starting with `/` are relative to `ROOT`.
+struct Raw(u64, &'static str, Option<&'static str>, u32);
+
+/// Expected output: action, path, rename source, is_dir.
+type Want = (Action, &'static str, Option<&'static str>, bool);
+
+struct Case {
+    name: &'static str,
+    events: &'static [Raw],
+    /// call `flush` after the last event
+    flush: bool,
+    want: &'static [Want],
+}
+
+fn abs(p: &str) -> PathBuf {
+    if p.starts_with('/') {
+        PathBuf::from(p)
+    } else if p.is_empty() {
+        PathBuf::from(ROOT)
+    } else {
+        PathBuf::from(ROOT).join(p)
+    }
+}
+
+fn raw(r: &Raw) -> RawEvent {
+    RawEvent {
+        side: Side::A,
+        source: if r.3 != 0 {
+            EventSource::Inotify
+        } else {
+            EventSource::Fanotify
+        },
+        mask: r.0,
+        path: abs(r.1),
+        from: r.2.map(abs),
+        cookie: r.3,
+        pid: Some(42),
+        overflow: r.0 & Q_OVERFLOW != 0,
+        ts: SystemTime::UNIX_EPOCH,
+    }
+}
+
+const CASES: &[Case] = &[
+    // ---- writes ----
+    Case {
+        name: "create file",
+        events: &[Raw(CREATE, "a", None, 0)],
+        flush: false,
+        want: &[(Action::Write, "a", None, false)],
+    },
+    Case {
+        name: "create+modify+close_write merged by fanotify",
+        events: &[Raw(CREATE | MODIFY | CLOSE_WRITE, "a", None, 0)],
+        flush: false,
+        want: &[(Action::Write, "a", None, false)],
+    },
+    Case {
+        name: "inotify create, modify, close_write",
+        events: &[
+            Raw(CREATE, "a", None, 0),
+            Raw(MODIFY, "a", None, 0),
+            Raw(CLOSE_WRITE, "a", None, 0),
+        ],
+        flush: false,
+        want: &[
+            (Action::Write, "a", None, false),
+            (Action::Write, "a", None, false),
+            (Action::Write, "a", None, false),
+        ],
+    },
+    Case {
+        name: "mkdir",
+        events: &[Raw(CREATE | ONDIR, "d", None, 0)],
+        flush: false,
+        want: &[(Action::Write, "d", None, true)],
+    },
+    Case {
+        name: "attribute change",
+        events: &[Raw(ATTRIB, "a", None, 0)],
+        flush: false,
+        want: &[(Action::Write, "a", None, false)],
+    },
+    Case {
+        name: "attribute change on the root",
+        events: &[Raw(ATTRIB | ONDIR, "", None, 0)],
+        flush: false,
+        want: &[(Action::Write, "", None, true)],
+    },
+    Case {
+        name: "read-only access is dropped",
+        events: &[Raw(ACCESS, "a", None, 0)],
+        flush: false,
+        want: &[],
+    },
+    // ---- deletes ----
+    Case {
+        name: "unlink",
+        events: &[Raw(DELETE, "a", None, 0)],
+        flush: false,
+        want: &[(Action::Delete, "a", None, false)],
+    },
+    Case {
+        name: "rmdir",
+        events: &[Raw(DELETE | ONDIR, "d", None, 0)],
+        flush: false,
+        want: &[(Action::Delete, "d", None, true)],
+    },
+    Case {
+        name: "modify then delete merged: delete wins",
+        events: &[Raw(MODIFY | CLOSE_WRITE | DELETE, "a", None, 0)],
+        flush: false,
+        want: &[(Action::Delete, "a", None, false)],
+    },
+    Case {
+        name: "create and delete merged: order unknown",
+        events: &[Raw(CREATE | DELETE, "a", None, 0)],
+        flush: false,
+        want: &[(Action::Rescan, "a", None, false)],
+    },
+    Case {
+        name: "root deleted is a rescan, not a mass delete",
+        events: &[Raw(DELETE_SELF | ONDIR, "", None, 0)],
+        flush: false,
+        want: &[(Action::Rescan, "", None, true)],
+    },
+    Case {
+        name: "root moved away is a rescan, not a mass delete",
+        events: &[Raw(MOVE_SELF | ONDIR, "", None, 0)],
+        flush: false,
+        want: &[(Action::Rescan, "", None, true)],
+    },
+    Case {
+        name: "root's entry deleted in its parent (wide mark) is a rescan",
+        events: &[Raw(DELETE | ONDIR, "", None, 0)],
+        flush: false,
+        want: &[(Action::Rescan, "", None, true)],
+    },
+    Case {
+        name: "root's entry renamed away in its parent (wide mark) is a rescan",
+        events: &[Raw(RENAME | ONDIR, "/pair/elsewhere", Some(""), 0)],
+        flush: false,
+        want: &[(Action::Rescan, "", None, true)],
+    },
+    Case {
+        name: "root replaced by a directory moved over it is a rescan",
+        events: &[Raw(RENAME | ONDIR, "", Some("/pair/other"), 0)],
+        flush: false,
+        want: &[(Action::Rescan, "", None, true)],
+    },
+    Case {
+        name: "delete_self below the root is left to the parent's DELETE",
+        events: &[
+            Raw(DELETE_SELF | ONDIR, "d", None, 0),
+            Raw(DELETE | ONDIR, "d", None, 0),
+        ],
+        flush: false,
+        want: &[(Action::Delete, "d", None, true)],
+    },
+    Case {
+        name: "inotify IGNORED after a watch is gone",
+        events: &[Raw(IN_IGNORED, "d", None, 0)],
+        flush: false,
+        want: &[],
+    },
+    // ---- renames ----
+    Case {
+        name: "fanotify FAN_RENAME inside the root",
+        events: &[Raw(RENAME, "b", Some("a"), 0)],
+        flush: false,
+        want: &[(Action::Rename, "b", Some("a"), false)],
+    },
+    Case {
+        name: "fanotify FAN_RENAME of a directory",
+        events: &[Raw(RENAME | ONDIR, "x/e", Some("d"), 0)],
+        flush: false,
+        want: &[(Action::Rename, "x/e", Some("d"), true)],
+    },
+    Case {
+        name: "inotify move paired by the ffi layer",
+        events: &[Raw(MOVED_FROM | MOVED_TO, "b", Some("a"), 7)],
+        flush: false,
+        want: &[(Action::Rename, "b", Some("a"), false)],
+    },
+    Case {
+        name: "inotify move split across reads, paired by cookie",
+        events: &[Raw(MOVED_FROM, "a", None, 7), Raw(MOVED_TO, "b", None, 7)],
+        flush: false,
+        want: &[(Action::Rename, "b", Some("a"), false)],
+    },
+    Case {
+        name: "moved into the root from outside",
+        events: &[Raw(RENAME, "b", Some("/elsewhere/a"), 0)],
+        flush: false,
+        want: &[(Action::Write, "b", None, false)],
+    },
+    Case {
+        name: "moved out of the root",
+        events: &[Raw(RENAME, "/elsewhere/b", Some("a"), 0)],
+        flush: false,
+        want: &[(Action::Delete, "a", None, false)],
+    },
+    Case {
+        name: "directory moved in",
+        events: &[Raw(MOVED_FROM | MOVED_TO | ONDIR, "d", Some("/tmp/d"), 3)],
+        flush: false,
+        want: &[(Action::Write, "d", None, true)],
+    },
+    Case {
+        name: "moved_to without moved_from is a create",
+        events: &[Raw(MOVED_TO, "b", None, 9)],
+        flush: false,
+        want: &[(Action::Write, "b", None, false)],
+    },
+    Case {
+        name: "moved_from followed by an unrelated event is a delete",
+        events: &[Raw(MOVED_FROM, "a", None, 7), Raw(CREATE, "c", None, 0)],
+        flush: false,
+        want: &[
+            (Action::Delete, "a", None, false),
+            (Action::Write, "c", None, false),
+        ],
+    },
+    Case {
+        name: "moved_from with a different cookie than the moved_to",
+        events: &[Raw(MOVED_FROM, "a", None, 1), Raw(MOVED_TO, "b", None, 2)],
+        flush: false,
+        want: &[
+            (Action::Delete, "a", None, false),
+            (Action::Write, "b", None, false),
+        ],
+    },
+    Case {
+        name: "moved_from held until flush",
+        events: &[Raw(MOVED_FROM | ONDIR, "d", None, 7)],
+        flush: true,
+        want: &[(Action::Delete, "d", None, true)],
+    },
+    Case {
+        name: "moved_from held without flush produces nothing yet",
+        events: &[Raw(MOVED_FROM, "a", None, 7)],
+        flush: false,
+        want: &[],
+    },
+    Case {
+        name: "fanotify moved_from without rename support is a delete",
+        events: &[Raw(MOVED_FROM, "a", None, 0)],
+        flush: false,
+        want: &[(Action::Delete, "a", None, false)],
+    },
+    Case {
+        name: "fanotify moved_to without rename support is a create",
+        events: &[Raw(MOVED_TO | ONDIR, "d", None, 0)],
+        flush: false,
+        want: &[(Action::Write, "d", None, true)],
+    },
+    // ---- outside / overflow ----
+    Case {
+        name: "event outside the root",
+        events: &[Raw(CREATE, "/elsewhere/a", None, 0)],
+        flush: false,
+        want: &[],
+    },
+    Case {
+        name: "marker directory is not synced",
+        events: &[
+            Raw(CREATE | ONDIR, ".synchron", None, 0),
+            Raw(CREATE, ".synchron/pair-id.tmp", None, 0),
+            Raw(CLOSE_WRITE, ".synchron/pair-id.tmp", None, 0),
+            Raw(
+                RENAME,
+                ".synchron/pair-id",
+                Some(".synchron/pair-id.tmp"),
+                0,
+            ),
+            Raw(DELETE | ONDIR, ".synchron", None, 0),
+        ],
+        flush: false,
+        want: &[],
+    },
+    Case {
+        name: "only the top-level .synchron is the marker directory",
+        events: &[Raw(CREATE, "d/.synchron/x", None, 0)],
+        flush: false,
+        want: &[(Action::Write, "d/.synchron/x", None, false)],
+    },
+    Case {
+        name: "moved out of the marker directory",
+        events: &[Raw(RENAME, "a", Some(".synchron/a"), 0)],
+        flush: false,
+        want: &[(Action::Write, "a", None, false)],
+    },
+    Case {
+        name: "moved into the marker directory",
+        events: &[Raw(MOVED_FROM | MOVED_TO, ".synchron/a", Some("a"), 4)],
+        flush: false,
+        want: &[(Action::Delete, "a", None, false)],
+    },
+    Case {
+        name: "queue overflow",
+        events: &[Raw(Q_OVERFLOW, "", None, 0)],
+        flush: false,
+        want: &[(Action::Rescan, "", None, true)],
+    },
+    Case {
+        name: "overflow settles a pending moved_from first",
+        events: &[Raw(MOVED_FROM, "a", None, 7), Raw(Q_OVERFLOW, "", None, 0)],
+        flush: false,
+        want: &[
+            (Action::Delete, "a", None, false),
+            (Action::Rescan, "", None, true),
+        ],
+    },
+];
+
+fn run(case: &Case) -> Vec<NormalizedEvent> {
+    let mut n = Normalizer::new(ROOT);
+    let mut out = Vec::new();
+    for r in case.events {
+        n.push(raw(r), &mut out);
+    }
+    if case.flush {
+        n.flush(&mut out);
+    }
+    out
+}
+
+#[test]
+fn normalizer_table() {
+    let mut failures = Vec::new();
+    for case in CASES {
+        let got: Vec<_> = run(case)
+            .into_iter()
+            .map(|e| (e.action, e.path, e.from, e.is_dir))
+            .collect();
+        let want: Vec<_> = case
+            .want
+            .iter()
+            .map(|&(a, p, f, d)| (a, PathBuf::from(p), f.map(PathBuf::from), d))
+            .collect();
+        if got != want {
+            failures.push(format!("{}:\n  want {want:?}\n  got  {got:?}", case.name));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn pending_moved_from_is_visible() {
+    let mut n = Normalizer::new(ROOT);
+    let mut out = Vec::new();
+    n.push(raw(&Raw(MOVED_FROM, "a", None, 7)), &mut out);
+    assert!(n.has_pending());
+    n.push(raw(&Raw(MOVED_TO, "b", None, 7)), &mut out);
+    assert!(!n.has_pending());
+    assert_eq!(out.len(), 1);
+}
+
+#[test]
+fn created_only_for_new_entries() {
+    let created = |r: Raw| {
+        let mut out = Vec::new();
+        Normalizer::new(ROOT).push(raw(&r), &mut out);
+        out[0].created
+    };
+    assert!(created(Raw(CREATE, "a", None, 0)));
+    assert!(created(Raw(CREATE | MODIFY, "a", None, 0)));
+    assert!(created(Raw(RENAME, "b", Some("/elsewhere/a"), 0)));
+    assert!(!created(Raw(MODIFY, "a", None, 0)));
+    assert!(!created(Raw(RENAME, "b", Some("a"), 0)));
+    assert!(!created(Raw(DELETE, "a", None, 0)));
+}
+
+#[test]
+fn side_pid_and_timestamp_are_carried_over() {
+    let mut n = Normalizer::new(ROOT);
+    let mut out = Vec::new();
+    let mut ev = raw(&Raw(CLOSE_WRITE, "a", None, 0));
+    ev.side = Side::B;
+    n.push(ev, &mut out);
+    assert_eq!(out[0].side, Side::B);
+    assert_eq!(out[0].pid, Some(42));
+    assert_eq!(out[0].ts, SystemTime::UNIX_EPOCH);
+}
+
+#[test]
+fn only_lost_events_are_flagged_overflow() {
+    let overflow = |r: Raw| {
+        let mut out = Vec::new();
+        Normalizer::new(ROOT).push(raw(&r), &mut out);
+        out[0].overflow
+    };
+    assert!(overflow(Raw(Q_OVERFLOW, "", None, 0)));
+    // 根目录没了：要重扫，但没有丢事件
+    assert!(!overflow(Raw(DELETE_SELF | ONDIR, "", None, 0)));
+    assert!(!overflow(Raw(CREATE | DELETE, "a", None, 0)));
+}
@@ -0,0 +1,86 @@
+//! Pair root validation on real directories.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use synchron_utils::marker;
+use synchron_watcher::roots::{check_root, check_root_with, RootError};
+
+#[test]
+fn plain_directory_is_accepted_and_canonicalized() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::create_dir(dir.path().join("r")).unwrap();
+    let checked = check_root(&dir.path().join("r/../r/."), false).unwrap();
+    assert_eq!(
+        checked.root,
+        fs::canonicalize(dir.path().join("r")).unwrap()
+    );
+}
+
+#[test]
+fn missing_paths_and_files_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let err = check_root(&dir.path().join("missing"), false).unwrap_err();
+    assert!(matches!(err, RootError::Access(..)), "{err}");
+
+    fs::write(dir.path().join("f"), "").unwrap();
+    let err = check_root(&dir.path().join("f"), false).unwrap_err();
+    assert!(matches!(err, RootError::NotDir(_)), "{err}");
+}
+
+#[test]
+fn pseudo_filesystems_are_rejected() {
+    let err = check_root(Path::new("/proc/self"), false).unwrap_err();
+    assert!(
+        matches!(err, RootError::Unsupported { ref fs_type, .. } if fs_type == "proc"),
+        "{err}"
+    );
+}
+
+#[test]
+fn root_of_another_pair_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    marker::write(dir.path(), "other").unwrap();
+    let err = check_root(dir.path(), false).unwrap_err();
+    assert!(
+        matches!(err, RootError::InUse { ref pair_id, .. } if pair_id == "other"),
+        "{err}"
+    );
+}
+
+#[test]
+fn stale_marker_does_not_make_the_root_in_use() {
+    let dir = tempfile::tempdir().unwrap();
+    marker::write(dir.path(), "gone").unwrap();
+    let checked = check_root_with(dir.path(), false, |id| id == "gone").unwrap();
+    assert_eq!(checked.root, std::fs::canonicalize(dir.path()).unwrap());
+    let err = check_root_with(dir.path(), false, |id| id == "other").unwrap_err();
+    assert!(matches!(err, RootError::InUse { ref pair_id, .. } if pair_id == "gone"));
+}
+
+#[test]
+fn nested_mounts_need_skip_submounts() {
+    let dir = tempfile::tempdir().unwrap();
+    let inner = dir.path().join("inner");
+    fs::create_dir(&inner).unwrap();
+    let mounted = Command::new("mount")
+        .args(["-t", "tmpfs", "synchron-test"])
+        .arg(&inner)
+        .status()
+        .is_ok_and(|s| s.success());
+    if !mounted {
+        return;
+    }
+    let refused = check_root(dir.path(), false);
+    let skipped = check_root(dir.path(), true);
+    Command::new("umount").arg(&inner).status().unwrap();
+
+    match refused.unwrap_err() {
+        RootError::Submounts { mounts, .. } => {
+            assert_eq!(mounts, [fs::canonicalize(&inner).unwrap()])
+        }
+        e => panic!("{e}"),
+    }
+    let skipped = skipped.unwrap();
+    assert_eq!(skipped.warnings.len(), 1, "{:?}", skipped.warnings);
+}
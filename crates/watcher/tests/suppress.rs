@@ -0,0 +1,140 @@
+//! Loop suppression: pid attribution and staging-file ignore marks.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::fd::AsFd;
+use std::process::{Child, Command};
+use synchron_ffi::{
+    DirFd, Errno, Fanotify, FanotifyEventMask, FanotifyInitFlags, FanotifyMarkFlags, OpenFlags,
+    PidFd,
+};
+use synchron_watcher::suppress::{Origin, Suppressor};
+
+fn sleeper() -> Child {
+    Command::new("sleep").arg("30").spawn().unwrap()
+}
+
+fn reap(mut child: Child) {
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn own_process_and_strangers() {
+    let s = Suppressor::default();
+    assert_eq!(s.origin(std::process::id() as i32, None), Origin::Own);
+    let other = sleeper();
+    assert_eq!(s.origin(other.id() as i32, None), Origin::External);
+    reap(other);
+}
+
+#[test]
+fn live_worker_is_own() {
+    let mut s = Suppressor::default();
+    let worker = sleeper();
+    let pid = worker.id() as i32;
+    s.register(pid).unwrap();
+    assert!(s.is_worker(pid));
+
+    let event_pidfd = PidFd::open(pid).unwrap();
+    assert_eq!(s.origin(pid, None), Origin::Own);
+    assert_eq!(s.origin(pid, Some(event_pidfd.as_fd())), Origin::Own);
+    reap(worker);
+}
+
+#[test]
+fn exited_worker_is_external() {
+    let mut s = Suppressor::default();
+    let worker = sleeper();
+    let pid = worker.id() as i32;
+    s.register(pid).unwrap();
+    let event_pidfd = PidFd::open(pid).unwrap();
+    reap(worker);
+
+    // 退出后无法区分迟到的事件和复用了 pid 的进程：按外部处理
+    assert_eq!(s.origin(pid, None), Origin::External);
+    assert_eq!(s.origin(pid, Some(event_pidfd.as_fd())), Origin::External);
+    assert_eq!(s.prune(), 1);
+    assert!(!s.is_worker(pid));
+}
+
+#[test]
+fn event_from_an_exited_process_is_not_the_live_worker() {
+    let mut s = Suppressor::default();
+    let worker = sleeper();
+    let pid = worker.id() as i32;
+    s.register(pid).unwrap();
+    let gone = sleeper();
+    let gone_pidfd = PidFd::open(gone.id() as i32).unwrap();
+    reap(gone);
+
+    assert_eq!(s.origin(pid, Some(gone_pidfd.as_fd())), Origin::External);
+    reap(worker);
+}
+
+fn fanotify() -> Option<Fanotify> {
+    // ATTRIB 只能在 FID 模式下标记，和 collector 建的组一致
+    let flags = FanotifyInitFlags::CLASS_NOTIF
+        | FanotifyInitFlags::CLOEXEC
+        | FanotifyInitFlags::NONBLOCK
+        | FanotifyInitFlags::REPORT_FID;
+    match Fanotify::new(flags, OpenFlags::RDONLY | OpenFlags::CLOEXEC) {
+        Ok(fan) => Some(fan),
+        // 需要 CAP_SYS_ADMIN
+        Err(e) if e.errno == Errno::EPERM => None,
+        Err(e) => panic!("fanotify_init: {e}"),
+    }
+}
+
+#[test]
+fn ignore_mark_covers_only_the_staging_file() {
+    let Some(fan) = fanotify() else {
+        return;
+    };
+    let dir = tempfile::tempdir().unwrap();
+    let staging = dir.path().join(".synchron-tmp");
+    let user = dir.path().join("user");
+    File::create(&user).unwrap();
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&staging)
+        .unwrap();
+    for p in [&staging, &user] {
+        fan.mark(
+            FanotifyMarkFlags::ADD,
+            FanotifyEventMask::MODIFY,
+            DirFd::CWD,
+            Some(p),
+        )
+        .unwrap();
+    }
+
+    let mut buf = vec![0u8; 4096];
+    let s = Suppressor::new(true);
+    let guard = s.guard_write(&fan, file.as_fd()).unwrap();
+    assert!(guard.is_some());
+    file.write_all(b"ours").unwrap();
+    OpenOptions::new()
+        .append(true)
+        .open(&user)
+        .unwrap()
+        .write_all(b"theirs")
+        .unwrap();
+    // 只有用户文件的 MODIFY
+    assert_eq!(fan.read_events(&mut buf).unwrap().len(), 1);
+
+    drop(guard);
+    file.write_all(b"again").unwrap();
+    assert_eq!(fan.read_events(&mut buf).unwrap().len(), 1);
+}
+
+#[test]
+fn no_ignore_mark_unless_enabled() {
+    let Some(fan) = fanotify() else {
+        return;
+    };
+    let file = tempfile::tempfile().unwrap();
+    let s = Suppressor::default();
+    assert!(s.guard_write(&fan, file.as_fd()).unwrap().is_none());
+}